@@ -0,0 +1,51 @@
+//! Benchmarks for query string parsing.
+
+#![allow(missing_docs, reason = "benchmarks")]
+
+use std::{borrow::Cow, collections::HashMap, hint::black_box};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use miku_http_util::request::parser::{OwnedQuery, Query};
+
+const PLAIN: &str = "appkey=1d8b6e7d45233436&build=7210300&c_locale=zh_CN&channel=master&\
+                     mobi_app=android&platform=android&s_locale=zh_CN&statistics=abcdefg&\
+                     ts=1700000000&sign=0123456789abcdef0123456789abcdef";
+
+const ENCODED: &str = "keyword=%E4%BD%A0%E5%A5%BD%20world&appkey=1d8b6e7d45233436&\
+                       redirect=https%3A%2F%2Fexample.com%2Fa%3Fb%3Dc&ts=1700000000";
+
+/// The previous implementation, always going through `fluent_uri` decoding.
+fn baseline(query: &str) -> HashMap<Cow<'_, str>, Cow<'_, str>, foldhash::fast::RandomState> {
+    use fluent_uri::encoding::{encoder::IQuery, EStr};
+
+    EStr::<IQuery>::new(query)
+        .unwrap_or(EStr::EMPTY)
+        .split('&')
+        .map(|pair| pair.split_once('=').unwrap_or((pair, EStr::EMPTY)))
+        .map(|(k, v)| {
+            (
+                k.decode().into_string_lossy(),
+                v.decode().into_string_lossy(),
+            )
+        })
+        .collect()
+}
+
+fn bench_query_parse(c: &mut Criterion) {
+    for (name, query) in [("plain", PLAIN), ("encoded", ENCODED)] {
+        let mut group = c.benchmark_group(name);
+
+        group.bench_function("baseline", |b| b.iter(|| baseline(black_box(query))));
+        group.bench_function("Query::parse", |b| {
+            b.iter(|| Query::parse(black_box(query)))
+        });
+        group.bench_function("OwnedQuery::parse", |b| {
+            b.iter(|| OwnedQuery::parse(black_box(query)))
+        });
+
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_query_parse);
+criterion_main!(benches);
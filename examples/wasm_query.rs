@@ -0,0 +1,48 @@
+//! Building and parsing query strings from a browser worker.
+//!
+//! ```sh
+//! cargo build --example wasm_query --release \
+//!     --target wasm32-unknown-unknown \
+//!     --no-default-features --features feat-request-builder,feat-request-parser
+//! wasm-bindgen --target web --out-dir pkg \
+//!     target/wasm32-unknown-unknown/release/examples/wasm_query.wasm
+//! ```
+//!
+//! Then in the worker:
+//!
+//! ```js
+//! import init, { signed_query, query_value } from "./pkg/wasm_query.js";
+//!
+//! await init();
+//! const query = signed_query("page", "2", "0123456789abcdef");
+//! query_value(query, "sign");
+//! ```
+
+#[cfg(target_arch = "wasm32")]
+/// Functions exported to JavaScript.
+pub mod worker {
+    use miku_http_util::request::{
+        builder::{Md5Signer, Query},
+        parser::OwnedQuery,
+    };
+    use wasm_bindgen::prelude::wasm_bindgen;
+
+    #[wasm_bindgen]
+    /// Build the query string with one pair, signed with MD5 and given suffix
+    /// salt.
+    pub fn signed_query(key: &str, value: &str, salt: &str) -> String {
+        match Query::with_capacity(1)
+            .push(key, value)
+            .build_signed(Md5Signer::new_default().with_suffix_salt(Some(salt)))
+        {
+            Ok(query) => query,
+            Err(e) => match e {},
+        }
+    }
+
+    #[wasm_bindgen]
+    /// Returns the (decoded) value of the key in the query string.
+    pub fn query_value(query: &str, key: &str) -> Option<String> {
+        OwnedQuery::parse(query).get(key).map(ToOwned::to_owned)
+    }
+}
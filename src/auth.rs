@@ -0,0 +1,13 @@
+//! Authentication utilities
+
+#[cfg(feature = "feat-auth-api-key")]
+pub mod api_key;
+#[cfg(feature = "feat-auth-basic")]
+pub mod basic;
+#[cfg(feature = "feat-auth-bearer")]
+pub mod bearer;
+#[cfg(feature = "feat-auth-oauth2")]
+pub mod oauth2;
+
+// re-export
+pub use crate::BoxFuture;
@@ -0,0 +1,395 @@
+//! Server-side API key / bearer token validation, see [`ApiKeyLayer`] and
+//! `WithApiKeyHandler` (with feature `feat-integrate-axum`).
+//!
+//! The key is extracted from the configured [`KeySource`] and validated with
+//! the [`KeyValidator`]. The authenticated principal is inserted into the
+//! request extensions as [`Authenticated`], or the request is rejected with
+//! `401 Unauthorized`.
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    hash::BuildHasher,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use http::{header, HeaderName, HeaderValue, Request, Response, StatusCode};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::percent;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Where to extract the API key from.
+pub enum KeySource {
+    /// The header, e.g. `X-Api-Key`.
+    ///
+    /// For `Authorization`, the `Bearer ` prefix is stripped.
+    Header(HeaderName),
+
+    /// The query parameter, e.g. `api_key`.
+    Query(&'static str),
+}
+
+impl KeySource {
+    /// `Authorization: Bearer ...`
+    pub const BEARER: Self = Self::Header(header::AUTHORIZATION);
+
+    /// Extract the API key from the request.
+    pub fn extract<B>(&self, req: &Request<B>) -> Option<String> {
+        match self {
+            Self::Header(name) => {
+                let value = req.headers().get(name)?.to_str().ok()?.trim();
+
+                let value = if *name == header::AUTHORIZATION {
+                    let (scheme, token) = value.split_once(' ')?;
+
+                    if !scheme.eq_ignore_ascii_case("bearer") {
+                        return None;
+                    }
+
+                    token.trim()
+                } else {
+                    value
+                };
+
+                (!value.is_empty()).then(|| value.to_owned())
+            }
+            Self::Query(key) => req.uri().query()?.split('&').find_map(|pair| {
+                let (k, v) = pair.split_once('=')?;
+
+                let mut decoded = String::new();
+                percent::decode_form_to(k, &mut decoded);
+
+                if decoded != *key || v.is_empty() {
+                    return None;
+                }
+
+                decoded.clear();
+                percent::decode_form_to(v, &mut decoded);
+
+                Some(decoded)
+            }),
+        }
+    }
+}
+
+/// Validates API keys, returning the authenticated principal.
+///
+/// Implemented for `HashMap<String, P>`, mapping keys to principals.
+pub trait KeyValidator: Send + Sync + 'static {
+    /// The authenticated principal, e.g. the user ID.
+    type Principal: Clone + Send + Sync + 'static;
+
+    /// Validate the key, returns the principal if it's valid.
+    fn validate(&self, key: &str) -> Option<Self::Principal>;
+}
+
+impl<P, H> KeyValidator for HashMap<String, P, H>
+where
+    P: Clone + Send + Sync + 'static,
+    H: BuildHasher + Send + Sync + 'static,
+{
+    type Principal = P;
+
+    fn validate(&self, key: &str) -> Option<Self::Principal> {
+        self.get(key).cloned()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The authenticated principal, inserted into the request extensions.
+pub struct Authenticated<P>(pub P);
+
+/// Authenticate the request, inserting [`Authenticated`] into the request
+/// extensions.
+///
+/// Returns `false` if the key is missing or invalid.
+pub fn authenticate<B, V>(req: &mut Request<B>, source: &KeySource, validator: &V) -> bool
+where
+    V: KeyValidator + ?Sized,
+{
+    let Some(principal) = source.extract(req).and_then(|key| validator.validate(&key)) else {
+        #[cfg(feature = "feat-tracing")]
+        tracing::debug!(
+            "Rejected request to {} with missing or invalid key",
+            req.uri().path()
+        );
+
+        return false;
+    };
+
+    req.extensions_mut().insert(Authenticated(principal));
+
+    true
+}
+
+/// The `401 Unauthorized` response.
+fn unauthorized<B>(source: &KeySource) -> Response<B>
+where
+    B: Default,
+{
+    let mut response = Response::new(B::default());
+    *response.status_mut() = StatusCode::UNAUTHORIZED;
+
+    if *source == KeySource::BEARER {
+        response
+            .headers_mut()
+            .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+    }
+
+    response
+}
+
+#[derive(Debug)]
+/// [`Layer`] validating the API key of requests, see the module
+/// documentation.
+pub struct ApiKeyLayer<V> {
+    validator: Arc<V>,
+    source: KeySource,
+}
+
+impl<V> Clone for ApiKeyLayer<V> {
+    fn clone(&self) -> Self {
+        Self {
+            validator: self.validator.clone(),
+            source: self.source.clone(),
+        }
+    }
+}
+
+impl<V> ApiKeyLayer<V> {
+    #[inline]
+    /// Create a new [`ApiKeyLayer`].
+    pub fn new(validator: V, source: KeySource) -> Self {
+        Self {
+            validator: Arc::new(validator),
+            source,
+        }
+    }
+}
+
+impl<S, V> Layer<S> for ApiKeyLayer<V> {
+    type Service = ApiKeyService<S, V>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ApiKeyService {
+            inner,
+            validator: self.validator.clone(),
+            source: self.source.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+/// [`Service`] validating the API key of requests, see [`ApiKeyLayer`].
+pub struct ApiKeyService<S, V> {
+    inner: S,
+    validator: Arc<V>,
+    source: KeySource,
+}
+
+impl<S, V> Clone for ApiKeyService<S, V>
+where
+    S: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            validator: self.validator.clone(),
+            source: self.source.clone(),
+        }
+    }
+}
+
+impl<S, V, ReqBody, ResBody> Service<Request<ReqBody>> for ApiKeyService<S, V>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+    V: KeyValidator,
+    ResBody: Default + Send + 'static,
+{
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+    type Response = S::Response;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        if authenticate(&mut req, &self.source, &*self.validator) {
+            Box::pin(self.inner.call(req))
+        } else {
+            Box::pin(std::future::ready(Ok(unauthorized(&self.source))))
+        }
+    }
+}
+
+#[cfg(feature = "feat-integrate-axum")]
+mod integrate_axum {
+    use std::{future::Future, pin::Pin, sync::Arc};
+
+    use axum::{extract::Request, handler::Handler, response::Response};
+
+    use super::{authenticate, unauthorized, KeySource, KeyValidator};
+
+    #[derive(Debug)]
+    /// Wrapper over handler validating the API key, like
+    /// [`WithQueryHandler`](crate::request::parser::integration::WithQueryHandler).
+    pub struct WithApiKeyHandler<H, V> {
+        inner: H,
+        validator: Arc<V>,
+        source: KeySource,
+    }
+
+    impl<H, V> Clone for WithApiKeyHandler<H, V>
+    where
+        H: Clone,
+    {
+        fn clone(&self) -> Self {
+            Self {
+                inner: self.inner.clone(),
+                validator: self.validator.clone(),
+                source: self.source.clone(),
+            }
+        }
+    }
+
+    impl<H, V> WithApiKeyHandler<H, V> {
+        #[inline]
+        /// Create a new [`WithApiKeyHandler`].
+        ///
+        /// The validator is shared, so it's taken as [`Arc`].
+        pub const fn new(inner: H, validator: Arc<V>, source: KeySource) -> Self {
+            Self {
+                inner,
+                validator,
+                source,
+            }
+        }
+    }
+
+    impl<H, V, T, S> Handler<T, S> for WithApiKeyHandler<H, V>
+    where
+        H: Handler<T, S>,
+        V: KeyValidator,
+    {
+        type Future = Pin<Box<dyn Future<Output = Response> + Send>>;
+
+        fn call(self, mut req: Request, state: S) -> Self::Future {
+            if authenticate(&mut req, &self.source, &*self.validator) {
+                Box::pin(self.inner.call(req, state))
+            } else {
+                Box::pin(std::future::ready(unauthorized(&self.source)))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "feat-integrate-axum")]
+// re-export
+pub use self::integrate_axum::WithApiKeyHandler;
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use super::*;
+
+    fn validator() -> HashMap<String, &'static str> {
+        HashMap::from([("k1".to_owned(), "alice")])
+    }
+
+    #[test]
+    fn test_extract() {
+        let req = Request::get("/?a=1&api%5Fkey=k%31")
+            .header("authorization", "bearer  k2 ")
+            .header("x-api-key", "k3")
+            .body(())
+            .unwrap();
+
+        assert_eq!(KeySource::Query("api_key").extract(&req).unwrap(), "k1");
+        assert_eq!(KeySource::BEARER.extract(&req).unwrap(), "k2");
+        assert_eq!(
+            KeySource::Header(HeaderName::from_static("x-api-key"))
+                .extract(&req)
+                .unwrap(),
+            "k3"
+        );
+        assert_eq!(KeySource::Query("b").extract(&req), None);
+
+        let req = Request::get("/?api+key=a+b%2B").body(()).unwrap();
+        assert_eq!(KeySource::Query("api key").extract(&req).unwrap(), "a b+");
+
+        let req = Request::get("/")
+            .header("authorization", "Basic xxx")
+            .body(())
+            .unwrap();
+        assert_eq!(KeySource::BEARER.extract(&req), None);
+    }
+
+    #[tokio::test]
+    async fn test_api_key_layer() {
+        let echo = tower::service_fn(|req: Request<()>| {
+            let Authenticated(principal) = req.extensions().get::<Authenticated<&str>>().unwrap();
+
+            std::future::ready(Ok::<_, Infallible>(Response::new((*principal).to_owned())))
+        });
+        let mut service = ApiKeyLayer::new(validator(), KeySource::BEARER).layer(echo);
+
+        let req = Request::get("/")
+            .header("authorization", "Bearer k1")
+            .body(())
+            .unwrap();
+        let response = service.call(req).await.unwrap();
+        assert_eq!(response.body(), "alice");
+
+        let req = Request::get("/")
+            .header("authorization", "Bearer k2")
+            .body(())
+            .unwrap();
+        let response = service.call(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers()[header::WWW_AUTHENTICATE], "Bearer");
+    }
+
+    #[cfg(feature = "feat-integrate-axum")]
+    #[tokio::test]
+    async fn test_with_api_key_handler() {
+        use axum::{extract::Extension, handler::Handler};
+
+        async fn whoami(
+            Extension(Authenticated(principal)): Extension<Authenticated<&'static str>>,
+        ) -> &'static str {
+            principal
+        }
+
+        let handler =
+            WithApiKeyHandler::new(whoami, Arc::new(validator()), KeySource::Query("api_key"));
+
+        let response = handler
+            .clone()
+            .call(
+                axum::extract::Request::get("/?api_key=k1")
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+                (),
+            )
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = handler
+            .call(
+                axum::extract::Request::get("/")
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+                (),
+            )
+            .await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,253 @@
+//! Server-side basic authentication, see [`BasicAuthLayer`].
+
+use std::{
+    fmt,
+    future::Future,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use http::{header, HeaderMap, HeaderValue, Request, Response, StatusCode};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::BoxFuture;
+
+/// Verifies the credentials of basic authentication.
+///
+/// Implemented for async closures `Fn(String, String) -> impl Future<Output =
+/// bool>`, taking the username and the password.
+pub trait BasicVerifier: Send + Sync + 'static {
+    /// Verify the credentials.
+    fn verify(&self, username: String, password: String) -> BoxFuture<'_, bool>;
+}
+
+impl<F, Fut> BasicVerifier for F
+where
+    F: Fn(String, String) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = bool> + Send + 'static,
+{
+    fn verify(&self, username: String, password: String) -> BoxFuture<'_, bool> {
+        Box::pin(self(username, password))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The authenticated username, inserted into the request extensions.
+pub struct BasicUser(pub String);
+
+/// Parse the username and password from `Authorization: Basic ...`.
+pub fn parse_basic(headers: &HeaderMap) -> Option<(String, String)> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?.trim();
+    let (scheme, credentials) = value.split_once(' ')?;
+
+    if !scheme.eq_ignore_ascii_case("basic") {
+        return None;
+    }
+
+    let credentials = String::from_utf8(STANDARD.decode(credentials.trim()).ok()?).ok()?;
+    let (username, password) = credentials.split_once(':')?;
+
+    Some((username.to_owned(), password.to_owned()))
+}
+
+/// [`Layer`] for basic authentication: parses `Authorization: Basic ...`,
+/// verifies the credentials with the [`BasicVerifier`], and either inserts
+/// [`BasicUser`] into the request extensions or responds `401 Unauthorized`
+/// with the realm.
+pub struct BasicAuthLayer<V> {
+    verifier: Arc<V>,
+    realm: &'static str,
+}
+
+impl<V> fmt::Debug for BasicAuthLayer<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BasicAuthLayer")
+            .field("realm", &self.realm)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<V> Clone for BasicAuthLayer<V> {
+    fn clone(&self) -> Self {
+        Self {
+            verifier: self.verifier.clone(),
+            realm: self.realm,
+        }
+    }
+}
+
+impl<V> BasicAuthLayer<V> {
+    #[inline]
+    /// Create a new [`BasicAuthLayer`], with realm `Restricted`.
+    pub fn new(verifier: V) -> Self {
+        Self {
+            verifier: Arc::new(verifier),
+            realm: "Restricted",
+        }
+    }
+
+    #[inline]
+    /// Set the realm.
+    pub fn with_realm(self, realm: &'static str) -> Self {
+        Self { realm, ..self }
+    }
+}
+
+impl<S, V> Layer<S> for BasicAuthLayer<V> {
+    type Service = BasicAuthService<S, V>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        BasicAuthService {
+            inner,
+            verifier: self.verifier.clone(),
+            realm: self.realm,
+        }
+    }
+}
+
+/// [`Service`] for basic authentication, see [`BasicAuthLayer`].
+pub struct BasicAuthService<S, V> {
+    inner: S,
+    verifier: Arc<V>,
+    realm: &'static str,
+}
+
+impl<S, V> fmt::Debug for BasicAuthService<S, V>
+where
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BasicAuthService")
+            .field("inner", &self.inner)
+            .field("realm", &self.realm)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S, V> Clone for BasicAuthService<S, V>
+where
+    S: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            verifier: self.verifier.clone(),
+            realm: self.realm,
+        }
+    }
+}
+
+impl<S, V, ReqBody, ResBody> Service<Request<ReqBody>> for BasicAuthService<S, V>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>> + Clone + Send + 'static,
+    S::Future: Send,
+    S::Error: Send,
+    V: BasicVerifier,
+    ReqBody: Send + 'static,
+    ResBody: Default,
+{
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+    type Response = S::Response;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let verifier = self.verifier.clone();
+        let realm = self.realm;
+
+        Box::pin(async move {
+            if let Some((username, password)) = parse_basic(req.headers()) {
+                if verifier.verify(username.clone(), password).await {
+                    req.extensions_mut().insert(BasicUser(username));
+
+                    return inner.call(req).await;
+                }
+
+                #[cfg(feature = "feat-tracing")]
+                tracing::debug!("Rejected invalid credentials of `{username}`");
+            }
+
+            let mut response = Response::new(ResBody::default());
+            *response.status_mut() = StatusCode::UNAUTHORIZED;
+
+            if let Ok(value) =
+                HeaderValue::try_from(format!(r#"Basic realm="{realm}", charset="UTF-8""#))
+            {
+                response
+                    .headers_mut()
+                    .insert(header::WWW_AUTHENTICATE, value);
+            }
+
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_basic() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(parse_basic(&headers), None);
+
+        // alice:p:w
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("basic YWxpY2U6cDp3"),
+        );
+        assert_eq!(
+            parse_basic(&headers),
+            Some(("alice".to_owned(), "p:w".to_owned()))
+        );
+
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer YWxpY2U6cDp3"),
+        );
+        assert_eq!(parse_basic(&headers), None);
+    }
+
+    #[tokio::test]
+    async fn test_basic_auth_layer() {
+        let echo = tower::service_fn(|req: Request<()>| {
+            let BasicUser(username) = req.extensions().get::<BasicUser>().unwrap();
+
+            std::future::ready(Ok::<_, Infallible>(Response::new(username.clone())))
+        });
+        let mut service = BasicAuthLayer::new(|username: String, password: String| async move {
+            username == "alice" && password == "p:w"
+        })
+        .with_realm("admin")
+        .layer(echo);
+
+        let req = Request::get("/")
+            .header(header::AUTHORIZATION, "Basic YWxpY2U6cDp3")
+            .body(())
+            .unwrap();
+        let response = service.call(req).await.unwrap();
+        assert_eq!(response.body(), "alice");
+
+        // alice:wrong
+        let req = Request::get("/")
+            .header(header::AUTHORIZATION, "Basic YWxpY2U6d3Jvbmc=")
+            .body(())
+            .unwrap();
+        let response = service.call(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            response.headers()[header::WWW_AUTHENTICATE],
+            r#"Basic realm="admin", charset="UTF-8""#
+        );
+    }
+}
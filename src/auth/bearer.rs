@@ -0,0 +1,373 @@
+//! Client-side bearer token management, see [`TokenManager`], and the tower
+//! [`BearerAuthLayer`] injecting `Authorization: Bearer ...`.
+
+use std::{
+    fmt,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use http::{header::AUTHORIZATION, HeaderValue, Request, StatusCode};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::{error::BoxError, response::ResponseExt, BoxFuture};
+
+#[derive(Clone, PartialEq, Eq)]
+/// An access token.
+pub struct Token {
+    /// The access token.
+    pub access_token: String,
+
+    /// When the token expires, if known.
+    pub expires_at: Option<Instant>,
+}
+
+impl fmt::Debug for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Token")
+            .field("access_token", &"[REDACTED]")
+            .field("expires_at", &self.expires_at)
+            .finish()
+    }
+}
+
+impl Token {
+    #[inline]
+    /// Create a new [`Token`] never expiring.
+    pub fn new(access_token: impl Into<String>) -> Self {
+        Self {
+            access_token: access_token.into(),
+            expires_at: None,
+        }
+    }
+
+    #[inline]
+    /// Set the token to expire after `expires_in` from now.
+    pub fn with_expires_in(self, expires_in: Duration) -> Self {
+        Self {
+            expires_at: Some(Instant::now() + expires_in),
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Returns if the token expires within `skew` from now.
+    pub fn expires_within(&self, skew: Duration) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= Instant::now() + skew)
+    }
+}
+
+/// Source of access tokens, e.g. the `OAuth2` flows in
+/// `auth::oauth2`.
+pub trait TokenSource: Send + Sync + 'static {
+    /// Fetch a fresh token.
+    ///
+    /// # Errors
+    ///
+    /// Any error fetching the token.
+    fn fetch(&self) -> BoxFuture<'_, Result<Token, BoxError>>;
+}
+
+impl TokenSource for Token {
+    fn fetch(&self) -> BoxFuture<'_, Result<Token, BoxError>> {
+        Box::pin(std::future::ready(Ok(self.clone())))
+    }
+}
+
+struct TokenManagerInner {
+    source: Box<dyn TokenSource>,
+    refresh_before: Duration,
+    token: tokio::sync::Mutex<Option<Token>>,
+}
+
+#[derive(Clone)]
+/// Caches the token of a [`TokenSource`], refreshing it before expiry.
+///
+/// Cheap to clone, clones share the cached token. Concurrent refreshes are
+/// coalesced.
+pub struct TokenManager {
+    inner: Arc<TokenManagerInner>,
+}
+
+impl fmt::Debug for TokenManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenManager")
+            .field("refresh_before", &self.inner.refresh_before)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TokenManager {
+    /// Default time before expiry to refresh the token.
+    pub const DEFAULT_REFRESH_BEFORE: Duration = Duration::from_secs(60);
+
+    #[inline]
+    /// Create a new [`TokenManager`].
+    pub fn new<T>(source: T) -> Self
+    where
+        T: TokenSource,
+    {
+        Self::with_refresh_before(source, Self::DEFAULT_REFRESH_BEFORE)
+    }
+
+    #[inline]
+    /// Create a new [`TokenManager`], refreshing the token `refresh_before`
+    /// its expiry.
+    pub fn with_refresh_before<T>(source: T, refresh_before: Duration) -> Self
+    where
+        T: TokenSource,
+    {
+        Self {
+            inner: Arc::new(TokenManagerInner {
+                source: Box::new(source),
+                refresh_before,
+                token: tokio::sync::Mutex::new(None),
+            }),
+        }
+    }
+
+    /// Returns the cached access token, or fetches a fresh one if there's none
+    /// or it's about to expire.
+    ///
+    /// # Errors
+    ///
+    /// Any error fetching the token.
+    pub async fn access_token(&self) -> Result<String, BoxError> {
+        let mut token = self.inner.token.lock().await;
+
+        match &*token {
+            Some(token) if !token.expires_within(self.inner.refresh_before) => {
+                Ok(token.access_token.clone())
+            }
+            _ => {
+                #[cfg(feature = "feat-tracing")]
+                tracing::debug!("Fetching fresh token");
+
+                let fresh = self.inner.source.fetch().await?;
+                let access_token = fresh.access_token.clone();
+
+                *token = Some(fresh);
+
+                Ok(access_token)
+            }
+        }
+    }
+
+    /// Drop the cached token if it's still `stale`, e.g. rejected with `401`.
+    pub async fn invalidate(&self, stale: &str) {
+        let mut token = self.inner.token.lock().await;
+
+        if token
+            .as_ref()
+            .is_some_and(|token| token.access_token == stale)
+        {
+            *token = None;
+        }
+    }
+}
+
+#[derive(Debug)]
+#[derive(thiserror::Error)]
+/// Error returned by [`BearerAuthService`].
+pub enum BearerAuthError<E> {
+    #[error("failed to fetch token: {0}")]
+    /// Failed to fetch the token.
+    Token(BoxError),
+
+    #[error(transparent)]
+    /// Error of the inner service.
+    Inner(E),
+}
+
+#[derive(Debug, Clone)]
+/// [`Layer`] injecting `Authorization: Bearer ...` with the token of the
+/// [`TokenManager`].
+///
+/// On `401 Unauthorized`, the token is invalidated and the request is retried
+/// once with a fresh token.
+pub struct BearerAuthLayer {
+    manager: TokenManager,
+}
+
+impl BearerAuthLayer {
+    #[inline]
+    /// Create a new [`BearerAuthLayer`].
+    pub const fn new(manager: TokenManager) -> Self {
+        Self { manager }
+    }
+}
+
+impl<S> Layer<S> for BearerAuthLayer {
+    type Service = BearerAuthService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        BearerAuthService {
+            inner,
+            manager: self.manager.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// [`Service`] injecting `Authorization: Bearer ...`, see
+/// [`BearerAuthLayer`].
+pub struct BearerAuthService<S> {
+    inner: S,
+    manager: TokenManager,
+}
+
+impl<S> Service<Request<Bytes>> for BearerAuthService<S>
+where
+    S: Service<Request<Bytes>, Response = ResponseExt> + Clone + Send + 'static,
+    S::Future: Send,
+    S::Error: Send,
+{
+    type Error = BearerAuthError<S::Error>;
+    type Future = BoxFuture<'static, Result<ResponseExt, Self::Error>>;
+    type Response = ResponseExt;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(BearerAuthError::Inner)
+    }
+
+    fn call(&mut self, req: Request<Bytes>) -> Self::Future {
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let manager = self.manager.clone();
+
+        Box::pin(async move {
+            let mut retry = clone_request(&req);
+
+            let access_token = manager
+                .access_token()
+                .await
+                .map_err(BearerAuthError::Token)?;
+            let response = inner
+                .call(authorize(req, &access_token)?)
+                .await
+                .map_err(BearerAuthError::Inner)?;
+
+            if response.response_parts.status != StatusCode::UNAUTHORIZED {
+                return Ok(response);
+            }
+
+            #[cfg(feature = "feat-tracing")]
+            tracing::warn!("Token rejected with 401, retrying with a fresh token");
+
+            manager.invalidate(&access_token).await;
+
+            let access_token = manager
+                .access_token()
+                .await
+                .map_err(BearerAuthError::Token)?;
+            retry = authorize(retry, &access_token)?;
+
+            std::future::poll_fn(|cx| inner.poll_ready(cx))
+                .await
+                .map_err(BearerAuthError::Inner)?;
+            inner.call(retry).await.map_err(BearerAuthError::Inner)
+        })
+    }
+}
+
+/// Set `Authorization: Bearer ...` of the request.
+fn authorize<B, E>(
+    mut req: Request<B>,
+    access_token: &str,
+) -> Result<Request<B>, BearerAuthError<E>> {
+    let mut value = HeaderValue::try_from(format!("Bearer {access_token}"))
+        .map_err(|e| BearerAuthError::Token(e.into()))?;
+    value.set_sensitive(true);
+
+    req.headers_mut().insert(AUTHORIZATION, value);
+
+    Ok(req)
+}
+
+/// Clone the request, without extensions.
+fn clone_request(req: &Request<Bytes>) -> Request<Bytes> {
+    let mut request = Request::new(req.body().clone());
+    *request.method_mut() = req.method().clone();
+    *request.uri_mut() = req.uri().clone();
+    *request.version_mut() = req.version();
+    *request.headers_mut() = req.headers().clone();
+    request
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex,
+    };
+
+    use super::*;
+
+    /// Issues `token-1`, `token-2`, ...
+    struct Counter(AtomicU32);
+
+    impl TokenSource for Counter {
+        fn fetch(&self) -> BoxFuture<'_, Result<Token, BoxError>> {
+            let n = self.0.fetch_add(1, Ordering::Relaxed) + 1;
+
+            Box::pin(async move { Ok(Token::new(format!("token-{n}"))) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_token_manager() {
+        let manager = TokenManager::new(Counter(AtomicU32::new(0)));
+
+        assert_eq!(manager.access_token().await.unwrap(), "token-1");
+        assert_eq!(manager.access_token().await.unwrap(), "token-1");
+
+        manager.invalidate("token-0").await;
+        assert_eq!(manager.access_token().await.unwrap(), "token-1");
+
+        manager.invalidate("token-1").await;
+        assert_eq!(manager.access_token().await.unwrap(), "token-2");
+
+        let token = Token::new("t").with_expires_in(Duration::from_secs(30));
+        assert!(token.expires_within(TokenManager::DEFAULT_REFRESH_BEFORE));
+        assert!(!token.expires_within(Duration::ZERO));
+        assert!(!format!("{token:?}").contains("\"t\""));
+    }
+
+    #[tokio::test]
+    async fn test_bearer_auth_layer() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+
+        // Accepts `token-2` only.
+        let upstream = tower::service_fn({
+            let seen = seen.clone();
+
+            move |req: Request<Bytes>| {
+                let authorization = req.headers()[AUTHORIZATION].to_str().unwrap().to_owned();
+                let status = if authorization == "Bearer token-2" {
+                    200
+                } else {
+                    401
+                };
+
+                seen.lock().unwrap().push(authorization);
+
+                std::future::ready(ResponseExt::builder().status(status).build())
+            }
+        });
+
+        let manager = TokenManager::new(Counter(AtomicU32::new(0)));
+        let mut service = BearerAuthLayer::new(manager).layer(upstream);
+
+        let response = service
+            .call(Request::new(Bytes::from_static(b"payload")))
+            .await
+            .unwrap();
+
+        assert_eq!(response.response_parts.status, 200);
+        assert_eq!(*seen.lock().unwrap(), ["Bearer token-1", "Bearer token-2"]);
+    }
+}
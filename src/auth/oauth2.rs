@@ -0,0 +1,329 @@
+//! `OAuth2` token flows: [`ClientCredentials`] and [`RefreshToken`], as
+//! [`TokenSource`]s for [`TokenManager`](super::bearer::TokenManager).
+//!
+//! Token requests are sent with the given HTTP client service.
+
+use std::{
+    fmt,
+    sync::{Mutex, PoisonError},
+    time::Duration,
+};
+
+use bytes::Bytes;
+use http::{header, Method, Request, Uri};
+use tower_service::Service;
+
+use super::bearer::{Token, TokenSource};
+use crate::{
+    error::BoxError,
+    percent,
+    response::{JsonError, ResponseExt, StatusError},
+    BoxFuture,
+};
+
+#[derive(Debug)]
+#[derive(thiserror::Error)]
+/// Error fetching the token with the `OAuth2` flows.
+pub enum OAuth2Error {
+    #[error("invalid token request: {0}")]
+    /// Failed to build the token request.
+    Request(#[from] http::Error),
+
+    #[error("token request failed: {0}")]
+    /// Error of the HTTP client service.
+    Service(#[source] BoxError),
+
+    #[error("token request failed: {0}")]
+    /// The token endpoint responded with an error status.
+    Status(#[from] StatusError),
+
+    #[error("invalid token response: {0}")]
+    /// The token response is not valid JSON, or missing `access_token`.
+    InvalidResponse(#[from] JsonError),
+}
+
+#[derive(Debug, serde::Deserialize)]
+/// Successful token response, RFC 6749, 5.1.
+struct TokenResponse {
+    access_token: String,
+
+    #[serde(default)]
+    expires_in: Option<u64>,
+
+    #[serde(default)]
+    refresh_token: Option<String>,
+}
+
+#[derive(Clone)]
+/// The client-credentials flow, RFC 6749, 4.4.
+pub struct ClientCredentials<S> {
+    service: S,
+    token_endpoint: Uri,
+    client_id: String,
+    client_secret: String,
+    scope: Option<String>,
+}
+
+impl<S> fmt::Debug for ClientCredentials<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClientCredentials")
+            .field("token_endpoint", &self.token_endpoint)
+            .field("client_id", &self.client_id)
+            .field("scope", &self.scope)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S> ClientCredentials<S> {
+    #[inline]
+    /// Create a new [`ClientCredentials`] flow.
+    pub fn new(
+        service: S,
+        token_endpoint: Uri,
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+    ) -> Self {
+        Self {
+            service,
+            token_endpoint,
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+            scope: None,
+        }
+    }
+
+    #[inline]
+    /// Set the requested scope.
+    pub fn with_scope(self, scope: impl Into<String>) -> Self {
+        Self {
+            scope: Some(scope.into()),
+            ..self
+        }
+    }
+}
+
+impl<S> TokenSource for ClientCredentials<S>
+where
+    S: Service<Request<Bytes>, Response = ResponseExt> + Clone + Send + Sync + 'static,
+    S::Future: Send,
+    S::Error: Into<BoxError>,
+{
+    fn fetch(&self) -> BoxFuture<'_, Result<Token, BoxError>> {
+        Box::pin(async move {
+            let mut form = vec![
+                ("grant_type", "client_credentials"),
+                ("client_id", &self.client_id),
+                ("client_secret", &self.client_secret),
+            ];
+            if let Some(scope) = &self.scope {
+                form.push(("scope", scope));
+            }
+
+            let response = request_token(self.service.clone(), &self.token_endpoint, &form).await?;
+
+            Ok(response.into())
+        })
+    }
+}
+
+/// The refresh-token flow, RFC 6749, 6.
+///
+/// The refresh token is updated if the server rotates it.
+pub struct RefreshToken<S> {
+    service: S,
+    token_endpoint: Uri,
+    client_id: String,
+    client_secret: Option<String>,
+    refresh_token: Mutex<String>,
+}
+
+impl<S> fmt::Debug for RefreshToken<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RefreshToken")
+            .field("token_endpoint", &self.token_endpoint)
+            .field("client_id", &self.client_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S> RefreshToken<S> {
+    #[inline]
+    /// Create a new [`RefreshToken`] flow.
+    pub fn new(
+        service: S,
+        token_endpoint: Uri,
+        client_id: impl Into<String>,
+        refresh_token: impl Into<String>,
+    ) -> Self {
+        Self {
+            service,
+            token_endpoint,
+            client_id: client_id.into(),
+            client_secret: None,
+            refresh_token: Mutex::new(refresh_token.into()),
+        }
+    }
+
+    #[inline]
+    /// Set the client secret, for confidential clients.
+    pub fn with_client_secret(self, client_secret: impl Into<String>) -> Self {
+        Self {
+            client_secret: Some(client_secret.into()),
+            ..self
+        }
+    }
+
+    /// Returns the current refresh token.
+    pub fn refresh_token(&self) -> String {
+        self.refresh_token
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+impl<S> TokenSource for RefreshToken<S>
+where
+    S: Service<Request<Bytes>, Response = ResponseExt> + Clone + Send + Sync + 'static,
+    S::Future: Send,
+    S::Error: Into<BoxError>,
+{
+    fn fetch(&self) -> BoxFuture<'_, Result<Token, BoxError>> {
+        Box::pin(async move {
+            let refresh_token = self.refresh_token();
+
+            let mut form = vec![
+                ("grant_type", "refresh_token"),
+                ("refresh_token", &refresh_token),
+                ("client_id", &self.client_id),
+            ];
+            if let Some(client_secret) = &self.client_secret {
+                form.push(("client_secret", client_secret));
+            }
+
+            let mut response =
+                request_token(self.service.clone(), &self.token_endpoint, &form).await?;
+
+            if let Some(rotated) = response.refresh_token.take() {
+                *self
+                    .refresh_token
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = rotated;
+            }
+
+            Ok(response.into())
+        })
+    }
+}
+
+impl From<TokenResponse> for Token {
+    fn from(response: TokenResponse) -> Self {
+        let token = Self::new(response.access_token);
+
+        match response.expires_in {
+            Some(expires_in) => token.with_expires_in(Duration::from_secs(expires_in)),
+            None => token,
+        }
+    }
+}
+
+/// Send the token request with the form.
+async fn request_token<S>(
+    mut service: S,
+    token_endpoint: &Uri,
+    form: &[(&str, &str)],
+) -> Result<TokenResponse, OAuth2Error>
+where
+    S: Service<Request<Bytes>, Response = ResponseExt>,
+    S::Error: Into<BoxError>,
+{
+    let body = form
+        .iter()
+        .map(|(k, v)| {
+            format!(
+                "{}={}",
+                percent::encode(k, percent::FORM),
+                percent::encode(v, percent::FORM)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri(token_endpoint)
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .header(header::ACCEPT, "application/json")
+        .body(Bytes::from(body))?;
+
+    std::future::poll_fn(|cx| service.poll_ready(cx))
+        .await
+        .map_err(|e| OAuth2Error::Service(e.into()))?;
+
+    let response = service
+        .call(request)
+        .await
+        .map_err(|e| OAuth2Error::Service(e.into()))?
+        .error_for_status()?;
+
+    Ok(response.json::<TokenResponse>()?.body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::response::BuildError;
+
+    fn token_endpoint(req: Request<Bytes>) -> std::future::Ready<Result<ResponseExt, BuildError>> {
+        let body = std::str::from_utf8(req.body()).unwrap();
+
+        let response = if body.starts_with("grant_type=client_credentials") {
+            assert!(body.ends_with("&scope=read%20write"));
+
+            ResponseExt::builder().text(r#"{"access_token":"a","expires_in":3600}"#)
+        } else if body.starts_with("grant_type=refresh_token&refresh_token=r1") {
+            ResponseExt::builder().text(r#"{"access_token":"b","refresh_token":"r2"}"#)
+        } else {
+            ResponseExt::builder()
+                .status(400)
+                .text(r#"{"error":"invalid_grant"}"#)
+        };
+
+        std::future::ready(response.build())
+    }
+
+    #[tokio::test]
+    async fn test_client_credentials() {
+        let token = ClientCredentials::new(
+            tower::service_fn(token_endpoint),
+            Uri::from_static("https://auth.example.com/token"),
+            "id",
+            "secret",
+        )
+        .with_scope("read write")
+        .fetch()
+        .await
+        .unwrap();
+
+        assert_eq!(token.access_token, "a");
+        assert!(!token.expires_within(Duration::from_secs(60)));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_token() {
+        let flow = RefreshToken::new(
+            tower::service_fn(token_endpoint),
+            Uri::from_static("https://auth.example.com/token"),
+            "id",
+            "r1",
+        );
+
+        let token = flow.fetch().await.unwrap();
+        assert_eq!(token.access_token, "b");
+        assert_eq!(token.expires_at, None);
+        assert_eq!(flow.refresh_token(), "r2");
+
+        let e = flow.fetch().await.unwrap_err();
+        assert!(e.to_string().contains("invalid_grant"));
+    }
+}
@@ -0,0 +1,13 @@
+//! `http-body` wrappers for both request and response bodies.
+
+#[cfg(feature = "feat-body-progress")]
+pub mod progress;
+#[cfg(feature = "feat-body-throttle")]
+pub mod throttle;
+
+#[cfg(feature = "feat-body-progress")]
+// re-export
+pub use self::progress::ProgressBody;
+#[cfg(feature = "feat-body-throttle")]
+// re-export
+pub use self::throttle::{Bandwidth, ThrottledBody};
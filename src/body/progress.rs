@@ -0,0 +1,143 @@
+//! Progress reporting of bodies, see [`ProgressBody`].
+
+use std::{
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use bytes::Buf;
+use http_body::{Body, Frame, SizeHint};
+
+#[derive(Debug)]
+/// Body invoking the callback with `(bytes_transferred, total_hint)` per data
+/// frame, e.g. for rendering progress bars.
+///
+/// The total hint is the exact [`SizeHint`] of the body when wrapped, if any,
+/// see [`with_total`](Self::with_total) for overriding it.
+///
+/// ```rust,no_run
+/// # fn example<B: http_body::Body>(body: B) {
+/// use miku_http_util::body::ProgressBody;
+///
+/// let body = ProgressBody::new(body, |transferred, total| match total {
+///     Some(total) => eprint!("\r{transferred} / {total} bytes"),
+///     None => eprint!("\r{transferred} bytes"),
+/// });
+/// # }
+/// ```
+pub struct ProgressBody<B, F> {
+    inner: Pin<Box<B>>,
+    callback: F,
+    transferred: u64,
+    total: Option<u64>,
+}
+
+impl<B, F> ProgressBody<B, F>
+where
+    B: Body,
+    F: FnMut(u64, Option<u64>),
+{
+    #[inline]
+    /// Wrap the body with the callback.
+    pub fn new(inner: B, callback: F) -> Self {
+        let total = inner.size_hint().exact();
+
+        Self {
+            inner: Box::pin(inner),
+            callback,
+            transferred: 0,
+            total,
+        }
+    }
+}
+
+impl<B, F> ProgressBody<B, F> {
+    #[inline]
+    #[must_use]
+    /// Set the total hint, e.g. from `Content-Length` of the response.
+    pub fn with_total(self, total: Option<u64>) -> Self {
+        Self { total, ..self }
+    }
+
+    #[inline]
+    /// Returns the bytes transferred so far.
+    pub const fn transferred(&self) -> u64 {
+        self.transferred
+    }
+
+    #[inline]
+    /// Returns the total hint, if any.
+    pub const fn total(&self) -> Option<u64> {
+        self.total
+    }
+}
+
+impl<B, F> Body for ProgressBody<B, F>
+where
+    B: Body,
+    F: FnMut(u64, Option<u64>) + Unpin,
+{
+    type Data = B::Data;
+    type Error = B::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let frame = ready!(self.inner.as_mut().poll_frame(cx));
+
+        if let Some(data) = frame
+            .as_ref()
+            .and_then(|frame| frame.as_ref().ok())
+            .and_then(Frame::data_ref)
+        {
+            let this = &mut *self;
+
+            this.transferred += data.remaining() as u64;
+            (this.callback)(this.transferred, this.total);
+        }
+
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{convert::Infallible, future::poll_fn};
+
+    use bytes::Bytes;
+    use http_body_util::StreamBody;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_progress_body() {
+        let chunks = ["hello", " ", "world"]
+            .map(|chunk| Ok::<_, Infallible>(Frame::data(Bytes::from(chunk))));
+
+        let mut progress = Vec::new();
+        let mut body = ProgressBody::new(
+            StreamBody::new(futures_util::stream::iter(chunks)),
+            |transferred, total| progress.push((transferred, total)),
+        )
+        .with_total(Some(11));
+
+        assert_eq!(body.total(), Some(11));
+
+        while let Some(frame) = poll_fn(|cx| Pin::new(&mut body).poll_frame(cx)).await {
+            frame.unwrap();
+        }
+
+        assert_eq!(body.transferred(), 11);
+        drop(body);
+        assert_eq!(progress, [(5, Some(11)), (6, Some(11)), (11, Some(11))]);
+    }
+}
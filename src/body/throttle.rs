@@ -0,0 +1,228 @@
+//! Bandwidth throttling of bodies, see [`ThrottledBody`].
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, PoisonError},
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+
+use bytes::Buf;
+use http_body::{Body, Frame, SizeHint};
+use tokio::time::{Instant, Sleep};
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+#[derive(Debug, Clone)]
+/// Bandwidth limit in bytes per second: a token bucket of bytes.
+///
+/// Cheap to clone, clones share the bucket, so that a limit can be shared by
+/// multiple bodies, e.g. all the downloads of a scraper.
+pub struct Bandwidth {
+    rate: u64,
+    burst: u64,
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+impl Bandwidth {
+    /// Create a new [`Bandwidth`] of `rate` bytes per second (at least 1),
+    /// bursting up to `rate` bytes.
+    pub fn new(rate: u64) -> Self {
+        let rate = rate.max(1);
+
+        Self {
+            rate,
+            burst: rate,
+            bucket: Arc::new(Mutex::new(Bucket {
+                #[allow(clippy::cast_precision_loss, reason = "bytes per second")]
+                tokens: rate as f64,
+                updated: Instant::now(),
+            })),
+        }
+    }
+
+    #[must_use]
+    /// Set the max burst in bytes (at least 1), i.e. the capacity of the
+    /// bucket. The bucket is refilled to it.
+    pub fn with_burst(self, burst: u64) -> Self {
+        let burst = burst.max(1);
+
+        #[allow(clippy::cast_precision_loss, reason = "bytes")]
+        {
+            let mut bucket = self.bucket.lock().unwrap_or_else(PoisonError::into_inner);
+            bucket.tokens = burst as f64;
+            bucket.updated = Instant::now();
+        }
+
+        Self { burst, ..self }
+    }
+
+    #[inline]
+    /// Returns the rate in bytes per second.
+    pub const fn rate(&self) -> u64 {
+        self.rate
+    }
+
+    #[inline]
+    /// Returns the max burst in bytes.
+    pub const fn burst(&self) -> u64 {
+        self.burst
+    }
+
+    #[allow(clippy::cast_precision_loss, reason = "bytes")]
+    /// Refill the bucket, returns the time to wait until it's out of debt.
+    fn delay(&self) -> Option<Duration> {
+        let now = Instant::now();
+        let rate = self.rate as f64;
+
+        let mut bucket = self.bucket.lock().unwrap_or_else(PoisonError::into_inner);
+        bucket.tokens = (bucket.tokens + now.duration_since(bucket.updated).as_secs_f64() * rate)
+            .min(self.burst as f64);
+        bucket.updated = now;
+
+        (bucket.tokens < 0.0).then(|| Duration::from_secs_f64(-bucket.tokens / rate))
+    }
+
+    #[allow(clippy::cast_precision_loss, reason = "bytes")]
+    /// Take the tokens, going into debt if not enough.
+    fn consume(&self, bytes: usize) {
+        self.bucket
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .tokens -= bytes as f64;
+    }
+}
+
+#[derive(Debug)]
+/// Body throttled by the [`Bandwidth`], for both uploading (request bodies)
+/// and downloading (response bodies).
+///
+/// Data frames are not split: a frame larger than the available tokens is
+/// yielded at once, then the next frame waits until the debt is repaid, so
+/// the average rate is kept.
+///
+/// ```rust,no_run
+/// # fn example<B: http_body::Body>(body: B) {
+/// use miku_http_util::body::{Bandwidth, ThrottledBody};
+///
+/// // 64 KiB/s, bursting up to 16 KiB.
+/// let bandwidth = Bandwidth::new(64 * 1024).with_burst(16 * 1024);
+/// let body = ThrottledBody::new(body, bandwidth.clone());
+/// # }
+/// ```
+pub struct ThrottledBody<B> {
+    inner: Pin<Box<B>>,
+    bandwidth: Bandwidth,
+    sleep: Option<Pin<Box<Sleep>>>,
+}
+
+impl<B> ThrottledBody<B> {
+    #[inline]
+    /// Wrap the body with the [`Bandwidth`].
+    pub fn new(inner: B, bandwidth: Bandwidth) -> Self {
+        Self {
+            inner: Box::pin(inner),
+            bandwidth,
+            sleep: None,
+        }
+    }
+
+    #[inline]
+    /// Returns the [`Bandwidth`].
+    pub const fn bandwidth(&self) -> &Bandwidth {
+        &self.bandwidth
+    }
+}
+
+impl<B> Body for ThrottledBody<B>
+where
+    B: Body,
+{
+    type Data = B::Data;
+    type Error = B::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        loop {
+            if let Some(sleep) = &mut self.sleep {
+                ready!(sleep.as_mut().poll(cx));
+                self.sleep = None;
+            }
+
+            match self.bandwidth.delay() {
+                Some(delay) => self.sleep = Some(Box::pin(tokio::time::sleep(delay))),
+                None => break,
+            }
+        }
+
+        let frame = ready!(self.inner.as_mut().poll_frame(cx));
+
+        if let Some(data) = frame
+            .as_ref()
+            .and_then(|frame| frame.as_ref().ok())
+            .and_then(Frame::data_ref)
+        {
+            self.bandwidth.consume(data.remaining());
+        }
+
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{convert::Infallible, future::poll_fn};
+
+    use bytes::Bytes;
+    use http_body_util::StreamBody;
+
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_throttled_body() {
+        let chunks = (0..3).map(|_| Ok::<_, Infallible>(Frame::data(Bytes::from(vec![0; 100]))));
+        let mut body = ThrottledBody::new(
+            StreamBody::new(futures_util::stream::iter(chunks)),
+            Bandwidth::new(1000).with_burst(100),
+        );
+
+        let start = Instant::now();
+        let mut received = 0;
+
+        while let Some(frame) = poll_fn(|cx| Pin::new(&mut body).poll_frame(cx)).await {
+            received += frame.unwrap().into_data().unwrap().len();
+        }
+
+        // The first chunk is within the burst, then 100 ms per chunk.
+        assert_eq!(received, 300);
+        assert_eq!(start.elapsed(), Duration::from_millis(200));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_bandwidth() {
+        let bandwidth = Bandwidth::new(0).with_burst(0);
+        assert_eq!((bandwidth.rate(), bandwidth.burst()), (1, 1));
+        assert_eq!(bandwidth.delay(), None);
+
+        bandwidth.consume(3);
+        assert_eq!(bandwidth.delay(), Some(Duration::from_secs(2)));
+
+        tokio::time::advance(Duration::from_millis(500)).await;
+        assert_eq!(bandwidth.delay(), Some(Duration::from_millis(1500)));
+    }
+}
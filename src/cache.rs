@@ -0,0 +1,597 @@
+//! HTTP cache for clients (RFC 9111 subset), see [`CacheLayer`] and the
+//! pluggable [`CacheStore`].
+//!
+//! Freshness and storability follow [`CachePolicy`]. Stale responses with
+//! validators are revalidated with conditional requests, and responses with
+//! `Vary` are stored as variants of the same resource.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, PoisonError},
+    task::{Context, Poll},
+    time::SystemTime,
+};
+
+use bytes::Bytes;
+use http::{header, HeaderMap, HeaderValue, Method, Request, StatusCode};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::{
+    response::{cache::vary_key, CacheControl, CachePolicy, ResponseExt},
+    BoxFuture,
+};
+
+#[derive(Debug, Clone)]
+/// A stored response.
+pub struct CacheEntry {
+    /// The request method.
+    pub method: Method,
+
+    /// The request headers listed in `Vary` of the response, for selecting
+    /// the variant.
+    pub varied_headers: HeaderMap,
+
+    /// The response.
+    pub response: ResponseExt,
+
+    /// When the response was received.
+    pub response_time: SystemTime,
+}
+
+impl CacheEntry {
+    /// Create a new [`CacheEntry`], keeping only the varied request headers.
+    pub fn new(
+        method: Method,
+        request_headers: &HeaderMap,
+        response: ResponseExt,
+        response_time: SystemTime,
+    ) -> Self {
+        let mut varied_headers = HeaderMap::new();
+
+        for name in response
+            .response_parts
+            .headers
+            .get_all(header::VARY)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .filter_map(|name| header::HeaderName::try_from(name.trim()).ok())
+        {
+            for value in request_headers.get_all(&name) {
+                varied_headers.append(name.clone(), value.clone());
+            }
+        }
+
+        Self {
+            method,
+            varied_headers,
+            response,
+            response_time,
+        }
+    }
+
+    /// The [`CachePolicy`] of the stored response.
+    pub fn policy(&self, shared: bool) -> CachePolicy {
+        let (mut request, ()) = Request::new(()).into_parts();
+        request.method = self.method.clone();
+        request.headers = self.varied_headers.clone();
+
+        CachePolicy::new(&request, &self.response.response_parts, self.response_time).shared(shared)
+    }
+
+    /// Whether the stored response is the variant for the request headers.
+    pub fn matches(&self, request_headers: &HeaderMap) -> bool {
+        let headers = &self.response.response_parts.headers;
+
+        vary_key(headers, request_headers)
+            .is_some_and(|key| Some(key) == vary_key(headers, &self.varied_headers))
+    }
+}
+
+/// Storage of [`CacheEntry`], keyed by the request method and URI. All
+/// variants of a resource are stored under the same key.
+///
+/// Errors should be handled (e.g. logged) by the implementation, since a
+/// cache miss is always safe.
+pub trait CacheStore: Send + Sync + 'static {
+    /// Get all stored variants.
+    fn get(&self, key: &str) -> BoxFuture<'_, Vec<CacheEntry>>;
+
+    /// Replace all stored variants, or remove them if `entries` is empty.
+    fn put(&self, key: &str, entries: Vec<CacheEntry>) -> BoxFuture<'_, ()>;
+}
+
+impl<St: CacheStore> CacheStore for Arc<St> {
+    fn get(&self, key: &str) -> BoxFuture<'_, Vec<CacheEntry>> {
+        (**self).get(key)
+    }
+
+    fn put(&self, key: &str, entries: Vec<CacheEntry>) -> BoxFuture<'_, ()> {
+        (**self).put(key, entries)
+    }
+}
+
+#[derive(Debug)]
+/// In-memory [`CacheStore`] evicting the least recently used resource.
+pub struct MemoryStore {
+    capacity: usize,
+    inner: Mutex<MemoryStoreInner>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryStoreInner {
+    tick: u64,
+    entries: HashMap<String, (u64, Vec<CacheEntry>)>,
+}
+
+impl MemoryStore {
+    #[inline]
+    /// Create a new [`MemoryStore`] holding at most `capacity` resources.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            inner: Mutex::default(),
+        }
+    }
+
+    /// Returns the number of stored resources.
+    pub fn len(&self) -> usize {
+        self.inner
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entries
+            .len()
+    }
+
+    #[inline]
+    /// Returns `true` if nothing is stored.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl CacheStore for MemoryStore {
+    fn get(&self, key: &str) -> BoxFuture<'_, Vec<CacheEntry>> {
+        let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+
+        inner.tick += 1;
+        let tick = inner.tick;
+
+        let entries = inner
+            .entries
+            .get_mut(key)
+            .map(|(last_used, entries)| {
+                *last_used = tick;
+                entries.clone()
+            })
+            .unwrap_or_default();
+
+        Box::pin(std::future::ready(entries))
+    }
+
+    fn put(&self, key: &str, entries: Vec<CacheEntry>) -> BoxFuture<'_, ()> {
+        let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if entries.is_empty() {
+            inner.entries.remove(key);
+        } else {
+            inner.tick += 1;
+            let tick = inner.tick;
+
+            inner.entries.insert(key.to_owned(), (tick, entries));
+
+            if inner.entries.len() > self.capacity {
+                let lru = inner
+                    .entries
+                    .iter()
+                    .min_by_key(|(_, (last_used, _))| *last_used)
+                    .map(|(key, _)| key.clone());
+
+                if let Some(lru) = lru {
+                    inner.entries.remove(&lru);
+                }
+            }
+        }
+
+        Box::pin(std::future::ready(()))
+    }
+}
+
+#[derive(Clone)]
+/// [`Layer`] caching the responses in the [`CacheStore`].
+///
+/// - Fresh stored responses are returned directly, with `Age` updated.
+/// - Stale ones are revalidated with `If-None-Match` / `If-Modified-Since`,
+///   and `304 Not Modified` refreshes the stored response.
+/// - Successful unsafe requests (e.g. `POST`) invalidate the stored `GET`
+///   responses of the URI.
+/// - `Cache-Control: no-store` / `no-cache` of the request bypasses the cache
+///   or forces revalidation respectively.
+pub struct CacheLayer {
+    store: Arc<dyn CacheStore>,
+    shared: bool,
+}
+
+impl std::fmt::Debug for CacheLayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CacheLayer")
+            .field("shared", &self.shared)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CacheLayer {
+    #[inline]
+    /// Create a new [`CacheLayer`] with the [`CacheStore`], as a private
+    /// cache.
+    pub fn new<St: CacheStore>(store: St) -> Self {
+        Self {
+            store: Arc::new(store),
+            shared: false,
+        }
+    }
+
+    #[inline]
+    /// Set whether the cache is a shared one, see [`CachePolicy::shared`].
+    pub fn with_shared(self, shared: bool) -> Self {
+        Self { shared, ..self }
+    }
+}
+
+impl<S> Layer<S> for CacheLayer {
+    type Service = CacheService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CacheService {
+            inner,
+            store: self.store.clone(),
+            shared: self.shared,
+        }
+    }
+}
+
+#[derive(Clone)]
+/// [`Service`] caching the responses, see [`CacheLayer`].
+pub struct CacheService<S> {
+    inner: S,
+    store: Arc<dyn CacheStore>,
+    shared: bool,
+}
+
+impl<S: std::fmt::Debug> std::fmt::Debug for CacheService<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CacheService")
+            .field("inner", &self.inner)
+            .field("shared", &self.shared)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The primary cache key of the request.
+fn cache_key(method: &Method, uri: &http::Uri) -> String {
+    format!("{method} {uri}")
+}
+
+/// Set `Age` of the stored response.
+fn with_age(mut entry: CacheEntry, policy: &CachePolicy, now: SystemTime) -> ResponseExt {
+    entry
+        .response
+        .response_parts
+        .headers
+        .insert(header::AGE, HeaderValue::from(policy.age(now).as_secs()));
+
+    entry.response
+}
+
+/// Update the stored response with the `304 Not Modified` one, see RFC 9111,
+/// section 4.3.4.
+fn freshen(entry: &mut CacheEntry, not_modified: &ResponseExt, response_time: SystemTime) {
+    let headers = &mut entry.response.response_parts.headers;
+
+    for name in not_modified.response_parts.headers.keys() {
+        if name == header::CONTENT_LENGTH {
+            continue;
+        }
+
+        headers.remove(name);
+
+        for value in not_modified.response_parts.headers.get_all(name) {
+            headers.append(name.clone(), value.clone());
+        }
+    }
+
+    entry.response_time = response_time;
+}
+
+/// Serve the `GET` / `HEAD` request from the cache, or the inner service.
+async fn serve<S>(
+    mut inner: S,
+    store: Arc<dyn CacheStore>,
+    shared: bool,
+    mut req: Request<Bytes>,
+) -> Result<ResponseExt, S::Error>
+where
+    S: Service<Request<Bytes>, Response = ResponseExt>,
+{
+    let key = cache_key(req.method(), req.uri());
+
+    let mut entries = store.get(&key).await;
+    let matched = entries
+        .iter()
+        .position(|entry| entry.matches(req.headers()));
+
+    let mut revalidating = false;
+
+    if let Some(entry) = matched.map(|index| &entries[index]) {
+        let policy = entry.policy(shared);
+        let now = crate::time::now();
+
+        if !CacheControl::from_headers(req.headers()).no_cache && policy.is_fresh(now) {
+            return Ok(with_age(entry.clone(), &policy, now));
+        }
+
+        // Leave the conditional requests of the caller as is.
+        if !req.headers().contains_key(header::IF_NONE_MATCH)
+            && !req.headers().contains_key(header::IF_MODIFIED_SINCE)
+        {
+            req.headers_mut().extend(policy.revalidation_headers());
+            revalidating = true;
+        }
+    }
+
+    let (mut request_parts, ()) = Request::new(()).into_parts();
+    request_parts.method = req.method().clone();
+    request_parts.headers = req.headers().clone();
+
+    let response = inner.call(req).await?;
+    let response_time = crate::time::now();
+
+    let not_modified = response.response_parts.status == StatusCode::NOT_MODIFIED;
+
+    let (response, entry) = match matched {
+        Some(index) if revalidating && not_modified => {
+            let mut entry = entries.swap_remove(index);
+            freshen(&mut entry, &response, response_time);
+
+            (entry.response.clone(), entry)
+        }
+        // `304 Not Modified` to the conditional request of the caller is not
+        // a complete response, never store it.
+        _ if not_modified => return Ok(response),
+        _ => {
+            let storable =
+                CachePolicy::new(&request_parts, &response.response_parts, response_time)
+                    .shared(shared)
+                    .is_storable();
+
+            if !storable {
+                return Ok(response);
+            }
+
+            if let Some(index) = matched {
+                entries.swap_remove(index);
+            }
+
+            let entry = CacheEntry::new(
+                request_parts.method,
+                &request_parts.headers,
+                response.clone(),
+                response_time,
+            );
+
+            (response, entry)
+        }
+    };
+
+    entries.push(entry);
+    store.put(&key, entries).await;
+
+    Ok(response)
+}
+
+impl<S> Service<Request<Bytes>> for CacheService<S>
+where
+    S: Service<Request<Bytes>, Response = ResponseExt> + Clone + Send + 'static,
+    S::Future: Send,
+    S::Error: Send,
+{
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<ResponseExt, Self::Error>>;
+    type Response = ResponseExt;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Bytes>) -> Self::Future {
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let store = self.store.clone();
+
+        if matches!(*req.method(), Method::GET | Method::HEAD)
+            && !CacheControl::from_headers(req.headers()).no_store
+        {
+            return Box::pin(serve(inner, store, self.shared, req));
+        }
+
+        // Unsafe methods invalidate the stored responses, see RFC 9111,
+        // section 4.4.
+        let invalidated = (!req.method().is_safe()).then(|| {
+            [
+                cache_key(&Method::GET, req.uri()),
+                cache_key(&Method::HEAD, req.uri()),
+            ]
+        });
+
+        Box::pin(async move {
+            let response = inner.call(req).await?;
+
+            let status = response.response_parts.status;
+            if let Some(keys) = invalidated {
+                if status.is_success() || status.is_redirection() {
+                    for key in keys {
+                        store.put(&key, Vec::new()).await;
+                    }
+                }
+            }
+
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use super::*;
+
+    /// Origin counting the requests, serving `ETag: "v1"` and echoing
+    /// `Accept-Language`.
+    fn origin(
+        calls: &Arc<AtomicUsize>,
+        cache_control: &'static str,
+    ) -> tower::util::ServiceFn<
+        impl FnMut(Request<Bytes>) -> std::future::Ready<Result<ResponseExt, Infallible>> + Clone,
+    > {
+        tower::service_fn({
+            let calls = calls.clone();
+
+            move |req: Request<Bytes>| {
+                calls.fetch_add(1, Ordering::Relaxed);
+
+                let builder = ResponseExt::builder()
+                    .header(header::CACHE_CONTROL, cache_control)
+                    .header(header::ETAG, "\"v1\"")
+                    .header(header::VARY, "accept-language");
+
+                let response = if req.headers().get(header::IF_NONE_MATCH)
+                    == Some(&HeaderValue::from_static("\"v1\""))
+                {
+                    builder.status(StatusCode::NOT_MODIFIED)
+                } else {
+                    let language = req
+                        .headers()
+                        .get(header::ACCEPT_LANGUAGE)
+                        .map(|v| v.to_str().unwrap().to_owned())
+                        .unwrap_or_default();
+
+                    builder.text(language)
+                };
+
+                std::future::ready(Ok(response.build().unwrap()))
+            }
+        })
+    }
+
+    fn get(language: &'static str) -> Request<Bytes> {
+        Request::get("https://example.com/a")
+            .header(header::ACCEPT_LANGUAGE, language)
+            .body(Bytes::new())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_fresh() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let store = Arc::new(MemoryStore::new(8));
+        let mut service = CacheLayer::new(store.clone()).layer(origin(&calls, "max-age=60"));
+
+        let response = service.call(get("en")).await.unwrap();
+        assert_eq!(response.body, "en");
+
+        let response = service.call(get("en")).await.unwrap();
+        assert_eq!(response.body, "en");
+        assert_eq!(response.response_parts.headers[header::AGE], "0");
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+
+        // Another variant.
+        let response = service.call(get("zh")).await.unwrap();
+        assert_eq!(response.body, "zh");
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+        assert_eq!(store.get("GET https://example.com/a").await.len(), 2);
+
+        let req = Request::head("https://example.com/a")
+            .body(Bytes::new())
+            .unwrap();
+        service.call(req).await.unwrap();
+        assert_eq!(store.get("HEAD https://example.com/a").await.len(), 1);
+
+        // Invalidated by unsafe method.
+        let req = Request::post("https://example.com/a")
+            .body(Bytes::new())
+            .unwrap();
+        service.call(req).await.unwrap();
+        assert!(store.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_revalidate() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut service = CacheLayer::new(MemoryStore::new(8)).layer(origin(&calls, "no-cache"));
+
+        service.call(get("en")).await.unwrap();
+
+        let response = service.call(get("en")).await.unwrap();
+        assert_eq!(response.response_parts.status, StatusCode::OK);
+        assert_eq!(response.body, "en");
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn test_conditional_request() {
+        let conditional = || {
+            let mut req = get("en");
+            req.headers_mut()
+                .insert(header::IF_NONE_MATCH, HeaderValue::from_static("\"v1\""));
+            req
+        };
+
+        for cache_control in ["max-age=60", "no-cache"] {
+            let mut service =
+                CacheLayer::new(MemoryStore::new(8)).layer(origin(&Arc::default(), cache_control));
+
+            // Nothing stored yet.
+            let response = service.call(conditional()).await.unwrap();
+            assert_eq!(response.response_parts.status, StatusCode::NOT_MODIFIED);
+
+            let response = service.call(get("en")).await.unwrap();
+            assert_eq!(response.response_parts.status, StatusCode::OK);
+            assert_eq!(response.body, "en");
+
+            // The stored response is stale, the caller revalidates it.
+            if cache_control == "no-cache" {
+                let response = service.call(conditional()).await.unwrap();
+                assert_eq!(response.response_parts.status, StatusCode::NOT_MODIFIED);
+            }
+
+            let response = service.call(get("en")).await.unwrap();
+            assert_eq!(response.response_parts.status, StatusCode::OK);
+            assert_eq!(response.body, "en", "{cache_control}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_lru() {
+        let store = MemoryStore::new(2);
+        let entry = CacheEntry::new(
+            Method::GET,
+            &HeaderMap::new(),
+            ResponseExt::builder().build().unwrap(),
+            SystemTime::now(),
+        );
+
+        store.put("a", vec![entry.clone()]).await;
+        store.put("b", vec![entry.clone()]).await;
+        store.get("a").await;
+        store.put("c", vec![entry]).await;
+
+        assert_eq!(store.len(), 2);
+        assert!(store.get("b").await.is_empty());
+        assert_eq!(store.get("a").await.len(), 1);
+    }
+}
@@ -0,0 +1,479 @@
+//! Circuit breaker for upstream hosts, see [`CircuitBreaker`], and the tower
+//! [`CircuitBreakerLayer`].
+
+use std::{
+    collections::{HashMap, VecDeque},
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, PoisonError},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use http::Request;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::response::ResponseExt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// State of a circuit.
+pub enum CircuitState {
+    /// Requests are allowed.
+    Closed,
+
+    /// Requests fail fast.
+    Open,
+
+    /// A probe request is allowed, to see if the upstream recovered.
+    HalfOpen,
+}
+
+#[derive(Debug)]
+struct Circuit {
+    state: CircuitState,
+    consecutive_failures: u32,
+    window: VecDeque<bool>,
+    opened_at: Instant,
+    probing: bool,
+}
+
+impl Circuit {
+    fn new() -> Self {
+        Self {
+            state: CircuitState::Closed,
+            consecutive_failures: 0,
+            window: VecDeque::new(),
+            opened_at: Instant::now(),
+            probing: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(thiserror::Error)]
+#[error("circuit of `{key}` is open, retry in {retry_in:?}")]
+/// Error returned by [`CircuitBreaker::guard`] when the circuit is open.
+pub struct CircuitOpen {
+    /// The key of the circuit.
+    pub key: String,
+
+    /// Time until the next probe is allowed.
+    pub retry_in: Duration,
+}
+
+#[derive(Debug, Clone)]
+/// Circuit breaker: a circuit per host or per key, so that services calling
+/// flaky upstreams can fail fast.
+///
+/// A closed circuit opens when either threshold is reached:
+///
+/// - consecutive failures
+/// - failure rate over the recent requests, once there're enough of them
+///
+/// After the probe interval, an open circuit becomes half-open and allows a
+/// single probe request: the circuit closes if it succeeds, or opens again.
+///
+/// Cheap to clone, clones share the circuits.
+///
+/// ```rust
+/// use miku_http_util::circuit_breaker::CircuitBreaker;
+///
+/// let breaker = CircuitBreaker::new().with_consecutive_failures(1);
+///
+/// breaker.guard("a.com").unwrap().failure();
+/// assert!(breaker.guard("a.com").is_err());
+/// ```
+pub struct CircuitBreaker {
+    consecutive_failures: u32,
+    failure_rate: f64,
+    window: usize,
+    min_requests: usize,
+    probe_interval: Duration,
+    circuits: Arc<Mutex<HashMap<String, Circuit>>>,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CircuitBreaker {
+    #[inline]
+    /// Create a new [`CircuitBreaker`] with default settings:
+    ///
+    /// - open after 5 consecutive failures
+    /// - or failure rate reaching 50% over the recent 20 requests, with at
+    ///   least 10 requests
+    /// - probe every 30s
+    pub fn new() -> Self {
+        Self {
+            consecutive_failures: 5,
+            failure_rate: 0.5,
+            window: 20,
+            min_requests: 10,
+            probe_interval: Duration::from_secs(30),
+            circuits: Arc::default(),
+        }
+    }
+
+    #[inline]
+    /// Set the consecutive failures threshold.
+    pub fn with_consecutive_failures(self, consecutive_failures: u32) -> Self {
+        Self {
+            consecutive_failures,
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Set the failure rate threshold, over the recent `window` requests, with
+    /// at least `min_requests` of them.
+    pub fn with_failure_rate(self, failure_rate: f64, window: usize, min_requests: usize) -> Self {
+        Self {
+            failure_rate,
+            window: window.max(1),
+            min_requests,
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Set the probe interval, i.e. how long an open circuit stays open.
+    pub fn with_probe_interval(self, probe_interval: Duration) -> Self {
+        Self {
+            probe_interval,
+            ..self
+        }
+    }
+
+    /// Returns the state of the circuit of `key`.
+    pub fn state(&self, key: &str) -> CircuitState {
+        self.circuits
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(key)
+            .map_or(CircuitState::Closed, |circuit| circuit.state)
+    }
+
+    /// Check if a request to `key` is allowed, returning a [`CircuitGuard`] to
+    /// report the outcome with.
+    ///
+    /// # Errors
+    ///
+    /// [`CircuitOpen`] if the circuit is open, or half-open with a probe
+    /// request in flight.
+    pub fn guard(&self, key: &str) -> Result<CircuitGuard, CircuitOpen> {
+        let mut circuits = self.circuits.lock().unwrap_or_else(PoisonError::into_inner);
+        let circuit = circuits.entry(key.to_owned()).or_insert_with(Circuit::new);
+
+        match circuit.state {
+            CircuitState::Closed => {}
+            CircuitState::Open => {
+                let elapsed = circuit.opened_at.elapsed();
+
+                if elapsed < self.probe_interval {
+                    return Err(CircuitOpen {
+                        key: key.to_owned(),
+                        retry_in: self.probe_interval - elapsed,
+                    });
+                }
+
+                circuit.state = CircuitState::HalfOpen;
+                circuit.probing = true;
+            }
+            CircuitState::HalfOpen => {
+                if circuit.probing {
+                    return Err(CircuitOpen {
+                        key: key.to_owned(),
+                        retry_in: Duration::ZERO,
+                    });
+                }
+
+                circuit.probing = true;
+            }
+        }
+
+        Ok(CircuitGuard {
+            breaker: self.clone(),
+            key: key.to_owned(),
+            probe: circuit.state == CircuitState::HalfOpen,
+            reported: false,
+        })
+    }
+
+    fn report(&self, key: &str, success: bool) {
+        let mut circuits = self.circuits.lock().unwrap_or_else(PoisonError::into_inner);
+        let circuit = circuits.entry(key.to_owned()).or_insert_with(Circuit::new);
+
+        match circuit.state {
+            CircuitState::HalfOpen if success => {
+                #[cfg(feature = "feat-tracing")]
+                tracing::info!("Circuit of `{key}` closed");
+
+                *circuit = Circuit::new();
+            }
+            CircuitState::HalfOpen => {
+                #[cfg(feature = "feat-tracing")]
+                tracing::warn!("Probe to `{key}` failed, circuit opened again");
+
+                circuit.state = CircuitState::Open;
+                circuit.opened_at = Instant::now();
+                circuit.probing = false;
+            }
+            CircuitState::Closed => {
+                if self.record(circuit, success) {
+                    #[cfg(feature = "feat-tracing")]
+                    tracing::warn!("Circuit of `{key}` opened");
+
+                    circuit.state = CircuitState::Open;
+                    circuit.opened_at = Instant::now();
+                }
+            }
+            // Reported by a guard created before the circuit opened.
+            CircuitState::Open => {}
+        }
+    }
+
+    /// Record the outcome to a closed circuit, returns if it should open.
+    fn record(&self, circuit: &mut Circuit, success: bool) -> bool {
+        if circuit.window.len() >= self.window {
+            circuit.window.pop_front();
+        }
+        circuit.window.push_back(success);
+
+        if success {
+            circuit.consecutive_failures = 0;
+            return false;
+        }
+
+        circuit.consecutive_failures += 1;
+
+        let failures = circuit.window.iter().filter(|success| !**success).count();
+
+        #[allow(
+            clippy::cast_precision_loss,
+            reason = "window size never gets that large"
+        )]
+        let rate_exceeded = circuit.window.len() >= self.min_requests
+            && failures as f64 >= self.failure_rate * circuit.window.len() as f64;
+
+        circuit.consecutive_failures >= self.consecutive_failures || rate_exceeded
+    }
+
+    fn release_probe(&self, key: &str) {
+        if let Some(circuit) = self
+            .circuits
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get_mut(key)
+        {
+            circuit.probing = false;
+        }
+    }
+}
+
+#[derive(Debug)]
+#[must_use = "report the outcome with `success` or `failure`"]
+/// Guard returned by [`CircuitBreaker::guard`].
+///
+/// Dropping it without reporting releases the probe slot of a half-open
+/// circuit, without counting.
+pub struct CircuitGuard {
+    breaker: CircuitBreaker,
+    key: String,
+    probe: bool,
+    reported: bool,
+}
+
+impl CircuitGuard {
+    /// Report the request succeeded.
+    pub fn success(mut self) {
+        self.reported = true;
+        self.breaker.report(&self.key, true);
+    }
+
+    /// Report the request failed.
+    pub fn failure(mut self) {
+        self.reported = true;
+        self.breaker.report(&self.key, false);
+    }
+}
+
+impl Drop for CircuitGuard {
+    fn drop(&mut self) {
+        if self.probe && !self.reported {
+            self.breaker.release_probe(&self.key);
+        }
+    }
+}
+
+#[derive(Debug)]
+#[derive(thiserror::Error)]
+/// Error returned by [`CircuitBreakerService`].
+pub enum CircuitBreakerError<E> {
+    #[error(transparent)]
+    /// The circuit is open.
+    Open(CircuitOpen),
+
+    #[error(transparent)]
+    /// Error of the inner service.
+    Inner(E),
+}
+
+#[derive(Debug, Clone)]
+/// [`Layer`] failing fast according to the [`CircuitBreaker`], keyed by the
+/// host of the request URI.
+///
+/// Errors of the inner service and `5xx` responses count as failures.
+pub struct CircuitBreakerLayer {
+    breaker: CircuitBreaker,
+}
+
+impl CircuitBreakerLayer {
+    #[inline]
+    /// Create a new [`CircuitBreakerLayer`].
+    pub const fn new(breaker: CircuitBreaker) -> Self {
+        Self { breaker }
+    }
+}
+
+impl<S> Layer<S> for CircuitBreakerLayer {
+    type Service = CircuitBreakerService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CircuitBreakerService {
+            inner,
+            breaker: self.breaker.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// [`Service`] failing fast according to the [`CircuitBreaker`], see
+/// [`CircuitBreakerLayer`].
+pub struct CircuitBreakerService<S> {
+    inner: S,
+    breaker: CircuitBreaker,
+}
+
+impl<S, B> Service<Request<B>> for CircuitBreakerService<S>
+where
+    S: Service<Request<B>, Response = ResponseExt>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Error = CircuitBreakerError<S::Error>;
+    type Future = Pin<Box<dyn Future<Output = Result<ResponseExt, Self::Error>> + Send>>;
+    type Response = ResponseExt;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner
+            .poll_ready(cx)
+            .map_err(CircuitBreakerError::Inner)
+    }
+
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        let guard = match self.breaker.guard(req.uri().host().unwrap_or_default()) {
+            Ok(guard) => guard,
+            Err(e) => return Box::pin(std::future::ready(Err(CircuitBreakerError::Open(e)))),
+        };
+
+        let fut = self.inner.call(req);
+
+        Box::pin(async move {
+            match fut.await {
+                Ok(response) => {
+                    if response.response_parts.status.is_server_error() {
+                        guard.failure();
+                    } else {
+                        guard.success();
+                    }
+
+                    Ok(response)
+                }
+                Err(e) => {
+                    guard.failure();
+
+                    Err(CircuitBreakerError::Inner(e))
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_consecutive_failures() {
+        let breaker = CircuitBreaker::new()
+            .with_consecutive_failures(2)
+            .with_probe_interval(Duration::ZERO);
+
+        breaker.guard("a").unwrap().failure();
+        breaker.guard("a").unwrap().success();
+        breaker.guard("a").unwrap().failure();
+        assert_eq!(breaker.state("a"), CircuitState::Closed);
+        breaker.guard("a").unwrap().failure();
+        assert_eq!(breaker.state("a"), CircuitState::Open);
+        assert_eq!(breaker.state("b"), CircuitState::Closed);
+
+        // Probe interval elapsed, a single probe is allowed.
+        let probe = breaker.guard("a").unwrap();
+        assert_eq!(breaker.state("a"), CircuitState::HalfOpen);
+        assert_eq!(breaker.guard("a").unwrap_err().retry_in, Duration::ZERO);
+
+        // Dropped without reporting.
+        drop(probe);
+        breaker.guard("a").unwrap().failure();
+        assert_eq!(breaker.state("a"), CircuitState::Open);
+
+        breaker.guard("a").unwrap().success();
+        assert_eq!(breaker.state("a"), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_failure_rate() {
+        let breaker = CircuitBreaker::new()
+            .with_consecutive_failures(u32::MAX)
+            .with_failure_rate(0.5, 4, 4);
+
+        for success in [true, false, true] {
+            let guard = breaker.guard("a").unwrap();
+            if success {
+                guard.success();
+            } else {
+                guard.failure();
+            }
+        }
+        assert_eq!(breaker.state("a"), CircuitState::Closed);
+
+        breaker.guard("a").unwrap().failure();
+        assert_eq!(breaker.state("a"), CircuitState::Open);
+
+        let e = breaker.guard("a").unwrap_err();
+        assert!(e.retry_in > Duration::from_secs(29));
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_layer() {
+        let breaker = CircuitBreaker::new().with_consecutive_failures(1);
+        let unavailable = tower::service_fn(|_: Request<()>| {
+            std::future::ready(ResponseExt::builder().status(503).build())
+        });
+        let mut service = CircuitBreakerLayer::new(breaker.clone()).layer(unavailable);
+
+        let req = || Request::get("http://a.com/").body(()).unwrap();
+
+        let response = service.call(req()).await.unwrap();
+        assert_eq!(response.response_parts.status, 503);
+
+        assert!(matches!(
+            service.call(req()).await,
+            Err(CircuitBreakerError::Open(_))
+        ));
+    }
+}
@@ -0,0 +1,444 @@
+//! Content Security Policy violation reports, see [`parse_csp_reports`] and
+//! [`CspReportService`].
+//!
+//! Both the legacy `report-uri` payload (`application/csp-report`) and the
+//! Reporting API payload (`application/reports+json`, for `report-to`) are
+//! supported, normalized into [`CspReport`]s.
+
+use std::{
+    convert::Infallible,
+    future::{ready, Ready},
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use http::{header, Request, Response, StatusCode};
+use serde::Deserialize;
+use tower_service::Service;
+
+/// Media type of the legacy CSP report (`report-uri`).
+pub const CSP_REPORT: &str = "application/csp-report";
+
+/// Media type of the Reporting API payload (`report-to`).
+pub const REPORTS_JSON: &str = "application/reports+json";
+
+#[derive(Debug)]
+#[derive(thiserror::Error)]
+/// Errors when parsing CSP reports.
+pub enum CspReportError {
+    #[error("unsupported content type `{0}`")]
+    /// Neither [`CSP_REPORT`] nor [`REPORTS_JSON`] (nor `application/json`).
+    UnsupportedContentType(String),
+
+    #[error(transparent)]
+    /// Malformed payload.
+    Json(#[from] serde_json::Error),
+}
+
+impl CspReportError {
+    /// Returns the status code to respond with, i.e. `415 Unsupported Media
+    /// Type` or `400 Bad Request`.
+    pub const fn status(&self) -> StatusCode {
+        match self {
+            Self::UnsupportedContentType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Self::Json(_) => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+/// Whether the violated policy is enforced or report-only.
+pub enum CspDisposition {
+    /// `Content-Security-Policy`.
+    Enforce,
+
+    /// `Content-Security-Policy-Report-Only`.
+    Report,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+/// A CSP violation report.
+///
+/// Fields are named after the Reporting API ones, while the legacy
+/// (kebab-case) names are accepted as well.
+pub struct CspReport {
+    /// URL of the document where the violation occurred.
+    #[serde(rename = "documentURL", alias = "document-uri")]
+    pub document_url: Option<String>,
+
+    /// Referrer of the document.
+    pub referrer: Option<String>,
+
+    /// URL of the blocked resource, or keywords like `inline` and `eval`.
+    #[serde(rename = "blockedURL", alias = "blocked-uri")]
+    pub blocked_url: Option<String>,
+
+    /// The directive whose enforcement caused the violation, like
+    /// `script-src-elem`.
+    #[serde(rename = "effectiveDirective", alias = "effective-directive")]
+    pub effective_directive: Option<String>,
+
+    /// The violated directive, legacy payload only (and deprecated there).
+    #[serde(rename = "violated-directive")]
+    pub violated_directive: Option<String>,
+
+    /// The policy as received.
+    #[serde(rename = "originalPolicy", alias = "original-policy")]
+    pub original_policy: Option<String>,
+
+    /// Whether the policy is enforced.
+    pub disposition: Option<CspDisposition>,
+
+    /// Status code of the document.
+    #[serde(rename = "statusCode", alias = "status-code")]
+    pub status_code: Option<u16>,
+
+    /// URL of the script where the violation occurred.
+    #[serde(rename = "sourceFile", alias = "source-file")]
+    pub source_file: Option<String>,
+
+    /// Line number in `source_file`.
+    #[serde(rename = "lineNumber", alias = "line-number")]
+    pub line_number: Option<u32>,
+
+    /// Column number in `source_file`.
+    #[serde(rename = "columnNumber", alias = "column-number")]
+    pub column_number: Option<u32>,
+
+    /// The first 40 characters of the inline script, event handler or style.
+    #[serde(rename = "sample", alias = "script-sample")]
+    pub sample: Option<String>,
+
+    /// User agent submitting the report, Reporting API payload only.
+    #[serde(skip)]
+    pub user_agent: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct LegacyPayload {
+    #[serde(rename = "csp-report")]
+    csp_report: CspReport,
+}
+
+#[derive(Deserialize)]
+struct ReportingApiReport {
+    #[serde(rename = "type")]
+    report_type: String,
+
+    user_agent: Option<String>,
+
+    body: serde_json::Value,
+}
+
+/// Parse CSP reports of the content type (with parameters, if any).
+///
+/// For the Reporting API payload, reports other than `csp-violation` are
+/// skipped. `application/json` is accepted for either format.
+///
+/// # Errors
+///
+/// See [`CspReportError`].
+pub fn parse_csp_reports(
+    content_type: Option<&str>,
+    body: &[u8],
+) -> Result<Vec<CspReport>, CspReportError> {
+    let media_type = content_type
+        .and_then(|value| value.split(';').next())
+        .unwrap_or_default()
+        .trim();
+
+    let is_legacy = if media_type.eq_ignore_ascii_case(CSP_REPORT) {
+        true
+    } else if media_type.eq_ignore_ascii_case(REPORTS_JSON) {
+        false
+    } else if media_type.eq_ignore_ascii_case("application/json") {
+        // The legacy payload is an object, while the Reporting API one is an
+        // array.
+        body.iter()
+            .find(|b| !b.is_ascii_whitespace())
+            .is_some_and(|&b| b == b'{')
+    } else {
+        return Err(CspReportError::UnsupportedContentType(
+            media_type.to_owned(),
+        ));
+    };
+
+    if is_legacy {
+        let payload: LegacyPayload = serde_json::from_slice(body)?;
+
+        return Ok(vec![payload.csp_report]);
+    }
+
+    let reports: Vec<ReportingApiReport> = serde_json::from_slice(body)?;
+
+    reports
+        .into_iter()
+        .filter(|report| report.report_type == "csp-violation")
+        .map(|report| {
+            let mut parsed: CspReport = serde_json::from_value(report.body)?;
+            parsed.user_agent = report.user_agent;
+
+            Ok(parsed)
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+/// [`Service`] receiving CSP reports, i.e. the endpoint of `report-uri` /
+/// `report-to`, handing the parsed reports to the callback.
+///
+/// Responds with `204 No Content`, or the status of [`CspReportError`] if the
+/// payload is invalid.
+pub struct CspReportService<F> {
+    on_reports: F,
+}
+
+impl<F> CspReportService<F> {
+    #[inline]
+    /// Create a new [`CspReportService`] with the callback.
+    pub const fn new(on_reports: F) -> Self {
+        Self { on_reports }
+    }
+}
+
+/// Parse the request and call the callback, returning the status code.
+fn handle<F>(req: &Request<Bytes>, on_reports: &F) -> StatusCode
+where
+    F: Fn(Vec<CspReport>),
+{
+    let content_type = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok());
+
+    let result = parse_csp_reports(content_type, req.body());
+
+    #[cfg(feature = "feat-tracing")]
+    if let Err(e) = &result {
+        tracing::debug!("Invalid CSP report: {e}");
+    }
+
+    match result {
+        Ok(reports) => {
+            on_reports(reports);
+
+            StatusCode::NO_CONTENT
+        }
+        Err(e) => e.status(),
+    }
+}
+
+impl<F> Service<Request<Bytes>> for CspReportService<F>
+where
+    F: Fn(Vec<CspReport>),
+{
+    type Error = Infallible;
+    type Future = Ready<Result<Self::Response, Self::Error>>;
+    type Response = Response<Bytes>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request<Bytes>) -> Self::Future {
+        let mut response = Response::new(Bytes::new());
+        *response.status_mut() = handle(&req, &self.on_reports);
+
+        ready(Ok(response))
+    }
+}
+
+#[cfg(feature = "feat-integrate-axum")]
+mod integrate_axum {
+    use std::{future::Future, pin::Pin};
+
+    use axum::{
+        extract::Request,
+        handler::Handler,
+        response::{IntoResponse, Response},
+    };
+    use http::StatusCode;
+
+    use super::{handle, CspReport};
+
+    /// Max size of the report payload.
+    const MAX_SIZE: usize = 64 * 1024;
+
+    #[derive(Debug, Clone)]
+    /// Axum handler receiving CSP reports, like [`CspReportService`](super::CspReportService).
+    pub struct CspReportHandler<F> {
+        on_reports: F,
+    }
+
+    impl<F> CspReportHandler<F> {
+        #[inline]
+        /// Create a new [`CspReportHandler`] with the callback.
+        pub const fn new(on_reports: F) -> Self {
+            Self { on_reports }
+        }
+    }
+
+    impl<F, S> Handler<(), S> for CspReportHandler<F>
+    where
+        F: Fn(Vec<CspReport>) + Clone + Send + Sync + 'static,
+    {
+        type Future = Pin<Box<dyn Future<Output = Response> + Send>>;
+
+        fn call(self, req: Request, _state: S) -> Self::Future {
+            Box::pin(async move {
+                let (parts, body) = req.into_parts();
+
+                let Ok(body) = axum::body::to_bytes(body, MAX_SIZE).await else {
+                    return StatusCode::PAYLOAD_TOO_LARGE.into_response();
+                };
+
+                handle(&Request::from_parts(parts, body), &self.on_reports).into_response()
+            })
+        }
+    }
+}
+
+#[cfg(feature = "feat-integrate-axum")]
+// re-export
+pub use self::integrate_axum::CspReportHandler;
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    const LEGACY: &str = r#"{
+        "csp-report": {
+            "document-uri": "https://example.com/page",
+            "referrer": "",
+            "violated-directive": "script-src-elem",
+            "effective-directive": "script-src-elem",
+            "original-policy": "script-src 'self'; report-uri /csp",
+            "disposition": "enforce",
+            "blocked-uri": "https://evil.com/x.js",
+            "line-number": 10,
+            "status-code": 200,
+            "script-sample": ""
+        }
+    }"#;
+
+    const REPORTING_API: &str = r#"[
+        {
+            "type": "csp-violation",
+            "age": 10,
+            "url": "https://example.com/page",
+            "user_agent": "Mozilla/5.0",
+            "body": {
+                "documentURL": "https://example.com/page",
+                "blockedURL": "inline",
+                "effectiveDirective": "style-src-attr",
+                "originalPolicy": "style-src 'self'; report-to csp",
+                "disposition": "report",
+                "statusCode": 200,
+                "sample": "color: red"
+            }
+        },
+        {
+            "type": "deprecation",
+            "url": "https://example.com/page",
+            "body": {}
+        }
+    ]"#;
+
+    #[test]
+    fn test_parse_csp_reports() {
+        let reports = parse_csp_reports(Some(CSP_REPORT), LEGACY.as_bytes()).unwrap();
+        assert_eq!(
+            reports,
+            [CspReport {
+                document_url: Some("https://example.com/page".to_owned()),
+                referrer: Some(String::new()),
+                blocked_url: Some("https://evil.com/x.js".to_owned()),
+                effective_directive: Some("script-src-elem".to_owned()),
+                violated_directive: Some("script-src-elem".to_owned()),
+                original_policy: Some("script-src 'self'; report-uri /csp".to_owned()),
+                disposition: Some(CspDisposition::Enforce),
+                status_code: Some(200),
+                line_number: Some(10),
+                sample: Some(String::new()),
+                ..CspReport::default()
+            }]
+        );
+
+        let reports = parse_csp_reports(
+            Some("application/reports+json; charset=utf-8"),
+            REPORTING_API.as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].blocked_url.as_deref(), Some("inline"));
+        assert_eq!(reports[0].disposition, Some(CspDisposition::Report));
+        assert_eq!(reports[0].user_agent.as_deref(), Some("Mozilla/5.0"));
+
+        assert_eq!(
+            parse_csp_reports(Some("application/json"), LEGACY.as_bytes())
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(matches!(
+            parse_csp_reports(Some("text/plain"), LEGACY.as_bytes()),
+            Err(CspReportError::UnsupportedContentType(_))
+        ));
+        assert!(matches!(
+            parse_csp_reports(Some(CSP_REPORT), b"[]"),
+            Err(CspReportError::Json(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_csp_report_service() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+
+        let mut service = CspReportService::new({
+            let received = received.clone();
+            move |reports: Vec<CspReport>| received.lock().unwrap().extend(reports)
+        });
+
+        let req = Request::post("/csp")
+            .header(header::CONTENT_TYPE, CSP_REPORT)
+            .body(Bytes::from_static(LEGACY.as_bytes()))
+            .unwrap();
+        let response = service.call(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(received.lock().unwrap().len(), 1);
+
+        let req = Request::post("/csp")
+            .header(header::CONTENT_TYPE, CSP_REPORT)
+            .body(Bytes::from_static(b"not json"))
+            .unwrap();
+        let response = service.call(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[cfg(feature = "feat-integrate-axum")]
+    #[tokio::test]
+    async fn test_csp_report_handler() {
+        use axum::handler::Handler;
+
+        let handler = CspReportHandler::new(|reports: Vec<CspReport>| {
+            assert_eq!(
+                reports[0].effective_directive.as_deref(),
+                Some("style-src-attr")
+            );
+        });
+
+        let response = handler
+            .call(
+                axum::extract::Request::post("/csp")
+                    .header(header::CONTENT_TYPE, REPORTS_JSON)
+                    .body(axum::body::Body::from(REPORTING_API))
+                    .unwrap(),
+                (),
+            )
+            .await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    }
+}
@@ -0,0 +1,123 @@
+//! Crate-level error type, see [`Error`].
+
+use core::fmt;
+#[cfg(feature = "std")]
+use std::error::Error as StdError;
+
+/// Type alias of [`Result`](core::result::Result) with [`Error`] by default.
+pub type Result<T, E = Error> = core::result::Result<T, E>;
+
+#[cfg(feature = "std")]
+/// Type-erased error, for errors of user-provided callbacks or services.
+pub type BoxError = Box<dyn StdError + Send + Sync>;
+
+#[derive(Debug)]
+#[non_exhaustive]
+/// Unified error type of this crate, for matching error kinds.
+///
+/// Implements [`std::error::Error`], so `anyhow::Error` can be converted from
+/// it with `?` as usual.
+pub enum Error {
+    #[cfg(feature = "feat-request-misc-proxy")]
+    /// Invalid proxy, see [`ProxyScheme`](crate::request::misc::proxy::ProxyScheme).
+    Proxy(crate::request::misc::proxy::Error),
+
+    #[cfg(feature = "feat-request-header")]
+    /// Invalid header value, see
+    /// [`HeaderMapExtT`](crate::request::header::HeaderMapExtT).
+    Header(crate::request::header::HeaderError),
+
+    #[cfg(any(feature = "feat-integrate-axum", feature = "feat-integrate-tower"))]
+    /// Invalid query, see
+    /// [`get_query`](crate::request::parser::integration::get_query).
+    QueryParse(crate::request::parser::integration::ParseQueryError),
+
+    #[cfg(all(feature = "feat-request-builder", feature = "std"))]
+    /// Failed to sign the query, see
+    /// [`SignerT`](crate::request::builder::SignerT).
+    Sign(BoxError),
+
+    #[cfg(feature = "feat-response")]
+    /// Failed to decode the response (or part of it, e.g. the gRPC status).
+    ResponseDecode(BoxError),
+}
+
+impl fmt::Display for Error {
+    #[allow(unused_variables, reason = "no variant if no feature is enabled")]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            #[cfg(feature = "feat-request-misc-proxy")]
+            Self::Proxy(ref e) => write!(f, "proxy error: {e}"),
+            #[cfg(feature = "feat-request-header")]
+            Self::Header(ref e) => write!(f, "header error: {e}"),
+            #[cfg(any(feature = "feat-integrate-axum", feature = "feat-integrate-tower"))]
+            Self::QueryParse(ref e) => write!(f, "query error: {e}"),
+            #[cfg(all(feature = "feat-request-builder", feature = "std"))]
+            Self::Sign(ref e) => write!(f, "sign error: {e}"),
+            #[cfg(feature = "feat-response")]
+            Self::ResponseDecode(ref e) => write!(f, "response decode error: {e}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match *self {
+            #[cfg(feature = "feat-request-misc-proxy")]
+            Self::Proxy(ref e) => Some(e),
+            #[cfg(feature = "feat-request-header")]
+            Self::Header(ref e) => Some(e),
+            #[cfg(any(feature = "feat-integrate-axum", feature = "feat-integrate-tower"))]
+            Self::QueryParse(ref e) => Some(e),
+            #[cfg(all(feature = "feat-request-builder", feature = "std"))]
+            Self::Sign(ref e) => Some(&**e),
+            #[cfg(feature = "feat-response")]
+            Self::ResponseDecode(ref e) => Some(&**e),
+        }
+    }
+}
+
+impl Error {
+    #[cfg(all(feature = "feat-request-builder", feature = "std"))]
+    #[inline]
+    /// Create an [`Error::Sign`] from the
+    /// [`SignerT::Error`](crate::request::builder::SignerT::Error).
+    pub fn sign<E>(e: E) -> Self
+    where
+        E: Into<BoxError>,
+    {
+        Self::Sign(e.into())
+    }
+
+    #[cfg(feature = "feat-response")]
+    #[inline]
+    /// Create an [`Error::ResponseDecode`].
+    pub fn response_decode<E>(e: E) -> Self
+    where
+        E: Into<BoxError>,
+    {
+        Self::ResponseDecode(e.into())
+    }
+}
+
+#[cfg(feature = "feat-request-misc-proxy")]
+impl From<crate::request::misc::proxy::Error> for Error {
+    fn from(e: crate::request::misc::proxy::Error) -> Self {
+        Self::Proxy(e)
+    }
+}
+
+#[cfg(feature = "feat-request-header")]
+impl From<crate::request::header::HeaderError> for Error {
+    fn from(e: crate::request::header::HeaderError) -> Self {
+        Self::Header(e)
+    }
+}
+
+#[cfg(any(feature = "feat-integrate-axum", feature = "feat-integrate-tower"))]
+impl From<crate::request::parser::integration::ParseQueryError> for Error {
+    fn from(e: crate::request::parser::integration::ParseQueryError) -> Self {
+        Self::QueryParse(e)
+    }
+}
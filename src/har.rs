@@ -0,0 +1,549 @@
+//! HAR (HTTP Archive) 1.2 export and import.
+//!
+//! Converts request / response pairs to and from HAR entries, for interop with
+//! browser devtools and API debugging proxies.
+//!
+//! See <http://www.softwareishard.com/blog/har-12-spec/>.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use bytes::Bytes;
+use http::{request, HeaderMap, HeaderName, HeaderValue, Method, StatusCode, Uri, Version};
+use serde::{Deserialize, Serialize};
+
+use crate::response::ResponseExt;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// The root of a HAR file.
+pub struct Har {
+    /// The log.
+    pub log: HarLog,
+}
+
+impl Har {
+    /// Create a new HAR with given entries.
+    pub fn new(entries: Vec<HarEntry>) -> Self {
+        Self {
+            log: HarLog {
+                version: "1.2".to_owned(),
+                creator: HarCreator {
+                    name: env!("CARGO_PKG_NAME").to_owned(),
+                    version: env!("CARGO_PKG_VERSION").to_owned(),
+                },
+                entries,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// HAR log.
+pub struct HarLog {
+    /// Version of the format, `1.2`.
+    pub version: String,
+
+    /// The creator application.
+    pub creator: HarCreator,
+
+    /// The entries.
+    pub entries: Vec<HarEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// HAR creator.
+pub struct HarCreator {
+    /// Name of the application.
+    pub name: String,
+
+    /// Version of the application.
+    pub version: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// HAR entry, a request / response pair.
+pub struct HarEntry {
+    /// Start time of the request, ISO 8601.
+    pub started_date_time: String,
+
+    /// Total elapsed time of the request in milliseconds.
+    pub time: f64,
+
+    /// The request.
+    pub request: HarRequest,
+
+    /// The response.
+    pub response: HarResponse,
+
+    /// Cache info, always empty.
+    #[serde(default)]
+    pub cache: HarCache,
+
+    /// Timings.
+    pub timings: HarTimings,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// HAR request.
+pub struct HarRequest {
+    /// Request method.
+    pub method: String,
+
+    /// Absolute URL of the request.
+    pub url: String,
+
+    /// HTTP version, like `HTTP/1.1`.
+    pub http_version: String,
+
+    /// Cookies, always empty (they are kept in the headers).
+    #[serde(default)]
+    pub cookies: Vec<HarNameValue>,
+
+    /// Headers.
+    pub headers: Vec<HarNameValue>,
+
+    /// Decoded query pairs.
+    pub query_string: Vec<HarNameValue>,
+
+    /// Request body.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_data: Option<HarPostData>,
+
+    /// Size of the headers, `-1` if unknown.
+    pub headers_size: i64,
+
+    /// Size of the body, `-1` if unknown.
+    pub body_size: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// HAR request body.
+pub struct HarPostData {
+    /// Mime type of the body.
+    pub mime_type: String,
+
+    /// The body text, base64-encoded if `encoding` is `base64`.
+    pub text: String,
+
+    /// Encoding of `text` (non-standard, but widely used).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// HAR response.
+pub struct HarResponse {
+    /// Status code.
+    pub status: u16,
+
+    /// Status text.
+    pub status_text: String,
+
+    /// HTTP version, like `HTTP/1.1`.
+    pub http_version: String,
+
+    /// Cookies, always empty (they are kept in the headers).
+    #[serde(default)]
+    pub cookies: Vec<HarNameValue>,
+
+    /// Headers.
+    pub headers: Vec<HarNameValue>,
+
+    /// Response body.
+    pub content: HarContent,
+
+    /// Redirection target from `Location`.
+    #[serde(rename = "redirectURL")]
+    pub redirect_url: String,
+
+    /// Size of the headers, `-1` if unknown.
+    pub headers_size: i64,
+
+    /// Size of the body, `-1` if unknown.
+    pub body_size: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// HAR response body.
+pub struct HarContent {
+    /// Length of the body.
+    pub size: i64,
+
+    /// Mime type of the body.
+    pub mime_type: String,
+
+    /// The body text, base64-encoded if `encoding` is `base64`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+
+    /// Encoding of `text`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// HAR name-value pair, for headers and query string.
+pub struct HarNameValue {
+    /// Name.
+    pub name: String,
+
+    /// Value.
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+/// HAR cache info, not recorded.
+pub struct HarCache {}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// HAR timings in milliseconds, `-1` if not applicable.
+pub struct HarTimings {
+    /// Time to send the request.
+    pub send: f64,
+
+    /// Time waiting for the response.
+    pub wait: f64,
+
+    /// Time to read the response.
+    pub receive: f64,
+}
+
+#[derive(Debug)]
+#[derive(thiserror::Error)]
+/// Errors when converting HAR entries back to requests / responses.
+pub enum HarError {
+    #[error(transparent)]
+    /// Invalid method.
+    Method(#[from] http::method::InvalidMethod),
+
+    #[error(transparent)]
+    /// Invalid URL.
+    Uri(#[from] http::uri::InvalidUri),
+
+    #[error(transparent)]
+    /// Invalid status code.
+    Status(#[from] http::status::InvalidStatusCode),
+
+    #[error("invalid HTTP version `{0}`")]
+    /// Invalid HTTP version.
+    Version(String),
+
+    #[error(transparent)]
+    /// Invalid header name.
+    HeaderName(#[from] http::header::InvalidHeaderName),
+
+    #[error(transparent)]
+    /// Invalid header value.
+    HeaderValue(#[from] http::header::InvalidHeaderValue),
+
+    #[error(transparent)]
+    /// Invalid base64 body.
+    Body(#[from] base64::DecodeError),
+}
+
+impl HarEntry {
+    /// Create a HAR entry from the request and response.
+    ///
+    /// `request` should carry an absolute URI. `started` is when the request
+    /// was sent, and `time` is the total elapsed time.
+    pub fn new(
+        request: &request::Parts,
+        request_body: &[u8],
+        response: &ResponseExt,
+        started: SystemTime,
+        time: Duration,
+    ) -> Self {
+        let mime_type = |headers: &HeaderMap| {
+            headers
+                .get(http::header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default()
+                .to_owned()
+        };
+
+        let query_string = request
+            .uri
+            .query()
+            .unwrap_or_default()
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+                let decode = |v: &str| {
+                    percent_encoding::percent_decode_str(&v.replace('+', " "))
+                        .decode_utf8_lossy()
+                        .into_owned()
+                };
+
+                HarNameValue {
+                    name: decode(name),
+                    value: decode(value),
+                }
+            })
+            .collect();
+
+        let post_data = (!request_body.is_empty()).then(|| {
+            let (text, encoding) = encode_body(request_body);
+
+            HarPostData {
+                mime_type: mime_type(&request.headers),
+                text,
+                encoding,
+            }
+        });
+
+        let (text, encoding) = encode_body(&response.body);
+        let time = time.as_secs_f64() * 1000.0;
+
+        Self {
+            started_date_time: format_iso8601(started),
+            time,
+            request: HarRequest {
+                method: request.method.to_string(),
+                url: request.uri.to_string(),
+                http_version: format!("{:?}", request.version),
+                cookies: Vec::new(),
+                headers: headers_to_har(&request.headers),
+                query_string,
+                post_data,
+                headers_size: -1,
+                body_size: request_body.len() as i64,
+            },
+            response: HarResponse {
+                status: response.response_parts.status.as_u16(),
+                status_text: response
+                    .response_parts
+                    .status
+                    .canonical_reason()
+                    .unwrap_or_default()
+                    .to_owned(),
+                http_version: format!("{:?}", response.response_parts.version),
+                cookies: Vec::new(),
+                headers: headers_to_har(&response.response_parts.headers),
+                content: HarContent {
+                    size: response.body.len() as i64,
+                    mime_type: mime_type(&response.response_parts.headers),
+                    text: Some(text),
+                    encoding,
+                },
+                redirect_url: response
+                    .response_parts
+                    .headers
+                    .get(http::header::LOCATION)
+                    .and_then(|v| v.to_str().ok())
+                    .unwrap_or_default()
+                    .to_owned(),
+                headers_size: -1,
+                body_size: response.body.len() as i64,
+            },
+            cache: HarCache {},
+            timings: HarTimings {
+                send: 0.0,
+                wait: time,
+                receive: 0.0,
+            },
+        }
+    }
+
+    /// Convert back to the request parts and body.
+    ///
+    /// # Errors
+    ///
+    /// See [`HarError`].
+    pub fn to_request(&self) -> Result<(request::Parts, Bytes), HarError> {
+        let (mut parts, ()) = http::Request::new(()).into_parts();
+
+        parts.method = Method::from_bytes(self.request.method.as_bytes())?;
+        parts.uri = self.request.url.parse::<Uri>()?;
+        parts.version = parse_version(&self.request.http_version)?;
+        parts.headers = headers_from_har(&self.request.headers)?;
+
+        let body = match &self.request.post_data {
+            Some(post_data) => decode_body(&post_data.text, post_data.encoding.as_deref())?,
+            None => Bytes::new(),
+        };
+
+        Ok((parts, body))
+    }
+
+    /// Convert back to the response.
+    ///
+    /// # Errors
+    ///
+    /// See [`HarError`].
+    pub fn to_response(&self) -> Result<ResponseExt, HarError> {
+        let (mut response_parts, ()) = http::Response::new(()).into_parts();
+
+        response_parts.status = StatusCode::from_u16(self.response.status)?;
+        response_parts.version = parse_version(&self.response.http_version)?;
+        response_parts.headers = headers_from_har(&self.response.headers)?;
+
+        let body = match &self.response.content.text {
+            Some(text) => decode_body(text, self.response.content.encoding.as_deref())?,
+            None => Bytes::new(),
+        };
+
+        Ok(ResponseExt {
+            response_parts,
+            body,
+        })
+    }
+}
+
+fn headers_to_har(headers: &HeaderMap) -> Vec<HarNameValue> {
+    headers
+        .iter()
+        .map(|(k, v)| HarNameValue {
+            name: k.as_str().to_owned(),
+            value: String::from_utf8_lossy(v.as_bytes()).into_owned(),
+        })
+        .collect()
+}
+
+fn headers_from_har(headers: &[HarNameValue]) -> Result<HeaderMap, HarError> {
+    let mut map = HeaderMap::with_capacity(headers.len());
+
+    for HarNameValue { name, value } in headers {
+        // HTTP/2 pseudo headers recorded by browsers.
+        if name.starts_with(':') {
+            continue;
+        }
+
+        map.append(
+            HeaderName::try_from(name.as_str())?,
+            HeaderValue::try_from(value.as_str())?,
+        );
+    }
+
+    Ok(map)
+}
+
+fn encode_body(body: &[u8]) -> (String, Option<String>) {
+    match std::str::from_utf8(body) {
+        Ok(text) => (text.to_owned(), None),
+        Err(_) => (BASE64_STANDARD.encode(body), Some("base64".to_owned())),
+    }
+}
+
+fn decode_body(text: &str, encoding: Option<&str>) -> Result<Bytes, HarError> {
+    match encoding {
+        Some(encoding) if encoding.eq_ignore_ascii_case("base64") => {
+            Ok(BASE64_STANDARD.decode(text)?.into())
+        }
+        _ => Ok(Bytes::copy_from_slice(text.as_bytes())),
+    }
+}
+
+fn parse_version(version: &str) -> Result<Version, HarError> {
+    match version.to_ascii_uppercase().as_str() {
+        "HTTP/0.9" => Ok(Version::HTTP_09),
+        "HTTP/1.0" => Ok(Version::HTTP_10),
+        "HTTP/1.1" | "" | "UNKNOWN" => Ok(Version::HTTP_11),
+        "HTTP/2" | "HTTP/2.0" | "H2" => Ok(Version::HTTP_2),
+        "HTTP/3" | "HTTP/3.0" | "H3" => Ok(Version::HTTP_3),
+        _ => Err(HarError::Version(version.to_owned())),
+    }
+}
+
+/// Format the time as ISO 8601 in UTC, like `2025-01-01T00:00:00.000Z`.
+fn format_iso8601(time: SystemTime) -> String {
+    let elapsed = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = elapsed.as_secs();
+    let days = (secs / 86400) as i64;
+    let secs_of_day = secs % 86400;
+
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60,
+        elapsed.subsec_millis()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_iso8601() {
+        assert_eq!(format_iso8601(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        assert_eq!(
+            format_iso8601(UNIX_EPOCH + Duration::from_millis(1_709_251_199_123)),
+            "2024-02-29T23:59:59.123Z"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let (request, ()) = http::Request::post("https://example.com/api?q=a%20b&page=1")
+            .header("content-type", "application/json")
+            .body(())
+            .unwrap()
+            .into_parts();
+
+        let (response_parts, ()) = http::Response::builder()
+            .status(StatusCode::OK)
+            .header("content-type", "application/octet-stream")
+            .body(())
+            .unwrap()
+            .into_parts();
+        let response = ResponseExt {
+            response_parts,
+            body: Bytes::from_static(b"\xff\xfe"),
+        };
+
+        let entry = HarEntry::new(
+            &request,
+            b"{}",
+            &response,
+            UNIX_EPOCH,
+            Duration::from_millis(42),
+        );
+
+        assert_eq!(
+            entry.request.query_string,
+            [
+                HarNameValue {
+                    name: "q".to_owned(),
+                    value: "a b".to_owned()
+                },
+                HarNameValue {
+                    name: "page".to_owned(),
+                    value: "1".to_owned()
+                }
+            ]
+        );
+        assert_eq!(entry.response.content.text.as_deref(), Some("//4="));
+        assert_eq!(entry.response.content.encoding.as_deref(), Some("base64"));
+
+        let json = serde_json::to_string(&Har::new(vec![entry])).unwrap();
+        let har: Har = serde_json::from_str(&json).unwrap();
+        let entry = &har.log.entries[0];
+
+        let (request, body) = entry.to_request().unwrap();
+        assert_eq!(request.method, Method::POST);
+        assert_eq!(request.uri, "https://example.com/api?q=a%20b&page=1");
+        assert_eq!(body, "{}");
+
+        let response = entry.to_response().unwrap();
+        assert_eq!(response.response_parts.status, StatusCode::OK);
+        assert_eq!(response.body, b"\xff\xfe"[..]);
+    }
+}
@@ -0,0 +1,77 @@
+//! Tower middlewares
+
+#[cfg(feature = "feat-layer-body-limit")]
+pub mod body_limit;
+#[cfg(feature = "feat-layer-compression")]
+pub mod compression;
+#[cfg(feature = "feat-layer-deadline")]
+pub mod deadline;
+#[cfg(feature = "feat-layer-decompression")]
+pub mod decompression;
+#[cfg(feature = "feat-layer-digest")]
+pub mod digest;
+#[cfg(feature = "feat-layer-feature-flags")]
+pub mod feature_flags;
+#[cfg(feature = "feat-layer-header-policy")]
+pub mod header_policy;
+#[cfg(feature = "feat-layer-host")]
+pub mod host;
+#[cfg(feature = "feat-layer-locale")]
+pub mod locale;
+#[cfg(feature = "feat-layer-log")]
+pub mod log;
+#[cfg(feature = "feat-layer-metrics")]
+pub mod metrics;
+#[cfg(feature = "feat-layer-mirror")]
+pub mod mirror;
+#[cfg(feature = "feat-layer-negotiate")]
+pub mod negotiate;
+#[cfg(feature = "feat-layer-proxy")]
+pub mod proxy;
+#[cfg(feature = "feat-layer-route")]
+pub mod route;
+#[cfg(feature = "feat-layer-tenant")]
+pub mod tenant;
+#[cfg(feature = "feat-layer-trace")]
+pub mod trace;
+
+use alloc::{string::String, vec::Vec};
+
+/// Normalize the path for low-cardinality span fields or metric labels:
+/// numeric, UUID and long hex segments are replaced with `{id}`.
+pub fn normalize_path(path: &str) -> String {
+    fn is_id(segment: &str) -> bool {
+        let is_numeric = !segment.is_empty() && segment.bytes().all(|b| b.is_ascii_digit());
+        let is_uuid = segment.len() == 36
+            && segment.bytes().enumerate().all(|(idx, b)| match idx {
+                8 | 13 | 18 | 23 => b == b'-',
+                _ => b.is_ascii_hexdigit(),
+            });
+        let is_hex = segment.len() >= 16 && segment.bytes().all(|b| b.is_ascii_hexdigit());
+
+        is_numeric || is_uuid || is_hex
+    }
+
+    path.split('/')
+        .map(|segment| if is_id(segment) { "{id}" } else { segment })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(
+            normalize_path("/users/42/orders/0c5b2444-70a0-4932-980c-b4dc0d3f02b5"),
+            "/users/{id}/orders/{id}"
+        );
+        assert_eq!(
+            normalize_path("/blobs/deadbeefdeadbeef/v2"),
+            "/blobs/{id}/v2"
+        );
+        assert_eq!(normalize_path("/"), "/");
+    }
+}
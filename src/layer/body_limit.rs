@@ -0,0 +1,260 @@
+//! Request body size limit for servers, see [`BodyLimitLayer`].
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use bytes::{Buf, Bytes};
+use http::{header::CONTENT_LENGTH, Request, Response, StatusCode};
+use http_body::{Body, Frame, SizeHint};
+use tower_layer::Layer;
+use tower_service::Service;
+
+#[derive(Debug)]
+#[derive(thiserror::Error)]
+/// Error reading the [`LimitedBody`].
+pub enum BodyLimitError {
+    #[error("request body exceeds the limit of {0} bytes")]
+    /// The body exceeds the limit.
+    TooLarge(usize),
+
+    #[error(transparent)]
+    /// Error of the inner body.
+    Body(Box<dyn std::error::Error + Send + Sync>),
+}
+
+#[derive(Debug, Clone)]
+/// [`Layer`] enforcing the max request body size, responding
+/// `413 Payload Too Large`.
+///
+/// Requests are rejected up front when `Content-Length` exceeds the limit.
+/// Otherwise the body is wrapped as [`LimitedBody`], counting the streamed
+/// bytes and failing with [`BodyLimitError::TooLarge`] once the limit is
+/// exceeded, in which case the response of the inner service is replaced.
+pub struct BodyLimitLayer {
+    max_size: usize,
+    rejection: Bytes,
+}
+
+impl BodyLimitLayer {
+    #[inline]
+    /// Create a new [`BodyLimitLayer`] with the max body size in bytes.
+    pub const fn new(max_size: usize) -> Self {
+        Self {
+            max_size,
+            rejection: Bytes::new(),
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Set the body of the `413 Payload Too Large` response, empty by default.
+    pub fn with_rejection(self, rejection: impl Into<Bytes>) -> Self {
+        Self {
+            rejection: rejection.into(),
+            ..self
+        }
+    }
+}
+
+impl<S> Layer<S> for BodyLimitLayer {
+    type Service = BodyLimitService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        BodyLimitService {
+            inner,
+            config: self.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// [`Service`] limiting the request body size, see [`BodyLimitLayer`].
+pub struct BodyLimitService<S> {
+    inner: S,
+    config: BodyLimitLayer,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for BodyLimitService<S>
+where
+    S: Service<Request<LimitedBody<ReqBody>>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+    ResBody: From<Bytes> + Send + 'static,
+{
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+    type Response = S::Response;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let max_size = self.config.max_size;
+        let rejection = self.config.rejection.clone();
+
+        let content_length = req
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok());
+
+        if content_length.is_some_and(|len| len > max_size as u64) {
+            #[cfg(feature = "feat-tracing")]
+            tracing::warn!(
+                "Rejected request: `Content-Length` exceeds the limit of {max_size} bytes"
+            );
+
+            return Box::pin(std::future::ready(Ok(payload_too_large(rejection))));
+        }
+
+        let (parts, body) = req.into_parts();
+        let body = LimitedBody::new(body, max_size);
+        let exceeded = body.exceeded.clone();
+
+        let future = self.inner.call(Request::from_parts(parts, body));
+
+        Box::pin(async move {
+            let response = future.await?;
+
+            if exceeded.load(Ordering::Relaxed) {
+                #[cfg(feature = "feat-tracing")]
+                tracing::warn!("Rejected request: body exceeds the limit of {max_size} bytes");
+
+                return Ok(payload_too_large(rejection));
+            }
+
+            Ok(response)
+        })
+    }
+}
+
+fn payload_too_large<B: From<Bytes>>(body: Bytes) -> Response<B> {
+    let mut response = Response::new(B::from(body));
+    *response.status_mut() = StatusCode::PAYLOAD_TOO_LARGE;
+    response
+}
+
+#[derive(Debug)]
+/// Request body counting the streamed bytes, see [`BodyLimitLayer`].
+pub struct LimitedBody<B> {
+    inner: Pin<Box<B>>,
+    remaining: usize,
+    max_size: usize,
+    exceeded: Arc<AtomicBool>,
+}
+
+impl<B> LimitedBody<B> {
+    #[inline]
+    /// Wrap the body with the max size in bytes.
+    pub fn new(inner: B, max_size: usize) -> Self {
+        Self {
+            inner: Box::pin(inner),
+            remaining: max_size,
+            max_size,
+            exceeded: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+impl<B> Body for LimitedBody<B>
+where
+    B: Body,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    type Data = B::Data;
+    type Error = BodyLimitError;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        if self.exceeded.load(Ordering::Relaxed) {
+            return Poll::Ready(Some(Err(BodyLimitError::TooLarge(self.max_size))));
+        }
+
+        let frame = match self.inner.as_mut().poll_frame(cx) {
+            Poll::Ready(Some(Ok(frame))) => frame,
+            Poll::Ready(Some(Err(e))) => {
+                return Poll::Ready(Some(Err(BodyLimitError::Body(e.into()))));
+            }
+            Poll::Ready(None) => return Poll::Ready(None),
+            Poll::Pending => return Poll::Pending,
+        };
+
+        if let Some(data) = frame.data_ref() {
+            match self.remaining.checked_sub(data.remaining()) {
+                Some(remaining) => self.remaining = remaining,
+                None => {
+                    self.exceeded.store(true, Ordering::Relaxed);
+
+                    return Poll::Ready(Some(Err(BodyLimitError::TooLarge(self.max_size))));
+                }
+            }
+        }
+
+        Poll::Ready(Some(Ok(frame)))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_body_limit_layer() {
+        let collect = tower::service_fn(|req: Request<LimitedBody<String>>| async move {
+            let mut body = req.into_body();
+            let mut collected = Vec::new();
+
+            while let Some(frame) =
+                std::future::poll_fn(|cx| Pin::new(&mut body).poll_frame(cx)).await
+            {
+                match frame.map(Frame::into_data) {
+                    Ok(Ok(data)) => collected.extend_from_slice(data.chunk()),
+                    Ok(Err(_)) => {}
+                    Err(_) => return Ok(Response::new(Bytes::from_static(b"error"))),
+                }
+            }
+
+            Ok::<_, Infallible>(Response::new(Bytes::from(collected)))
+        });
+        let mut service = BodyLimitLayer::new(4)
+            .with_rejection("too large")
+            .layer(collect);
+
+        let req = Request::new("1234".to_owned());
+        let response = service.call(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body(), "1234");
+
+        let req = Request::new("12345".to_owned());
+        let response = service.call(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(response.body(), "too large");
+
+        let req = Request::builder()
+            .header(CONTENT_LENGTH, "5")
+            .body(String::new())
+            .unwrap();
+        let response = service.call(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
@@ -0,0 +1,340 @@
+//! Response compression layer for servers, see [`WithCompressionLayer`].
+//!
+//! Pairs with the decompression of
+//! [`ResponseExt::decompressed`](crate::response::ResponseExt::decompressed).
+
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use http::{
+    header::{
+        ACCEPT_ENCODING, ACCEPT_RANGES, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, VARY,
+    },
+    HeaderMap, HeaderValue, Request, Response, StatusCode,
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use super::negotiate::{match_media_type, negotiate_encoding};
+
+/// Default content codings, in order of preference, i.e. all the codings with
+/// the codec feature enabled.
+pub const DEFAULT_ENCODINGS: &[&str] = &[
+    #[cfg(feature = "feat-layer-compression-zstd")]
+    "zstd",
+    #[cfg(feature = "feat-layer-compression-brotli")]
+    "br",
+    #[cfg(feature = "feat-layer-compression-gzip")]
+    "gzip",
+];
+
+/// Default media ranges worth compressing.
+pub const DEFAULT_CONTENT_TYPES: &[&str] = &[
+    "text/*",
+    "application/json",
+    "application/javascript",
+    "application/xml",
+    "application/wasm",
+    "image/svg+xml",
+];
+
+#[derive(Debug, Clone, Copy)]
+/// [`Layer`] compressing the (buffered) response body, according to the
+/// `Accept-Encoding` of the request.
+///
+/// The response is left untouched if:
+///
+/// - it's already encoded (has `Content-Encoding`), partial or has no content;
+/// - its `Content-Type` is not in the allowlist;
+/// - its body is smaller than the threshold;
+/// - the client accepts none of the codings (or prefers `identity`);
+/// - the compressed body is not smaller.
+///
+/// `Vary: Accept-Encoding` is appended to all responses that may be
+/// compressed, i.e. those with allowed `Content-Type`.
+pub struct WithCompressionLayer {
+    encodings: &'static [&'static str],
+    content_types: &'static [&'static str],
+    min_size: usize,
+}
+
+impl Default for WithCompressionLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WithCompressionLayer {
+    #[inline]
+    /// Create a new [`WithCompressionLayer`], with [`DEFAULT_ENCODINGS`],
+    /// [`DEFAULT_CONTENT_TYPES`] and threshold of 1 KiB.
+    pub const fn new() -> Self {
+        Self {
+            encodings: DEFAULT_ENCODINGS,
+            content_types: DEFAULT_CONTENT_TYPES,
+            min_size: 1024,
+        }
+    }
+
+    #[inline]
+    /// Set the content codings, in order of preference.
+    ///
+    /// Codings without the codec feature enabled are ignored.
+    pub const fn with_encodings(self, encodings: &'static [&'static str]) -> Self {
+        Self { encodings, ..self }
+    }
+
+    #[inline]
+    /// Set the allowlist of media ranges, like `text/*`.
+    pub const fn with_content_types(self, content_types: &'static [&'static str]) -> Self {
+        Self {
+            content_types,
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Set the minimum body size in bytes to compress.
+    pub const fn with_min_size(self, min_size: usize) -> Self {
+        Self { min_size, ..self }
+    }
+
+    /// Whether the response may be compressed, regardless of the request.
+    fn is_compressible(&self, status: StatusCode, headers: &HeaderMap) -> bool {
+        if status == StatusCode::NO_CONTENT
+            || status == StatusCode::NOT_MODIFIED
+            || status == StatusCode::PARTIAL_CONTENT
+            || headers.contains_key(CONTENT_ENCODING)
+        {
+            return false;
+        }
+
+        headers
+            .get(CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .is_some_and(|content_type| {
+                self.content_types
+                    .iter()
+                    .any(|range| match_media_type(range, content_type).is_some())
+            })
+    }
+
+    /// Compress the response if possible.
+    fn compress(
+        &self,
+        accept_encoding: Option<&str>,
+        response: Response<Bytes>,
+    ) -> Response<Bytes> {
+        let (mut parts, body) = response.into_parts();
+
+        if !self.is_compressible(parts.status, &parts.headers) {
+            return Response::from_parts(parts, body);
+        }
+
+        append_vary(&mut parts.headers);
+
+        if body.len() < self.min_size {
+            return Response::from_parts(parts, body);
+        }
+
+        let mut candidates: Vec<_> = self
+            .encodings
+            .iter()
+            .copied()
+            .filter(|encoding| DEFAULT_ENCODINGS.contains(encoding))
+            .collect();
+        candidates.push("identity");
+
+        let Some(encoding) = negotiate_encoding(accept_encoding, &candidates)
+            .filter(|encoding| !encoding.eq_ignore_ascii_case("identity"))
+        else {
+            return Response::from_parts(parts, body);
+        };
+
+        match compress(encoding, &body) {
+            Ok(compressed) if compressed.len() < body.len() => {
+                parts
+                    .headers
+                    .insert(CONTENT_ENCODING, HeaderValue::from_static(encoding));
+                parts
+                    .headers
+                    .insert(CONTENT_LENGTH, HeaderValue::from(compressed.len()));
+                // Ranges of the compressed representation are not supported.
+                parts.headers.remove(ACCEPT_RANGES);
+
+                Response::from_parts(parts, Bytes::from(compressed))
+            }
+            _ => Response::from_parts(parts, body),
+        }
+    }
+}
+
+/// Append `Accept-Encoding` to `Vary` if not listed yet.
+fn append_vary(headers: &mut HeaderMap) {
+    let listed = headers
+        .get_all(VARY)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|value| {
+            let value = value.trim();
+
+            value == "*" || value.eq_ignore_ascii_case(ACCEPT_ENCODING.as_str())
+        });
+
+    if !listed {
+        headers.append(VARY, HeaderValue::from_static("accept-encoding"));
+    }
+}
+
+/// Compress the body with given content coding.
+fn compress(encoding: &str, body: &[u8]) -> io::Result<Vec<u8>> {
+    #[allow(unused_imports, reason = "unused if no codec feature is enabled")]
+    use std::io::Write;
+
+    match encoding {
+        #[cfg(feature = "feat-layer-compression-gzip")]
+        "gzip" => {
+            let mut encoder = flate2::write::GzEncoder::new(
+                Vec::with_capacity(body.len() / 2),
+                flate2::Compression::default(),
+            );
+            encoder.write_all(body)?;
+            encoder.finish()
+        }
+        #[cfg(feature = "feat-layer-compression-brotli")]
+        "br" => {
+            let mut encoder =
+                brotli::CompressorWriter::new(Vec::with_capacity(body.len() / 2), 4096, 5, 22);
+            encoder.write_all(body)?;
+            Ok(encoder.into_inner())
+        }
+        #[cfg(feature = "feat-layer-compression-zstd")]
+        "zstd" => zstd::stream::encode_all(body, 0),
+        _ => {
+            let _ = body;
+
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("unsupported content encoding `{encoding}`"),
+            ))
+        }
+    }
+}
+
+impl<S> Layer<S> for WithCompressionLayer {
+    type Service = WithCompressionService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        WithCompressionService {
+            inner,
+            config: *self,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// [`Service`] compressing the response body, see [`WithCompressionLayer`].
+pub struct WithCompressionService<S> {
+    inner: S,
+    config: WithCompressionLayer,
+}
+
+impl<S, ReqBody> Service<Request<ReqBody>> for WithCompressionService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<Bytes>>,
+    S::Future: Send + 'static,
+{
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+    type Response = Response<Bytes>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let config = self.config;
+
+        let mut accept_encoding = req
+            .headers()
+            .get_all(ACCEPT_ENCODING)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .peekable();
+        let accept_encoding = accept_encoding
+            .peek()
+            .is_some()
+            .then(|| accept_encoding.collect::<Vec<_>>().join(","));
+
+        let fut = self.inner.call(req);
+
+        Box::pin(async move {
+            fut.await
+                .map(|response| config.compress(accept_encoding.as_deref(), response))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(content_type: &'static str, len: usize) -> Response<Bytes> {
+        Response::builder()
+            .header(CONTENT_TYPE, content_type)
+            .header(CONTENT_LENGTH, len)
+            .body(Bytes::from("a".repeat(len)))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_not_compressed() {
+        let layer = WithCompressionLayer::new().with_encodings(&["unknown"]);
+
+        let compressed = layer.compress(Some("unknown"), response("image/png", 4096));
+        assert!(!compressed.headers().contains_key(VARY));
+        assert_eq!(compressed.body().len(), 4096);
+
+        let compressed = layer.compress(Some("unknown"), response("text/plain", 16));
+        assert_eq!(compressed.headers()[VARY], "accept-encoding");
+        assert_eq!(compressed.body().len(), 16);
+
+        let compressed = layer.compress(None, response("text/plain", 4096));
+        assert!(!compressed.headers().contains_key(CONTENT_ENCODING));
+        assert_eq!(compressed.body().len(), 4096);
+    }
+
+    #[cfg(feature = "feat-layer-compression-gzip")]
+    #[test]
+    fn test_gzip() {
+        use std::io::Read;
+
+        let layer = WithCompressionLayer::new().with_encodings(&["gzip"]);
+
+        let compressed = layer.compress(
+            Some("gzip;q=0.5, identity;q=0.1"),
+            response("text/plain; charset=utf-8", 4096),
+        );
+        assert_eq!(compressed.headers()[CONTENT_ENCODING], "gzip");
+        assert_eq!(compressed.headers()[VARY], "accept-encoding");
+        assert_eq!(
+            compressed.headers()[CONTENT_LENGTH],
+            compressed.body().len().to_string()
+        );
+
+        let mut decompressed = String::new();
+        flate2::read::GzDecoder::new(&compressed.body()[..])
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, "a".repeat(4096));
+
+        let compressed = layer.compress(Some("gzip;q=0.5, identity"), response("text/plain", 4096));
+        assert!(!compressed.headers().contains_key(CONTENT_ENCODING));
+    }
+}
@@ -0,0 +1,413 @@
+//! Request deadlines, see [`DeadlineLayer`].
+
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use http::{header::CONTENT_TYPE, HeaderMap, HeaderName, HeaderValue, Request};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// `grpc-timeout`, see [`parse_grpc_timeout`].
+pub const GRPC_TIMEOUT: HeaderName = HeaderName::from_static("grpc-timeout");
+
+/// `X-Request-Timeout`, the timeout in milliseconds.
+pub const X_REQUEST_TIMEOUT: HeaderName = HeaderName::from_static("x-request-timeout");
+
+#[derive(Debug)]
+#[derive(thiserror::Error)]
+/// Errors of [`DeadlineService`].
+pub enum DeadlineError<E> {
+    #[error("deadline exceeded")]
+    /// The deadline is exceeded.
+    Elapsed,
+
+    #[error(transparent)]
+    /// Error of the inner service.
+    Inner(E),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// The deadline of the request, stored in the request extensions by
+/// [`DeadlineService`].
+pub struct Deadline(Instant);
+
+impl Deadline {
+    #[inline]
+    /// Create a new [`Deadline`] at the instant.
+    pub const fn new(instant: Instant) -> Self {
+        Self(instant)
+    }
+
+    #[inline]
+    /// Create a new [`Deadline`] after the timeout from now.
+    pub fn after(timeout: Duration) -> Self {
+        Self(Instant::now() + timeout)
+    }
+
+    #[inline]
+    /// Returns the instant of the deadline.
+    pub const fn instant(&self) -> Instant {
+        self.0
+    }
+
+    #[inline]
+    /// Returns the remaining time, zero if exceeded.
+    pub fn remaining(&self) -> Duration {
+        self.0.saturating_duration_since(Instant::now())
+    }
+
+    #[inline]
+    /// Whether the deadline is exceeded.
+    pub fn is_exceeded(&self) -> bool {
+        self.0 <= Instant::now()
+    }
+}
+
+/// Parse the `grpc-timeout` header value: at most 8 digits followed by the
+/// unit, `H`, `M`, `S`, `m` (milliseconds), `u` or `n`.
+pub fn parse_grpc_timeout(value: &str) -> Option<Duration> {
+    if !value.is_ascii() {
+        return None;
+    }
+
+    let (value, unit) = value.split_at(value.len().checked_sub(1)?);
+
+    if value.is_empty() || value.len() > 8 || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let value: u64 = value.parse().ok()?;
+
+    match unit {
+        "H" => Some(Duration::from_secs(value * 3600)),
+        "M" => Some(Duration::from_secs(value * 60)),
+        "S" => Some(Duration::from_secs(value)),
+        "m" => Some(Duration::from_millis(value)),
+        "u" => Some(Duration::from_micros(value)),
+        "n" => Some(Duration::from_nanos(value)),
+        _ => None,
+    }
+}
+
+/// Encode the timeout as the `grpc-timeout` header value, with the finest unit
+/// fitting in 8 digits (rounded up).
+pub fn encode_grpc_timeout(timeout: Duration) -> String {
+    const MAX: u128 = 99_999_999;
+
+    let nanos = timeout.as_nanos();
+
+    [
+        (1, 'n'),
+        (1_000, 'u'),
+        (1_000_000, 'm'),
+        (1_000_000_000, 'S'),
+        (60_000_000_000, 'M'),
+    ]
+    .into_iter()
+    .find_map(|(per, unit)| {
+        let value = nanos.div_ceil(per);
+
+        (value <= MAX).then(|| format!("{value}{unit}"))
+    })
+    .unwrap_or_else(|| format!("{}H", nanos.div_ceil(3_600_000_000_000).min(MAX)))
+}
+
+#[derive(Debug, Clone)]
+/// [`Layer`] enforcing the deadline of requests around the inner service,
+/// failing with [`DeadlineError::Elapsed`] once exceeded.
+///
+/// The deadline is, in order:
+///
+/// - the [`Deadline`] in the request extensions, e.g. set by the caller when
+///   acting as a client;
+/// - read from `grpc-timeout`, or the configured header
+///   ([`X_REQUEST_TIMEOUT`] by default, in milliseconds), capped by the max
+///   timeout;
+/// - the default timeout, if any.
+///
+/// The [`Deadline`] is stored in the request extensions. When acting as a
+/// client (see [`with_propagate`](Self::with_propagate)), the remaining time
+/// is set as the outgoing `grpc-timeout` for gRPC requests, or the configured
+/// header for others.
+pub struct DeadlineLayer {
+    header: HeaderName,
+    default_timeout: Option<Duration>,
+    max_timeout: Option<Duration>,
+    propagate: bool,
+}
+
+impl Default for DeadlineLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DeadlineLayer {
+    #[inline]
+    /// Create a new [`DeadlineLayer`], without default or max timeout.
+    pub const fn new() -> Self {
+        Self {
+            header: X_REQUEST_TIMEOUT,
+            default_timeout: None,
+            max_timeout: None,
+            propagate: false,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Set the header of the timeout in milliseconds, [`X_REQUEST_TIMEOUT`] by
+    /// default.
+    pub fn with_header(self, header: HeaderName) -> Self {
+        Self { header, ..self }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Set the timeout of requests without one.
+    pub fn with_default_timeout(self, default_timeout: Option<Duration>) -> Self {
+        Self {
+            default_timeout,
+            ..self
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Set the max timeout read from the headers.
+    pub fn with_max_timeout(self, max_timeout: Option<Duration>) -> Self {
+        Self {
+            max_timeout,
+            ..self
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Whether to set the outgoing header with the remaining time, when
+    /// acting as a client.
+    pub fn with_propagate(self, propagate: bool) -> Self {
+        Self { propagate, ..self }
+    }
+
+    /// Read the timeout from the headers, capped by the max timeout.
+    fn timeout_from_headers(&self, headers: &HeaderMap) -> Option<Duration> {
+        let timeout = headers
+            .get(GRPC_TIMEOUT)
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_grpc_timeout)
+            .or_else(|| {
+                headers
+                    .get(&self.header)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.trim().parse().ok())
+                    .map(Duration::from_millis)
+            })?;
+
+        Some(
+            self.max_timeout
+                .map_or(timeout, |max_timeout| timeout.min(max_timeout)),
+        )
+    }
+}
+
+impl<S> Layer<S> for DeadlineLayer {
+    type Service = DeadlineService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        DeadlineService {
+            inner,
+            config: self.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// [`Service`] enforcing deadlines, see [`DeadlineLayer`].
+pub struct DeadlineService<S> {
+    inner: S,
+    config: DeadlineLayer,
+}
+
+impl<S, B> Service<Request<B>> for DeadlineService<S>
+where
+    S: Service<Request<B>>,
+    S::Future: Send + 'static,
+    S::Response: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Error = DeadlineError<S::Error>;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+    type Response = S::Response;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(DeadlineError::Inner)
+    }
+
+    fn call(&mut self, mut req: Request<B>) -> Self::Future {
+        let deadline = req.extensions().get::<Deadline>().copied().or_else(|| {
+            self.config
+                .timeout_from_headers(req.headers())
+                .or(self.config.default_timeout)
+                .map(Deadline::after)
+        });
+
+        let Some(deadline) = deadline else {
+            let future = self.inner.call(req);
+
+            return Box::pin(async move { future.await.map_err(DeadlineError::Inner) });
+        };
+
+        if deadline.is_exceeded() {
+            return Box::pin(std::future::ready(Err(DeadlineError::Elapsed)));
+        }
+
+        req.extensions_mut().insert(deadline);
+
+        if self.config.propagate {
+            let remaining = deadline.remaining();
+
+            let is_grpc = req
+                .headers()
+                .get(CONTENT_TYPE)
+                .is_some_and(|v| v.as_bytes().starts_with(b"application/grpc"));
+
+            let (name, value) = if is_grpc {
+                (GRPC_TIMEOUT, encode_grpc_timeout(remaining))
+            } else {
+                (
+                    self.config.header.clone(),
+                    remaining.as_millis().max(1).to_string(),
+                )
+            };
+
+            if let Ok(value) = HeaderValue::try_from(value) {
+                req.headers_mut().insert(name, value);
+            }
+        }
+
+        let future = self.inner.call(req);
+
+        Box::pin(async move {
+            match tokio::time::timeout_at(deadline.instant().into(), future).await {
+                Ok(result) => result.map_err(DeadlineError::Inner),
+                Err(_) => {
+                    #[cfg(feature = "feat-tracing")]
+                    tracing::warn!("Deadline exceeded");
+
+                    Err(DeadlineError::Elapsed)
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use super::*;
+
+    #[test]
+    fn test_grpc_timeout() {
+        for (value, expected) in [
+            ("1H", Some(Duration::from_secs(3600))),
+            ("2M", Some(Duration::from_secs(120))),
+            ("30S", Some(Duration::from_secs(30))),
+            ("100m", Some(Duration::from_millis(100))),
+            ("5u", Some(Duration::from_micros(5))),
+            ("99999999n", Some(Duration::from_nanos(99_999_999))),
+            ("123456789n", None),
+            ("m", None),
+            ("1x", None),
+            ("1é", None),
+            ("", None),
+        ] {
+            assert_eq!(parse_grpc_timeout(value), expected, "{value}");
+        }
+
+        assert_eq!(encode_grpc_timeout(Duration::from_nanos(1500)), "1500n");
+        assert_eq!(encode_grpc_timeout(Duration::from_millis(100)), "100000u");
+        assert_eq!(encode_grpc_timeout(Duration::from_secs(300)), "300000m");
+        assert_eq!(
+            encode_grpc_timeout(Duration::from_secs(86400 * 2000)),
+            "2880000M"
+        );
+    }
+
+    async fn upstream(req: Request<()>) -> Result<Request<()>, Infallible> {
+        if req.uri().path() == "/slow" {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+        }
+
+        Ok(req)
+    }
+
+    #[tokio::test]
+    async fn test_deadline_layer() {
+        let mut service = DeadlineLayer::new()
+            .with_max_timeout(Some(Duration::from_secs(60)))
+            .layer(tower::service_fn(upstream));
+
+        let req = Request::get("/")
+            .header("grpc-timeout", "1H")
+            .body(())
+            .unwrap();
+        let req = service.call(req).await.unwrap();
+        let remaining = req.extensions().get::<Deadline>().unwrap().remaining();
+        assert!(remaining > Duration::from_secs(50) && remaining <= Duration::from_secs(60));
+
+        let req = Request::get("/")
+            .header("x-request-timeout", "10")
+            .body(())
+            .unwrap();
+        let req = service.call(req).await.unwrap();
+        assert!(req.extensions().get::<Deadline>().is_some());
+
+        let req = Request::get("/slow")
+            .header("x-request-timeout", "10")
+            .body(())
+            .unwrap();
+        assert!(matches!(
+            service.call(req).await,
+            Err(DeadlineError::Elapsed)
+        ));
+
+        let req = Request::get("/slow").body(()).unwrap();
+        let req = service.call(req);
+        tokio::time::timeout(Duration::from_millis(10), req)
+            .await
+            .unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn test_deadline_layer_propagate() {
+        let mut service = DeadlineLayer::new()
+            .with_default_timeout(Some(Duration::from_secs(5)))
+            .with_propagate(true)
+            .layer(tower::service_fn(upstream));
+
+        let req = Request::get("/").body(()).unwrap();
+        let req = service.call(req).await.unwrap();
+        let timeout: u64 = req.headers()["x-request-timeout"]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(timeout > 4000 && timeout <= 5000);
+
+        let mut req = Request::get("/")
+            .header("content-type", "application/grpc+proto")
+            .body(())
+            .unwrap();
+        req.extensions_mut()
+            .insert(Deadline::after(Duration::from_millis(100)));
+        let req = service.call(req).await.unwrap();
+        let timeout = parse_grpc_timeout(req.headers()["grpc-timeout"].to_str().unwrap()).unwrap();
+        assert!(timeout > Duration::from_millis(50) && timeout <= Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,291 @@
+//! Request decompression layer for servers, see
+//! [`WithDecompressionLayer`].
+
+use std::{
+    future::Future,
+    io::{self, Read},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use http::{
+    header::{CONTENT_ENCODING, CONTENT_LENGTH},
+    HeaderValue, Request, Response, StatusCode,
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+#[derive(Debug)]
+#[derive(thiserror::Error)]
+/// Error decompressing the request body.
+pub enum DecompressError {
+    #[error("unsupported content encoding `{0}`")]
+    /// The content coding is not supported.
+    Unsupported(String),
+
+    #[error("decompressed body exceeds the limit of {0} bytes")]
+    /// The decompressed body exceeds the limit.
+    TooLarge(usize),
+
+    #[error("invalid compressed body: {0}")]
+    /// The body is corrupted.
+    Io(#[from] io::Error),
+}
+
+impl DecompressError {
+    /// The status code of the response rejecting the request.
+    pub const fn status(&self) -> StatusCode {
+        match self {
+            Self::Unsupported(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Self::TooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::Io(_) => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// [`Layer`] decompressing the (buffered) request body according to its
+/// `Content-Encoding`.
+///
+/// `Content-Encoding` is removed and `Content-Length` is updated after
+/// decompression. Requests are rejected with:
+///
+/// - `415 Unsupported Media Type`, if the coding is not supported;
+/// - `413 Payload Too Large`, if the decompressed body exceeds the limits;
+/// - `400 Bad Request`, if the body is corrupted.
+///
+/// Supported codings are gated behind features:
+/// `feat-layer-decompression-gzip` (`gzip` and `deflate`) and
+/// `feat-layer-decompression-zstd`.
+pub struct WithDecompressionLayer {
+    max_size: usize,
+    max_ratio: Option<usize>,
+}
+
+impl Default for WithDecompressionLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WithDecompressionLayer {
+    #[inline]
+    /// Create a new [`WithDecompressionLayer`], with the size limit of 16 MiB
+    /// and the ratio limit of 100.
+    pub const fn new() -> Self {
+        Self {
+            max_size: 16 * 1024 * 1024,
+            max_ratio: Some(100),
+        }
+    }
+
+    #[inline]
+    /// Set the maximum size in bytes of the decompressed body.
+    pub const fn with_max_size(self, max_size: usize) -> Self {
+        Self { max_size, ..self }
+    }
+
+    #[inline]
+    /// Set the maximum ratio of the decompressed size to the compressed
+    /// size, `None` for unlimited.
+    pub const fn with_max_ratio(self, max_ratio: Option<usize>) -> Self {
+        Self { max_ratio, ..self }
+    }
+
+    /// The size limit of the decompressed body.
+    fn limit(&self, compressed_len: usize) -> usize {
+        self.max_ratio.map_or(self.max_size, |max_ratio| {
+            self.max_size.min(compressed_len.saturating_mul(max_ratio))
+        })
+    }
+
+    /// Decompress the request body in place.
+    ///
+    /// # Errors
+    ///
+    /// See [`DecompressError`].
+    pub fn decompress<B>(&self, req: &mut Request<B>) -> Result<(), DecompressError>
+    where
+        B: AsRef<[u8]> + From<Vec<u8>>,
+    {
+        let Some(encodings) = req.headers().get(CONTENT_ENCODING) else {
+            return Ok(());
+        };
+
+        let encodings = encodings
+            .to_str()
+            .map_err(|_| {
+                DecompressError::Unsupported(
+                    String::from_utf8_lossy(encodings.as_bytes()).into_owned(),
+                )
+            })?
+            .to_ascii_lowercase();
+
+        let limit = self.limit(req.body().as_ref().len());
+        let mut body = None::<Vec<u8>>;
+
+        // Codings are listed in the order they were applied.
+        for encoding in encodings
+            .rsplit(',')
+            .map(str::trim)
+            .filter(|encoding| !encoding.is_empty() && *encoding != "identity")
+        {
+            let compressed = body.as_deref().unwrap_or(req.body().as_ref());
+            body = Some(decompress(encoding, compressed, limit)?);
+        }
+
+        req.headers_mut().remove(CONTENT_ENCODING);
+
+        if let Some(body) = body {
+            req.headers_mut()
+                .insert(CONTENT_LENGTH, HeaderValue::from(body.len()));
+            *req.body_mut() = B::from(body);
+        }
+
+        Ok(())
+    }
+}
+
+/// Decompress the body with given content coding, up to `limit` bytes.
+fn decompress(encoding: &str, body: &[u8], limit: usize) -> Result<Vec<u8>, DecompressError> {
+    match encoding {
+        #[cfg(feature = "feat-layer-decompression-gzip")]
+        "gzip" | "x-gzip" => read_to_end(flate2::read::MultiGzDecoder::new(body), limit),
+        #[cfg(feature = "feat-layer-decompression-gzip")]
+        "deflate" => read_to_end(flate2::read::ZlibDecoder::new(body), limit),
+        #[cfg(feature = "feat-layer-decompression-zstd")]
+        "zstd" => read_to_end(zstd::stream::read::Decoder::new(body)?, limit),
+        _ => {
+            let _ = (body, limit);
+
+            Err(DecompressError::Unsupported(encoding.to_owned()))
+        }
+    }
+}
+
+#[allow(dead_code, reason = "unused if no codec feature is enabled")]
+fn read_to_end(decoder: impl Read, limit: usize) -> Result<Vec<u8>, DecompressError> {
+    let mut buf = Vec::new();
+
+    // Read one more byte to tell if the limit is exceeded.
+    decoder
+        .take((limit as u64).saturating_add(1))
+        .read_to_end(&mut buf)?;
+
+    if buf.len() > limit {
+        return Err(DecompressError::TooLarge(limit));
+    }
+
+    Ok(buf)
+}
+
+impl<S> Layer<S> for WithDecompressionLayer {
+    type Service = WithDecompressionService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        WithDecompressionService {
+            inner,
+            config: *self,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// [`Service`] decompressing the request body, see
+/// [`WithDecompressionLayer`].
+pub struct WithDecompressionService<S> {
+    inner: S,
+    config: WithDecompressionLayer,
+}
+
+impl<S, ResBody> Service<Request<Bytes>> for WithDecompressionService<S>
+where
+    S: Service<Request<Bytes>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+    ResBody: Default + Send + 'static,
+{
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+    type Response = S::Response;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<Bytes>) -> Self::Future {
+        if let Err(e) = self.config.decompress(&mut req) {
+            #[cfg(feature = "feat-tracing")]
+            tracing::debug!("Rejected request: {e}");
+
+            let mut response = Response::new(ResBody::default());
+            *response.status_mut() = e.status();
+
+            return Box::pin(std::future::ready(Ok(response)));
+        }
+
+        Box::pin(self.inner.call(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(encoding: &'static str, body: Vec<u8>) -> Request<Bytes> {
+        Request::post("/")
+            .header(CONTENT_ENCODING, encoding)
+            .header(CONTENT_LENGTH, body.len())
+            .body(Bytes::from(body))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let mut req = request("unknown", b"hello".to_vec());
+        let err = WithDecompressionLayer::new()
+            .decompress(&mut req)
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        let mut req = request("identity", b"hello".to_vec());
+        WithDecompressionLayer::new().decompress(&mut req).unwrap();
+        assert!(!req.headers().contains_key(CONTENT_ENCODING));
+        assert_eq!(req.body(), "hello");
+    }
+
+    #[cfg(feature = "feat-layer-decompression-gzip")]
+    #[test]
+    fn test_gzip() {
+        use std::io::Write;
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+        encoder.write_all(&[b'a'; 4096]).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut req = request("gzip", compressed.clone());
+        WithDecompressionLayer::new()
+            .with_max_ratio(None)
+            .decompress(&mut req)
+            .unwrap();
+        assert_eq!(req.body(), &[b'a'; 4096][..]);
+        assert_eq!(req.headers()[CONTENT_LENGTH], "4096");
+        assert!(!req.headers().contains_key(CONTENT_ENCODING));
+
+        let mut req = request("gzip", compressed.clone());
+        let err = WithDecompressionLayer::new()
+            .with_max_ratio(Some(2))
+            .decompress(&mut req)
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let mut req = request("gzip", compressed);
+        let err = WithDecompressionLayer::new()
+            .with_max_size(4095)
+            .with_max_ratio(None)
+            .decompress(&mut req)
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
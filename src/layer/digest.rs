@@ -0,0 +1,128 @@
+//! Request body digest generation layer, see [`WithContentDigestLayer`].
+//!
+//! Pairs with the digest verification of
+//! [`response::digest`](crate::response::digest).
+
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use http::{header::CONTENT_LENGTH, HeaderValue, Request};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::response::digest::{digest_header, DigestAlgorithm};
+
+#[derive(Debug, Clone, Copy)]
+/// [`Layer`] inserting the digest header of the (buffered) request body, see
+/// [`digest_header`].
+///
+/// Existing digest header is overwritten.
+pub struct WithContentDigestLayer {
+    algorithm: DigestAlgorithm,
+    fix_content_length: bool,
+}
+
+impl Default for WithContentDigestLayer {
+    fn default() -> Self {
+        Self::new(DigestAlgorithm::Sha256)
+    }
+}
+
+impl WithContentDigestLayer {
+    #[inline]
+    /// Create a new [`WithContentDigestLayer`].
+    pub const fn new(algorithm: DigestAlgorithm) -> Self {
+        Self {
+            algorithm,
+            fix_content_length: false,
+        }
+    }
+
+    #[inline]
+    /// Whether to set `Content-Length` to the actual length of the body.
+    pub const fn with_fix_content_length(self, fix_content_length: bool) -> Self {
+        Self {
+            fix_content_length,
+            ..self
+        }
+    }
+}
+
+impl<S> Layer<S> for WithContentDigestLayer {
+    type Service = WithContentDigestService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        WithContentDigestService {
+            inner,
+            config: *self,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// [`Service`] inserting the digest header of the request body, see
+/// [`WithContentDigestLayer`].
+pub struct WithContentDigestService<S> {
+    inner: S,
+    config: WithContentDigestLayer,
+}
+
+impl<S> Service<Request<Bytes>> for WithContentDigestService<S>
+where
+    S: Service<Request<Bytes>>,
+{
+    type Error = S::Error;
+    type Future = S::Future;
+    type Response = S::Response;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<Bytes>) -> Self::Future {
+        let (name, value) = digest_header(self.config.algorithm, req.body());
+        req.headers_mut().insert(name, value);
+
+        if self.config.fix_content_length {
+            let content_length = HeaderValue::from(req.body().len());
+            req.headers_mut().insert(CONTENT_LENGTH, content_length);
+        }
+
+        self.inner.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use super::*;
+    use crate::response::digest::verify_headers;
+
+    #[tokio::test]
+    async fn test_content_digest_layer() {
+        let echo =
+            tower::service_fn(|req: Request<Bytes>| std::future::ready(Ok::<_, Infallible>(req)));
+
+        let mut service = WithContentDigestLayer::default()
+            .with_fix_content_length(true)
+            .layer(echo);
+
+        let req = Request::post("/")
+            .header(CONTENT_LENGTH, "1")
+            .body(Bytes::from_static(b"{\"hello\": \"world\"}\n"))
+            .unwrap();
+        let req = service.call(req).await.unwrap();
+
+        assert_eq!(
+            req.headers()["content-digest"],
+            "sha-256=:RK/0qy18MlBSVnWgjwz6lZEWjP/lF5HF9bvEF8FabDg=:"
+        );
+        assert_eq!(req.headers()[CONTENT_LENGTH], "19");
+        assert_eq!(verify_headers(req.headers(), req.body()), Ok(()));
+
+        let mut service = WithContentDigestLayer::new(DigestAlgorithm::Md5).layer(echo);
+        let req = service.call(Request::new(Bytes::new())).await.unwrap();
+        assert_eq!(req.headers()["content-md5"], "1B2M2Y8AsgTpgAmY7PhCfg==");
+    }
+}
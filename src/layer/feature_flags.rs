@@ -0,0 +1,258 @@
+//! Header-based feature flags, see [`WithFeatureFlagsLayer`].
+//!
+//! Note that the headers are controlled by the client, so strip them at the
+//! edge if the flags must not be set by end users.
+
+use std::{
+    collections::HashMap,
+    task::{Context, Poll},
+};
+
+use http::{HeaderMap, Request};
+use tower_layer::Layer;
+use tower_service::Service;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Value of a feature flag, coerced from the header value.
+pub enum FlagValue {
+    /// `true` / `false`, `on` / `off`, `yes` / `no` (case-insensitive), or
+    /// empty (`true`).
+    Bool(bool),
+
+    /// Integers like `42`, `-1`.
+    Int(i64),
+
+    /// Anything else.
+    Str(String),
+}
+
+impl FlagValue {
+    /// Coerce the header value.
+    pub fn coerce(value: &str) -> Self {
+        let value = value.trim();
+
+        match value.to_ascii_lowercase().as_str() {
+            "" | "true" | "on" | "yes" => Self::Bool(true),
+            "false" | "off" | "no" => Self::Bool(false),
+            _ => value
+                .parse()
+                .map_or_else(|_| Self::Str(value.to_owned()), Self::Int),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// Feature flags parsed from the request headers, stored in the request
+/// extensions by [`WithFeatureFlagsService`].
+///
+/// Flag names are lowercase header names without the prefix, e.g.
+/// `new-checkout` of `x-feature-new-checkout`.
+pub struct FeatureFlags {
+    flags: HashMap<String, FlagValue>,
+}
+
+impl FeatureFlags {
+    /// Parse the flags from the headers, see [`WithFeatureFlagsLayer`].
+    ///
+    /// Values which are not visible ASCII are ignored. When a header appears
+    /// more than once, the first one wins.
+    pub fn from_headers(headers: &HeaderMap, config: &WithFeatureFlagsLayer) -> Self {
+        let mut flags = HashMap::new();
+
+        for (name, value) in headers {
+            let name = name.as_str();
+
+            let flag = match config.prefix.and_then(|prefix| name.strip_prefix(prefix)) {
+                Some(flag) if !flag.is_empty() => flag,
+                _ if config.headers.iter().any(|h| h.eq_ignore_ascii_case(name)) => name,
+                _ => continue,
+            };
+
+            if flags.contains_key(flag) {
+                continue;
+            }
+
+            if let Ok(value) = value.to_str() {
+                flags.insert(flag.to_owned(), FlagValue::coerce(value));
+            }
+        }
+
+        Self { flags }
+    }
+
+    #[inline]
+    /// Returns the value of the flag.
+    pub fn get(&self, flag: &str) -> Option<&FlagValue> {
+        self.flags.get(flag)
+    }
+
+    /// Whether the flag is on, i.e. `true` or a non-zero integer.
+    pub fn is_enabled(&self, flag: &str) -> bool {
+        matches!(self.get(flag), Some(FlagValue::Bool(true)))
+            || matches!(self.get(flag), Some(FlagValue::Int(n)) if *n != 0)
+    }
+
+    /// Returns the integer value of the flag.
+    pub fn get_int(&self, flag: &str) -> Option<i64> {
+        match self.get(flag)? {
+            FlagValue::Int(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// Returns the string value of the flag (booleans and integers included,
+    /// as is).
+    pub fn get_str(&self, flag: &str) -> Option<String> {
+        match self.get(flag)? {
+            FlagValue::Bool(b) => Some(b.to_string()),
+            FlagValue::Int(n) => Some(n.to_string()),
+            FlagValue::Str(s) => Some(s.clone()),
+        }
+    }
+
+    #[inline]
+    /// Returns the number of flags.
+    pub fn len(&self) -> usize {
+        self.flags.len()
+    }
+
+    #[inline]
+    /// Whether there's no flag.
+    pub fn is_empty(&self) -> bool {
+        self.flags.is_empty()
+    }
+
+    #[inline]
+    /// Iterate over the flags, in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &FlagValue)> {
+        self.flags
+            .iter()
+            .map(|(flag, value)| (flag.as_str(), value))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// [`Layer`] parsing the [`FeatureFlags`] from the declared headers, storing
+/// it in the request extensions.
+///
+/// Headers with the prefix (`x-feature-` by default), and the explicitly
+/// declared ones (named as is), are parsed.
+pub struct WithFeatureFlagsLayer {
+    prefix: Option<&'static str>,
+    headers: &'static [&'static str],
+}
+
+impl Default for WithFeatureFlagsLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WithFeatureFlagsLayer {
+    #[inline]
+    /// Create a new [`WithFeatureFlagsLayer`] with prefix `x-feature-`.
+    pub const fn new() -> Self {
+        Self {
+            prefix: Some("x-feature-"),
+            headers: &[],
+        }
+    }
+
+    #[inline]
+    /// Set the lowercase header name prefix, `None` to parse only the
+    /// declared headers.
+    pub const fn with_prefix(self, prefix: Option<&'static str>) -> Self {
+        Self { prefix, ..self }
+    }
+
+    #[inline]
+    /// Set the declared headers, e.g. `x-canary`.
+    pub const fn with_headers(self, headers: &'static [&'static str]) -> Self {
+        Self { headers, ..self }
+    }
+}
+
+impl<S> Layer<S> for WithFeatureFlagsLayer {
+    type Service = WithFeatureFlagsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        WithFeatureFlagsService {
+            inner,
+            config: *self,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// [`Service`] parsing the feature flags, see [`WithFeatureFlagsLayer`].
+pub struct WithFeatureFlagsService<S> {
+    inner: S,
+    config: WithFeatureFlagsLayer,
+}
+
+impl<S, ReqBody> Service<Request<ReqBody>> for WithFeatureFlagsService<S>
+where
+    S: Service<Request<ReqBody>>,
+{
+    type Error = S::Error;
+    type Future = S::Future;
+    type Response = S::Response;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        let flags = FeatureFlags::from_headers(req.headers(), &self.config);
+
+        req.extensions_mut().insert(flags);
+
+        self.inner.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use super::*;
+
+    #[test]
+    fn test_coerce() {
+        assert_eq!(FlagValue::coerce("ON"), FlagValue::Bool(true));
+        assert_eq!(FlagValue::coerce(""), FlagValue::Bool(true));
+        assert_eq!(FlagValue::coerce(" no "), FlagValue::Bool(false));
+        assert_eq!(FlagValue::coerce("-3"), FlagValue::Int(-3));
+        assert_eq!(FlagValue::coerce("blue"), FlagValue::Str("blue".to_owned()));
+    }
+
+    #[tokio::test]
+    async fn test_with_feature_flags_layer() {
+        let echo = tower::service_fn(|req: Request<()>| {
+            std::future::ready(Ok::<_, Infallible>(
+                req.extensions().get::<FeatureFlags>().cloned().unwrap(),
+            ))
+        });
+        let mut service = WithFeatureFlagsLayer::new()
+            .with_headers(&["x-canary"])
+            .layer(echo);
+
+        let req = Request::get("/")
+            .header("X-Feature-New-Checkout", "true")
+            .header("x-feature-page-size", "50")
+            .header("x-feature-theme", "dark")
+            .header("x-feature-theme", "light")
+            .header("x-canary", "1")
+            .header("x-other", "1")
+            .body(())
+            .unwrap();
+
+        let flags = service.call(req).await.unwrap();
+        assert_eq!(flags.len(), 4);
+        assert!(flags.is_enabled("new-checkout"));
+        assert!(flags.is_enabled("x-canary"));
+        assert_eq!(flags.get_int("page-size"), Some(50));
+        assert_eq!(flags.get_str("theme").as_deref(), Some("dark"));
+        assert!(!flags.is_enabled("other"));
+    }
+}
@@ -0,0 +1,341 @@
+//! Response header policy enforcement for servers, see
+//! [`WithHeaderPolicyLayer`].
+
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use http::{header::Entry, HeaderMap, Request, Response, StatusCode};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::response::{
+    snapshot::{redact_headers, DEFAULT_REDACTED_HEADERS},
+    SecurityHeaders,
+};
+
+/// Header name prefixes stripped by default.
+pub const DEFAULT_STRIPPED_PREFIXES: &[&str] = &["x-internal-"];
+
+#[derive(Debug, Clone)]
+/// Header policy of the responses, see [`WithHeaderPolicyLayer`].
+pub struct HeaderPolicy {
+    stripped_prefixes: &'static [&'static str],
+    security: Option<SecurityHeaders>,
+    max_count: Option<usize>,
+    max_size: Option<usize>,
+    redacted_headers: &'static [&'static str],
+}
+
+impl Default for HeaderPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HeaderPolicy {
+    #[inline]
+    /// Create a new [`HeaderPolicy`], stripping [`DEFAULT_STRIPPED_PREFIXES`]
+    /// and logging with [`DEFAULT_REDACTED_HEADERS`] redacted.
+    pub const fn new() -> Self {
+        Self {
+            stripped_prefixes: DEFAULT_STRIPPED_PREFIXES,
+            security: None,
+            max_count: None,
+            max_size: None,
+            redacted_headers: DEFAULT_REDACTED_HEADERS,
+        }
+    }
+
+    #[must_use]
+    /// Set the header name prefixes (lowercase) to strip.
+    pub fn with_stripped_prefixes(self, stripped_prefixes: &'static [&'static str]) -> Self {
+        Self {
+            stripped_prefixes,
+            ..self
+        }
+    }
+
+    #[must_use]
+    /// Ensure the security headers exist, see [`SecurityHeaders::apply`].
+    pub fn with_security_headers(self, security: SecurityHeaders) -> Self {
+        Self {
+            security: Some(security),
+            ..self
+        }
+    }
+
+    #[must_use]
+    /// Cap the number of headers (values), responding `500 Internal Server
+    /// Error` instead if exceeded.
+    pub fn with_max_count(self, max_count: Option<usize>) -> Self {
+        Self { max_count, ..self }
+    }
+
+    #[must_use]
+    /// Cap the total size of header names and values in bytes, responding
+    /// `500 Internal Server Error` instead if exceeded.
+    pub fn with_max_size(self, max_size: Option<usize>) -> Self {
+        Self { max_size, ..self }
+    }
+
+    #[must_use]
+    /// Set the headers (lowercase) whose values are redacted when logging the
+    /// violations.
+    pub fn with_redacted_headers(self, redacted_headers: &'static [&'static str]) -> Self {
+        Self {
+            redacted_headers,
+            ..self
+        }
+    }
+
+    /// Enforce the policy on the headers.
+    ///
+    /// # Errors
+    ///
+    /// [`HeaderPolicyViolation`] if the count or size cap is exceeded, after
+    /// stripping and adding the security headers.
+    pub fn enforce(&self, headers: &mut HeaderMap) -> Result<(), HeaderPolicyViolation> {
+        let stripped: Vec<_> = headers
+            .keys()
+            .filter(|name| {
+                self.stripped_prefixes
+                    .iter()
+                    .any(|prefix| name.as_str().starts_with(prefix))
+            })
+            .cloned()
+            .collect();
+
+        if !stripped.is_empty() {
+            let mut removed = HeaderMap::new();
+            for name in stripped {
+                if let Entry::Occupied(entry) = headers.entry(&name) {
+                    for value in entry.remove_entry_mult().1 {
+                        removed.append(&name, value);
+                    }
+                }
+            }
+
+            log(&HeaderPolicyViolation::Stripped(redact_headers(
+                &removed,
+                self.redacted_headers,
+            )));
+        }
+
+        if let Some(security) = &self.security {
+            security.apply(headers);
+        }
+
+        let violation = if let Some(max) = self.max_count.filter(|&max| headers.len() > max) {
+            Some(HeaderPolicyViolation::TooMany {
+                count: headers.len(),
+                max,
+            })
+        } else {
+            let size = headers
+                .iter()
+                .map(|(name, value)| name.as_str().len() + value.len())
+                .sum();
+
+            self.max_size
+                .filter(|&max| size > max)
+                .map(|max| HeaderPolicyViolation::TooLarge { size, max })
+        };
+
+        match violation {
+            Some(violation) => {
+                log(&violation);
+                Err(violation)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+#[allow(clippy::missing_const_for_fn)]
+fn log(_violation: &HeaderPolicyViolation) {
+    #[cfg(feature = "feat-tracing")]
+    tracing::warn!("Header policy violation: {_violation}");
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Violation of the [`HeaderPolicy`].
+pub enum HeaderPolicyViolation {
+    /// The headers are stripped, values redacted.
+    Stripped(Vec<(String, String)>),
+
+    /// Too many headers.
+    TooMany {
+        /// The number of headers.
+        count: usize,
+
+        /// The cap.
+        max: usize,
+    },
+
+    /// The headers are too large.
+    TooLarge {
+        /// The total size of headers.
+        size: usize,
+
+        /// The cap.
+        max: usize,
+    },
+}
+
+impl fmt::Display for HeaderPolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Stripped(headers) => write!(f, "stripped headers {headers:?}"),
+            Self::TooMany { count, max } => write!(f, "{count} headers, the cap is {max}"),
+            Self::TooLarge { size, max } => {
+                write!(f, "headers of {size} bytes, the cap is {max}")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// [`Layer`] enforcing the [`HeaderPolicy`] on the responses of the handlers:
+/// internal headers stripped, security headers ensured, and header count /
+/// size capped.
+pub struct WithHeaderPolicyLayer {
+    policy: Arc<HeaderPolicy>,
+}
+
+impl WithHeaderPolicyLayer {
+    #[inline]
+    /// Create a new [`WithHeaderPolicyLayer`].
+    pub fn new(policy: HeaderPolicy) -> Self {
+        Self {
+            policy: Arc::new(policy),
+        }
+    }
+}
+
+impl<S> Layer<S> for WithHeaderPolicyLayer {
+    type Service = WithHeaderPolicyService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        WithHeaderPolicyService {
+            inner,
+            policy: self.policy.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// [`Service`] enforcing the header policy, see [`WithHeaderPolicyLayer`].
+pub struct WithHeaderPolicyService<S> {
+    inner: S,
+    policy: Arc<HeaderPolicy>,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for WithHeaderPolicyService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+    ResBody: Default,
+{
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+    type Response = S::Response;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let policy = self.policy.clone();
+        let future = self.inner.call(req);
+
+        Box::pin(async move {
+            let mut response = future.await?;
+
+            if policy.enforce(response.headers_mut()).is_err() {
+                let mut rejected = Response::new(ResBody::default());
+                *rejected.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+
+                return Ok(rejected);
+            }
+
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use http::{header, HeaderValue};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_header_policy_layer() {
+        let upstream = |extra: usize| {
+            tower::service_fn(move |_: Request<()>| {
+                let mut response = Response::new("ok".to_owned());
+                let headers = response.headers_mut();
+                headers.insert("x-internal-trace", HeaderValue::from_static("node-1"));
+                headers.insert(
+                    header::X_FRAME_OPTIONS,
+                    HeaderValue::from_static("SAMEORIGIN"),
+                );
+                for _ in 0..extra {
+                    headers.append("x-extra", HeaderValue::from_static("1"));
+                }
+
+                std::future::ready(Ok::<_, Infallible>(response))
+            })
+        };
+        let layer = WithHeaderPolicyLayer::new(
+            HeaderPolicy::new()
+                .with_security_headers(SecurityHeaders::recommended())
+                .with_max_count(Some(8)),
+        );
+
+        let response = layer
+            .layer(upstream(0))
+            .call(Request::new(()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(!response.headers().contains_key("x-internal-trace"));
+        assert_eq!(response.headers()[header::X_FRAME_OPTIONS], "SAMEORIGIN");
+        assert_eq!(
+            response.headers()[header::X_CONTENT_TYPE_OPTIONS],
+            "nosniff"
+        );
+
+        let response = layer
+            .layer(upstream(5))
+            .call(Request::new(()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(response.headers().is_empty());
+    }
+
+    #[test]
+    fn test_enforce() {
+        let policy = HeaderPolicy::new().with_max_size(Some(16));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-internal-token", HeaderValue::from_static("secret"));
+        headers.insert(header::SET_COOKIE, HeaderValue::from_static("a=1"));
+        policy.enforce(&mut headers).unwrap();
+        assert_eq!(headers.len(), 1);
+
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+        assert_eq!(
+            policy.enforce(&mut headers).unwrap_err(),
+            HeaderPolicyViolation::TooLarge { size: 35, max: 16 }
+        );
+    }
+}
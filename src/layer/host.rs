@@ -0,0 +1,391 @@
+//! `Host` / `:authority` validation against host header injection, see
+//! [`validate_host`] and [`WithHostValidationLayer`].
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use http::{header, uri::Authority, HeaderMap, Request, Response, StatusCode, Uri};
+use tower_layer::Layer;
+use tower_service::Service;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(thiserror::Error)]
+/// Error validating the host.
+pub enum HostError {
+    #[error("missing host")]
+    /// Neither `Host` nor `:authority` is present.
+    Missing,
+
+    #[error("invalid host `{0}`")]
+    /// Malformed host, e.g. with user info.
+    Invalid(String),
+
+    #[error("host `{0}` is not allowed")]
+    /// The host is not in the [`HostAllowList`].
+    NotAllowed(String),
+
+    #[error("host `{host}` mismatches authority `{authority}`")]
+    /// `Host` and `:authority` are both present but differ.
+    Mismatch {
+        /// The `Host` header value.
+        host: String,
+
+        /// The `:authority` of the request URI.
+        authority: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum HostPattern {
+    /// Exact host name or IP.
+    Exact(String),
+
+    /// `*.example.com`, stored as `.example.com`.
+    Subdomain(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct AllowedHost {
+    host: HostPattern,
+    /// `None` for any port.
+    port: Option<u16>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// Allowed hosts, see [`with_host`](Self::with_host) for the patterns.
+pub struct HostAllowList {
+    hosts: Vec<AllowedHost>,
+}
+
+impl HostAllowList {
+    #[inline]
+    /// Create an empty [`HostAllowList`], allowing nothing.
+    pub const fn new() -> Self {
+        Self { hosts: Vec::new() }
+    }
+
+    #[must_use]
+    /// Allow the host pattern, which is one of:
+    ///
+    /// - `example.com` or `[::1]`, the exact host with any port;
+    /// - `*.example.com`, any subdomain (but not `example.com` itself) with
+    ///   any port;
+    /// - any of above with `:port`, e.g. `example.com:8080`, with the exact
+    ///   port only (`:*` means any port).
+    ///
+    /// Host names are matched case-insensitively, ignoring the trailing dot.
+    /// Invalid patterns are ignored.
+    pub fn with_host(mut self, pattern: &str) -> Self {
+        let (host, port) = split_port(pattern);
+
+        let port = match port {
+            None | Some("*") => None,
+            Some(port) => match port.parse() {
+                Ok(port) => Some(port),
+                Err(_) => return self,
+            },
+        };
+
+        let host = normalize(host);
+        let host = match host.strip_prefix("*.") {
+            Some(suffix) if !suffix.is_empty() => HostPattern::Subdomain(format!(".{suffix}")),
+            Some(_) => return self,
+            None if host.is_empty() || host.contains('*') => return self,
+            None => HostPattern::Exact(host),
+        };
+
+        self.hosts.push(AllowedHost { host, port });
+        self
+    }
+
+    /// Whether the host (normalized, without port) and port are allowed.
+    fn allows(&self, host: &str, port: Option<u16>) -> bool {
+        self.hosts.iter().any(|allowed| {
+            let host_matched = match &allowed.host {
+                HostPattern::Exact(exact) => exact == host,
+                HostPattern::Subdomain(suffix) => {
+                    host.len() > suffix.len() && host.ends_with(suffix.as_str())
+                }
+            };
+
+            host_matched && allowed.port.map_or(true, |allowed| Some(allowed) == port)
+        })
+    }
+}
+
+impl<S: AsRef<str>> FromIterator<S> for HostAllowList {
+    fn from_iter<I: IntoIterator<Item = S>>(iter: I) -> Self {
+        iter.into_iter().fold(Self::new(), |list, pattern| {
+            list.with_host(pattern.as_ref())
+        })
+    }
+}
+
+/// Split `host:port`, taking care of IPv6 literal like `[::1]:8080`.
+fn split_port(value: &str) -> (&str, Option<&str>) {
+    let port_start = if value.starts_with('[') {
+        value.find(']').map(|end| end + 1)
+    } else {
+        value.find(':')
+    };
+
+    match port_start {
+        Some(index) if value[index..].starts_with(':') => {
+            (&value[..index], Some(&value[index + 1..]))
+        }
+        _ => (value, None),
+    }
+}
+
+/// Lowercase and strip the trailing dot.
+fn normalize(host: &str) -> String {
+    host.trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// Parse and validate the authority.
+fn check(authority: &str, allowed: &HostAllowList) -> Result<(String, Option<u16>), HostError> {
+    let invalid = || HostError::Invalid(authority.to_owned());
+
+    // Reject user info, and empty or non-numeric ports.
+    let parsed: Authority = authority.parse().map_err(|_| invalid())?;
+    if authority.contains('@') || parsed.host().is_empty() || (parsed.as_str().ends_with(':')) {
+        return Err(invalid());
+    }
+
+    let host = normalize(parsed.host());
+    let port = parsed.port_u16();
+
+    if !allowed.allows(&host, port) {
+        return Err(HostError::NotAllowed(authority.to_owned()));
+    }
+
+    Ok((host, port))
+}
+
+/// Validate the `Host` header against the [`HostAllowList`].
+///
+/// Returns the host (lowercase, without port and trailing dot).
+///
+/// # Errors
+///
+/// See [`HostError`].
+pub fn validate_host(headers: &HeaderMap, allowed: &HostAllowList) -> Result<String, HostError> {
+    let mut values = headers.get_all(header::HOST).iter();
+
+    let host = values.next().ok_or(HostError::Missing)?;
+    let host = host
+        .to_str()
+        .map_err(|_| HostError::Invalid(String::from_utf8_lossy(host.as_bytes()).into_owned()))?;
+
+    // Multiple `Host` headers are invalid, see RFC 9112, section 3.2.
+    if values.next().is_some() {
+        return Err(HostError::Invalid(host.to_owned()));
+    }
+
+    check(host, allowed).map(|(host, _)| host)
+}
+
+/// Validate both the `Host` header and the `:authority` (i.e. the authority
+/// of the request URI, for HTTP/2 and absolute-form requests).
+///
+/// When both are present, they must be the same.
+///
+/// # Errors
+///
+/// See [`HostError`].
+pub fn validate_authority(
+    uri: &Uri,
+    headers: &HeaderMap,
+    allowed: &HostAllowList,
+) -> Result<String, HostError> {
+    let Some(authority) = uri.authority() else {
+        return validate_host(headers, allowed);
+    };
+
+    let (host, port) = check(authority.as_str(), allowed)?;
+
+    match validate_host(headers, allowed) {
+        Err(HostError::Missing) => Ok(host),
+        Err(e) => Err(e),
+        Ok(from_header) => {
+            let port_from_header = headers
+                .get(header::HOST)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| split_port(v).1)
+                .and_then(|port| port.parse().ok());
+
+            if from_header == host && port_from_header == port {
+                Ok(host)
+            } else {
+                Err(HostError::Mismatch {
+                    host: headers
+                        .get(header::HOST)
+                        .and_then(|v| v.to_str().ok())
+                        .unwrap_or_default()
+                        .to_owned(),
+                    authority: authority.to_string(),
+                })
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// [`Layer`] rejecting requests whose `Host` / `:authority` is not allowed
+/// with `400 Bad Request`, see [`validate_authority`].
+pub struct WithHostValidationLayer {
+    allowed: Arc<HostAllowList>,
+}
+
+impl WithHostValidationLayer {
+    #[inline]
+    /// Create a new [`WithHostValidationLayer`].
+    pub fn new(allowed: HostAllowList) -> Self {
+        Self {
+            allowed: Arc::new(allowed),
+        }
+    }
+}
+
+impl<S> Layer<S> for WithHostValidationLayer {
+    type Service = WithHostValidationService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        WithHostValidationService {
+            inner,
+            allowed: self.allowed.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// [`Service`] validating the host, see [`WithHostValidationLayer`].
+pub struct WithHostValidationService<S> {
+    inner: S,
+    allowed: Arc<HostAllowList>,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for WithHostValidationService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+    ResBody: Default + Send + 'static,
+{
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+    type Response = S::Response;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let result = validate_authority(req.uri(), req.headers(), &self.allowed);
+
+        #[cfg(feature = "feat-tracing")]
+        if let Err(e) = &result {
+            tracing::warn!("Rejected request: {e}");
+        }
+
+        if result.is_err() {
+            let mut response = Response::new(ResBody::default());
+            *response.status_mut() = StatusCode::BAD_REQUEST;
+
+            return Box::pin(std::future::ready(Ok(response)));
+        }
+
+        Box::pin(self.inner.call(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::HeaderValue;
+
+    use super::*;
+
+    fn headers(host: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::HOST, HeaderValue::from_static(host));
+        headers
+    }
+
+    #[test]
+    fn test_validate_host() {
+        let allowed: HostAllowList = ["Example.com", "*.example.org", "localhost:8080", "[::1]"]
+            .into_iter()
+            .collect();
+
+        assert_eq!(
+            validate_host(&headers("example.COM."), &allowed),
+            Ok("example.com".to_owned())
+        );
+        assert_eq!(
+            validate_host(&headers("example.com:443"), &allowed).unwrap(),
+            "example.com"
+        );
+        assert_eq!(
+            validate_host(&headers("a.b.example.org"), &allowed).unwrap(),
+            "a.b.example.org"
+        );
+        assert_eq!(
+            validate_host(&headers("[::1]:3000"), &allowed).unwrap(),
+            "[::1]"
+        );
+        assert_eq!(
+            validate_host(&headers("localhost:8080"), &allowed).unwrap(),
+            "localhost"
+        );
+
+        assert_eq!(
+            validate_host(&headers("example.org"), &allowed),
+            Err(HostError::NotAllowed("example.org".to_owned()))
+        );
+        assert_eq!(
+            validate_host(&headers("localhost:8081"), &allowed).unwrap_err(),
+            HostError::NotAllowed("localhost:8081".to_owned())
+        );
+        assert_eq!(
+            validate_host(&headers("evil.com"), &allowed).unwrap_err(),
+            HostError::NotAllowed("evil.com".to_owned())
+        );
+        assert!(matches!(
+            validate_host(&headers("user@example.com"), &allowed),
+            Err(HostError::Invalid(_))
+        ));
+        assert_eq!(
+            validate_host(&HeaderMap::new(), &allowed),
+            Err(HostError::Missing)
+        );
+    }
+
+    #[test]
+    fn test_validate_authority() {
+        let allowed = HostAllowList::new().with_host("example.com");
+
+        let uri = Uri::from_static("https://example.com/a");
+        assert_eq!(
+            validate_authority(&uri, &HeaderMap::new(), &allowed).unwrap(),
+            "example.com"
+        );
+        assert_eq!(
+            validate_authority(&uri, &headers("example.com"), &allowed).unwrap(),
+            "example.com"
+        );
+        assert!(matches!(
+            validate_authority(&uri, &headers("example.com:8080"), &allowed),
+            Err(HostError::Mismatch { .. })
+        ));
+
+        let uri = Uri::from_static("/a");
+        assert_eq!(
+            validate_authority(&uri, &headers("example.com"), &allowed).unwrap(),
+            "example.com"
+        );
+    }
+}
@@ -0,0 +1,348 @@
+//! `Accept-Language` based locale resolution, see [`resolve_locale`] and
+//! [`WithLocaleLayer`].
+
+use std::{
+    borrow::Cow,
+    fmt,
+    str::FromStr,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use http::{header, HeaderValue, Request};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use super::negotiate::{match_language, parse_quality_list};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(thiserror::Error)]
+#[error("invalid language tag `{0}`")]
+/// Invalid language tag, see [`LanguageTag`].
+pub struct InvalidLanguageTag(pub String);
+
+#[derive(Debug, Clone)]
+/// Language tag (RFC 5646) like `en`, `zh-Hans-CN`, compared
+/// case-insensitively.
+///
+/// Only the syntax of subtags is checked, i.e. `-` separated subtags of 1 to
+/// 8 alphanumerics, where the first one is alphabetic.
+pub struct LanguageTag(Cow<'static, str>);
+
+impl LanguageTag {
+    /// Create a [`LanguageTag`] from a static string.
+    ///
+    /// # Panics
+    ///
+    /// Panic if the tag is invalid.
+    pub const fn from_static(tag: &'static str) -> Self {
+        assert!(is_valid(tag.as_bytes()), "invalid language tag");
+
+        Self(Cow::Borrowed(tag))
+    }
+
+    #[inline]
+    /// Returns the tag as string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    #[inline]
+    /// Returns the primary language subtag, e.g. `zh` of `zh-Hans-CN`.
+    pub fn primary_language(&self) -> &str {
+        self.0.split('-').next().unwrap_or_default()
+    }
+}
+
+/// Check the syntax of the language tag.
+const fn is_valid(tag: &[u8]) -> bool {
+    let mut idx = 0;
+    let mut subtag_len = 0;
+    let mut is_first = true;
+
+    while idx < tag.len() {
+        let b = tag[idx];
+
+        if b == b'-' {
+            if subtag_len == 0 {
+                return false;
+            }
+
+            subtag_len = 0;
+            is_first = false;
+        } else if b.is_ascii_alphabetic() || (!is_first && b.is_ascii_digit()) {
+            subtag_len += 1;
+
+            if subtag_len > 8 {
+                return false;
+            }
+        } else {
+            return false;
+        }
+
+        idx += 1;
+    }
+
+    subtag_len > 0
+}
+
+impl FromStr for LanguageTag {
+    type Err = InvalidLanguageTag;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if is_valid(s.as_bytes()) {
+            Ok(Self(Cow::Owned(s.to_owned())))
+        } else {
+            Err(InvalidLanguageTag(s.to_owned()))
+        }
+    }
+}
+
+impl PartialEq for LanguageTag {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.eq_ignore_ascii_case(&other.0)
+    }
+}
+
+impl Eq for LanguageTag {}
+
+impl AsRef<str> for LanguageTag {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Display for LanguageTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// RFC 4647 lookup: truncate the range from the end until a tag equals it,
+/// e.g. `zh-Hant-TW` -> `zh-Hant` -> `zh`.
+fn lookup<'s>(range: &str, candidates: &[&'s LanguageTag]) -> Option<&'s LanguageTag> {
+    let mut range = range;
+
+    loop {
+        if let Some(&tag) = candidates
+            .iter()
+            .find(|tag| tag.as_str().eq_ignore_ascii_case(range))
+        {
+            return Some(tag);
+        }
+
+        range = &range[..range.rfind('-')?];
+
+        // Singletons (like `x` of private use) are removed along with the
+        // following subtag.
+        if range.len() >= 2 && range.as_bytes()[range.len() - 2] == b'-' {
+            range = &range[..range.len() - 2];
+        }
+    }
+}
+
+/// Resolve the locale from `supported` according to the `Accept-Language`
+/// header value.
+///
+/// For each language range in order of quality, the RFC 4647 lookup is tried
+/// first (`zh-TW` matches `zh`), then the basic filtering (`zh` matches
+/// `zh-CN`), and `*` matches the first one. Tags excluded by ranges with
+/// `q=0` are never picked. Ties are broken by the order of `supported`.
+pub fn resolve_locale(
+    accept_language: &HeaderValue,
+    supported: &[LanguageTag],
+) -> Option<LanguageTag> {
+    let items = parse_quality_list(accept_language.to_str().ok()?);
+
+    let candidates: Vec<_> = supported
+        .iter()
+        .filter(|tag| {
+            !items.iter().any(|item| {
+                item.quality == 0
+                    && item.value != "*"
+                    && match_language(item.value, tag.as_str()).is_some()
+            })
+        })
+        .collect();
+
+    items
+        .iter()
+        .filter(|item| item.quality > 0)
+        .find_map(|item| {
+            if item.value == "*" {
+                return candidates.first().copied();
+            }
+
+            lookup(item.value, &candidates).or_else(|| {
+                candidates
+                    .iter()
+                    .find(|tag| match_language(item.value, tag.as_str()).is_some())
+                    .copied()
+            })
+        })
+        .cloned()
+}
+
+#[derive(Debug, Clone)]
+/// [`Layer`] resolving the locale, storing the [`LanguageTag`] in the request
+/// extensions.
+///
+/// When `Accept-Language` is missing or nothing matches, the default one
+/// (the first supported one unless [set](Self::with_default)) is stored.
+pub struct WithLocaleLayer {
+    supported: Arc<[LanguageTag]>,
+    default: Option<LanguageTag>,
+}
+
+impl WithLocaleLayer {
+    /// Create a new [`WithLocaleLayer`] with the supported locales, in order
+    /// of preference.
+    pub fn new<I>(supported: I) -> Self
+    where
+        I: IntoIterator<Item = LanguageTag>,
+    {
+        let supported: Arc<[LanguageTag]> = supported.into_iter().collect();
+
+        Self {
+            default: supported.first().cloned(),
+            supported,
+        }
+    }
+
+    #[must_use]
+    /// Set the default locale, `None` to store nothing when nothing matches.
+    pub fn with_default(self, default: Option<LanguageTag>) -> Self {
+        Self { default, ..self }
+    }
+}
+
+impl<S> Layer<S> for WithLocaleLayer {
+    type Service = WithLocaleService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        WithLocaleService {
+            inner,
+            config: self.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// [`Service`] resolving the locale, see [`WithLocaleLayer`].
+pub struct WithLocaleService<S> {
+    inner: S,
+    config: WithLocaleLayer,
+}
+
+impl<S, ReqBody> Service<Request<ReqBody>> for WithLocaleService<S>
+where
+    S: Service<Request<ReqBody>>,
+{
+    type Error = S::Error;
+    type Future = S::Future;
+    type Response = S::Response;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        let locale = req
+            .headers()
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|value| resolve_locale(value, &self.config.supported))
+            .or_else(|| self.config.default.clone());
+
+        if let Some(locale) = locale {
+            req.extensions_mut().insert(locale);
+        }
+
+        self.inner.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use super::*;
+
+    const SUPPORTED: &[LanguageTag] = &[
+        LanguageTag::from_static("en-US"),
+        LanguageTag::from_static("zh"),
+        LanguageTag::from_static("zh-Hant"),
+        LanguageTag::from_static("fr-CA"),
+    ];
+
+    fn resolve(accept_language: &'static str) -> Option<String> {
+        resolve_locale(&HeaderValue::from_static(accept_language), SUPPORTED)
+            .map(|tag| tag.to_string())
+    }
+
+    #[test]
+    fn test_language_tag() {
+        for tag in ["zh-Hans-CN", "x-private"] {
+            assert_eq!(tag.parse::<LanguageTag>().unwrap().as_str(), tag);
+        }
+
+        for tag in ["en-", "1en", "en-toolongsubtag"] {
+            assert_eq!(
+                tag.parse::<LanguageTag>().unwrap_err(),
+                InvalidLanguageTag(tag.to_owned())
+            );
+        }
+
+        let tag: LanguageTag = "ZH-hans".parse().unwrap();
+        assert_eq!(tag, LanguageTag::from_static("zh-Hans"));
+        assert_eq!(tag.primary_language(), "ZH");
+    }
+
+    #[test]
+    fn test_resolve_locale() {
+        assert_eq!(resolve("zh-Hant-TW, en;q=0.5"), Some("zh-Hant".to_owned()));
+        assert_eq!(resolve("zh-CN"), Some("zh".to_owned()));
+        assert_eq!(resolve("fr, en;q=0.5"), Some("fr-CA".to_owned()));
+        assert_eq!(resolve("de, EN-us;q=0.1"), Some("en-US".to_owned()));
+        assert_eq!(resolve("*, en;q=0"), Some("zh".to_owned()));
+        assert_eq!(resolve("zh-x-private-a"), Some("zh".to_owned()));
+        assert_eq!(resolve("de, ja"), None);
+    }
+
+    fn request(accept_language: Option<&'static str>) -> Request<()> {
+        let mut req = Request::new(());
+
+        if let Some(value) = accept_language {
+            req.headers_mut()
+                .insert(header::ACCEPT_LANGUAGE, HeaderValue::from_static(value));
+        }
+
+        req
+    }
+
+    #[tokio::test]
+    async fn test_with_locale_layer() {
+        let echo = tower::service_fn(|req: Request<()>| {
+            std::future::ready(Ok::<_, Infallible>(
+                req.extensions().get::<LanguageTag>().cloned(),
+            ))
+        });
+
+        let mut service = WithLocaleLayer::new(SUPPORTED.iter().cloned()).layer(echo);
+
+        assert_eq!(
+            service.call(request(Some("fr"))).await.unwrap(),
+            Some(LanguageTag::from_static("fr-CA"))
+        );
+        assert_eq!(
+            service.call(request(None)).await.unwrap(),
+            Some(LanguageTag::from_static("en-US"))
+        );
+
+        let mut service = WithLocaleLayer::new(SUPPORTED.iter().cloned())
+            .with_default(None)
+            .layer(echo);
+
+        assert_eq!(service.call(request(Some("de"))).await.unwrap(), None);
+    }
+}
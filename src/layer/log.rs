@@ -0,0 +1,338 @@
+//! Request / response logging layer, see [`WithHttpLogLayer`].
+
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use bytes::Bytes;
+use http::Request;
+use serde::Serialize;
+use tower_layer::Layer;
+use tower_service::Service;
+use tracing::Level;
+
+use crate::response::{
+    snapshot::{redact_headers, SnapshotBody, SnapshotOptions, DEFAULT_REDACTED_HEADERS},
+    ResponseExt,
+};
+
+/// Default max length of the bodies logged.
+pub const DEFAULT_BODY_LIMIT: usize = 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Serialize)]
+/// Structured summary of the request.
+pub struct RequestSummary {
+    /// Method.
+    pub method: String,
+
+    /// URI.
+    pub uri: String,
+
+    /// Headers in order, with sensitive values redacted.
+    pub headers: Vec<(String, String)>,
+
+    /// The (maybe truncated) body.
+    pub body: SnapshotBody,
+
+    /// Length of the original body.
+    pub body_size: usize,
+
+    /// Whether the body is truncated.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+}
+
+impl RequestSummary {
+    /// Create a [`RequestSummary`].
+    pub fn new(req: &Request<Bytes>, redacted_headers: &[&str], body_limit: usize) -> Self {
+        let (body, truncated) = SnapshotBody::truncate(req.body(), Some(body_limit));
+
+        Self {
+            method: req.method().to_string(),
+            uri: req.uri().to_string(),
+            headers: redact_headers(req.headers(), redacted_headers),
+            body,
+            body_size: req.body().len(),
+            truncated,
+        }
+    }
+}
+
+/// Emit an event at the dynamic level.
+macro_rules! event_at {
+    ($level:expr, $($arg:tt)+) => {
+        match $level {
+            Level::ERROR => tracing::error!($($arg)+),
+            Level::WARN => tracing::warn!($($arg)+),
+            Level::INFO => tracing::info!($($arg)+),
+            Level::DEBUG => tracing::debug!($($arg)+),
+            _ => tracing::trace!($($arg)+),
+        }
+    };
+}
+
+#[derive(Debug, Clone, Copy)]
+/// [`Layer`] logging structured request / response summaries, for debugging
+/// middleware stacks built from this crate.
+///
+/// Headers are redacted and bodies are truncated like
+/// [`ResponseExt::snapshot`], see [`SnapshotOptions`].
+///
+/// Responses with `5xx` status or failed requests are logged at the error
+/// level, others at the normal level.
+pub struct WithHttpLogLayer {
+    level: Level,
+    error_level: Level,
+    redacted_headers: &'static [&'static str],
+    body_limit: usize,
+}
+
+impl Default for WithHttpLogLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WithHttpLogLayer {
+    #[inline]
+    /// Create a new [`WithHttpLogLayer`], logging at `DEBUG` (`WARN` for
+    /// errors), with [`DEFAULT_REDACTED_HEADERS`] and [`DEFAULT_BODY_LIMIT`].
+    pub const fn new() -> Self {
+        Self {
+            level: Level::DEBUG,
+            error_level: Level::WARN,
+            redacted_headers: DEFAULT_REDACTED_HEADERS,
+            body_limit: DEFAULT_BODY_LIMIT,
+        }
+    }
+
+    #[inline]
+    /// Set the normal level.
+    pub const fn with_level(self, level: Level) -> Self {
+        Self { level, ..self }
+    }
+
+    #[inline]
+    /// Set the error level.
+    pub const fn with_error_level(self, error_level: Level) -> Self {
+        Self {
+            error_level,
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Set the headers (lowercase) whose values are redacted.
+    pub const fn with_redacted_headers(self, redacted_headers: &'static [&'static str]) -> Self {
+        Self {
+            redacted_headers,
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Set the max length of the bodies logged.
+    pub const fn with_body_limit(self, body_limit: usize) -> Self {
+        Self { body_limit, ..self }
+    }
+}
+
+impl<S> Layer<S> for WithHttpLogLayer {
+    type Service = WithHttpLogService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        WithHttpLogService {
+            inner,
+            config: *self,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// [`Service`] logging structured request / response summaries, see
+/// [`WithHttpLogLayer`].
+pub struct WithHttpLogService<S> {
+    inner: S,
+    config: WithHttpLogLayer,
+}
+
+impl<S> Service<Request<Bytes>> for WithHttpLogService<S>
+where
+    S: Service<Request<Bytes>, Response = ResponseExt>,
+    S::Future: Send + 'static,
+    S::Error: fmt::Display,
+{
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<ResponseExt, Self::Error>> + Send>>;
+    type Response = ResponseExt;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Bytes>) -> Self::Future {
+        let config = self.config;
+        let request = RequestSummary::new(&req, config.redacted_headers, config.body_limit);
+
+        let started = Instant::now();
+        let fut = self.inner.call(req);
+
+        Box::pin(async move {
+            let result = fut.await;
+
+            let request = serde_json::to_string(&request).unwrap_or_default();
+            #[allow(
+                clippy::cast_possible_truncation,
+                reason = "duration never gets that large"
+            )]
+            let duration_ms = started.elapsed().as_millis() as u64;
+
+            match &result {
+                Ok(response) => {
+                    let level = if response.response_parts.status.is_server_error() {
+                        config.error_level
+                    } else {
+                        config.level
+                    };
+                    let response = serde_json::to_string(
+                        &response.snapshot(
+                            SnapshotOptions::new()
+                                .with_redacted_headers(config.redacted_headers)
+                                .with_body_limit(Some(config.body_limit)),
+                        ),
+                    )
+                    .unwrap_or_default();
+
+                    event_at!(level, request, response, duration_ms, "HTTP exchange");
+                }
+                Err(e) => {
+                    event_at!(config.error_level, request, error = %e, duration_ms, "HTTP exchange failed");
+                }
+            }
+
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+
+    use tracing::{
+        field::{Field, Visit},
+        Event, Subscriber,
+    };
+    use tracing_subscriber::{
+        layer::{self, SubscriberExt},
+        util::SubscriberInitExt,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_request_summary() {
+        let req = Request::post("https://a.com/x")
+            .header("authorization", "Bearer secret")
+            .header("content-type", "text/plain")
+            .body(Bytes::from_static(b"hello world"))
+            .unwrap();
+
+        let summary = RequestSummary::new(&req, DEFAULT_REDACTED_HEADERS, 5);
+
+        assert_eq!(
+            serde_json::to_string(&summary).unwrap(),
+            r#"{"method":"POST","uri":"https://a.com/x","headers":[["authorization","[REDACTED]"],["content-type","text/plain"]],"body":{"encoding":"utf8","data":"hello"},"body_size":11,"truncated":true}"#
+        );
+    }
+
+    /// Records the events, as the level and the fields.
+    #[derive(Clone, Default)]
+    struct Events(Arc<Mutex<Vec<(Level, Fields)>>>);
+
+    struct Fields(HashMap<&'static str, String>);
+
+    impl Visit for Fields {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.insert(field.name(), value.to_owned());
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.0.insert(field.name(), format!("{value:?}"));
+        }
+    }
+
+    impl<S: Subscriber> tracing_subscriber::Layer<S> for Events {
+        fn on_event(&self, event: &Event<'_>, _ctx: layer::Context<'_, S>) {
+            let mut fields = Fields(HashMap::new());
+            event.record(&mut fields);
+
+            self.0
+                .lock()
+                .unwrap()
+                .push((*event.metadata().level(), fields));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_with_http_log_layer() {
+        let events = Events::default();
+        let _guard = tracing_subscriber::registry()
+            .with(events.clone())
+            .set_default();
+
+        let echo = tower::service_fn(|req: Request<Bytes>| {
+            let status = if req.uri().path() == "/fail" {
+                500
+            } else {
+                200
+            };
+
+            std::future::ready(
+                ResponseExt::builder()
+                    .status(status)
+                    .header("set-cookie", "session=secret")
+                    .bytes(req.into_body())
+                    .build(),
+            )
+        });
+        let mut service = WithHttpLogLayer::new()
+            .with_level(Level::INFO)
+            .with_body_limit(4)
+            .layer(echo);
+
+        for path in ["/", "/fail"] {
+            let req = Request::post(path)
+                .header("authorization", "Bearer secret")
+                .body(Bytes::from_static(b"payload"))
+                .unwrap();
+
+            assert_eq!(service.call(req).await.unwrap().body, "payload");
+        }
+
+        let events = events.0.lock().unwrap();
+        assert_eq!(
+            events.iter().map(|(level, _)| *level).collect::<Vec<_>>(),
+            [Level::INFO, Level::WARN]
+        );
+
+        let (_, Fields(fields)) = &events[0];
+        assert_eq!(fields["message"], "HTTP exchange");
+        assert_eq!(
+            fields["request"],
+            r#"{"method":"POST","uri":"/","headers":[["authorization","[REDACTED]"]],"body":{"encoding":"utf8","data":"payl"},"body_size":7,"truncated":true}"#
+        );
+        assert!(fields["response"].contains(r#"["set-cookie","[REDACTED]"]"#));
+        assert!(fields["response"].contains(r#""data":"payl""#));
+        assert!(!fields["response"].contains("secret"));
+        assert!(fields.contains_key("duration_ms"));
+    }
+}
@@ -0,0 +1,276 @@
+//! Metrics collection layer via the [`metrics`] facade, see
+//! [`WithMetricsLayer`].
+//!
+//! Recorded metrics, labelled by `method`, `route` and `status` (the status
+//! class, e.g. `2xx`, or `error` if the inner service fails):
+//!
+//! - `{prefix}_requests_total`: counter
+//! - `{prefix}_request_duration_seconds`: histogram
+
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use http::{Request, Response, StatusCode, Uri};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use super::normalize_path;
+
+/// Returns the status class label, e.g. `2xx`.
+pub fn status_class(status: StatusCode) -> &'static str {
+    match status.as_u16() {
+        100..=199 => "1xx",
+        200..=299 => "2xx",
+        300..=399 => "3xx",
+        400..=499 => "4xx",
+        _ => "5xx",
+    }
+}
+
+type RouteClassifier = Arc<dyn Fn(&Uri) -> String + Send + Sync>;
+
+#[derive(Clone)]
+/// [`Layer`] recording request metrics, see the module documentation.
+pub struct WithMetricsLayer {
+    prefix: &'static str,
+    classifier: RouteClassifier,
+}
+
+impl fmt::Debug for WithMetricsLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WithMetricsLayer")
+            .field("prefix", &self.prefix)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for WithMetricsLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WithMetricsLayer {
+    #[inline]
+    /// Create a new [`WithMetricsLayer`], with prefix `http` and the
+    /// normalized path (see [`normalize_path`]) as the route.
+    pub fn new() -> Self {
+        Self {
+            prefix: "http",
+            classifier: Arc::new(|uri: &Uri| normalize_path(uri.path())),
+        }
+    }
+
+    #[inline]
+    /// Set the prefix of metric names.
+    pub fn with_prefix(self, prefix: &'static str) -> Self {
+        Self { prefix, ..self }
+    }
+
+    #[inline]
+    /// Set the route classifier, mapping the request URI to the `route`
+    /// label.
+    ///
+    /// Keep the cardinality low, e.g. return the route template.
+    pub fn with_route_classifier<F>(self, classifier: F) -> Self
+    where
+        F: Fn(&Uri) -> String + Send + Sync + 'static,
+    {
+        Self {
+            classifier: Arc::new(classifier),
+            ..self
+        }
+    }
+}
+
+impl<S> Layer<S> for WithMetricsLayer {
+    type Service = WithMetricsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        WithMetricsService {
+            inner,
+            config: self.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+/// [`Service`] recording request metrics, see [`WithMetricsLayer`].
+pub struct WithMetricsService<S> {
+    inner: S,
+    config: WithMetricsLayer,
+}
+
+impl<S> fmt::Debug for WithMetricsService<S>
+where
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WithMetricsService")
+            .field("inner", &self.inner)
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for WithMetricsService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+{
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+    type Response = S::Response;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let method = req.method().to_string();
+        let route = (self.config.classifier)(req.uri());
+        let prefix = self.config.prefix;
+
+        let started = Instant::now();
+        let fut = self.inner.call(req);
+
+        Box::pin(async move {
+            let result = fut.await;
+
+            let status = match &result {
+                Ok(response) => status_class(response.status()),
+                Err(_) => "error",
+            };
+            let labels = [
+                ("method", method),
+                ("route", route),
+                ("status", status.to_owned()),
+            ];
+
+            metrics::counter!(format!("{prefix}_requests_total"), &labels).increment(1);
+            metrics::histogram!(format!("{prefix}_request_duration_seconds"), &labels)
+                .record(started.elapsed().as_secs_f64());
+
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        sync::{Arc, Mutex},
+    };
+
+    use metrics::{
+        Counter, CounterFn, Gauge, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder,
+        SharedString, Unit,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_status_class() {
+        assert_eq!(status_class(StatusCode::OK), "2xx");
+        assert_eq!(status_class(StatusCode::NOT_FOUND), "4xx");
+        assert_eq!(status_class(StatusCode::BAD_GATEWAY), "5xx");
+    }
+
+    /// Records the counter increments and the histogram samples, as
+    /// `name{labels} op`.
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<String>>>);
+
+    struct Handle(String, Arc<Mutex<Vec<String>>>);
+
+    impl CounterFn for Handle {
+        fn increment(&self, value: u64) {
+            self.1
+                .lock()
+                .unwrap()
+                .push(format!("{} += {value}", self.0));
+        }
+
+        fn absolute(&self, value: u64) {
+            self.1.lock().unwrap().push(format!("{} = {value}", self.0));
+        }
+    }
+
+    impl HistogramFn for Handle {
+        fn record(&self, _value: f64) {
+            self.1.lock().unwrap().push(format!("{} recorded", self.0));
+        }
+    }
+
+    impl Capture {
+        fn handle(&self, key: &Key) -> Arc<Handle> {
+            let labels: Vec<_> = key
+                .labels()
+                .map(|label| format!("{}={}", label.key(), label.value()))
+                .collect();
+
+            Arc::new(Handle(
+                format!("{}{{{}}}", key.name(), labels.join(",")),
+                self.0.clone(),
+            ))
+        }
+    }
+
+    impl Recorder for Capture {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            Counter::from_arc(self.handle(key))
+        }
+
+        fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+            Gauge::noop()
+        }
+
+        fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+            Histogram::from_arc(self.handle(key))
+        }
+    }
+
+    #[test]
+    fn test_with_metrics_layer() {
+        let ok200 = tower::service_fn(|_: Request<()>| {
+            std::future::ready(Ok::<_, Infallible>(Response::new(())))
+        });
+        let mut service = WithMetricsLayer::new()
+            .with_prefix("api")
+            .with_route_classifier(|uri| {
+                uri.path().split('/').take(2).collect::<Vec<_>>().join("/")
+            })
+            .layer(ok200);
+
+        let recorder = Capture::default();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        let response = metrics::with_local_recorder(&recorder, || {
+            runtime.block_on(service.call(Request::get("/users/42").body(()).unwrap()))
+        });
+
+        assert_eq!(response.unwrap().status(), 200);
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            [
+                "api_requests_total{method=GET,route=/users,status=2xx} += 1",
+                "api_request_duration_seconds{method=GET,route=/users,status=2xx} recorded",
+            ]
+        );
+    }
+}
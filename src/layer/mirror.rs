@@ -0,0 +1,302 @@
+//! Request mirroring (shadow traffic), see [`MirrorLayer`].
+
+use std::{
+    fmt,
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use http::{HeaderMap, HeaderName, HeaderValue, Method, Request, Uri};
+use tower_layer::Layer;
+use tower_service::Service;
+
+type Filter = Arc<dyn Fn(&Method, &Uri, &HeaderMap) -> bool + Send + Sync>;
+
+/// [`Layer`] mirroring the selected requests to a shadow target, e.g. for
+/// canary testing API migrations.
+///
+/// The mirrored request (method, URI, version, headers and body) is passed
+/// to the client closure, whose future is spawned with [`tokio::spawn`] and
+/// never awaited by the primary request, so the closure should send it to the
+/// shadow target and discard the response.
+///
+/// The request body must be buffered already (e.g. [`Bytes`]), and bodies
+/// larger than the cap are not mirrored.
+pub struct MirrorLayer<F> {
+    client: F,
+    max_body_size: usize,
+    sample_rate: f64,
+    filter: Option<Filter>,
+    header_rules: Vec<(HeaderName, Option<HeaderValue>)>,
+}
+
+impl<F: fmt::Debug> fmt::Debug for MirrorLayer<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MirrorLayer")
+            .field("client", &self.client)
+            .field("max_body_size", &self.max_body_size)
+            .field("sample_rate", &self.sample_rate)
+            .field("filter", &self.filter.is_some())
+            .field("header_rules", &self.header_rules)
+            .finish()
+    }
+}
+
+impl<F: Clone> Clone for MirrorLayer<F> {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            max_body_size: self.max_body_size,
+            sample_rate: self.sample_rate,
+            filter: self.filter.clone(),
+            header_rules: self.header_rules.clone(),
+        }
+    }
+}
+
+impl<F> MirrorLayer<F> {
+    #[inline]
+    /// Create a new [`MirrorLayer`] with the client closure, mirroring all
+    /// requests with bodies up to 64 KiB.
+    pub fn new(client: F) -> Self {
+        Self {
+            client,
+            max_body_size: 64 * 1024,
+            sample_rate: 1.0,
+            filter: None,
+            header_rules: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Set the max body size of mirrored requests.
+    pub fn with_max_body_size(self, max_body_size: usize) -> Self {
+        Self {
+            max_body_size,
+            ..self
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Set the ratio of the selected requests to mirror, clamped to `0.0..=1.0`.
+    ///
+    /// Sampling is deterministic, e.g. `0.25` mirrors exactly one of every 4
+    /// selected request.
+    pub fn with_sample_rate(self, sample_rate: f64) -> Self {
+        Self {
+            sample_rate: sample_rate.clamp(0.0, 1.0),
+            ..self
+        }
+    }
+
+    #[must_use]
+    /// Select the requests to mirror, all by default.
+    pub fn with_filter<P>(self, filter: P) -> Self
+    where
+        P: Fn(&Method, &Uri, &HeaderMap) -> bool + Send + Sync + 'static,
+    {
+        Self {
+            filter: Some(Arc::new(filter)),
+            ..self
+        }
+    }
+
+    #[must_use]
+    /// Set the header of mirrored requests, e.g. marking them as shadow
+    /// traffic or replacing the credentials.
+    pub fn with_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.header_rules.push((name, Some(value)));
+        self
+    }
+
+    #[must_use]
+    /// Remove the header from mirrored requests.
+    pub fn without_header(mut self, name: HeaderName) -> Self {
+        self.header_rules.push((name, None));
+        self
+    }
+}
+
+impl<S, F: Clone> Layer<S> for MirrorLayer<F> {
+    type Service = MirrorService<S, F>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MirrorService {
+            inner,
+            config: Arc::new(self.clone()),
+            counter: Arc::new(AtomicU64::new(0)),
+        }
+    }
+}
+
+/// [`Service`] mirroring requests, see [`MirrorLayer`].
+pub struct MirrorService<S, F> {
+    inner: S,
+    config: Arc<MirrorLayer<F>>,
+    counter: Arc<AtomicU64>,
+}
+
+impl<S: fmt::Debug, F: fmt::Debug> fmt::Debug for MirrorService<S, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MirrorService")
+            .field("inner", &self.inner)
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S: Clone, F> Clone for MirrorService<S, F> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            config: self.config.clone(),
+            counter: self.counter.clone(),
+        }
+    }
+}
+
+impl<S, F> MirrorService<S, F> {
+    /// Clone the request for mirroring, if selected.
+    fn mirror<B>(&self, req: &Request<B>) -> Option<Request<Bytes>>
+    where
+        B: AsRef<[u8]>,
+    {
+        let config = &self.config;
+
+        if let Some(filter) = &config.filter {
+            if !filter(req.method(), req.uri(), req.headers()) {
+                return None;
+            }
+        }
+
+        let body = req.body().as_ref();
+        if body.len() > config.max_body_size {
+            return None;
+        }
+
+        #[allow(
+            clippy::cast_precision_loss,
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss,
+            reason = "the counter won't exceed 2^53 in practice"
+        )]
+        let sampled = {
+            let n = self.counter.fetch_add(1, Ordering::Relaxed) as f64;
+
+            ((n + 1.0) * config.sample_rate).ceil() as u64 > (n * config.sample_rate).ceil() as u64
+        };
+        if !sampled {
+            return None;
+        }
+
+        let mut headers = req.headers().clone();
+        for (name, value) in &config.header_rules {
+            match value {
+                Some(value) => {
+                    headers.insert(name.clone(), value.clone());
+                }
+                None => {
+                    headers.remove(name);
+                }
+            }
+        }
+
+        let mut mirrored = Request::new(Bytes::copy_from_slice(body));
+        *mirrored.method_mut() = req.method().clone();
+        *mirrored.uri_mut() = req.uri().clone();
+        *mirrored.version_mut() = req.version();
+        *mirrored.headers_mut() = headers;
+
+        Some(mirrored)
+    }
+}
+
+impl<S, F, Fut, ReqBody> Service<Request<ReqBody>> for MirrorService<S, F>
+where
+    S: Service<Request<ReqBody>>,
+    F: Fn(Request<Bytes>) -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+    ReqBody: AsRef<[u8]>,
+{
+    type Error = S::Error;
+    type Future = S::Future;
+    type Response = S::Response;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        if let Some(mirrored) = self.mirror(&req) {
+            #[cfg(feature = "feat-tracing")]
+            tracing::debug!(uri = %mirrored.uri(), "Mirroring request");
+
+            tokio::spawn((self.config.client)(mirrored));
+        }
+
+        self.inner.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{convert::Infallible, sync::Mutex};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_mirror_layer() {
+        let mirrored = Arc::new(Mutex::new(Vec::new()));
+        let echo = tower::service_fn(|req: Request<Bytes>| {
+            std::future::ready(Ok::<_, Infallible>(req.into_body()))
+        });
+
+        let mut service = MirrorLayer::new({
+            let mirrored = mirrored.clone();
+
+            move |req: Request<Bytes>| {
+                mirrored.lock().unwrap().push(req);
+
+                async {}
+            }
+        })
+        .with_max_body_size(8)
+        .with_sample_rate(0.5)
+        .with_filter(|method, _, _| method != Method::GET)
+        .with_header(
+            HeaderName::from_static("x-shadow"),
+            HeaderValue::from_static("1"),
+        )
+        .without_header(http::header::AUTHORIZATION)
+        .layer(echo);
+
+        for body in ["a", "b", "c", "too long body"] {
+            let req = Request::post("https://example.com/api")
+                .header("authorization", "secret")
+                .body(Bytes::from(body))
+                .unwrap();
+
+            assert_eq!(service.call(req).await.unwrap(), body);
+        }
+
+        let req = Request::get("/").body(Bytes::new()).unwrap();
+        service.call(req).await.unwrap();
+
+        tokio::task::yield_now().await;
+
+        let mirrored = mirrored.lock().unwrap();
+        assert_eq!(mirrored.len(), 2);
+        assert_eq!(mirrored[0].body(), "a");
+        assert_eq!(mirrored[1].body(), "c");
+        assert_eq!(mirrored[0].uri(), "https://example.com/api");
+        assert_eq!(mirrored[0].headers()["x-shadow"], "1");
+        assert!(!mirrored[0].headers().contains_key("authorization"));
+    }
+}
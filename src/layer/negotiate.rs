@@ -0,0 +1,478 @@
+//! Content negotiation layer, see [`WithNegotiateLayer`].
+//!
+//! Parses `Accept`, `Accept-Encoding` and `Accept-Language` against the
+//! server-declared capabilities, and stores the [`Negotiated`] result in the
+//! request extensions.
+
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use http::{header, HeaderMap, HeaderName, Request, Response, StatusCode};
+use tower_layer::Layer;
+use tower_service::Service;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// An item of the header values with quality, like `text/html;q=0.8`.
+pub struct QualityItem<'a> {
+    /// The value, without any parameter.
+    pub value: &'a str,
+
+    /// The quality in thousandths, i.e. `q=0.8` is `800`.
+    pub quality: u16,
+}
+
+/// Parse header value like `text/html, application/json;q=0.9, */*;q=0.1`.
+///
+/// Items with invalid quality are skipped. Parameters other than `q` are
+/// ignored. The items are sorted by quality (descending), keeping the original
+/// order for items of the same quality.
+pub fn parse_quality_list(value: &str) -> Vec<QualityItem<'_>> {
+    let mut items: Vec<_> = value
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';');
+
+            let value = parts.next()?.trim();
+            if value.is_empty() {
+                return None;
+            }
+
+            let quality = match parts.find_map(|param| {
+                let (key, value) = param.split_once('=')?;
+                key.trim().eq_ignore_ascii_case("q").then(|| value.trim())
+            }) {
+                Some(quality) => parse_quality(quality)?,
+                None => 1000,
+            };
+
+            Some(QualityItem { value, quality })
+        })
+        .collect();
+
+    items.sort_by_key(|item| core::cmp::Reverse(item.quality));
+
+    items
+}
+
+/// Parse the quality value like `0.8` into thousandths.
+fn parse_quality(value: &str) -> Option<u16> {
+    let (int, frac) = value.split_once('.').unwrap_or((value, ""));
+
+    if frac.len() > 3 || !frac.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let frac = frac
+        .bytes()
+        .chain(std::iter::repeat(b'0'))
+        .take(3)
+        .fold(0, |acc, b| acc * 10 + u16::from(b - b'0'));
+
+    match int {
+        "0" => Some(frac),
+        "1" if frac == 0 => Some(1000),
+        _ => None,
+    }
+}
+
+/// Returns the specificity if the media range matches the media type, i.e.
+/// `2` for exact match, `1` for `type/*` and `0` for `*/*`.
+pub(crate) fn match_media_type(range: &str, media_type: &str) -> Option<u8> {
+    if range == "*/*" {
+        return Some(0);
+    }
+
+    let media_type = media_type.split(';').next().unwrap_or_default().trim();
+
+    if range.eq_ignore_ascii_case(media_type) {
+        return Some(2);
+    }
+
+    let (range_type, range_subtype) = range.split_once('/')?;
+    let (ty, _) = media_type.split_once('/')?;
+
+    (range_subtype == "*" && range_type.eq_ignore_ascii_case(ty)).then_some(1)
+}
+
+/// Returns the specificity if the coding matches, i.e. `1` for exact match
+/// and `0` for `*`.
+fn match_encoding(range: &str, encoding: &str) -> Option<u8> {
+    if range == "*" {
+        Some(0)
+    } else {
+        range.eq_ignore_ascii_case(encoding).then_some(1)
+    }
+}
+
+/// Returns the specificity if the language range matches the language tag
+/// (RFC 4647 basic filtering), i.e. the length of the range, and `0` for
+/// `*`.
+pub(crate) fn match_language(range: &str, tag: &str) -> Option<u8> {
+    if range == "*" {
+        return Some(0);
+    }
+
+    let matched = tag.len() >= range.len()
+        && tag.as_bytes()[..range.len()].eq_ignore_ascii_case(range.as_bytes())
+        && matches!(tag.as_bytes().get(range.len()), None | Some(b'-'));
+
+    matched.then(|| u8::try_from(range.len()).unwrap_or(u8::MAX))
+}
+
+/// Pick the supported value with the highest quality, by the most specific
+/// matching item. Ties are broken by the order of `supported`.
+fn negotiate<'c>(
+    items: &[QualityItem<'_>],
+    supported: &[&'c str],
+    matcher: fn(&str, &str) -> Option<u8>,
+    implicit: fn(&str) -> u16,
+) -> Option<&'c str> {
+    let mut best: Option<(&'c str, u16)> = None;
+
+    for &candidate in supported {
+        let quality = items
+            .iter()
+            .filter_map(|item| Some((matcher(item.value, candidate)?, item.quality)))
+            .max_by_key(|&(specificity, _)| specificity)
+            .map_or_else(|| implicit(candidate), |(_, quality)| quality);
+
+        if quality > 0 && best.map_or(true, |(_, best)| quality > best) {
+            best = Some((candidate, quality));
+        }
+    }
+
+    best.map(|(candidate, _)| candidate)
+}
+
+/// Pick the media type from `supported` according to the `Accept` header
+/// value (`*/*` if missing).
+pub fn negotiate_media_type<'c>(accept: Option<&str>, supported: &[&'c str]) -> Option<&'c str> {
+    negotiate(
+        &parse_quality_list(accept.unwrap_or("*/*")),
+        supported,
+        match_media_type,
+        |_| 0,
+    )
+}
+
+/// Pick the content coding from `supported` according to the
+/// `Accept-Encoding` header value.
+///
+/// `identity` is acceptable unless excluded explicitly (e.g. `identity;q=0`
+/// or `*;q=0`), so include it in `supported` if uncompressed responses are
+/// fine.
+pub fn negotiate_encoding<'c>(
+    accept_encoding: Option<&str>,
+    supported: &[&'c str],
+) -> Option<&'c str> {
+    negotiate(
+        &parse_quality_list(accept_encoding.unwrap_or_default()),
+        supported,
+        match_encoding,
+        |encoding| u16::from(encoding.eq_ignore_ascii_case("identity")),
+    )
+}
+
+/// Pick the language tag from `supported` according to the
+/// `Accept-Language` header value (`*` if missing).
+pub fn negotiate_language<'c>(
+    accept_language: Option<&str>,
+    supported: &[&'c str],
+) -> Option<&'c str> {
+    negotiate(
+        &parse_quality_list(accept_language.unwrap_or("*")),
+        supported,
+        match_language,
+        |_| 0,
+    )
+}
+
+/// Join all the values of the header, `None` if missing.
+fn header_value(headers: &HeaderMap, name: &HeaderName) -> Option<String> {
+    let mut values = headers.get_all(name).iter().filter_map(|v| v.to_str().ok());
+
+    let first = values.next()?.to_owned();
+
+    Some(values.fold(first, |mut acc, value| {
+        acc.push(',');
+        acc.push_str(value);
+        acc
+    }))
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// Result of content negotiation, stored in the request extensions by
+/// [`WithNegotiateService`].
+///
+/// Fields are `None` if no capability is declared, or nothing matches.
+pub struct Negotiated {
+    /// The media type, from `Accept`.
+    pub media_type: Option<&'static str>,
+
+    /// The content coding, from `Accept-Encoding`.
+    pub encoding: Option<&'static str>,
+
+    /// The language tag, from `Accept-Language`.
+    pub language: Option<&'static str>,
+}
+
+impl Negotiated {
+    /// Negotiate against the capabilities declared by the layer.
+    fn new(headers: &HeaderMap, layer: &WithNegotiateLayer) -> Self {
+        let negotiate =
+            |name: &HeaderName,
+             supported: &'static [&'static str],
+             f: fn(Option<&str>, &'static [&'static str]) -> Option<&'static str>| {
+                if supported.is_empty() {
+                    None
+                } else {
+                    f(header_value(headers, name).as_deref(), supported)
+                }
+            };
+
+        Self {
+            media_type: negotiate(&header::ACCEPT, layer.media_types, negotiate_media_type),
+            encoding: negotiate(
+                &header::ACCEPT_ENCODING,
+                layer.encodings,
+                negotiate_encoding,
+            ),
+            language: negotiate(
+                &header::ACCEPT_LANGUAGE,
+                layer.languages,
+                negotiate_language,
+            ),
+        }
+    }
+
+    /// Whether every declared capability set has a match.
+    fn is_acceptable(&self, layer: &WithNegotiateLayer) -> bool {
+        (layer.media_types.is_empty() || self.media_type.is_some())
+            && (layer.encodings.is_empty() || self.encoding.is_some())
+            && (layer.languages.is_empty() || self.language.is_some())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+/// [`Layer`] negotiating the content, storing [`Negotiated`] in the request
+/// extensions.
+///
+/// Optionally rejects the request with `406 Not Acceptable` if any declared
+/// capability set has no match, see
+/// [`with_reject_not_acceptable`](Self::with_reject_not_acceptable).
+pub struct WithNegotiateLayer {
+    media_types: &'static [&'static str],
+    encodings: &'static [&'static str],
+    languages: &'static [&'static str],
+    reject_not_acceptable: bool,
+}
+
+impl WithNegotiateLayer {
+    #[inline]
+    /// Create a new [`WithNegotiateLayer`] without any capability declared.
+    pub const fn new() -> Self {
+        Self {
+            media_types: &[],
+            encodings: &[],
+            languages: &[],
+            reject_not_acceptable: false,
+        }
+    }
+
+    #[inline]
+    /// Set the supported media types, in order of preference.
+    pub const fn with_media_types(self, media_types: &'static [&'static str]) -> Self {
+        Self {
+            media_types,
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Set the supported content codings, in order of preference.
+    ///
+    /// See [`negotiate_encoding`] for `identity`.
+    pub const fn with_encodings(self, encodings: &'static [&'static str]) -> Self {
+        Self { encodings, ..self }
+    }
+
+    #[inline]
+    /// Set the supported language tags, in order of preference.
+    pub const fn with_languages(self, languages: &'static [&'static str]) -> Self {
+        Self { languages, ..self }
+    }
+
+    #[inline]
+    /// Whether to reject with `406 Not Acceptable` if nothing matches.
+    pub const fn with_reject_not_acceptable(self, reject_not_acceptable: bool) -> Self {
+        Self {
+            reject_not_acceptable,
+            ..self
+        }
+    }
+}
+
+impl<S> Layer<S> for WithNegotiateLayer {
+    type Service = WithNegotiateService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        WithNegotiateService {
+            inner,
+            config: *self,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// [`Service`] negotiating the content, see [`WithNegotiateLayer`].
+pub struct WithNegotiateService<S> {
+    inner: S,
+    config: WithNegotiateLayer,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for WithNegotiateService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+    ResBody: Default + Send + 'static,
+{
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+    type Response = S::Response;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        let negotiated = Negotiated::new(req.headers(), &self.config);
+
+        if self.config.reject_not_acceptable && !negotiated.is_acceptable(&self.config) {
+            #[cfg(feature = "feat-tracing")]
+            tracing::debug!(?negotiated, "Not acceptable.");
+
+            let mut response = Response::new(ResBody::default());
+            *response.status_mut() = StatusCode::NOT_ACCEPTABLE;
+
+            return Box::pin(std::future::ready(Ok(response)));
+        }
+
+        req.extensions_mut().insert(negotiated);
+
+        Box::pin(self.inner.call(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_quality_list() {
+        assert_eq!(
+            parse_quality_list("text/html;level=1, application/json;q=0.9, */*;q=0.05, x;q=2"),
+            [
+                QualityItem {
+                    value: "text/html",
+                    quality: 1000
+                },
+                QualityItem {
+                    value: "application/json",
+                    quality: 900
+                },
+                QualityItem {
+                    value: "*/*",
+                    quality: 50
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_negotiate() {
+        const MEDIA_TYPES: &[&str] = &["application/json", "text/html"];
+
+        assert_eq!(
+            negotiate_media_type(Some("text/*, application/json;q=0.5"), MEDIA_TYPES),
+            Some("text/html")
+        );
+        assert_eq!(
+            negotiate_media_type(Some("*/*, text/html;q=0"), MEDIA_TYPES),
+            Some("application/json")
+        );
+        assert_eq!(
+            negotiate_media_type(None, MEDIA_TYPES),
+            Some("application/json")
+        );
+        assert_eq!(negotiate_media_type(Some("image/png"), MEDIA_TYPES), None);
+
+        const ENCODINGS: &[&str] = &["br", "gzip", "identity"];
+
+        assert_eq!(
+            negotiate_encoding(Some("gzip, br;q=0.8"), ENCODINGS),
+            Some("gzip")
+        );
+        assert_eq!(
+            negotiate_encoding(Some("deflate"), ENCODINGS),
+            Some("identity")
+        );
+        assert_eq!(negotiate_encoding(None, ENCODINGS), Some("identity"));
+        assert_eq!(negotiate_encoding(Some("*;q=0"), ENCODINGS), None);
+
+        const LANGUAGES: &[&str] = &["en-US", "zh-CN"];
+
+        assert_eq!(
+            negotiate_language(Some("zh, en;q=0.8"), LANGUAGES),
+            Some("zh-CN")
+        );
+        assert_eq!(
+            negotiate_language(Some("ja, *;q=0.1"), LANGUAGES),
+            Some("en-US")
+        );
+        assert_eq!(negotiate_language(Some("zh-TW"), LANGUAGES), None);
+    }
+
+    #[tokio::test]
+    async fn test_with_negotiate_layer() {
+        let echo = tower::service_fn(|req: Request<()>| {
+            let negotiated = req.extensions().get::<Negotiated>().copied();
+
+            std::future::ready(Ok::<_, Infallible>(Response::new(negotiated)))
+        });
+        let mut service = WithNegotiateLayer::new()
+            .with_media_types(&["application/json"])
+            .with_encodings(&["gzip", "identity"])
+            .with_reject_not_acceptable(true)
+            .layer(echo);
+
+        let req = Request::get("/")
+            .header("accept", "application/*")
+            .header("accept-encoding", "gzip")
+            .body(())
+            .unwrap();
+        let response = service.call(req).await.unwrap();
+
+        assert_eq!(
+            response.into_body(),
+            Some(Negotiated {
+                media_type: Some("application/json"),
+                encoding: Some("gzip"),
+                language: None,
+            })
+        );
+
+        let req = Request::get("/")
+            .header("accept", "text/html")
+            .body(())
+            .unwrap();
+        let response = service.call(req).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_ACCEPTABLE);
+    }
+}
@@ -0,0 +1,183 @@
+//! Proxy selection for clients, see [`ProxySelectorLayer`].
+
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use http::{header, Request, Uri};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::request::misc::proxy::{ProxyPool, ProxyScheme};
+
+/// Selector of the proxy for requests.
+pub trait ProxySelectorT {
+    /// Select the proxy for the request URI, `None` to connect directly.
+    fn select(&self, uri: &Uri) -> Option<ProxyScheme>;
+}
+
+impl ProxySelectorT for ProxyScheme {
+    #[inline]
+    fn select(&self, _uri: &Uri) -> Option<ProxyScheme> {
+        Some(self.clone())
+    }
+}
+
+impl ProxySelectorT for Option<ProxyScheme> {
+    #[inline]
+    fn select(&self, _uri: &Uri) -> Option<ProxyScheme> {
+        self.clone()
+    }
+}
+
+impl ProxySelectorT for ProxyPool {
+    #[inline]
+    /// Select the proxy of the best score, see [`ProxyPool::select_best`].
+    fn select(&self, _uri: &Uri) -> Option<ProxyScheme> {
+        self.select_best().cloned()
+    }
+}
+
+impl<F> ProxySelectorT for F
+where
+    F: Fn(&Uri) -> Option<ProxyScheme>,
+{
+    #[inline]
+    fn select(&self, uri: &Uri) -> Option<ProxyScheme> {
+        self(uri)
+    }
+}
+
+#[derive(Debug)]
+/// [`Layer`] selecting the proxy with the [`ProxySelectorT`], storing the
+/// chosen [`ProxyScheme`] in the request extensions for connectors.
+///
+/// When the request is forwarded by an `HTTP` proxy rather than tunneled
+/// (see [`ProxyScheme::needs_tunnel`]), `Proxy-Authorization` is set from
+/// the proxy's Basic auth, unless already present.
+pub struct ProxySelectorLayer<P> {
+    selector: Arc<P>,
+}
+
+impl<P> Clone for ProxySelectorLayer<P> {
+    fn clone(&self) -> Self {
+        Self {
+            selector: self.selector.clone(),
+        }
+    }
+}
+
+impl<P> ProxySelectorLayer<P> {
+    #[inline]
+    /// Create a new [`ProxySelectorLayer`].
+    pub fn new(selector: P) -> Self {
+        Self {
+            selector: Arc::new(selector),
+        }
+    }
+}
+
+impl<S, P> Layer<S> for ProxySelectorLayer<P> {
+    type Service = ProxySelectorService<S, P>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ProxySelectorService {
+            inner,
+            selector: self.selector.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+/// [`Service`] selecting the proxy, see [`ProxySelectorLayer`].
+pub struct ProxySelectorService<S, P> {
+    inner: S,
+    selector: Arc<P>,
+}
+
+impl<S: Clone, P> Clone for ProxySelectorService<S, P> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            selector: self.selector.clone(),
+        }
+    }
+}
+
+impl<S, P, ReqBody> Service<Request<ReqBody>> for ProxySelectorService<S, P>
+where
+    S: Service<Request<ReqBody>>,
+    P: ProxySelectorT,
+{
+    type Error = S::Error;
+    type Future = S::Future;
+    type Response = S::Response;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        if let Some(proxy) = self.selector.select(req.uri()) {
+            if !proxy.needs_tunnel(req.uri()) {
+                if let Some(auth) = proxy.http_auth() {
+                    req.headers_mut()
+                        .entry(header::PROXY_AUTHORIZATION)
+                        .or_insert_with(|| auth.clone());
+                }
+            }
+
+            #[cfg(feature = "feat-tracing")]
+            tracing::debug!(uri = %req.uri(), ?proxy, "Proxy selected");
+
+            req.extensions_mut().insert(proxy);
+        }
+
+        self.inner.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_proxy_selector_layer() {
+        let proxy: ProxyScheme = "http://u:p@127.0.0.1:7890".parse().unwrap();
+        let echo = tower::service_fn(|req: Request<()>| {
+            std::future::ready(Ok::<_, Infallible>((
+                req.extensions().get::<ProxyScheme>().cloned(),
+                req.headers().get(header::PROXY_AUTHORIZATION).cloned(),
+            )))
+        });
+
+        let mut service = ProxySelectorLayer::new({
+            let proxy = proxy.clone();
+
+            move |uri: &Uri| (uri.host() != Some("localhost")).then(|| proxy.clone())
+        })
+        .layer(echo);
+
+        let req = Request::get("http://example.com/").body(()).unwrap();
+        let (selected, auth) = service.call(req).await.unwrap();
+        assert_eq!(selected.as_ref(), Some(&proxy));
+        assert_eq!(auth.unwrap(), "Basic dTpw");
+
+        // Tunneled, the auth goes to `CONNECT` instead.
+        let req = Request::get("https://example.com/").body(()).unwrap();
+        let (selected, auth) = service.call(req).await.unwrap();
+        assert_eq!(selected.as_ref(), Some(&proxy));
+        assert!(auth.is_none());
+
+        let req = Request::get("http://localhost/").body(()).unwrap();
+        assert_eq!(service.call(req).await.unwrap(), (None, None));
+
+        let mut service = ProxySelectorLayer::new(ProxyPool::new([proxy.clone()])).layer(echo);
+        let req = Request::get("http://localhost/").body(()).unwrap();
+        let (selected, _) = service.call(req).await.unwrap();
+        assert_eq!(selected, Some(proxy));
+    }
+}
@@ -0,0 +1,118 @@
+//! Predicate-based routing, see [`RouteIfLayer`].
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+};
+
+use http::Request;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::request::predicate::Predicate;
+
+#[derive(Debug, Clone)]
+/// [`Layer`] routing the requests matching the [`Predicate`] to the other
+/// service, and the rest to the inner one.
+///
+/// Both services must be ready before a request is accepted.
+pub struct RouteIfLayer<T> {
+    predicate: Arc<Predicate>,
+    matched: T,
+}
+
+impl<T> RouteIfLayer<T> {
+    #[inline]
+    /// Create a new [`RouteIfLayer`], routing the requests matching the
+    /// predicate to `matched`.
+    pub fn new(predicate: Predicate, matched: T) -> Self {
+        Self {
+            predicate: Arc::new(predicate),
+            matched,
+        }
+    }
+}
+
+impl<S, T: Clone> Layer<S> for RouteIfLayer<T> {
+    type Service = RouteIfService<S, T>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RouteIfService {
+            inner,
+            matched: self.matched.clone(),
+            predicate: self.predicate.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// [`Service`] routing requests, see [`RouteIfLayer`].
+pub struct RouteIfService<S, T> {
+    inner: S,
+    matched: T,
+    predicate: Arc<Predicate>,
+}
+
+impl<S, T, ReqBody> Service<Request<ReqBody>> for RouteIfService<S, T>
+where
+    S: Service<Request<ReqBody>>,
+    S::Future: Send + 'static,
+    T: Service<Request<ReqBody>, Response = S::Response, Error = S::Error>,
+    T::Future: Send + 'static,
+{
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+    type Response = S::Response;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        ready!(self.inner.poll_ready(cx))?;
+
+        self.matched.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        if self.predicate.matches(&req) {
+            Box::pin(self.matched.call(req))
+        } else {
+            Box::pin(self.inner.call(req))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use http::Method;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_route_if_layer() {
+        let echo = |name: &'static str| {
+            tower::service_fn(move |_: Request<()>| std::future::ready(Ok::<_, Infallible>(name)))
+        };
+
+        let mut service = RouteIfLayer::new(
+            Predicate::path_prefix("/v2").and(!Predicate::method(Method::DELETE)),
+            echo("v2"),
+        )
+        .layer(echo("v1"));
+
+        for (method, path, expected) in [
+            (Method::GET, "/v2/users", "v2"),
+            (Method::DELETE, "/v2/users", "v1"),
+            (Method::GET, "/v1/users", "v1"),
+        ] {
+            let req = Request::builder()
+                .method(method)
+                .uri(path)
+                .body(())
+                .unwrap();
+
+            assert_eq!(service.call(req).await.unwrap(), expected);
+        }
+    }
+}
@@ -0,0 +1,352 @@
+//! Multi-tenant routing for gateways, see [`TenantLayer`].
+//!
+//! The tenant is resolved from a header or a query key, then the request is
+//! rewritten by the [`TenantRoute`] of the tenant: path prefix stripped or
+//! injected, and `Host` / authority replaced. The resolved [`Tenant`] is
+//! inserted into the request extensions.
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use http::{
+    header,
+    uri::{Authority, PathAndQuery},
+    HeaderName, HeaderValue, Request, Response, StatusCode, Uri,
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::request::parser::OwnedQuery;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// The resolved tenant ID, inserted into the request extensions by
+/// [`TenantLayer`].
+pub struct Tenant(pub String);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Where to resolve the tenant ID from.
+pub enum TenantSource {
+    /// The header value.
+    Header(HeaderName),
+
+    /// The query value.
+    Query(&'static str),
+}
+
+impl TenantSource {
+    fn resolve<B>(
+        &self,
+        req: &Request<B>,
+        query: &mut Option<Option<OwnedQuery>>,
+    ) -> Option<String> {
+        match self {
+            Self::Header(name) => req
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .filter(|v| !v.is_empty())
+                .map(str::to_owned),
+            Self::Query(key) => query
+                .get_or_insert_with(|| req.uri().query().map(OwnedQuery::parse))
+                .as_ref()
+                .and_then(|query| query.get(*key))
+                .filter(|v| !v.is_empty())
+                .map(str::to_owned),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// How to rewrite the requests of a tenant.
+pub struct TenantRoute {
+    strip_prefix: Option<String>,
+    prefix: Option<String>,
+    authority: Option<Authority>,
+}
+
+impl TenantRoute {
+    #[inline]
+    /// Create a new [`TenantRoute`], rewriting nothing.
+    pub const fn new() -> Self {
+        Self {
+            strip_prefix: None,
+            prefix: None,
+            authority: None,
+        }
+    }
+
+    #[must_use]
+    /// Strip the path prefix, e.g. `/acme` of `/acme/users`. Only whole
+    /// segments are stripped, so `/acme` does not match `/acmecorp`.
+    pub fn with_strip_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.strip_prefix = Some(prefix.into().trim_end_matches('/').to_owned());
+        self
+    }
+
+    #[must_use]
+    /// Inject the path prefix (after stripping), e.g. `/tenants/acme`.
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = Some(prefix.into().trim_end_matches('/').to_owned());
+        self
+    }
+
+    #[must_use]
+    /// Replace the `Host` header, and the authority of the request URI if it
+    /// has one (absolute-form or HTTP/2).
+    pub fn with_authority(mut self, authority: Authority) -> Self {
+        self.authority = Some(authority);
+        self
+    }
+
+    /// Rewrite the request, `None` if the rewritten URI is invalid.
+    fn rewrite<B>(&self, req: &mut Request<B>) -> Option<()> {
+        let path = req.uri().path();
+
+        let path = match &self.strip_prefix {
+            Some(prefix) => match path.strip_prefix(prefix.as_str()) {
+                Some("") => "/",
+                Some(rest) if rest.starts_with('/') => rest,
+                _ => path,
+            },
+            None => path,
+        };
+
+        let mut path_and_query = String::with_capacity(req.uri().path_and_query()?.as_str().len());
+
+        if let Some(prefix) = &self.prefix {
+            path_and_query.push_str(prefix);
+        }
+
+        path_and_query.push_str(path);
+
+        if let Some(query) = req.uri().query() {
+            path_and_query.push('?');
+            path_and_query.push_str(query);
+        }
+
+        let mut parts = req.uri().clone().into_parts();
+        parts.path_and_query = Some(PathAndQuery::try_from(path_and_query).ok()?);
+
+        if let Some(authority) = &self.authority {
+            if parts.authority.is_some() {
+                parts.authority = Some(authority.clone());
+            }
+
+            req.headers_mut().insert(
+                header::HOST,
+                HeaderValue::from_str(authority.as_str()).ok()?,
+            );
+        }
+
+        *req.uri_mut() = Uri::from_parts(parts).ok()?;
+
+        Some(())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct TenantConfig {
+    sources: Vec<TenantSource>,
+    tenants: HashMap<String, TenantRoute>,
+    fallback: Option<TenantRoute>,
+}
+
+#[derive(Debug, Clone, Default)]
+/// [`Layer`] resolving the tenant and rewriting the requests, see the
+/// [module-level documentation](self).
+///
+/// Requests whose tenant is missing or unknown are routed by the fallback
+/// route if any, or rejected with `400 Bad Request`.
+pub struct TenantLayer {
+    config: Arc<TenantConfig>,
+}
+
+impl TenantLayer {
+    #[inline]
+    /// Create a new [`TenantLayer`], without any source or tenant.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn config_mut(&mut self) -> &mut TenantConfig {
+        Arc::make_mut(&mut self.config)
+    }
+
+    #[must_use]
+    /// Resolve the tenant from the header. Sources are tried in order.
+    pub fn with_header(mut self, name: HeaderName) -> Self {
+        self.config_mut().sources.push(TenantSource::Header(name));
+        self
+    }
+
+    #[must_use]
+    /// Resolve the tenant from the query key. Sources are tried in order.
+    pub fn with_query_key(mut self, key: &'static str) -> Self {
+        self.config_mut().sources.push(TenantSource::Query(key));
+        self
+    }
+
+    #[must_use]
+    /// Add the tenant and its route.
+    pub fn with_tenant(mut self, id: impl Into<String>, route: TenantRoute) -> Self {
+        self.config_mut().tenants.insert(id.into(), route);
+        self
+    }
+
+    #[must_use]
+    /// Route the requests whose tenant is missing or unknown, instead of
+    /// rejecting them.
+    pub fn with_fallback(mut self, route: TenantRoute) -> Self {
+        self.config_mut().fallback = Some(route);
+        self
+    }
+}
+
+impl<S> Layer<S> for TenantLayer {
+    type Service = TenantService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TenantService {
+            inner,
+            config: self.config.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// [`Service`] resolving the tenant, see [`TenantLayer`].
+pub struct TenantService<S> {
+    inner: S,
+    config: Arc<TenantConfig>,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for TenantService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+    ResBody: Default + Send + 'static,
+{
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+    type Response = S::Response;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        let mut query = None;
+        let tenant = self
+            .config
+            .sources
+            .iter()
+            .find_map(|source| source.resolve(&req, &mut query));
+
+        let route = tenant
+            .as_ref()
+            .and_then(|tenant| self.config.tenants.get(tenant))
+            .or(self.config.fallback.as_ref());
+
+        let rewritten = route.and_then(|route| route.rewrite(&mut req));
+
+        if rewritten.is_none() {
+            #[cfg(feature = "feat-tracing")]
+            tracing::warn!("Rejected request of tenant {tenant:?}");
+
+            let mut response = Response::new(ResBody::default());
+            *response.status_mut() = StatusCode::BAD_REQUEST;
+
+            return Box::pin(std::future::ready(Ok(response)));
+        }
+
+        if let Some(tenant) = tenant {
+            req.extensions_mut().insert(Tenant(tenant));
+        }
+
+        Box::pin(self.inner.call(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use super::*;
+
+    fn echo(req: Request<()>) -> std::future::Ready<Result<Response<String>, Infallible>> {
+        std::future::ready(Ok(Response::new(format!(
+            "{} {} {:?}",
+            req.uri(),
+            req.headers()
+                .get(header::HOST)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default(),
+            req.extensions().get::<Tenant>().map(|t| &*t.0),
+        ))))
+    }
+
+    #[tokio::test]
+    async fn test_tenant_layer() {
+        let layer = TenantLayer::new()
+            .with_header(HeaderName::from_static("x-tenant"))
+            .with_query_key("tenant")
+            .with_tenant(
+                "acme",
+                TenantRoute::new()
+                    .with_strip_prefix("/acme")
+                    .with_prefix("/tenants/acme")
+                    .with_authority(Authority::from_static("acme.internal:8080")),
+            );
+
+        let call = |layer: &TenantLayer, uri: &str, tenant: Option<&'static str>| {
+            let mut req = Request::builder().uri(uri);
+            if let Some(tenant) = tenant {
+                req = req.header("x-tenant", tenant);
+            }
+
+            let mut service = layer.layer(tower::service_fn(echo));
+            let future = service.call(req.body(()).unwrap());
+
+            async move {
+                let response = future.await.unwrap();
+                (response.status(), response.into_body())
+            }
+        };
+
+        assert_eq!(
+            call(&layer, "/acme/users?a=1", Some("acme")).await,
+            (
+                StatusCode::OK,
+                r#"/tenants/acme/users?a=1 acme.internal:8080 Some("acme")"#.to_owned()
+            )
+        );
+        assert_eq!(
+            call(&layer, "http://gw.example.com/acmecorp?tenant=acme", None).await,
+            (
+                StatusCode::OK,
+                r#"http://acme.internal:8080/tenants/acme/acmecorp?tenant=acme acme.internal:8080 Some("acme")"#
+                    .to_owned()
+            )
+        );
+        assert_eq!(
+            call(&layer, "/users", Some("other")).await.0,
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            call(&layer, "/users", None).await.0,
+            StatusCode::BAD_REQUEST
+        );
+
+        let layer = layer.with_fallback(TenantRoute::new());
+        assert_eq!(
+            call(&layer, "/users", Some("other")).await,
+            (StatusCode::OK, r#"/users  Some("other")"#.to_owned())
+        );
+    }
+}
@@ -0,0 +1,310 @@
+//! Tracing span layer for HTTP requests, see [`WithTraceLayer`].
+
+use std::{
+    fmt::{self, Write},
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use http::{HeaderName, Request, Response};
+use tower_layer::Layer;
+use tower_service::Service;
+use tracing::{field, Instrument};
+
+use super::normalize_path;
+use crate::request::parser::{integration::get_query, OwnedQuery};
+
+/// Placeholder of redacted values.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Query keys redacted by default.
+pub const DEFAULT_REDACTED_KEYS: &[&str] = &[
+    "access_token",
+    "api_key",
+    "key",
+    "password",
+    "secret",
+    "sign",
+    "signature",
+    "token",
+];
+
+#[derive(Debug, Clone)]
+/// [`Layer`] opening a tracing span per request.
+///
+/// The span `http.request` has fields:
+///
+/// - `method`
+/// - `path`: normalized, see [`normalize_path`]
+/// - `query`: the selected query keys, with sensitive values redacted, see
+///   [`with_query_keys`](Self::with_query_keys)
+/// - `request_id`: from the request ID header
+/// - `status` and `latency_ms`: recorded on completion
+/// - `error`: recorded if the inner service fails
+pub struct WithTraceLayer {
+    query_keys: &'static [&'static str],
+    redacted_keys: &'static [&'static str],
+    request_id_header: HeaderName,
+}
+
+impl Default for WithTraceLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WithTraceLayer {
+    #[inline]
+    /// Create a new [`WithTraceLayer`], recording no query key, with request
+    /// ID header `X-Request-Id`.
+    pub const fn new() -> Self {
+        Self {
+            query_keys: &[],
+            redacted_keys: DEFAULT_REDACTED_KEYS,
+            request_id_header: HeaderName::from_static("x-request-id"),
+        }
+    }
+
+    #[inline]
+    /// Set the query keys to record.
+    ///
+    /// The parsed [`OwnedQuery`] in the request extensions (see
+    /// [`WithQueryLayer`](crate::request::parser::integration::WithQueryLayer))
+    /// is reused if any.
+    pub fn with_query_keys(self, query_keys: &'static [&'static str]) -> Self {
+        Self { query_keys, ..self }
+    }
+
+    #[inline]
+    /// Set the query keys whose values are redacted, [`DEFAULT_REDACTED_KEYS`]
+    /// by default.
+    pub fn with_redacted_keys(self, redacted_keys: &'static [&'static str]) -> Self {
+        Self {
+            redacted_keys,
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Set the request ID header.
+    pub fn with_request_id_header(self, request_id_header: HeaderName) -> Self {
+        Self {
+            request_id_header,
+            ..self
+        }
+    }
+
+    /// Format the selected query keys, with sensitive values redacted.
+    fn format_query(&self, query: &OwnedQuery) -> String {
+        let mut buf = String::new();
+
+        for &key in self.query_keys {
+            let Some(value) = query.get(key) else {
+                continue;
+            };
+
+            let value = if self
+                .redacted_keys
+                .iter()
+                .any(|redacted| redacted.eq_ignore_ascii_case(key))
+            {
+                REDACTED
+            } else {
+                value
+            };
+
+            if !buf.is_empty() {
+                buf.push('&');
+            }
+            let _ = write!(buf, "{key}={value}");
+        }
+
+        buf
+    }
+}
+
+impl<S> Layer<S> for WithTraceLayer {
+    type Service = WithTraceService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        WithTraceService {
+            inner,
+            config: self.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// [`Service`] opening a tracing span per request, see [`WithTraceLayer`].
+pub struct WithTraceService<S> {
+    inner: S,
+    config: WithTraceLayer,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for WithTraceService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+    S::Error: fmt::Display,
+{
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+    type Response = S::Response;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let span = tracing::info_span!(
+            "http.request",
+            method = %req.method(),
+            path = %normalize_path(req.uri().path()),
+            query = field::Empty,
+            request_id = field::Empty,
+            status = field::Empty,
+            latency_ms = field::Empty,
+            error = field::Empty,
+        );
+
+        if !self.config.query_keys.is_empty() {
+            let query = match get_query(&req) {
+                Ok(Some(query)) => Some(self.config.format_query(query)),
+                _ => OwnedQuery::parse_uri(req.uri()).map(|query| self.config.format_query(&query)),
+            };
+
+            if let Some(query) = query.filter(|query| !query.is_empty()) {
+                span.record("query", query);
+            }
+        }
+
+        if let Some(request_id) = req
+            .headers()
+            .get(&self.config.request_id_header)
+            .and_then(|v| v.to_str().ok())
+        {
+            span.record("request_id", request_id);
+        }
+
+        let started = Instant::now();
+        let fut = span.in_scope(|| self.inner.call(req));
+
+        Box::pin(
+            async move {
+                let result = fut.await;
+
+                let span = tracing::Span::current();
+                #[allow(
+                    clippy::cast_possible_truncation,
+                    reason = "latency never gets that large"
+                )]
+                span.record("latency_ms", started.elapsed().as_millis() as u64);
+
+                match &result {
+                    Ok(response) => {
+                        span.record("status", response.status().as_u16());
+                    }
+                    Err(e) => {
+                        span.record("error", field::display(e));
+                    }
+                }
+
+                tracing::info!("Finished processing request");
+
+                result
+            }
+            .instrument(span),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        sync::{Arc, Mutex},
+    };
+
+    use tracing::{
+        field::{Field, Visit},
+        span::{Attributes, Id, Record},
+        Subscriber,
+    };
+    use tracing_subscriber::{
+        layer::{self, SubscriberExt},
+        util::SubscriberInitExt,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_format_query() {
+        let layer = WithTraceLayer::new().with_query_keys(&["page", "token", "missing"]);
+        let query = OwnedQuery::parse("page=2&token=abc&other=1");
+
+        assert_eq!(layer.format_query(&query), "page=2&token=[REDACTED]");
+    }
+
+    /// Records the span fields, as `name=value`.
+    #[derive(Clone, Default)]
+    struct SpanFields(Arc<Mutex<Vec<String>>>);
+
+    impl Visit for SpanFields {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("{}={value:?}", field.name()));
+        }
+    }
+
+    impl<S: Subscriber> tracing_subscriber::Layer<S> for SpanFields {
+        fn on_new_span(&self, attrs: &Attributes<'_>, _id: &Id, _ctx: layer::Context<'_, S>) {
+            attrs.record(&mut self.clone());
+        }
+
+        fn on_record(&self, _id: &Id, values: &Record<'_>, _ctx: layer::Context<'_, S>) {
+            values.record(&mut self.clone());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_with_trace_layer() {
+        let fields = SpanFields::default();
+        let _guard = tracing_subscriber::registry()
+            .with(fields.clone())
+            .set_default();
+
+        let ok200 = tower::service_fn(|_: Request<()>| {
+            std::future::ready(Ok::<_, Infallible>(Response::new(())))
+        });
+        let mut service = WithTraceLayer::new()
+            .with_query_keys(&["page", "token"])
+            .layer(ok200);
+
+        let req = Request::get("/users/42?page=2&token=abc")
+            .header("x-request-id", "abc")
+            .body(())
+            .unwrap();
+
+        assert_eq!(service.call(req).await.unwrap().status(), 200);
+
+        let fields = fields.0.lock().unwrap();
+        for field in [
+            "method=GET",
+            "path=/users/{id}",
+            r#"query="page=2&token=[REDACTED]""#,
+            r#"request_id="abc""#,
+            "status=200",
+        ] {
+            assert!(
+                fields.iter().any(|f| f == field),
+                "{field} not in {fields:?}"
+            );
+        }
+        assert!(fields.iter().any(|f| f.starts_with("latency_ms=")));
+        assert!(!fields.iter().any(|f| f.starts_with("error=")));
+    }
+}
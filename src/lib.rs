@@ -1,5 +1,50 @@
 //! miku-http-util
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod auth;
+pub mod body;
+#[cfg(feature = "feat-cache")]
+pub mod cache;
+#[cfg(feature = "feat-circuit-breaker")]
+pub mod circuit_breaker;
+#[cfg(feature = "feat-csp-report")]
+pub mod csp;
+pub mod error;
+#[cfg(feature = "feat-har")]
+pub mod har;
+pub mod layer;
+#[cfg(feature = "feat-net")]
+pub mod net;
+#[cfg(feature = "feat-percent")]
+pub mod percent;
+#[cfg(feature = "feat-rate-limiter")]
+pub mod rate_limiter;
 pub mod request;
 #[cfg(feature = "feat-response")]
 pub mod response;
+#[cfg(feature = "feat-retry")]
+pub mod retry;
+#[cfg(feature = "feat-single-flight")]
+pub mod single_flight;
+pub mod testing;
+#[cfg(feature = "std")]
+#[allow(dead_code, reason = "unused if no time related feature is enabled")]
+mod time;
+#[cfg(feature = "feat-version")]
+pub mod version;
+#[cfg(feature = "feat-wire")]
+pub mod wire;
+#[cfg(feature = "feat-ws")]
+pub mod ws;
+
+#[cfg(feature = "std")]
+pub use error::BoxError;
+pub use error::{Error, Result};
+
+/// Boxed future, returned by the async callbacks, e.g.
+/// `TokenSource::fetch` or `CacheStore::get`.
+pub type BoxFuture<'a, T> =
+    core::pin::Pin<alloc::boxed::Box<dyn core::future::Future<Output = T> + Send + 'a>>;
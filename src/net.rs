@@ -0,0 +1,457 @@
+//! Networking helpers for connectors, see [`HappyEyeballs`], [`Resolve`] and
+//! [`SocksTarget`].
+
+use std::{
+    future::{poll_fn, Future},
+    io,
+    net::{IpAddr, SocketAddr},
+    pin::Pin,
+    task::Poll,
+    time::Duration,
+};
+
+use tokio::{
+    net::TcpStream,
+    time::{sleep, timeout, Instant},
+};
+
+type ConnectFuture = Pin<Box<dyn Future<Output = io::Result<TcpStream>> + Send>>;
+
+/// Boxed future of [`Resolve::resolve`].
+pub type ResolveFuture<'a> = Pin<Box<dyn Future<Output = io::Result<Vec<SocketAddr>>> + Send + 'a>>;
+
+/// DNS resolver.
+pub trait Resolve: Send + Sync {
+    /// Resolve the host name to the socket addresses with the port.
+    fn resolve<'a>(&'a self, host: &'a str, port: u16) -> ResolveFuture<'a>;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+/// [`Resolve`] with [`tokio::net::lookup_host`], i.e. `getaddrinfo` on the
+/// blocking thread pool.
+pub struct TokioResolver;
+
+impl Resolve for TokioResolver {
+    fn resolve<'a>(&'a self, host: &'a str, port: u16) -> ResolveFuture<'a> {
+        Box::pin(async move { Ok(tokio::net::lookup_host((host, port)).await?.collect()) })
+    }
+}
+
+/// Parse the IP literal, bracketed or not.
+fn parse_ip(host: &str) -> Option<IpAddr> {
+    host.strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host)
+        .parse()
+        .ok()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Destination in the SOCKS5 `CONNECT` request (RFC 1928, section 4).
+pub enum SocksTarget {
+    /// IP address, resolved locally (`socks5`) or an IP literal.
+    Addr(SocketAddr),
+
+    /// Domain name, resolved by the proxy (`socks5h`).
+    Domain(String, u16),
+}
+
+impl SocksTarget {
+    /// Returns the destination of the target host, honoring `remote_dns` of
+    /// [`ProxyScheme::Socks5`](crate::request::misc::proxy::ProxyScheme):
+    /// with `socks5h` the host name is sent to the proxy as is, and with
+    /// `socks5` it is resolved locally with the resolver (the first address
+    /// wins).
+    ///
+    /// IP literals are never resolved.
+    ///
+    /// # Errors
+    ///
+    /// Failed to resolve the host, or no address is resolved.
+    pub async fn resolve<R>(
+        resolver: &R,
+        host: &str,
+        port: u16,
+        remote_dns: bool,
+    ) -> io::Result<Self>
+    where
+        R: Resolve + ?Sized,
+    {
+        if let Some(ip) = parse_ip(host) {
+            return Ok(Self::Addr(SocketAddr::new(ip, port)));
+        }
+
+        if remote_dns {
+            return Ok(Self::Domain(host.to_owned(), port));
+        }
+
+        resolver
+            .resolve(host, port)
+            .await?
+            .into_iter()
+            .next()
+            .map(Self::Addr)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no address of {host}")))
+    }
+
+    /// Encode as `ATYP`, `DST.ADDR` and `DST.PORT` of the SOCKS5 request,
+    /// appending to the buffer.
+    ///
+    /// # Errors
+    ///
+    /// [`io::ErrorKind::InvalidInput`] if the domain name is longer than 255
+    /// bytes.
+    pub fn encode_socks5(&self, buf: &mut Vec<u8>) -> io::Result<()> {
+        match self {
+            Self::Addr(SocketAddr::V4(addr)) => {
+                buf.push(0x01);
+                buf.extend_from_slice(&addr.ip().octets());
+                buf.extend_from_slice(&addr.port().to_be_bytes());
+            }
+            Self::Addr(SocketAddr::V6(addr)) => {
+                buf.push(0x04);
+                buf.extend_from_slice(&addr.ip().octets());
+                buf.extend_from_slice(&addr.port().to_be_bytes());
+            }
+            Self::Domain(domain, port) => {
+                let len = u8::try_from(domain.len()).map_err(|_| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "domain name too long")
+                })?;
+
+                buf.push(0x03);
+                buf.push(len);
+                buf.extend_from_slice(domain.as_bytes());
+                buf.extend_from_slice(&port.to_be_bytes());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Sort the resolved addresses for connection attempts (RFC 8305, section
+/// 4): duplicates removed, address families interleaved, starting with the
+/// family of the first address (which the resolver has sorted per RFC 6724).
+pub fn interleave<I>(addrs: I) -> Vec<SocketAddr>
+where
+    I: IntoIterator<Item = SocketAddr>,
+{
+    let mut preferred = Vec::new();
+    let mut other = Vec::new();
+
+    for addr in addrs {
+        if preferred.contains(&addr) || other.contains(&addr) {
+            continue;
+        }
+
+        match preferred.first() {
+            Some(first) if first.is_ipv6() != addr.is_ipv6() => other.push(addr),
+            _ => preferred.push(addr),
+        }
+    }
+
+    let mut sorted = Vec::with_capacity(preferred.len() + other.len());
+    let mut other = other.into_iter();
+
+    for addr in preferred {
+        sorted.push(addr);
+        sorted.extend(other.next());
+    }
+    sorted.extend(other);
+
+    sorted
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Happy Eyeballs (RFC 8305) TCP dialer, racing connections to the resolved
+/// addresses so that a blackholed address family does not stall.
+///
+/// The addresses are tried in the order of [`interleave`], starting the next
+/// attempt once the previous one fails or the attempt delay elapses, and the
+/// first established connection wins (the others are dropped).
+///
+/// Note that the host is resolved with [`tokio::net::lookup_host`], i.e.
+/// `getaddrinfo`, so A and AAAA records are not queried separately.
+pub struct HappyEyeballs {
+    /// Delay between starting connection attempts.
+    attempt_delay: Duration,
+
+    /// Timeout of each connection attempt.
+    connect_timeout: Option<Duration>,
+}
+
+impl Default for HappyEyeballs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HappyEyeballs {
+    #[inline]
+    /// Create a new [`HappyEyeballs`], with the recommended attempt delay of
+    /// 250 ms and no timeout per attempt.
+    pub const fn new() -> Self {
+        Self {
+            attempt_delay: Duration::from_millis(250),
+            connect_timeout: None,
+        }
+    }
+
+    #[inline]
+    /// Set the delay between starting connection attempts, RFC 8305
+    /// recommends at least 100 ms and at most 2 seconds.
+    pub const fn with_attempt_delay(self, attempt_delay: Duration) -> Self {
+        Self {
+            attempt_delay,
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Set the timeout of each connection attempt.
+    pub const fn with_connect_timeout(self, connect_timeout: Option<Duration>) -> Self {
+        Self {
+            connect_timeout,
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Resolve the host with [`TokioResolver`] and connect to it, see
+    /// [`connect_with`](Self::connect_with).
+    ///
+    /// # Errors
+    ///
+    /// Failed to resolve the host, or the error of the last attempt.
+    pub async fn connect(&self, host: &str, port: u16) -> io::Result<TcpStream> {
+        self.connect_with(&TokioResolver, host, port).await
+    }
+
+    /// Resolve the host with the resolver (an IP address, bracketed or not, is
+    /// used as is) and connect to it, see
+    /// [`connect_addrs`](Self::connect_addrs).
+    ///
+    /// # Errors
+    ///
+    /// Failed to resolve the host, or the error of the last attempt.
+    pub async fn connect_with<R>(
+        &self,
+        resolver: &R,
+        host: &str,
+        port: u16,
+    ) -> io::Result<TcpStream>
+    where
+        R: Resolve + ?Sized,
+    {
+        if let Some(ip) = parse_ip(host) {
+            return self.connect_addrs([SocketAddr::new(ip, port)]).await;
+        }
+
+        let addrs = resolver.resolve(host, port).await?;
+
+        self.connect_addrs(addrs).await
+    }
+
+    #[cfg(feature = "feat-request-misc-proxy")]
+    /// Connect to the proxy server, see [`connect`](Self::connect).
+    ///
+    /// For `SOCKS5` proxies, resolve the target with [`SocksTarget::resolve`]
+    /// then.
+    ///
+    /// # Errors
+    ///
+    /// Failed to resolve the host, or the error of the last attempt.
+    pub async fn connect_proxy(
+        &self,
+        proxy: &crate::request::misc::proxy::ProxyScheme,
+    ) -> io::Result<TcpStream> {
+        use crate::request::misc::proxy::ProxyScheme;
+
+        match proxy {
+            ProxyScheme::Http {
+                is_https,
+                authority,
+                ..
+            } => {
+                let port = authority
+                    .port_u16()
+                    .unwrap_or(if *is_https { 443 } else { 80 });
+
+                self.connect(authority.host(), port).await
+            }
+            ProxyScheme::Socks5 { host, port, .. } => self.connect(host, *port).await,
+        }
+    }
+
+    /// Race connections to the addresses, see [`HappyEyeballs`].
+    ///
+    /// # Errors
+    ///
+    /// The error of the last attempt, or [`io::ErrorKind::InvalidInput`] if
+    /// there's no address.
+    pub async fn connect_addrs<I>(&self, addrs: I) -> io::Result<TcpStream>
+    where
+        I: IntoIterator<Item = SocketAddr>,
+    {
+        let mut addrs = interleave(addrs).into_iter();
+        let mut attempts: Vec<ConnectFuture> = Vec::new();
+        let mut last_error = None;
+        let mut failed = false;
+
+        let delay = sleep(self.attempt_delay);
+        tokio::pin!(delay);
+
+        poll_fn(|cx| loop {
+            // Start the next attempt: nothing in flight, the previous one
+            // failed, or the attempt delay elapsed.
+            if attempts.is_empty() || failed || delay.as_mut().poll(cx).is_ready() {
+                failed = false;
+
+                match addrs.next() {
+                    Some(addr) => {
+                        attempts.push(Box::pin(connect_one(addr, self.connect_timeout)));
+
+                        delay.as_mut().reset(Instant::now() + self.attempt_delay);
+                        let _ = delay.as_mut().poll(cx);
+                    }
+                    None if attempts.is_empty() => {
+                        return Poll::Ready(Err(last_error.take().unwrap_or_else(|| {
+                            io::Error::new(io::ErrorKind::InvalidInput, "no address to connect")
+                        })));
+                    }
+                    None => {}
+                }
+            }
+
+            let mut idx = 0;
+            while idx < attempts.len() {
+                match attempts[idx].as_mut().poll(cx) {
+                    Poll::Ready(Ok(stream)) => return Poll::Ready(Ok(stream)),
+                    Poll::Ready(Err(e)) => {
+                        #[cfg(feature = "feat-tracing")]
+                        tracing::debug!("Connection attempt failed: {e}");
+
+                        drop(attempts.swap_remove(idx));
+                        last_error = Some(e);
+                        failed = true;
+                    }
+                    Poll::Pending => idx += 1,
+                }
+            }
+
+            if !failed {
+                return Poll::Pending;
+            }
+        })
+        .await
+    }
+}
+
+async fn connect_one(addr: SocketAddr, connect_timeout: Option<Duration>) -> io::Result<TcpStream> {
+    match connect_timeout {
+        Some(connect_timeout) => timeout(connect_timeout, TcpStream::connect(addr))
+            .await
+            .map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("connecting to {addr} timed out"),
+                )
+            })?,
+        None => TcpStream::connect(addr).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn test_interleave() {
+        let v4 = |n: u8| SocketAddr::from(([10, 0, 0, n], 80));
+        let v6 = |n: u16| SocketAddr::from(([0xfd00, 0, 0, 0, 0, 0, 0, n], 80));
+
+        assert_eq!(
+            interleave([v6(1), v6(2), v6(3), v4(1), v6(1), v4(2)]),
+            [v6(1), v4(1), v6(2), v4(2), v6(3)]
+        );
+        assert_eq!(
+            interleave([v4(1), v4(2), v4(3), v6(1)]),
+            [v4(1), v6(1), v4(2), v4(3)]
+        );
+        assert!(interleave([]).is_empty());
+    }
+
+    struct FixedResolver(SocketAddr);
+
+    impl Resolve for FixedResolver {
+        fn resolve<'a>(&'a self, _host: &'a str, _port: u16) -> ResolveFuture<'a> {
+            Box::pin(async move { Ok(vec![self.0]) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_socks_target() {
+        let resolver = FixedResolver(SocketAddr::from(([10, 0, 0, 1], 443)));
+
+        let target = SocksTarget::resolve(&resolver, "example.com", 443, true)
+            .await
+            .unwrap();
+        assert_eq!(target, SocksTarget::Domain("example.com".to_owned(), 443));
+
+        let mut buf = Vec::new();
+        target.encode_socks5(&mut buf).unwrap();
+        assert_eq!(buf, b"\x03\x0bexample.com\x01\xbb");
+
+        let target = SocksTarget::resolve(&resolver, "example.com", 443, false)
+            .await
+            .unwrap();
+        assert_eq!(target, SocksTarget::Addr(resolver.0));
+
+        let target = SocksTarget::resolve(&resolver, "[::1]", 80, true)
+            .await
+            .unwrap();
+
+        let mut buf = Vec::new();
+        target.encode_socks5(&mut buf).unwrap();
+        assert_eq!(buf[0], 0x04);
+        assert_eq!(buf.len(), 1 + 16 + 2);
+
+        assert_eq!(
+            SocksTarget::Domain("a".repeat(256), 80)
+                .encode_socks5(&mut buf)
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::InvalidInput
+        );
+    }
+
+    #[tokio::test]
+    async fn test_connect_addrs() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // A closed port, refused at once.
+        let closed = {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            listener.local_addr().unwrap()
+        };
+
+        let dialer = HappyEyeballs::new()
+            .with_attempt_delay(Duration::from_millis(100))
+            .with_connect_timeout(Some(Duration::from_secs(5)));
+
+        let stream = dialer.connect_addrs([closed, addr]).await.unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), addr);
+
+        let stream = dialer.connect("127.0.0.1", addr.port()).await.unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), addr);
+
+        dialer.connect_addrs([closed]).await.unwrap_err();
+        assert_eq!(
+            dialer.connect_addrs([]).await.unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+    }
+}
@@ -0,0 +1,228 @@
+//! Percent-encoding (RFC 3986) with named encode sets, see [`encode_to`] and
+//! [`decode_to`].
+//!
+//! Pick the set by where the encoded string goes, instead of mixing
+//! [`NON_ALPHANUMERIC`] with hand-made sets.
+
+use alloc::{borrow::Cow, string::String};
+use core::fmt::Write;
+
+pub use percent_encoding::AsciiSet;
+use percent_encoding::{
+    percent_decode_str, percent_encode_byte, utf8_percent_encode, NON_ALPHANUMERIC,
+};
+
+/// Everything except the unreserved characters (`A-Z a-z 0-9 - . _ ~`).
+///
+/// The safest choice, the output can be put anywhere in a URI.
+pub const UNRESERVED: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// A key or value in the query, same as [`UNRESERVED`] so that `&`, `=`,
+/// `+` and `#` are always encoded.
+pub const QUERY: &AsciiSet = UNRESERVED;
+
+/// A path segment, keeping the sub-delims (`!$&'()*+,;=`), `:` and `@`, but
+/// encoding `/`.
+pub const PATH_SEGMENT: &AsciiSet = &UNRESERVED
+    .remove(b'!')
+    .remove(b'$')
+    .remove(b'&')
+    .remove(b'\'')
+    .remove(b'(')
+    .remove(b')')
+    .remove(b'*')
+    .remove(b'+')
+    .remove(b',')
+    .remove(b';')
+    .remove(b'=')
+    .remove(b':')
+    .remove(b'@');
+
+/// The user name or password of the user info, keeping the sub-delims
+/// (`!$&'()*+,;=`), but encoding `:` and `@`.
+pub const USERINFO: &AsciiSet = &UNRESERVED
+    .remove(b'!')
+    .remove(b'$')
+    .remove(b'&')
+    .remove(b'\'')
+    .remove(b'(')
+    .remove(b')')
+    .remove(b'*')
+    .remove(b'+')
+    .remove(b',')
+    .remove(b';')
+    .remove(b'=');
+
+/// A key or value of `application/x-www-form-urlencoded` (WHATWG URL),
+/// keeping `* - . _` only.
+///
+/// Spaces are encoded as `%20` rather than `+`, which is equally valid.
+pub const FORM: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'*')
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_');
+
+#[inline]
+/// Percent-encode the input with the set, borrowed if nothing is encoded.
+pub fn encode<'a>(input: &'a str, set: &'static AsciiSet) -> Cow<'a, str> {
+    utf8_percent_encode(input, set).into()
+}
+
+#[inline]
+/// Percent-encode the input with the set, appending to the buffer.
+pub fn encode_to(input: &str, set: &'static AsciiSet, buf: &mut String) {
+    let _ = write!(buf, "{}", utf8_percent_encode(input, set));
+}
+
+#[inline]
+/// Percent-decode the input, borrowed if nothing is decoded.
+///
+/// Invalid UTF-8 sequences are replaced with `U+FFFD`.
+pub fn decode(input: &str) -> Cow<'_, str> {
+    percent_decode_str(input).decode_utf8_lossy()
+}
+
+#[inline]
+/// Percent-decode the input, appending to the buffer.
+///
+/// Invalid UTF-8 sequences are replaced with `U+FFFD`.
+pub fn decode_to(input: &str, buf: &mut String) {
+    buf.push_str(&decode(input));
+}
+
+/// Decode a key or value of `application/x-www-form-urlencoded`, i.e. `+`
+/// as space, then percent-decode, appending to the buffer.
+pub fn decode_form_to(input: &str, buf: &mut String) {
+    if input.contains('+') {
+        decode_to(&input.replace('+', " "), buf);
+    } else {
+        decode_to(input, buf);
+    }
+}
+
+/// Normalize the percent-encoding (RFC 3986, section 6.2.2.2): decode the
+/// unreserved characters, and uppercase the hex digits of the others.
+///
+/// Borrowed if nothing is changed.
+pub fn normalize(input: &str) -> Cow<'_, str> {
+    if !input.contains('%') {
+        return Cow::Borrowed(input);
+    }
+
+    let mut normalized = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(idx) = rest.find('%') {
+        normalized.push_str(&rest[..idx]);
+
+        let byte = rest
+            .get(idx + 1..idx + 3)
+            .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match byte {
+            Some(byte) if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) => {
+                normalized.push(char::from(byte));
+            }
+            Some(byte) => normalized.push_str(percent_encode_byte(byte)),
+            None => {
+                normalized.push('%');
+                rest = &rest[idx + 1..];
+                continue;
+            }
+        }
+
+        rest = &rest[idx + 3..];
+    }
+
+    normalized.push_str(rest);
+
+    if normalized == input {
+        Cow::Borrowed(input)
+    } else {
+        Cow::Owned(normalized)
+    }
+}
+
+#[cfg(any(feature = "feat-integrate-tower", feature = "feat-request-uri"))]
+/// Remove the pairs of the keys (compared after percent-decoding) from the
+/// query, keeping the others as is, `None` if nothing is removed.
+pub(crate) fn strip_query_keys(query: &str, keys: &[&str]) -> Option<String> {
+    let is_stripped = |pair: &str| {
+        let key = pair.split_once('=').map_or(pair, |(key, _)| key);
+
+        keys.contains(&&*decode(key))
+    };
+
+    if !query.split('&').any(is_stripped) {
+        return None;
+    }
+
+    Some(
+        query
+            .split('&')
+            .filter(|pair| !is_stripped(pair))
+            .collect::<alloc::vec::Vec<_>>()
+            .join("&"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_sets() {
+        const INPUT: &str = "a b/c?d=e&f+g:h@i~j*你";
+
+        assert_eq!(
+            encode(INPUT, QUERY),
+            "a%20b%2Fc%3Fd%3De%26f%2Bg%3Ah%40i~j%2A%E4%BD%A0"
+        );
+        assert_eq!(
+            encode(INPUT, PATH_SEGMENT),
+            "a%20b%2Fc%3Fd=e&f+g:h@i~j*%E4%BD%A0"
+        );
+        assert_eq!(
+            encode(INPUT, USERINFO),
+            "a%20b%2Fc%3Fd=e&f+g%3Ah%40i~j*%E4%BD%A0"
+        );
+        assert_eq!(
+            encode(INPUT, FORM),
+            "a%20b%2Fc%3Fd%3De%26f%2Bg%3Ah%40i%7Ej*%E4%BD%A0"
+        );
+        assert!(matches!(encode("abc", QUERY), Cow::Borrowed("abc")));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        const INPUT: &str = "a b/c?d=e&f+g:h@i~j*你";
+
+        let mut buf = String::new();
+        for set in [QUERY, PATH_SEGMENT, USERINFO, FORM] {
+            let mut encoded = String::from("x=");
+            encode_to(INPUT, set, &mut encoded);
+
+            buf.clear();
+            decode_to(&encoded[2..], &mut buf);
+            assert_eq!(buf, INPUT);
+        }
+
+        buf.clear();
+        decode_form_to("a+b%2B", &mut buf);
+        assert_eq!(buf, "a b+");
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("%7e%2fa%zz%4"), "~%2Fa%zz%4");
+        assert_eq!(normalize("%+a%2"), "%+a%2");
+        assert_eq!(normalize("%e4%BD%a0"), "%E4%BD%A0");
+        assert!(matches!(normalize("a%2Fb"), Cow::Borrowed("a%2Fb")));
+    }
+}
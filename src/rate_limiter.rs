@@ -0,0 +1,309 @@
+//! Client-side rate limiter: token bucket per host or per key, see
+//! [`RateLimiter`], and the tower [`RateLimitLayer`].
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, PoisonError},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use http::Request;
+use tokio::time::Instant;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::response::{RateLimitInfo, ResponseExt};
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    paused_until: Option<Instant>,
+}
+
+#[derive(Debug, Clone)]
+/// Client-side rate limiter: a token bucket per host or per key.
+///
+/// Cheap to clone, clones share the buckets.
+///
+/// ```rust,no_run
+/// # async fn example() {
+/// use std::time::Duration;
+///
+/// use miku_http_util::rate_limiter::RateLimiter;
+///
+/// // 10 requests per second, bursting up to 10.
+/// let limiter = RateLimiter::new(10, Duration::from_secs(1)).with_auto_tune(true);
+///
+/// limiter.acquire("api.example.com").await;
+/// // send the request, then feed the response back
+/// // limiter.observe("api.example.com", &response);
+/// # }
+/// ```
+pub struct RateLimiter {
+    capacity: u32,
+    per: Duration,
+    auto_tune: bool,
+    buckets: Arc<Mutex<HashMap<String, Bucket>>>,
+}
+
+impl RateLimiter {
+    #[inline]
+    /// Create a new [`RateLimiter`], allowing `capacity` requests per `per`,
+    /// bursting up to `capacity`.
+    pub fn new(capacity: u32, per: Duration) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            per,
+            auto_tune: false,
+            buckets: Arc::default(),
+        }
+    }
+
+    #[inline]
+    /// Whether to tune the buckets with the `RateLimit-*` headers of
+    /// responses, see [`observe`](Self::observe).
+    pub fn with_auto_tune(self, auto_tune: bool) -> Self {
+        Self { auto_tune, ..self }
+    }
+
+    /// Tokens refilled per second.
+    fn rate(&self) -> f64 {
+        f64::from(self.capacity) / self.per.as_secs_f64().max(f64::EPSILON)
+    }
+
+    /// Take a token of the bucket, returns the time to wait before the request
+    /// can be sent.
+    ///
+    /// The token is reserved even if it's not available yet, so that waiters
+    /// are served in order.
+    pub fn reserve(&self, key: &str) -> Duration {
+        let now = Instant::now();
+        let rate = self.rate();
+        let capacity = f64::from(self.capacity);
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+        let bucket = buckets.entry(key.to_owned()).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+            paused_until: None,
+        });
+
+        let from = match bucket.paused_until {
+            Some(paused_until) if paused_until > now => paused_until,
+            _ => {
+                bucket.paused_until = None;
+                now
+            }
+        };
+
+        if from > bucket.updated {
+            bucket.tokens = (bucket.tokens
+                + from.duration_since(bucket.updated).as_secs_f64() * rate)
+                .min(capacity);
+            bucket.updated = from;
+        }
+
+        bucket.tokens -= 1.0;
+
+        let wait = from.duration_since(now)
+            + if bucket.tokens < 0.0 {
+                Duration::from_secs_f64(-bucket.tokens / rate)
+            } else {
+                Duration::ZERO
+            };
+
+        #[cfg(feature = "feat-tracing")]
+        if !wait.is_zero() {
+            tracing::debug!("Rate limited on `{key}`, waiting {wait:?}");
+        }
+
+        wait
+    }
+
+    /// Wait until a request to `key` can be sent.
+    pub async fn acquire(&self, key: &str) {
+        let wait = self.reserve(key);
+
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Tune the bucket of `key` with the parsed rate limit info:
+    ///
+    /// - tokens never exceed the remaining quota
+    /// - if the quota is exhausted, the bucket is paused until it resets
+    pub fn update(&self, key: &str, info: &RateLimitInfo) {
+        let Some(remaining) = info.remaining else {
+            return;
+        };
+
+        let now = Instant::now();
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+        let bucket = buckets.entry(key.to_owned()).or_insert(Bucket {
+            tokens: f64::from(self.capacity),
+            updated: now,
+            paused_until: None,
+        });
+
+        #[allow(
+            clippy::cast_precision_loss,
+            reason = "remaining quota never gets that large"
+        )]
+        let remaining = remaining as f64;
+        bucket.tokens = bucket.tokens.min(remaining);
+
+        if remaining == 0.0 {
+            if let Some(reset) = info.reset {
+                #[cfg(feature = "feat-tracing")]
+                tracing::warn!("Rate limit quota of `{key}` exhausted, pausing for {reset:?}");
+
+                bucket.tokens = 0.0;
+                bucket.paused_until = Some(now + reset);
+            }
+        }
+    }
+
+    /// Tune the bucket of `key` with the `RateLimit-*` headers of the
+    /// response, if auto-tuning is enabled.
+    pub fn observe(&self, key: &str, response: &ResponseExt) {
+        if !self.auto_tune {
+            return;
+        }
+
+        if let Some(info) = response.rate_limit() {
+            self.update(key, &info);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// [`Layer`] delaying requests according to the [`RateLimiter`], keyed by the
+/// host of the request URI.
+pub struct RateLimitLayer {
+    limiter: RateLimiter,
+}
+
+impl RateLimitLayer {
+    #[inline]
+    /// Create a new [`RateLimitLayer`].
+    pub const fn new(limiter: RateLimiter) -> Self {
+        Self { limiter }
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimitService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimitService {
+            inner,
+            limiter: self.limiter.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// [`Service`] delaying requests according to the [`RateLimiter`], see
+/// [`RateLimitLayer`].
+pub struct RateLimitService<S> {
+    inner: S,
+    limiter: RateLimiter,
+}
+
+impl<S, B> Service<Request<B>> for RateLimitService<S>
+where
+    S: Service<Request<B>, Response = ResponseExt> + Clone + Send + 'static,
+    S::Future: Send,
+    B: Send + 'static,
+{
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<ResponseExt, Self::Error>> + Send>>;
+    type Response = ResponseExt;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let limiter = self.limiter.clone();
+
+        Box::pin(async move {
+            let key = req.uri().host().unwrap_or_default().to_owned();
+
+            limiter.acquire(&key).await;
+
+            let response = inner.call(req).await?;
+
+            limiter.observe(&key, &response);
+
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_reserve() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(10));
+
+        assert_eq!(limiter.reserve("a"), Duration::ZERO);
+        assert_eq!(limiter.reserve("a"), Duration::ZERO);
+
+        assert_eq!(limiter.reserve("a"), Duration::from_secs(5));
+        assert_eq!(limiter.reserve("a"), Duration::from_secs(10));
+        assert_eq!(limiter.reserve("b"), Duration::ZERO);
+
+        tokio::time::advance(Duration::from_secs(4)).await;
+        assert_eq!(limiter.reserve("a"), Duration::from_secs(11));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_auto_tune() {
+        let limiter = RateLimiter::new(100, Duration::from_secs(1)).with_auto_tune(true);
+
+        let response = ResponseExt::builder()
+            .header("ratelimit-remaining", "0")
+            .header("ratelimit-reset", "30")
+            .build()
+            .unwrap();
+        limiter.observe("a", &response);
+
+        assert_eq!(limiter.reserve("a"), Duration::from_secs(30));
+
+        limiter.update(
+            "b",
+            &RateLimitInfo {
+                remaining: Some(1),
+                ..Default::default()
+            },
+        );
+        assert_eq!(limiter.reserve("b"), Duration::ZERO);
+        assert!(limiter.reserve("b") > Duration::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rate_limit_layer() {
+        let limiter = RateLimiter::new(1, Duration::from_millis(20));
+        let ok200 =
+            tower::service_fn(|_: Request<()>| std::future::ready(ResponseExt::builder().build()));
+        let mut service = RateLimitLayer::new(limiter.clone()).layer(ok200);
+
+        let started = Instant::now();
+        for _ in 0..3 {
+            let req = Request::get("http://a.com/").body(()).unwrap();
+            service.call(req).await.unwrap();
+        }
+        assert_eq!(started.elapsed(), Duration::from_millis(40));
+    }
+}
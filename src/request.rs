@@ -7,3 +7,7 @@ pub mod header;
 pub mod misc;
 #[cfg(feature = "feat-request-parser")]
 pub mod parser;
+#[cfg(feature = "feat-request-predicate")]
+pub mod predicate;
+#[cfg(feature = "feat-request-uri")]
+pub mod uri;
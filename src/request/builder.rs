@@ -1,6 +1,10 @@
 //! HTTP request utilities: builder related.
 
-use std::{borrow::Cow, convert::Infallible, ops};
+#[cfg(feature = "feat-request-builder-presets")]
+pub mod presets;
+
+use alloc::{borrow::Cow, string::String, vec::Vec};
+use core::{convert::Infallible, ops};
 
 use macro_toolset::{
     md5, str_concat,
@@ -18,6 +22,14 @@ pub type Queries<'q> = Query<'q>;
 #[derive(Debug)]
 #[repr(transparent)]
 /// Helper for query string building.
+///
+/// ## Deterministic output
+///
+/// [`build`](Self::build) keeps the push order, and
+/// [`build_sorted_stable`](Self::build_sorted_stable) (like [`Md5Signer`])
+/// sorts the pairs by key stably, so the output only depends on the pushed
+/// pairs and their order. Keys are written as is, and values are
+/// percent-encoded with everything except `A-Z a-z 0-9 - . _ ~` encoded.
 pub struct Query<'q> {
     inner: Vec<(Cow<'q, str>, Cow<'q, str>)>,
 }
@@ -55,6 +67,19 @@ impl<'q> Query<'q> {
         }
     }
 
+    #[cfg(feature = "feat-request-parser")]
+    /// Create from the parsed [`Query`](crate::request::parser::Query), e.g.
+    /// for modifying and re-building (or re-signing) it.
+    ///
+    /// The parsed one is unordered, so the pairs are sorted by key (then by
+    /// value).
+    pub fn from_query(mut query: crate::request::parser::Query<'q>) -> Self {
+        let mut inner: Vec<_> = query.drain().collect();
+        inner.sort_unstable();
+
+        Self { inner }
+    }
+
     #[inline]
     /// Push a new key-value pair into the query string builder.
     pub fn push(mut self, key: impl Into<Cow<'q, str>>, value: impl Into<Cow<'q, str>>) -> Self {
@@ -76,6 +101,9 @@ impl<'q> Query<'q> {
     /// Sort the inner query pairs by key.
     ///
     /// See [`sort_unstable_by`](https://doc.rust-lang.org/std/primitive.slice.html#method.sort_unstable_by) for more details about the time complexity.
+    ///
+    /// Pairs of the same key may be reordered, use
+    /// [`sort_stable`](Self::sort_stable) when the output is to be signed.
     pub fn sort(&mut self) {
         self.inner.sort_unstable_by(|l, r| l.0.cmp(&r.0));
     }
@@ -87,6 +115,21 @@ impl<'q> Query<'q> {
         self
     }
 
+    #[inline]
+    /// Sort the inner query pairs by key, stably, i.e. pairs of the same key
+    /// keep the push order.
+    pub fn sort_stable(&mut self) {
+        self.inner.sort_by(|l, r| l.0.cmp(&r.0));
+    }
+
+    #[inline]
+    /// Sort the query pairs by key, stably, see
+    /// [`sort_stable`](Self::sort_stable).
+    pub fn sorted_stable(mut self) -> Self {
+        self.sort_stable();
+        self
+    }
+
     #[inline]
     /// Get inner query pairs.
     pub const fn inner(&self) -> &Vec<(Cow<'q, str>, Cow<'q, str>)> {
@@ -144,6 +187,17 @@ impl<'q> Query<'q> {
         }))
     }
 
+    #[inline]
+    /// Build the query string deterministically: pairs sorted by key stably
+    /// (see [`sort_stable`](Self::sort_stable)), and values percent-encoded
+    /// as [`build`](Self::build) does.
+    ///
+    /// The output depends only on the pushed pairs and their order, which is
+    /// what signers rely on.
+    pub fn build_sorted_stable(self) -> String {
+        self.sorted_stable().build()
+    }
+
     #[inline]
     /// Build the query string with given signer.
     pub fn build_signed<S: SignerT>(self, signer: S) -> Result<String, S::Error> {
@@ -151,6 +205,14 @@ impl<'q> Query<'q> {
     }
 }
 
+#[cfg(feature = "feat-request-parser")]
+impl<'q> From<crate::request::parser::Query<'q>> for Query<'q> {
+    #[inline]
+    fn from(query: crate::request::parser::Query<'q>) -> Self {
+        Self::from_query(query)
+    }
+}
+
 /// Helper trait for query string signing.
 pub trait SignerT {
     /// The error type.
@@ -189,7 +251,7 @@ impl SignerT for Md5Signer<'_> {
     type Error = Infallible;
 
     fn build_signed(self, query: Query) -> Result<String, Self::Error> {
-        let query = query.sorted();
+        let query = query.sorted_stable();
 
         let mut final_string_buf = String::with_capacity(64);
 
@@ -272,6 +334,8 @@ impl<'s> Md5Signer<'s> {
 
 #[cfg(test)]
 mod tests {
+    use alloc::{borrow::ToOwned, format, string::ToString};
+
     use super::*;
 
     #[test]
@@ -287,4 +351,53 @@ mod tests {
             "test1=1&test2=2&sign=cc4f5844a6a1893a88d648cebba5462f"
         )
     }
+
+    /// Golden cases of the deterministic build mode, any change of the output
+    /// here breaks the signatures of downstream users.
+    const GOLDEN: &[(&[(&str, &str)], &str)] = &[
+        (&[], ""),
+        (&[("b", "1"), ("a", "2")], "a=2&b=1"),
+        (&[("a", "2"), ("b", "1"), ("a", "1")], "a=2&a=1&b=1"),
+        (&[("k", "a b+c/d?e=f&g")], "k=a%20b%2Bc%2Fd%3Fe%3Df%26g"),
+        (&[("k", "-._~*'你")], "k=-._~%2A%27%E4%BD%A0"),
+        (&[("k", "")], "k="),
+    ];
+
+    #[test]
+    fn test_build_sorted_stable_golden() {
+        for (pairs, expected) in GOLDEN {
+            let query = pairs
+                .iter()
+                .fold(Query::with_capacity(pairs.len()), |query, &(k, v)| {
+                    query.push(k, v)
+                });
+
+            assert_eq!(query.build_sorted_stable(), *expected, "{pairs:?}");
+        }
+
+        // Long enough for the unstable sort to reorder equal keys.
+        let values: Vec<String> = (0..64).map(|n| ((n * 37) % 64).to_string()).collect();
+        let query = values
+            .iter()
+            .fold(Query::with_capacity(64), |query, v| {
+                query.push("k", v.as_str())
+            })
+            .push("a", "0");
+        let expected = core::iter::once("a=0".to_owned())
+            .chain(values.iter().map(|v| format!("k={v}")))
+            .collect::<Vec<_>>()
+            .join("&");
+        assert_eq!(query.build_sorted_stable(), expected);
+
+        let signed = Query::with_capacity(4)
+            .push("b", "你 x")
+            .push("a", "2")
+            .push("a", "1")
+            .build_signed(Md5Signer::new_default().with_suffix_salt(Some("salt")))
+            .unwrap();
+        assert_eq!(
+            signed,
+            "a=2&a=1&b=%E4%BD%A0%20x&sign=f70d185cfa443126f7ea04d5f2c90111"
+        );
+    }
 }
@@ -0,0 +1,208 @@
+//! Ready-made signing configurations for common patterns, see [`Preset`].
+
+use std::{
+    borrow::Cow,
+    convert::Infallible,
+    sync::atomic::{AtomicU64, Ordering},
+    time::UNIX_EPOCH,
+};
+
+use sha2::{Digest, Sha256};
+
+use super::{Md5Signer, Query, SignerT};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Common query signing patterns, see [`signer`](Self::signer).
+pub enum Preset {
+    /// MD5 (hex) of the sorted query string with the secret as the suffix
+    /// salt, appended as `sign`, i.e. [`Md5Signer`].
+    SortedMd5WithSalt,
+
+    /// HMAC-SHA256 (hex) of the sorted query string with the secret, appended
+    /// as `signature`, after adding `timestamp` (Unix timestamp in seconds)
+    /// and `nonce`.
+    HmacSha256Hex,
+
+    /// Like [`HmacSha256Hex`](Self::HmacSha256Hex), but also adding
+    /// `body_hash`, the SHA-256 (hex) of the request body, so the body is
+    /// covered by the signature.
+    BodyHashInQuery,
+}
+
+impl Preset {
+    #[inline]
+    /// Create the [`PresetSigner`] with the secret.
+    pub const fn signer(self, secret: &str) -> PresetSigner<'_> {
+        PresetSigner {
+            preset: self,
+            secret,
+            timestamp: None,
+            nonce: None,
+            body: b"",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Signer of the [`Preset`].
+pub struct PresetSigner<'s> {
+    preset: Preset,
+    secret: &'s str,
+    timestamp: Option<u64>,
+    nonce: Option<&'s str>,
+    body: &'s [u8],
+}
+
+impl<'s> PresetSigner<'s> {
+    #[inline]
+    /// Set the timestamp, the current time by default.
+    pub const fn with_timestamp(self, timestamp: Option<u64>) -> Self {
+        Self { timestamp, ..self }
+    }
+
+    #[inline]
+    /// Set the nonce, a unique one is generated by default.
+    pub const fn with_nonce(self, nonce: Option<&'s str>) -> Self {
+        Self { nonce, ..self }
+    }
+
+    #[inline]
+    /// Set the request body, for [`Preset::BodyHashInQuery`].
+    pub const fn with_body(self, body: &'s [u8]) -> Self {
+        Self { body, ..self }
+    }
+
+    /// Sign the query with HMAC-SHA256, after adding `timestamp` and `nonce`.
+    fn build_hmac_signed(self, query: Query<'_>) -> String {
+        let timestamp = self.timestamp.unwrap_or_else(|| {
+            crate::time::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs()
+        });
+        let nonce = self
+            .nonce
+            .map_or_else(|| Cow::Owned(nonce()), Cow::Borrowed);
+
+        let query = query
+            .push("timestamp", timestamp.to_string())
+            .push("nonce", nonce)
+            .build_sorted_stable();
+
+        let signature = hex(&hmac_sha256(self.secret.as_bytes(), query.as_bytes()));
+
+        format!("{query}&signature={signature}")
+    }
+}
+
+impl SignerT for PresetSigner<'_> {
+    type Error = Infallible;
+
+    fn build_signed(self, query: Query) -> Result<String, Self::Error> {
+        match self.preset {
+            Preset::SortedMd5WithSalt => Md5Signer::new_default()
+                .with_suffix_salt(Some(self.secret))
+                .build_signed(query),
+            Preset::HmacSha256Hex => Ok(self.build_hmac_signed(query)),
+            Preset::BodyHashInQuery => {
+                let body_hash = hex(&Sha256::digest(self.body));
+
+                Ok(self.build_hmac_signed(query.push("body_hash", body_hash)))
+            }
+        }
+    }
+}
+
+/// Returns a unique (but not random) nonce: the current time in nanoseconds
+/// and a process-wide counter.
+fn nonce() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let nanos = crate::time::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+
+    format!(
+        "{nanos:x}{:04x}",
+        COUNTER.fetch_add(1, Ordering::Relaxed) & 0xffff
+    )
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// HMAC-SHA256 (RFC 2104).
+fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    const BLOCK_SIZE: usize = 64;
+
+    let mut block = [0; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Sha256::new();
+    inner.update(block.map(|b| b ^ 0x36));
+    inner.update(data);
+
+    let mut outer = Sha256::new();
+    outer.update(block.map(|b| b ^ 0x5c));
+    outer.update(inner.finalize());
+
+    outer.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hmac_sha256() {
+        // RFC 4231, test case 2.
+        assert_eq!(
+            hex(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_presets() {
+        let query = || Query::with_capacity(4).push("b", "2").push("a", "1");
+
+        assert_eq!(
+            query()
+                .build_signed(Preset::SortedMd5WithSalt.signer("secret"))
+                .unwrap(),
+            query()
+                .build_signed(Md5Signer::new_default().with_suffix_salt(Some("secret")))
+                .unwrap()
+        );
+
+        let signer = Preset::HmacSha256Hex
+            .signer("secret")
+            .with_timestamp(Some(1_700_000_000))
+            .with_nonce(Some("abc"));
+        let signed = query().build_signed(signer).unwrap();
+        let (unsigned, signature) = signed.rsplit_once("&signature=").unwrap();
+        assert_eq!(unsigned, "a=1&b=2&nonce=abc&timestamp=1700000000");
+        assert_eq!(signature, hex(&hmac_sha256(b"secret", unsigned.as_bytes())));
+
+        let signed = Query::with_capacity(4)
+            .build_signed(
+                Preset::BodyHashInQuery
+                    .signer("secret")
+                    .with_timestamp(Some(1_700_000_000))
+                    .with_nonce(Some("abc"))
+                    .with_body(b"{}"),
+            )
+            .unwrap();
+        assert!(signed.starts_with(
+            "body_hash=44136fa355b3678a1146ad16f7e8649e94fb4fc21fe77e8310c060f61caaff8a&nonce=abc&"
+        ));
+
+        assert_ne!(nonce(), nonce());
+    }
+}
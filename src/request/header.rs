@@ -2,9 +2,8 @@
 
 use std::convert::Infallible;
 
-use anyhow::{anyhow, Result};
 use http::{
-    header::{AsHeaderName, InvalidHeaderValue},
+    header::{self, AsHeaderName, InvalidHeaderValue},
     HeaderMap, HeaderName, HeaderValue,
 };
 use macro_toolset::{
@@ -13,6 +12,34 @@ use macro_toolset::{
     wrapper,
 };
 
+use crate::error::Result;
+
+#[cfg(feature = "feat-request-header-ext-template")]
+pub mod template;
+
+#[cfg(feature = "feat-request-header-ext-template")]
+// re-export
+pub use self::template::{HeaderTemplate, HeaderTemplateError, PlaceholderKind};
+
+#[derive(Debug)]
+#[derive(thiserror::Error)]
+/// Errors when decoding binary header values.
+pub enum HeaderError {
+    #[error("invalid base64 string `{value}`: {source}")]
+    /// Invalid Base64 string.
+    Base64 {
+        /// The header value
+        value: String,
+
+        /// The source error
+        source: macro_toolset::base64::DecodeError,
+    },
+
+    #[error(transparent)]
+    /// Failed to decode the protobuf message.
+    Decode(#[from] prost::DecodeError),
+}
+
 /// Trait helper for managing HTTP header keys.
 pub trait HeaderKeyT {
     /// `as_str_ext` and most times should be &'static
@@ -100,6 +127,54 @@ impl<T: HeaderKeyT> HeaderKeyT for BinaryKeyWrapper<T> {
 
 impl<T: HeaderKeyT> HeaderBinaryKeyT for BinaryKeyWrapper<T> {}
 
+/// Trait for typed header values, like [`CacheControl`].
+///
+/// With feature `feat-integrate-headers`, all [`headers::Header`]s implement
+/// this trait too.
+///
+/// [`CacheControl`]: crate::response::CacheControl
+pub trait TypedHeaderT: Sized {
+    /// The header name.
+    fn name() -> HeaderName;
+
+    /// Parse from the header value, `None` if invalid.
+    fn parse(value: &HeaderValue) -> Option<Self>;
+
+    /// Encode as the header value.
+    fn encode(&self) -> HeaderValue;
+}
+
+#[cfg(feature = "feat-integrate-headers")]
+impl<T: headers::Header> TypedHeaderT for T {
+    #[inline]
+    fn name() -> HeaderName {
+        <T as headers::Header>::name().clone()
+    }
+
+    #[inline]
+    fn parse(value: &HeaderValue) -> Option<Self> {
+        T::decode(&mut std::iter::once(value)).ok()
+    }
+
+    fn encode(&self) -> HeaderValue {
+        let mut values = Vec::with_capacity(1);
+        headers::Header::encode(self, &mut values);
+
+        if values.len() == 1 {
+            return values.pop().expect("must have one value");
+        }
+
+        // Combine multiple values into one, see RFC 9110, section 5.3.
+        let combined = values
+            .iter()
+            .map(HeaderValue::as_bytes)
+            .collect::<Vec<_>>()
+            .join(&b", "[..]);
+
+        HeaderValue::from_bytes(&combined).expect("combined values must be valid")
+    }
+}
+
 /// Trait for extending [`http::HeaderMap`]'s methods.
 ///
 /// If `T` implements this trait, `&mut T` will also implement this trait.
@@ -142,14 +217,17 @@ pub trait HeaderMapExtT {
     ///
     /// # Errors
     ///
-    /// - Invalid Base64 string.
+    /// - [`HeaderError::Base64`], invalid Base64 string.
     fn get_bin<K>(&self, key: K) -> Result<Option<Vec<u8>>>
     where
         K: HeaderBinaryKeyT,
     {
         if let Some(b64_str) = self.get_maybe_ascii(key) {
-            let decoded_bytes = b64_decode!(STANDARD_NO_PAD: b64_str)
-                .map_err(|e| anyhow!(e).context(b64_str.to_string()))?;
+            let decoded_bytes =
+                b64_decode!(STANDARD_NO_PAD: b64_str).map_err(|source| HeaderError::Base64 {
+                    value: b64_str.to_owned(),
+                    source,
+                })?;
             Ok(Some(decoded_bytes))
         } else {
             Ok(None)
@@ -162,13 +240,18 @@ pub trait HeaderMapExtT {
     ///
     /// # Errors
     ///
-    /// - Invalid Base64 string.
+    /// - [`HeaderError::Base64`], invalid Base64 string.
     fn get_bin_to_buffer<K>(&self, key: K, buffer: &mut Vec<u8>) -> Result<()>
     where
         K: HeaderBinaryKeyT,
     {
         if let Some(b64_str) = self.get_maybe_ascii(key) {
-            b64_decode!(STANDARD_NO_PAD: b64_str, buffer)?;
+            b64_decode!(STANDARD_NO_PAD: b64_str, buffer).map_err(|source| {
+                HeaderError::Base64 {
+                    value: b64_str.to_owned(),
+                    source,
+                }
+            })?;
         }
 
         Ok(())
@@ -180,15 +263,15 @@ pub trait HeaderMapExtT {
     ///
     /// # Errors
     ///
-    /// - [`prost::DecodeError`].
-    /// - Invalid Base64 string.
+    /// - [`HeaderError::Decode`], see [`prost::DecodeError`].
+    /// - [`HeaderError::Base64`], invalid Base64 string.
     fn get_bin_struct<K, T>(&self, key: K) -> Result<Option<T>>
     where
         K: HeaderBinaryKeyT,
         T: prost::Message + Default,
     {
         if let Some(bin) = self.get_bin(key)? {
-            Ok(Some(T::decode(bin.as_slice())?))
+            Ok(Some(T::decode(bin.as_slice()).map_err(HeaderError::from)?))
         } else {
             Ok(None)
         }
@@ -200,15 +283,15 @@ pub trait HeaderMapExtT {
     ///
     /// # Errors
     ///
-    /// - [`prost::DecodeError`].
-    /// - Invalid Base64 string.
+    /// - [`HeaderError::Decode`], see [`prost::DecodeError`].
+    /// - [`HeaderError::Base64`], invalid Base64 string.
     fn get_bin_struct_or_default<K, T>(&self, key: K) -> Result<T>
     where
         K: HeaderBinaryKeyT,
         T: prost::Message + Default,
     {
         if let Some(bin) = self.get_bin(key)? {
-            Ok(T::decode(bin.as_slice())?)
+            Ok(T::decode(bin.as_slice()).map_err(HeaderError::from)?)
         } else {
             Ok(T::default())
         }
@@ -417,6 +500,114 @@ pub trait HeaderMapExtT {
         self
     }
 
+    #[inline]
+    /// Returns the typed header value, `None` if missing or invalid.
+    fn get_typed<T: TypedHeaderT>(&self) -> Option<T> {
+        T::parse(self.get_exact(T::name())?)
+    }
+
+    #[inline]
+    /// Insert the typed header value, replacing the existing one.
+    fn insert_typed<T: TypedHeaderT>(&mut self, value: &T) -> &mut Self {
+        self.insert_exact(T::name(), value.encode())
+    }
+
+    /// Whether `TE` contains `trailers`, i.e. the client is willing to accept
+    /// trailer fields (required by gRPC).
+    fn accepts_trailers(&self) -> bool {
+        self.get_exact(header::TE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| {
+                value.split(',').any(|coding| {
+                    coding
+                        .split(';')
+                        .next()
+                        .unwrap_or_default()
+                        .trim()
+                        .eq_ignore_ascii_case("trailers")
+                })
+            })
+    }
+
+    /// Add `trailers` to `TE`, keeping the existing transfer codings.
+    ///
+    /// It's a no-op if `trailers` is already there.
+    fn insert_te_trailers(&mut self) -> &mut Self {
+        if self.accepts_trailers() {
+            return self;
+        }
+
+        let value = match self.get_exact(header::TE) {
+            Some(te) if !te.is_empty() => {
+                let mut value = te.as_bytes().to_vec();
+                value.extend_from_slice(b", trailers");
+
+                HeaderValue::from_bytes(&value)
+                    .unwrap_or_else(|_| HeaderValue::from_static("trailers"))
+            }
+            _ => HeaderValue::from_static("trailers"),
+        };
+
+        self.insert_exact(header::TE, value)
+    }
+
+    /// Declare the trailer fields to be sent, i.e. set `Trailer` to the
+    /// comma-separated names.
+    ///
+    /// It's a no-op if `names` is empty.
+    ///
+    /// # Panics
+    ///
+    /// Panic if the name is not a valid header name, see
+    /// [`HeaderKeyT::to_header_name`].
+    fn insert_trailer_names<I, K>(&mut self, names: I) -> &mut Self
+    where
+        I: IntoIterator<Item = K>,
+        K: HeaderKeyT,
+    {
+        let value = names.into_iter().map(|name| name.to_header_name()).fold(
+            String::new(),
+            |mut value, name| {
+                if !value.is_empty() {
+                    value.push_str(", ");
+                }
+                value.push_str(name.as_str());
+                value
+            },
+        );
+
+        if value.is_empty() {
+            return self;
+        }
+
+        self.insert_exact(
+            header::TRAILER,
+            HeaderValue::try_from(value).expect("Header names should be valid header value"),
+        )
+    }
+
+    /// Returns the trailer fields declared in `Trailer`, invalid names are
+    /// ignored.
+    fn trailer_names(&self) -> Vec<HeaderName> {
+        self.get_exact(header::TRAILER)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| {
+                value
+                    .split(',')
+                    .filter_map(|name| HeaderName::from_bytes(name.trim().as_bytes()).ok())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    #[cfg(feature = "feat-response")]
+    #[inline]
+    /// Parse [`RateLimitInfo`](crate::response::RateLimitInfo) from the
+    /// `RateLimit-*` or `X-RateLimit-*` headers.
+    fn rate_limit_info(&self) -> Option<crate::response::RateLimitInfo> {
+        crate::response::RateLimitInfo::from_fn(|key| self.get_exact(key), crate::time::now())
+    }
+
     /// Check if key exist, just a bridge to [`HeaderMap`] or any else
     fn contains_headerkey(&self, key: impl HeaderKeyT) -> bool;
 
@@ -478,3 +669,201 @@ impl HeaderMapExtT for HeaderMap {
         self
     }
 }
+
+impl HeaderMapExtT for http::request::Parts {
+    #[inline]
+    fn contains_headerkey(&self, key: impl HeaderKeyT) -> bool {
+        self.headers.contains_headerkey(key)
+    }
+
+    #[inline]
+    fn get_exact<K>(&self, key: K) -> Option<&HeaderValue>
+    where
+        K: AsHeaderName,
+    {
+        self.headers.get_exact(key)
+    }
+
+    #[inline]
+    fn insert_exact(&mut self, key: HeaderName, value: HeaderValue) -> &mut Self {
+        self.headers.insert_exact(key, value);
+        self
+    }
+}
+
+impl HeaderMapExtT for http::response::Parts {
+    #[inline]
+    fn contains_headerkey(&self, key: impl HeaderKeyT) -> bool {
+        self.headers.contains_headerkey(key)
+    }
+
+    #[inline]
+    fn get_exact<K>(&self, key: K) -> Option<&HeaderValue>
+    where
+        K: AsHeaderName,
+    {
+        self.headers.get_exact(key)
+    }
+
+    #[inline]
+    fn insert_exact(&mut self, key: HeaderName, value: HeaderValue) -> &mut Self {
+        self.headers.insert_exact(key, value);
+        self
+    }
+}
+
+#[cfg(feature = "feat-request-header-ext-trailers")]
+/// Extension trait for the trailers [`Frame`](http_body::Frame).
+///
+/// The trailer map can be read or written with [`HeaderMapExtT`] helpers via
+/// [`Frame::trailers_ref`](http_body::Frame::trailers_ref) /
+/// [`Frame::trailers_mut`](http_body::Frame::trailers_mut).
+pub trait TrailersFrameExtT: Sized {
+    /// Create a trailers frame, with the trailer map filled by `f`.
+    fn trailers_with<F>(f: F) -> Self
+    where
+        F: FnOnce(&mut HeaderMap);
+
+    /// Remove the trailer fields not in `declared`, see
+    /// [`HeaderMapExtT::trailer_names`].
+    ///
+    /// It's a no-op for data frames.
+    fn retain_declared(&mut self, declared: &[HeaderName]) -> &mut Self;
+}
+
+#[cfg(feature = "feat-request-header-ext-trailers")]
+impl<D> TrailersFrameExtT for http_body::Frame<D> {
+    fn trailers_with<F>(f: F) -> Self
+    where
+        F: FnOnce(&mut HeaderMap),
+    {
+        let mut trailers = HeaderMap::new();
+        f(&mut trailers);
+
+        Self::trailers(trailers)
+    }
+
+    fn retain_declared(&mut self, declared: &[HeaderName]) -> &mut Self {
+        if let Some(trailers) = self.trailers_mut() {
+            let undeclared: Vec<_> = trailers
+                .keys()
+                .filter(|name| !declared.contains(name))
+                .cloned()
+                .collect();
+
+            for name in undeclared {
+                trailers.remove(name);
+            }
+        }
+
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "feat-response")]
+    #[test]
+    fn test_typed() {
+        use std::time::Duration;
+
+        use crate::response::CacheControl;
+
+        let mut headers = HeaderMap::new();
+        headers.insert_typed(&CacheControl {
+            no_cache: true,
+            max_age: Some(Duration::from_secs(60)),
+            ..CacheControl::default()
+        });
+
+        assert_eq!(headers["cache-control"], "no-cache, max-age=60");
+        assert_eq!(
+            headers.get_typed::<CacheControl>().unwrap().max_age,
+            Some(Duration::from_secs(60))
+        );
+    }
+
+    #[test]
+    fn test_parts() {
+        let (mut parts, ()) = http::Request::new(()).into_parts();
+        parts.insert_ascii_static("x-test", "1");
+        assert_eq!(parts.get_ascii("x-test"), Some("1"));
+        assert_eq!(parts.headers["x-test"], "1");
+
+        let (mut parts, ()) = http::Response::new(()).into_parts();
+        assert!(!parts.contains_headerkey("x-test"));
+        parts.insert_ascii("x-test", "2").unwrap();
+        assert_eq!(parts.get_ascii("x-test"), Some("2"));
+    }
+
+    #[cfg(feature = "feat-integrate-headers")]
+    #[test]
+    fn test_typed_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert_typed(&headers::ContentLength(42));
+
+        assert_eq!(headers["content-length"], "42");
+        assert_eq!(
+            headers.get_typed::<headers::ContentLength>(),
+            Some(headers::ContentLength(42))
+        );
+    }
+
+    #[test]
+    fn test_trailers() {
+        let mut headers = HeaderMap::new();
+        assert!(!headers.accepts_trailers());
+
+        headers.insert_ascii_static("te", "gzip; q=0.5");
+        headers.insert_te_trailers().insert_te_trailers();
+        assert_eq!(headers["te"], "gzip; q=0.5, trailers");
+        assert!(headers.accepts_trailers());
+
+        headers.insert_trailer_names(["grpc-status", "grpc-message"]);
+        assert_eq!(headers["trailer"], "grpc-status, grpc-message");
+        assert_eq!(
+            headers.trailer_names(),
+            [
+                HeaderName::from_static("grpc-status"),
+                HeaderName::from_static("grpc-message")
+            ]
+        );
+    }
+
+    #[cfg(feature = "feat-request-header-ext-trailers")]
+    #[test]
+    fn test_trailers_frame() {
+        use http_body::Frame;
+
+        let mut frame = Frame::<()>::trailers_with(|trailers| {
+            trailers
+                .insert_ascii_static("grpc-status", "0")
+                .insert_bin_byte(
+                    BinaryKeyWrapper {
+                        inner: "x-extra-bin",
+                    },
+                    b"\x00\x01",
+                )
+                .insert_ascii_static("x-undeclared", "1");
+        });
+
+        frame.retain_declared(&[
+            HeaderName::from_static("grpc-status"),
+            HeaderName::from_static("x-extra-bin"),
+        ]);
+
+        let trailers = frame.trailers_ref().unwrap();
+        assert_eq!(trailers.get_ascii("grpc-status"), Some("0"));
+        assert_eq!(
+            trailers
+                .get_bin(BinaryKeyWrapper {
+                    inner: "x-extra-bin"
+                })
+                .unwrap(),
+            Some(vec![0, 1])
+        );
+        assert!(!trailers.contains_key("x-undeclared"));
+    }
+}
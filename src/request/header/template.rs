@@ -0,0 +1,374 @@
+//! HTTP request utilities: header value templates with placeholders, see
+//! [`HeaderTemplate`].
+
+use std::{fmt, str::FromStr};
+
+use http::{header::InvalidHeaderValue, HeaderValue};
+
+#[derive(Debug)]
+#[derive(thiserror::Error)]
+/// Error compiling or rendering the [`HeaderTemplate`].
+pub enum HeaderTemplateError {
+    #[error("unclosed placeholder at {0}")]
+    /// `{` without the matching `}`, at the byte offset.
+    Unclosed(usize),
+
+    #[error("unmatched `}}` at {0}, use `}}}}` for a literal one")]
+    /// `}` without the matching `{`, at the byte offset.
+    Unmatched(usize),
+
+    #[error("invalid placeholder name `{0}`")]
+    /// The placeholder name is empty, or contains characters other than
+    /// ASCII alphanumerics, `_`, `-` and `.`.
+    InvalidName(String),
+
+    #[error("unknown placeholder type `{0}`, expected `int` or `token`")]
+    /// The placeholder type is unknown.
+    UnknownKind(String),
+
+    #[error("missing value of placeholder `{0}`")]
+    /// No value of the placeholder is given when rendering.
+    Missing(String),
+
+    #[error("invalid value of placeholder `{name}`, expected {}", .kind.as_str())]
+    /// The value does not match the placeholder type.
+    InvalidValue {
+        /// The placeholder name
+        name: String,
+
+        /// The placeholder type
+        kind: PlaceholderKind,
+    },
+
+    #[error(transparent)]
+    /// The literal text or the rendered value is not a valid header value.
+    InvalidHeaderValue(#[from] InvalidHeaderValue),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Type of the placeholder, like `{id:int}`.
+pub enum PlaceholderKind {
+    /// Any text valid in header values, `{name}`.
+    Text,
+
+    /// Unsigned decimal integer, `{name:int}`.
+    Int,
+
+    /// Token (RFC 9110, section 5.6.2), `{name:token}`.
+    Token,
+}
+
+impl PlaceholderKind {
+    #[inline]
+    /// Returns the type name, `text`, `int` or `token`.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Int => "int",
+            Self::Token => "token",
+        }
+    }
+
+    /// Whether the value matches the type. For [`PlaceholderKind::Text`],
+    /// it's validated with the rendered header value.
+    pub fn matches(&self, value: &str) -> bool {
+        match self {
+            Self::Text => true,
+            Self::Int => !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()),
+            Self::Token => {
+                !value.is_empty()
+                    && value
+                        .bytes()
+                        .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Placeholder(String, PlaceholderKind),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Header value template, compiled from strings like `Bearer {token}` or
+/// `{region}.api.example.com`, and rendered with the placeholder values at
+/// request time, e.g. for config-driven header injection.
+///
+/// Placeholders may be typed, like `{id:int}` or `{name:token}`, see
+/// [`PlaceholderKind`]. Use `{{` and `}}` for literal braces.
+///
+/// ```rust
+/// # use miku_http_util::request::header::HeaderTemplate;
+/// let template: HeaderTemplate = "Bearer {token}".parse().unwrap();
+///
+/// let value = template.render(&[("token", "abc")]).unwrap();
+/// assert_eq!(value, "Bearer abc");
+///
+/// // Header injection is rejected.
+/// assert!(template.render(&[("token", "abc\r\nx-admin: 1")]).is_err());
+/// ```
+pub struct HeaderTemplate {
+    segments: Vec<Segment>,
+    sensitive: bool,
+}
+
+impl HeaderTemplate {
+    /// Compile the template.
+    ///
+    /// # Errors
+    ///
+    /// [`HeaderTemplateError`] if the template is malformed, or the literal
+    /// text is not valid in header values.
+    pub fn parse(template: &str) -> Result<Self, HeaderTemplateError> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+
+        let flush = |literal: &mut String, segments: &mut Vec<Segment>| {
+            if literal.is_empty() {
+                return Ok(());
+            }
+
+            HeaderValue::from_str(literal)?;
+            segments.push(Segment::Literal(std::mem::take(literal)));
+
+            Ok::<_, HeaderTemplateError>(())
+        };
+
+        let mut chars = template.char_indices().peekable();
+
+        while let Some((idx, c)) = chars.next() {
+            match c {
+                '{' if chars.next_if(|&(_, c)| c == '{').is_some() => literal.push('{'),
+                '}' if chars.next_if(|&(_, c)| c == '}').is_some() => literal.push('}'),
+                '{' => {
+                    let len = template[idx + 1..]
+                        .find('}')
+                        .ok_or(HeaderTemplateError::Unclosed(idx))?;
+                    let end = idx + 1 + len;
+
+                    while chars.next_if(|&(i, _)| i <= end).is_some() {}
+
+                    let (name, kind) = match template[idx + 1..end].split_once(':') {
+                        Some((name, kind)) => (name.trim(), Some(kind.trim())),
+                        None => (template[idx + 1..end].trim(), None),
+                    };
+
+                    let valid_name = !name.is_empty()
+                        && name
+                            .bytes()
+                            .all(|b| b.is_ascii_alphanumeric() || b"_-.".contains(&b));
+
+                    if !valid_name {
+                        return Err(HeaderTemplateError::InvalidName(name.to_owned()));
+                    }
+
+                    let kind = match kind {
+                        None => PlaceholderKind::Text,
+                        Some("int") => PlaceholderKind::Int,
+                        Some("token") => PlaceholderKind::Token,
+                        Some(kind) => {
+                            return Err(HeaderTemplateError::UnknownKind(kind.to_owned()))
+                        }
+                    };
+
+                    flush(&mut literal, &mut segments)?;
+                    segments.push(Segment::Placeholder(name.to_owned(), kind));
+                }
+                '}' => return Err(HeaderTemplateError::Unmatched(idx)),
+                c => literal.push(c),
+            }
+        }
+
+        flush(&mut literal, &mut segments)?;
+
+        Ok(Self {
+            segments,
+            sensitive: false,
+        })
+    }
+
+    #[inline]
+    #[must_use]
+    /// Mark the rendered header values as sensitive, e.g. for `Authorization`,
+    /// see [`HeaderValue::set_sensitive`].
+    pub fn with_sensitive(self, sensitive: bool) -> Self {
+        Self { sensitive, ..self }
+    }
+
+    /// Returns the placeholders and their types, in order.
+    pub fn placeholders(&self) -> impl Iterator<Item = (&str, PlaceholderKind)> {
+        self.segments.iter().filter_map(|segment| match segment {
+            Segment::Placeholder(name, kind) => Some((name.as_str(), *kind)),
+            Segment::Literal(_) => None,
+        })
+    }
+
+    #[inline]
+    /// Render the header value with the placeholder values, see
+    /// [`render_with`](Self::render_with).
+    ///
+    /// # Errors
+    ///
+    /// See [`render_with`](Self::render_with).
+    pub fn render(&self, values: &[(&str, &str)]) -> Result<HeaderValue, HeaderTemplateError> {
+        self.render_with(|name| {
+            values
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|&(_, value)| value)
+        })
+    }
+
+    /// Render the header value, looking up the placeholder values by name.
+    ///
+    /// # Errors
+    ///
+    /// - [`HeaderTemplateError::Missing`] if any placeholder value is missing
+    /// - [`HeaderTemplateError::InvalidValue`] if any value does not match the
+    ///   placeholder type
+    /// - [`HeaderTemplateError::InvalidHeaderValue`] if the rendered value is
+    ///   not a valid header value, e.g. with CR / LF
+    pub fn render_with<F, V>(&self, mut values: F) -> Result<HeaderValue, HeaderTemplateError>
+    where
+        F: FnMut(&str) -> Option<V>,
+        V: AsRef<str>,
+    {
+        let mut rendered = String::new();
+
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(literal) => rendered.push_str(literal),
+                Segment::Placeholder(name, kind) => {
+                    let value =
+                        values(name).ok_or_else(|| HeaderTemplateError::Missing(name.clone()))?;
+                    let value = value.as_ref();
+
+                    if !kind.matches(value) {
+                        return Err(HeaderTemplateError::InvalidValue {
+                            name: name.clone(),
+                            kind: *kind,
+                        });
+                    }
+
+                    rendered.push_str(value);
+                }
+            }
+        }
+
+        let mut value = HeaderValue::try_from(rendered)?;
+        value.set_sensitive(self.sensitive);
+
+        Ok(value)
+    }
+}
+
+impl FromStr for HeaderTemplate {
+    type Err = HeaderTemplateError;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl fmt::Display for HeaderTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(literal) => {
+                    f.write_str(&literal.replace('{', "{{").replace('}', "}}"))?;
+                }
+                Segment::Placeholder(name, PlaceholderKind::Text) => write!(f, "{{{name}}}")?,
+                Segment::Placeholder(name, kind) => write!(f, "{{{name}:{}}}", kind.as_str())?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let template = HeaderTemplate::parse("{region}.api.example.com:{port:int}")
+            .unwrap()
+            .with_sensitive(true);
+
+        assert_eq!(
+            template.placeholders().collect::<Vec<_>>(),
+            [
+                ("region", PlaceholderKind::Text),
+                ("port", PlaceholderKind::Int)
+            ]
+        );
+
+        let value = template
+            .render(&[("region", "eu-west-1"), ("port", "8443")])
+            .unwrap();
+        assert_eq!(value, "eu-west-1.api.example.com:8443");
+        assert!(value.is_sensitive());
+
+        assert!(matches!(
+            template.render(&[("region", "eu")]).unwrap_err(),
+            HeaderTemplateError::Missing(name) if name == "port"
+        ));
+        assert!(matches!(
+            template
+                .render(&[("region", "eu"), ("port", "-1")])
+                .unwrap_err(),
+            HeaderTemplateError::InvalidValue {
+                kind: PlaceholderKind::Int,
+                ..
+            }
+        ));
+        assert!(matches!(
+            template
+                .render(&[("region", "eu\n"), ("port", "1")])
+                .unwrap_err(),
+            HeaderTemplateError::InvalidHeaderValue(_)
+        ));
+    }
+
+    #[test]
+    fn test_parse() {
+        let template = HeaderTemplate::parse("{{literal}} {name:token}").unwrap();
+        assert_eq!(template.to_string(), "{{literal}} {name:token}");
+        assert_eq!(
+            template.render(&[("name", "gzip")]).unwrap(),
+            "{literal} gzip"
+        );
+        assert!(matches!(
+            template.render(&[("name", "a b")]).unwrap_err(),
+            HeaderTemplateError::InvalidValue {
+                kind: PlaceholderKind::Token,
+                ..
+            }
+        ));
+
+        assert!(matches!(
+            HeaderTemplate::parse("Bearer {token").unwrap_err(),
+            HeaderTemplateError::Unclosed(7)
+        ));
+        assert!(matches!(
+            HeaderTemplate::parse("a}b").unwrap_err(),
+            HeaderTemplateError::Unmatched(1)
+        ));
+        assert!(matches!(
+            HeaderTemplate::parse("{}").unwrap_err(),
+            HeaderTemplateError::InvalidName(_)
+        ));
+        assert!(matches!(
+            HeaderTemplate::parse("{id:uuid}").unwrap_err(),
+            HeaderTemplateError::UnknownKind(kind) if kind == "uuid"
+        ));
+        assert!(matches!(
+            HeaderTemplate::parse("a\nb").unwrap_err(),
+            HeaderTemplateError::InvalidHeaderValue(_)
+        ));
+    }
+}
@@ -1,4 +1,18 @@
 //! Request related miscellaneous items.
 
+#[cfg(feature = "feat-request-misc-curl")]
+pub mod curl;
+#[cfg(feature = "feat-request-misc-expect")]
+pub mod expect;
+#[cfg(feature = "feat-request-misc-host")]
+pub mod host;
+#[cfg(feature = "feat-request-misc-prefer")]
+pub mod prefer;
+#[cfg(feature = "feat-request-misc-profile")]
+pub mod profile;
 #[cfg(feature = "feat-request-misc-proxy")]
 pub mod proxy;
+#[cfg(feature = "feat-request-misc-replay")]
+pub mod replay;
+#[cfg(feature = "feat-request-misc-target")]
+pub mod target;
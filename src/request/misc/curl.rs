@@ -0,0 +1,564 @@
+//! Importing requests from curl command lines, see [`RequestExt::from_curl`].
+
+use bytes::Bytes;
+use http::{
+    header::{AUTHORIZATION, CONTENT_TYPE, COOKIE, REFERER, USER_AGENT},
+    HeaderName, Method, Request,
+};
+
+use super::proxy::{basic_auth, ProxyScheme};
+
+#[derive(Debug)]
+#[derive(thiserror::Error)]
+/// Errors when importing curl command lines.
+pub enum CurlError {
+    #[error("not a curl command")]
+    /// The command does not start with `curl`.
+    NotCurl,
+
+    #[error("unterminated quote")]
+    /// Unterminated quote in the command line.
+    UnterminatedQuote,
+
+    #[error("missing value of option `{0}`")]
+    /// The option requires a value.
+    MissingValue(String),
+
+    #[error("unsupported option `{0}`")]
+    /// The option is not supported, e.g. reading data from file.
+    Unsupported(String),
+
+    #[error("missing URL")]
+    /// No URL is given.
+    MissingUrl,
+
+    #[error("invalid header `{0}`")]
+    /// The header is not like `Name: value`.
+    InvalidHeader(String),
+
+    #[error(transparent)]
+    /// Invalid method, URI or header.
+    Http(#[from] http::Error),
+
+    #[error("invalid proxy: {0}")]
+    /// Invalid proxy, see [`ProxyScheme`].
+    Proxy(crate::Error),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// Parsed curl command line.
+pub struct CurlCommand {
+    /// The method set by `-X`, `-I` or `-G`.
+    pub method: Option<String>,
+
+    /// The URL, `http://` is prepended if no scheme is given like curl does.
+    pub url: String,
+
+    /// Headers in order, including those set by `-A`, `-e` and `-b`.
+    pub headers: Vec<(String, String)>,
+
+    /// Data set by `--data*` options, in order.
+    pub data: Vec<String>,
+
+    /// Whether `-G` is set, i.e. data is appended to the query.
+    pub get: bool,
+
+    /// `user:password` set by `-u`.
+    pub user: Option<String>,
+
+    /// Proxy set by `-x`.
+    pub proxy: Option<String>,
+}
+
+impl CurlCommand {
+    /// Parse the curl command line.
+    ///
+    /// Line continuations (`\` at the end of lines) and shell quoting are
+    /// supported, so are combined short options like `-sSL` or `-sXPOST`.
+    /// Options not affecting the request itself (e.g. `-s`, `-L`, `-o file`)
+    /// are ignored.
+    ///
+    /// # Errors
+    ///
+    /// See [`CurlError`]. Unknown options are rejected with
+    /// [`CurlError::Unsupported`], since whether they take a value is unknown.
+    pub fn parse(command: &str) -> Result<Self, CurlError> {
+        let args = split_args(command)?;
+        let mut args = args.into_iter();
+
+        if args.next().as_deref() != Some("curl") {
+            return Err(CurlError::NotCurl);
+        }
+
+        let mut parsed = Self::default();
+
+        while let Some(arg) = args.next() {
+            if arg.starts_with("--") {
+                parsed.option(&arg, None, &mut args)?;
+                continue;
+            }
+
+            let Some(flags) = arg.strip_prefix('-').filter(|flags| !flags.is_empty()) else {
+                parsed.url = arg;
+                continue;
+            };
+
+            // Combined short options like `-sSL`, the one taking a value
+            // consumes the rest, e.g. `-sXPOST`.
+            for (idx, flag) in flags.char_indices() {
+                let option = format!("-{flag}");
+
+                if takes_value(&option) || skips_value(&option) {
+                    let rest = &flags[idx + flag.len_utf8()..];
+
+                    parsed.option(
+                        &option,
+                        (!rest.is_empty()).then(|| rest.to_owned()),
+                        &mut args,
+                    )?;
+                    break;
+                }
+
+                parsed.option(&option, None, &mut args)?;
+            }
+        }
+
+        if parsed.url.is_empty() {
+            return Err(CurlError::MissingUrl);
+        }
+
+        if !parsed.url.contains("://") {
+            parsed.url.insert_str(0, "http://");
+        }
+
+        Ok(parsed)
+    }
+
+    /// Handle the option, with the attached value (like `-XPOST`) if any, or
+    /// the next argument as the value if it takes one.
+    fn option(
+        &mut self,
+        option: &str,
+        attached: Option<String>,
+        args: &mut impl Iterator<Item = String>,
+    ) -> Result<(), CurlError> {
+        if takes_value(option) || skips_value(option) {
+            let value = attached
+                .or_else(|| args.next())
+                .ok_or_else(|| CurlError::MissingValue(option.to_owned()))?;
+
+            if takes_value(option) {
+                self.apply(option, value)?;
+            }
+
+            return Ok(());
+        }
+
+        match option {
+            "-G" | "--get" => self.get = true,
+            "-I" | "--head" => self.method = Some("HEAD".to_owned()),
+            option if is_ignored_flag(option) => {}
+            option => return Err(CurlError::Unsupported(option.to_owned())),
+        }
+
+        Ok(())
+    }
+
+    /// Apply the option with value.
+    fn apply(&mut self, option: &str, value: String) -> Result<(), CurlError> {
+        match option {
+            "-X" | "--request" => self.method = Some(value),
+            "-H" | "--header" => {
+                let (name, value) = value
+                    .split_once(':')
+                    .ok_or_else(|| CurlError::InvalidHeader(value.clone()))?;
+                self.headers
+                    .push((name.trim().to_owned(), value.trim().to_owned()));
+            }
+            "-A" | "--user-agent" => self.headers.push((USER_AGENT.to_string(), value)),
+            "-e" | "--referer" => self.headers.push((REFERER.to_string(), value)),
+            "-b" | "--cookie" => self.headers.push((COOKIE.to_string(), value)),
+            "-d" | "--data" | "--data-ascii" | "--data-binary" => {
+                if value.starts_with('@') {
+                    return Err(CurlError::Unsupported(format!("{option} {value}")));
+                }
+                self.data.push(value);
+            }
+            "--data-raw" => self.data.push(value),
+            "--data-urlencode" => self.data.push(match value.split_once('=') {
+                Some((name, content)) => format!("{name}={}", urlencode(content)),
+                None => urlencode(&value),
+            }),
+            "--json" => {
+                self.headers
+                    .push((CONTENT_TYPE.to_string(), "application/json".to_owned()));
+                self.headers
+                    .push(("accept".to_owned(), "application/json".to_owned()));
+                self.data.push(value);
+            }
+            "-u" | "--user" => self.user = Some(value),
+            "-x" | "--proxy" => self.proxy = Some(value),
+            "--url" => self.url = value,
+            _ => return Err(CurlError::Unsupported(option.to_owned())),
+        }
+
+        Ok(())
+    }
+
+    /// Build the [`Request`].
+    ///
+    /// Like curl, the method defaults to `POST` when data is given (unless
+    /// `-G` is set), and `Content-Type` defaults to
+    /// `application/x-www-form-urlencoded`. The proxy, if any, is inserted
+    /// into the request extensions as [`ProxyScheme`].
+    ///
+    /// # Errors
+    ///
+    /// Invalid method, URL, header or proxy.
+    pub fn into_request(self) -> Result<Request<Bytes>, CurlError> {
+        let data = self.data.join("&");
+
+        let mut url = self.url;
+        let method = match self.method {
+            Some(method) => Method::from_bytes(method.as_bytes()).map_err(http::Error::from)?,
+            None if self.get => Method::GET,
+            None if !self.data.is_empty() => Method::POST,
+            None => Method::GET,
+        };
+
+        let body = if self.get {
+            if !data.is_empty() {
+                url.push(if url.contains('?') { '&' } else { '?' });
+                url.push_str(&data);
+            }
+            Bytes::new()
+        } else {
+            Bytes::from(data)
+        };
+
+        let mut request = Request::builder().method(method).uri(url);
+
+        let mut has_content_type = false;
+        for (name, value) in &self.headers {
+            let name = HeaderName::try_from(name.as_str()).map_err(http::Error::from)?;
+            has_content_type |= name == CONTENT_TYPE;
+            request = request.header(name, value);
+        }
+
+        if !has_content_type && !body.is_empty() {
+            request = request.header(CONTENT_TYPE, "application/x-www-form-urlencoded");
+        }
+
+        if let Some(user) = &self.user {
+            request = request.header(
+                AUTHORIZATION,
+                match user.split_once(':') {
+                    Some((user_name, password)) => basic_auth(user_name, Some(password)),
+                    None => basic_auth(user, None::<&str>),
+                },
+            );
+        }
+
+        if let Some(proxy) = &self.proxy {
+            let proxy = if proxy.contains("://") {
+                proxy.parse::<ProxyScheme>()
+            } else {
+                format!("http://{proxy}").parse::<ProxyScheme>()
+            }
+            .map_err(CurlError::Proxy)?;
+
+            request = request.extension(proxy);
+        }
+
+        Ok(request.body(body)?)
+    }
+}
+
+/// Extension trait for [`Request`].
+pub trait RequestExt: Sized {
+    /// Import the request from a curl command line, e.g. snippets in API
+    /// documents.
+    ///
+    /// See [`CurlCommand::parse`] and [`CurlCommand::into_request`].
+    ///
+    /// # Errors
+    ///
+    /// See [`CurlError`].
+    fn from_curl(command: &str) -> Result<Self, CurlError>;
+}
+
+impl RequestExt for Request<Bytes> {
+    fn from_curl(command: &str) -> Result<Self, CurlError> {
+        CurlCommand::parse(command)?.into_request()
+    }
+}
+
+/// Whether the option takes a value.
+fn takes_value(option: &str) -> bool {
+    matches!(
+        option,
+        "-X" | "--request"
+            | "-H"
+            | "--header"
+            | "-A"
+            | "--user-agent"
+            | "-e"
+            | "--referer"
+            | "-b"
+            | "--cookie"
+            | "-d"
+            | "--data"
+            | "--data-ascii"
+            | "--data-binary"
+            | "--data-raw"
+            | "--data-urlencode"
+            | "--json"
+            | "-u"
+            | "--user"
+            | "-x"
+            | "--proxy"
+            | "--url"
+    )
+}
+
+/// Whether the option takes a value but does not affect the request itself,
+/// so is ignored.
+fn skips_value(option: &str) -> bool {
+    matches!(
+        option,
+        "-o" | "--output"
+            | "-w"
+            | "--write-out"
+            | "-m"
+            | "--max-time"
+            | "--connect-timeout"
+            | "--retry"
+            | "--retry-delay"
+            | "--retry-max-time"
+            | "--max-redirs"
+            | "-c"
+            | "--cookie-jar"
+            | "-D"
+            | "--dump-header"
+            | "--limit-rate"
+            | "-y"
+            | "--speed-time"
+            | "-Y"
+            | "--speed-limit"
+            | "--cacert"
+            | "--capath"
+            | "--stderr"
+            | "--trace"
+            | "--trace-ascii"
+    )
+}
+
+/// Whether the option is a flag not affecting the request itself, so is
+/// ignored.
+fn is_ignored_flag(option: &str) -> bool {
+    matches!(
+        option,
+        "-s" | "--silent"
+            | "-S"
+            | "--show-error"
+            | "-L"
+            | "--location"
+            | "-k"
+            | "--insecure"
+            | "-v"
+            | "--verbose"
+            | "-i"
+            | "--include"
+            | "-f"
+            | "--fail"
+            | "--fail-with-body"
+            | "-#"
+            | "--progress-bar"
+            | "--no-progress-meter"
+            | "-N"
+            | "--no-buffer"
+            | "-g"
+            | "--globoff"
+            | "-O"
+            | "--remote-name"
+            | "-J"
+            | "--remote-header-name"
+            | "--compressed"
+            | "--http1.1"
+            | "--http2"
+            | "--http2-prior-knowledge"
+            | "--http3"
+            | "-4"
+            | "--ipv4"
+            | "-6"
+            | "--ipv6"
+    )
+}
+
+fn urlencode(value: &str) -> String {
+    crate::percent::encode(value, crate::percent::FORM).into_owned()
+}
+
+/// Split the command line into arguments like POSIX shells.
+fn split_args(command: &str) -> Result<Vec<String>, CurlError> {
+    let mut args = Vec::new();
+    let mut current: Option<String> = None;
+    let mut chars = command.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                // line continuation
+                Some('\n') => {}
+                Some('\r') => {
+                    let _ = chars.next();
+                }
+                Some(c) => current.get_or_insert_with(String::new).push(c),
+                None => {}
+            },
+            '\'' => {
+                let current = current.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => current.push(c),
+                        None => return Err(CurlError::UnterminatedQuote),
+                    }
+                }
+            }
+            '"' => {
+                let current = current.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => current.push(c),
+                            Some('\n') => {}
+                            Some(c) => {
+                                current.push('\\');
+                                current.push(c);
+                            }
+                            None => return Err(CurlError::UnterminatedQuote),
+                        },
+                        Some(c) => current.push(c),
+                        None => return Err(CurlError::UnterminatedQuote),
+                    }
+                }
+            }
+            c if c.is_whitespace() => args.extend(current.take()),
+            c => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    args.extend(current);
+
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use http::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_split_args() {
+        assert_eq!(
+            split_args("curl -H 'a: b c' \"x\\\"y\" \\\n  --data a\\ b ''").unwrap(),
+            ["curl", "-H", "a: b c", "x\"y", "--data", "a b", ""]
+        );
+        assert!(matches!(
+            split_args("curl 'abc"),
+            Err(CurlError::UnterminatedQuote)
+        ));
+    }
+
+    #[test]
+    fn test_from_curl() {
+        let request = Request::from_curl(
+            r#"curl -sSL -XPUT 'https://api.example.com/v1/items?id=1' \
+                -H 'Content-Type: application/json' \
+                -H "X-Trace: abc" \
+                -u alice:secret \
+                -x socks5h://127.0.0.1:1080 \
+                --data-raw '{"name":"miku"}'"#,
+        )
+        .unwrap();
+
+        assert_eq!(request.method(), Method::PUT);
+        assert_eq!(request.uri(), "https://api.example.com/v1/items?id=1");
+        assert_eq!(request.headers()[CONTENT_TYPE], "application/json");
+        assert_eq!(request.headers()["x-trace"], "abc");
+        assert_eq!(
+            request.headers()[AUTHORIZATION],
+            HeaderValue::from_static("Basic YWxpY2U6c2VjcmV0")
+        );
+        assert_eq!(request.body(), r#"{"name":"miku"}"#);
+        assert!(matches!(
+            request.extensions().get::<ProxyScheme>(),
+            Some(ProxyScheme::Socks5 {
+                remote_dns: true,
+                port: 1080,
+                ..
+            })
+        ));
+
+        let request =
+            Request::from_curl("curl example.com/search -G -d q=1 --data-urlencode 'name=a b'")
+                .unwrap();
+
+        assert_eq!(request.method(), Method::GET);
+        assert_eq!(request.uri(), "http://example.com/search?q=1&name=a%20b");
+        assert!(request.body().is_empty());
+
+        let request = Request::from_curl("curl https://example.com -d a=1 -d b=2").unwrap();
+
+        assert_eq!(request.method(), Method::POST);
+        assert_eq!(
+            request.headers()[CONTENT_TYPE],
+            "application/x-www-form-urlencoded"
+        );
+        assert_eq!(request.body(), "a=1&b=2");
+
+        assert!(matches!(
+            Request::from_curl("wget https://example.com"),
+            Err(CurlError::NotCurl)
+        ));
+        assert!(matches!(
+            Request::from_curl("curl -d @body.json https://example.com"),
+            Err(CurlError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let parsed = CurlCommand::parse(
+            "curl -sXPOST -o out.bin https://example.com -w '%{http_code}' -m 5 -sHx-a:1",
+        )
+        .unwrap();
+
+        assert_eq!(parsed.method.as_deref(), Some("POST"));
+        assert_eq!(parsed.url, "https://example.com");
+        assert_eq!(parsed.headers, [("x-a".to_owned(), "1".to_owned())]);
+
+        let parsed = CurlCommand::parse("curl -sI example.com").unwrap();
+        assert_eq!(parsed.method.as_deref(), Some("HEAD"));
+
+        let parsed = CurlCommand::parse("curl -sG example.com -d q=1").unwrap();
+        assert!(parsed.get);
+        assert_eq!(parsed.data, ["q=1"]);
+
+        assert!(matches!(
+            CurlCommand::parse("curl -o"),
+            Err(CurlError::MissingValue(option)) if option == "-o"
+        ));
+        assert!(matches!(
+            CurlCommand::parse("curl --unix-socket /run/a.sock http://a"),
+            Err(CurlError::Unsupported(option)) if option == "--unix-socket"
+        ));
+        assert!(matches!(
+            CurlCommand::parse("curl -sF a=@file http://a"),
+            Err(CurlError::Unsupported(option)) if option == "-F"
+        ));
+    }
+}
@@ -0,0 +1,262 @@
+//! `Expect: 100-continue` (RFC 9110, section 10.1.1) for clients, see
+//! [`ExpectContinue`] and the sans-io [`ContinueHandshake`].
+
+use std::time::Duration;
+
+use http::{header, HeaderValue, Method, Request, StatusCode, Version};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Policy of sending `Expect: 100-continue`.
+pub struct ExpectContinue {
+    /// Send `Expect: 100-continue` only if the body is at least this size
+    /// (bodies of unknown size always qualify).
+    threshold: u64,
+
+    /// How long to wait for `100 Continue` before sending the body anyway.
+    timeout: Duration,
+}
+
+impl Default for ExpectContinue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExpectContinue {
+    #[inline]
+    /// Create a new [`ExpectContinue`], with a threshold of 1 MiB and a
+    /// timeout of 1 second.
+    pub const fn new() -> Self {
+        Self {
+            threshold: 1024 * 1024,
+            timeout: Duration::from_secs(1),
+        }
+    }
+
+    #[inline]
+    /// Set the body size threshold.
+    pub const fn with_threshold(self, threshold: u64) -> Self {
+        Self { threshold, ..self }
+    }
+
+    #[inline]
+    /// Set the timeout of waiting for `100 Continue`.
+    pub const fn with_timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+
+    #[inline]
+    /// Returns the timeout of waiting for `100 Continue`.
+    pub const fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Whether to send `Expect: 100-continue` for the request, with the body
+    /// size (`None` if unknown, e.g. streaming).
+    ///
+    /// Never for HTTP/1.0 (which doesn't support it), `GET`, `HEAD` and empty
+    /// bodies.
+    pub fn should_expect(&self, method: &Method, version: Version, body_len: Option<u64>) -> bool {
+        if version < Version::HTTP_11 || matches!(*method, Method::GET | Method::HEAD) {
+            return false;
+        }
+
+        body_len.map_or(true, |len| len > 0 && len >= self.threshold)
+    }
+
+    /// Insert `Expect: 100-continue` if [`should_expect`](Self::should_expect),
+    /// returning the [`ContinueHandshake`] to drive.
+    pub fn apply<B>(&self, req: &mut Request<B>, body_len: Option<u64>) -> ContinueHandshake {
+        let expecting = self.should_expect(req.method(), req.version(), body_len);
+
+        if expecting {
+            req.headers_mut()
+                .insert(header::EXPECT, HeaderValue::from_static("100-continue"));
+        }
+
+        ContinueHandshake::new(expecting)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HandshakeState {
+    /// Request head sent, waiting for `100 Continue` before sending the body.
+    AwaitingContinue,
+
+    /// The body is being (or has been) sent.
+    SendingBody,
+
+    /// Final response received.
+    Done,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// What the client should do next, see [`ContinueHandshake`].
+pub enum ContinueAction {
+    /// Start sending the body.
+    SendBody,
+
+    /// Keep waiting, e.g. for interim responses other than `100 Continue`.
+    Wait,
+
+    /// `417 Expectation Failed` before sending the body: retry the request
+    /// without `Expect`.
+    RetryWithoutExpect,
+
+    /// Final response received, handle it.
+    Final {
+        /// The body has not been (fully) sent, so the connection must be
+        /// closed rather than reused.
+        close_connection: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Sans-io state of the `Expect: 100-continue` handshake.
+///
+/// After sending the request head, wait for a response head for up to
+/// [`ExpectContinue::timeout`], feed the status to
+/// [`on_response`](Self::on_response) or call
+/// [`on_timeout`](Self::on_timeout), and follow the returned
+/// [`ContinueAction`].
+pub struct ContinueHandshake {
+    state: HandshakeState,
+}
+
+impl ContinueHandshake {
+    #[inline]
+    /// Create a new [`ContinueHandshake`], `expecting` is whether
+    /// `Expect: 100-continue` was sent.
+    pub const fn new(expecting: bool) -> Self {
+        Self {
+            state: if expecting {
+                HandshakeState::AwaitingContinue
+            } else {
+                HandshakeState::SendingBody
+            },
+        }
+    }
+
+    #[inline]
+    /// Whether the body should be held until `100 Continue` or timeout.
+    pub fn is_awaiting_continue(&self) -> bool {
+        self.state == HandshakeState::AwaitingContinue
+    }
+
+    #[inline]
+    /// Whether the final response has been received.
+    pub fn is_done(&self) -> bool {
+        self.state == HandshakeState::Done
+    }
+
+    /// Interpret the status of a received response head.
+    ///
+    /// `101 Switching Protocols` is treated as final.
+    pub fn on_response(&mut self, status: StatusCode) -> ContinueAction {
+        if self.state == HandshakeState::Done {
+            return ContinueAction::Final {
+                close_connection: false,
+            };
+        }
+
+        let awaiting = self.is_awaiting_continue();
+
+        match status {
+            StatusCode::CONTINUE if awaiting => {
+                self.state = HandshakeState::SendingBody;
+
+                ContinueAction::SendBody
+            }
+            status if status.is_informational() && status != StatusCode::SWITCHING_PROTOCOLS => {
+                ContinueAction::Wait
+            }
+            StatusCode::EXPECTATION_FAILED if awaiting => {
+                self.state = HandshakeState::Done;
+
+                ContinueAction::RetryWithoutExpect
+            }
+            _ => {
+                self.state = HandshakeState::Done;
+
+                ContinueAction::Final {
+                    close_connection: awaiting,
+                }
+            }
+        }
+    }
+
+    /// No response within the timeout: send the body anyway, as servers may
+    /// not support `Expect`.
+    pub fn on_timeout(&mut self) -> ContinueAction {
+        if self.is_awaiting_continue() {
+            self.state = HandshakeState::SendingBody;
+
+            ContinueAction::SendBody
+        } else {
+            ContinueAction::Wait
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_expect() {
+        let policy = ExpectContinue::new().with_threshold(1024);
+
+        assert!(policy.should_expect(&Method::PUT, Version::HTTP_11, Some(4096)));
+        assert!(policy.should_expect(&Method::POST, Version::HTTP_11, None));
+        assert!(!policy.should_expect(&Method::POST, Version::HTTP_11, Some(10)));
+        assert!(!policy.should_expect(&Method::POST, Version::HTTP_10, Some(4096)));
+        assert!(!policy.should_expect(&Method::GET, Version::HTTP_11, None));
+
+        let mut req = Request::post("/upload").body(()).unwrap();
+        let handshake = policy.apply(&mut req, Some(4096));
+        assert_eq!(req.headers()[header::EXPECT], "100-continue");
+        assert!(handshake.is_awaiting_continue());
+    }
+
+    #[test]
+    fn test_handshake() {
+        let mut handshake = ContinueHandshake::new(true);
+        assert_eq!(
+            handshake.on_response(StatusCode::from_u16(103).unwrap()),
+            ContinueAction::Wait
+        );
+        assert_eq!(
+            handshake.on_response(StatusCode::CONTINUE),
+            ContinueAction::SendBody
+        );
+        assert_eq!(
+            handshake.on_response(StatusCode::OK),
+            ContinueAction::Final {
+                close_connection: false
+            }
+        );
+        assert!(handshake.is_done());
+
+        let mut handshake = ContinueHandshake::new(true);
+        assert_eq!(
+            handshake.on_response(StatusCode::EXPECTATION_FAILED),
+            ContinueAction::RetryWithoutExpect
+        );
+
+        let mut handshake = ContinueHandshake::new(true);
+        assert_eq!(
+            handshake.on_response(StatusCode::UNAUTHORIZED),
+            ContinueAction::Final {
+                close_connection: true
+            }
+        );
+
+        let mut handshake = ContinueHandshake::new(true);
+        assert_eq!(handshake.on_timeout(), ContinueAction::SendBody);
+        assert_eq!(handshake.on_timeout(), ContinueAction::Wait);
+        assert_eq!(
+            handshake.on_response(StatusCode::CONTINUE),
+            ContinueAction::Wait
+        );
+    }
+}
@@ -0,0 +1,305 @@
+//! Host and port parsing of user input, like `example.com`,
+//! `example.com:8443` or `[::1]:443`, see [`HostPort`].
+
+use std::{
+    fmt,
+    net::{IpAddr, Ipv6Addr},
+    str::FromStr,
+    sync::Arc,
+};
+
+use http::uri::{Authority, InvalidUri};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(thiserror::Error)]
+/// Errors parsing [`HostPort`].
+pub enum HostPortError {
+    #[error("empty host")]
+    /// The input (or the host part) is empty.
+    Empty,
+
+    #[error("invalid host `{0}`")]
+    /// Invalid host, e.g. with a scheme, path or user info, or an invalid IPv6
+    /// literal.
+    InvalidHost(String),
+
+    #[error("invalid port `{0}`")]
+    /// The port is not in `1..=65535`.
+    InvalidPort(String),
+}
+
+/// Returns the default port of the scheme (case-insensitive), if known.
+///
+/// - `http`, `ws`: 80
+/// - `https`, `wss`: 443
+/// - `socks4`, `socks4a`, `socks5`, `socks5h`: 1080
+pub fn default_port(scheme: &str) -> Option<u16> {
+    match scheme.to_ascii_lowercase().as_str() {
+        "http" | "ws" => Some(80),
+        "https" | "wss" => Some(443),
+        "socks4" | "socks4a" | "socks5" | "socks5h" => Some(1080),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// The host of [`HostPort`].
+pub enum Host {
+    /// Domain name, lowercase and without the trailing dot.
+    Domain(Arc<str>),
+
+    /// IPv4 or IPv6 address.
+    Ip(IpAddr),
+}
+
+impl fmt::Display for Host {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Domain(domain) => f.write_str(domain),
+            Self::Ip(IpAddr::V4(ip)) => write!(f, "{ip}"),
+            Self::Ip(IpAddr::V6(ip)) => write!(f, "[{ip}]"),
+        }
+    }
+}
+
+impl FromStr for Host {
+    type Err = HostPortError;
+
+    /// Parse the host, IPv6 addresses must be bracketed.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(HostPortError::Empty);
+        }
+
+        if let Some(ip) = s.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            return ip
+                .parse::<Ipv6Addr>()
+                .map(|ip| Self::Ip(IpAddr::V6(ip)))
+                .map_err(|_| HostPortError::InvalidHost(s.to_owned()));
+        }
+
+        if let Ok(ip) = s.parse() {
+            return Ok(Self::Ip(IpAddr::V4(ip)));
+        }
+
+        let domain = s.strip_suffix('.').unwrap_or(s);
+
+        let valid = domain.len() <= 253
+            && domain.split('.').all(|label| {
+                !label.is_empty()
+                    && label.len() <= 63
+                    && !label.starts_with('-')
+                    && !label.ends_with('-')
+                    && label
+                        .bytes()
+                        .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+            });
+
+        if !valid {
+            return Err(HostPortError::InvalidHost(s.to_owned()));
+        }
+
+        Ok(Self::Domain(domain.to_ascii_lowercase().into()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Host and optional port, parsed from user input like config files.
+///
+/// Accepted forms are `host`, `host:port`, `[ipv6]` and `[ipv6]:port`, while
+/// schemes, user info and paths are rejected. Bare IPv6 addresses like `::1`
+/// are accepted too, as there's no port to be confused with.
+///
+/// # Examples
+///
+/// ```rust
+/// # use miku_http_util::request::misc::host::HostPort;
+/// let host_port: HostPort = "Example.com".parse().unwrap();
+/// assert_eq!(host_port.port(), None);
+/// assert_eq!(host_port.port_or_default("https"), Some(443));
+/// assert_eq!(
+///     host_port.with_default_port("https").to_authority().unwrap(),
+///     "example.com:443"
+/// );
+///
+/// let host_port: HostPort = "[::1]:8443".parse().unwrap();
+/// assert_eq!(host_port.port(), Some(8443));
+/// assert_eq!(host_port.to_string(), "[::1]:8443");
+/// ```
+pub struct HostPort {
+    host: Host,
+    port: Option<u16>,
+}
+
+impl FromStr for HostPort {
+    type Err = HostPortError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        if s.is_empty() {
+            return Err(HostPortError::Empty);
+        }
+
+        if s.contains(['/', '?', '#', '@']) || s.contains(char::is_whitespace) {
+            return Err(HostPortError::InvalidHost(s.to_owned()));
+        }
+
+        // Bare IPv6 address.
+        if let Ok(ip) = s.parse::<Ipv6Addr>() {
+            return Ok(Self::new(Host::Ip(IpAddr::V6(ip)), None));
+        }
+
+        let (host, port) = match s.rfind(':') {
+            Some(idx) if !s[idx..].contains(']') => (&s[..idx], Some(&s[idx + 1..])),
+            _ => (s, None),
+        };
+
+        let port = port
+            .map(|port| {
+                port.bytes()
+                    .all(|b| b.is_ascii_digit())
+                    .then(|| port.parse::<u16>().ok())
+                    .flatten()
+                    .filter(|&port| port != 0)
+                    .ok_or_else(|| HostPortError::InvalidPort(port.to_owned()))
+            })
+            .transpose()?;
+
+        Ok(Self::new(host.parse()?, port))
+    }
+}
+
+impl fmt::Display for HostPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.port {
+            Some(port) => write!(f, "{}:{port}", self.host),
+            None => write!(f, "{}", self.host),
+        }
+    }
+}
+
+impl HostPort {
+    #[inline]
+    /// Create a new [`HostPort`].
+    pub const fn new(host: Host, port: Option<u16>) -> Self {
+        Self { host, port }
+    }
+
+    #[inline]
+    /// Returns the host.
+    pub const fn host(&self) -> &Host {
+        &self.host
+    }
+
+    #[inline]
+    /// Returns the explicit port.
+    pub const fn port(&self) -> Option<u16> {
+        self.port
+    }
+
+    #[inline]
+    /// Returns the explicit port, or the default port of the scheme, see
+    /// [`default_port`].
+    pub fn port_or_default(&self, scheme: &str) -> Option<u16> {
+        self.port.or_else(|| default_port(scheme))
+    }
+
+    #[must_use]
+    #[inline]
+    /// Set the port.
+    pub fn with_port(self, port: Option<u16>) -> Self {
+        Self { port, ..self }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Set the port to the default port of the scheme if not specified.
+    pub fn with_default_port(self, scheme: &str) -> Self {
+        let port = self.port_or_default(scheme);
+
+        Self { port, ..self }
+    }
+
+    /// Convert to [`Authority`].
+    ///
+    /// # Errors
+    ///
+    /// [`InvalidUri`], which should not happen as the host has been
+    /// validated.
+    pub fn to_authority(&self) -> Result<Authority, InvalidUri> {
+        Authority::try_from(self.to_string())
+    }
+}
+
+impl TryFrom<&HostPort> for Authority {
+    type Error = InvalidUri;
+
+    fn try_from(host_port: &HostPort) -> Result<Self, Self::Error> {
+        host_port.to_authority()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let host_port: HostPort = "Example.COM.".parse().unwrap();
+        assert_eq!(host_port.host(), &Host::Domain("example.com".into()));
+        assert_eq!(host_port.port(), None);
+
+        let host_port: HostPort = " example.com:8443 ".parse().unwrap();
+        assert_eq!(host_port.port(), Some(8443));
+        assert_eq!(host_port.to_authority().unwrap(), "example.com:8443");
+
+        let host_port: HostPort = "127.0.0.1:80".parse().unwrap();
+        assert_eq!(host_port.host(), &Host::Ip(Ipv4Addr::LOCALHOST.into()));
+
+        let host_port: HostPort = "[::1]:443".parse().unwrap();
+        assert_eq!(host_port.host(), &Host::Ip(Ipv6Addr::LOCALHOST.into()));
+        assert_eq!(host_port.port(), Some(443));
+
+        let host_port: HostPort = "::1".parse().unwrap();
+        assert_eq!(host_port.port(), None);
+        assert_eq!(host_port.with_default_port("wss").to_string(), "[::1]:443");
+
+        for (input, err) in [
+            ("", HostPortError::Empty),
+            (":80", HostPortError::Empty),
+            ("example.com:", HostPortError::InvalidPort(String::new())),
+            ("example.com:0", HostPortError::InvalidPort("0".to_owned())),
+            (
+                "example.com:+80",
+                HostPortError::InvalidPort("+80".to_owned()),
+            ),
+            (
+                "example.com:65536",
+                HostPortError::InvalidPort("65536".to_owned()),
+            ),
+            (
+                "http://example.com",
+                HostPortError::InvalidHost("http://example.com".to_owned()),
+            ),
+            (
+                "u@example.com",
+                HostPortError::InvalidHost("u@example.com".to_owned()),
+            ),
+            (
+                "exa mple.com",
+                HostPortError::InvalidHost("exa mple.com".to_owned()),
+            ),
+            (
+                "-example.com",
+                HostPortError::InvalidHost("-example.com".to_owned()),
+            ),
+            ("a..b", HostPortError::InvalidHost("a..b".to_owned())),
+            ("[::g]:443", HostPortError::InvalidHost("[::g]".to_owned())),
+        ] {
+            assert_eq!(input.parse::<HostPort>(), Err(err), "{input}");
+        }
+    }
+}
@@ -0,0 +1,315 @@
+//! `Prefer` / `Preference-Applied` (RFC 7240), see [`Prefer`].
+
+use std::time::Duration;
+
+use http::{header, HeaderMap, HeaderName, HeaderValue};
+
+/// `Prefer` header name.
+pub const PREFER: HeaderName = HeaderName::from_static("prefer");
+
+/// `Preference-Applied` header name.
+pub const PREFERENCE_APPLIED: HeaderName = HeaderName::from_static("preference-applied");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// `return` preference.
+pub enum PreferReturn {
+    /// `return=minimal`, e.g. `204 No Content` for updates.
+    Minimal,
+
+    /// `return=representation`, the full resource.
+    Representation,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// `handling` preference.
+pub enum PreferHandling {
+    /// `handling=strict`, reject on any invalid or unsupported part.
+    Strict,
+
+    /// `handling=lenient`, process as much as possible.
+    Lenient,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// Parsed `Prefer` (or `Preference-Applied`) preferences.
+pub struct Prefer {
+    /// `return`
+    pub return_preference: Option<PreferReturn>,
+
+    /// `wait`, in seconds.
+    pub wait: Option<Duration>,
+
+    /// `respond-async`
+    pub respond_async: bool,
+
+    /// `handling`
+    pub handling: Option<PreferHandling>,
+
+    /// Other preferences, as lowercase name and optional (unquoted) value,
+    /// like `odata.maxpagesize=50`.
+    pub others: Vec<(String, Option<String>)>,
+}
+
+impl Prefer {
+    #[inline]
+    /// Create an empty [`Prefer`].
+    pub const fn new() -> Self {
+        Self {
+            return_preference: None,
+            wait: None,
+            respond_async: false,
+            handling: None,
+            others: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    /// Set `return`.
+    pub fn with_return(self, return_preference: PreferReturn) -> Self {
+        Self {
+            return_preference: Some(return_preference),
+            ..self
+        }
+    }
+
+    #[must_use]
+    /// Set `wait`, truncated to seconds.
+    pub fn with_wait(self, wait: Duration) -> Self {
+        Self {
+            wait: Some(Duration::from_secs(wait.as_secs())),
+            ..self
+        }
+    }
+
+    #[must_use]
+    /// Set `respond-async`.
+    pub fn with_respond_async(self) -> Self {
+        Self {
+            respond_async: true,
+            ..self
+        }
+    }
+
+    #[must_use]
+    /// Set `handling`.
+    pub fn with_handling(self, handling: PreferHandling) -> Self {
+        Self {
+            handling: Some(handling),
+            ..self
+        }
+    }
+
+    #[must_use]
+    /// Add other preference, the name is lowercased.
+    pub fn with_other(mut self, name: &str, value: Option<&str>) -> Self {
+        self.others
+            .push((name.to_ascii_lowercase(), value.map(str::to_owned)));
+        self
+    }
+
+    /// Parse the preferences, like `return=minimal, wait=10; foo=bar`.
+    ///
+    /// Preference parameters are ignored. When a preference appears more than
+    /// once, the first one wins, as RFC 7240 requires. Malformed values of
+    /// known preferences are ignored.
+    pub fn parse(value: &str) -> Self {
+        let mut prefer = Self::default();
+        let mut seen = Vec::new();
+
+        for preference in value.split(',') {
+            let preference = preference.split(';').next().unwrap_or_default();
+
+            let (name, value) = match preference.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+                None => (preference.trim(), None),
+            };
+
+            if name.is_empty() {
+                continue;
+            }
+
+            let name = name.to_ascii_lowercase();
+            if seen.contains(&name) {
+                continue;
+            }
+
+            prefer.apply(&name, value);
+            seen.push(name);
+        }
+
+        prefer
+    }
+
+    /// Apply the preference of the lowercase name.
+    fn apply(&mut self, name: &str, value: Option<&str>) {
+        let value_lowercase = value.map(str::to_ascii_lowercase);
+
+        match (name, value_lowercase.as_deref()) {
+            ("return", Some("minimal")) => self.return_preference = Some(PreferReturn::Minimal),
+            ("return", Some("representation")) => {
+                self.return_preference = Some(PreferReturn::Representation);
+            }
+            ("wait", Some(seconds)) => {
+                self.wait = seconds.parse().ok().map(Duration::from_secs);
+            }
+            ("respond-async", _) => self.respond_async = true,
+            ("handling", Some("strict")) => self.handling = Some(PreferHandling::Strict),
+            ("handling", Some("lenient")) => self.handling = Some(PreferHandling::Lenient),
+            ("return" | "wait" | "handling", _) => {}
+            _ => self
+                .others
+                .push((name.to_owned(), value.map(str::to_owned))),
+        }
+    }
+
+    /// Parse all the headers of the name, i.e. [`PREFER`] or
+    /// [`PREFERENCE_APPLIED`].
+    pub fn from_headers(headers: &HeaderMap, name: &HeaderName) -> Self {
+        let value = headers
+            .get_all(name)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .collect::<Vec<_>>()
+            .join(",");
+
+        Self::parse(&value)
+    }
+
+    /// Whether no preference is set.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::new()
+    }
+
+    /// Returns the header value, `None` if empty.
+    ///
+    /// Values are quoted if not tokens.
+    pub fn to_header_value(&self) -> Option<HeaderValue> {
+        let mut preferences = Vec::new();
+
+        if let Some(return_preference) = self.return_preference {
+            preferences.push(match return_preference {
+                PreferReturn::Minimal => "return=minimal".to_owned(),
+                PreferReturn::Representation => "return=representation".to_owned(),
+            });
+        }
+
+        if self.respond_async {
+            preferences.push("respond-async".to_owned());
+        }
+
+        if let Some(wait) = self.wait {
+            preferences.push(format!("wait={}", wait.as_secs()));
+        }
+
+        if let Some(handling) = self.handling {
+            preferences.push(match handling {
+                PreferHandling::Strict => "handling=strict".to_owned(),
+                PreferHandling::Lenient => "handling=lenient".to_owned(),
+            });
+        }
+
+        for (name, value) in &self.others {
+            preferences.push(match value {
+                Some(value) if is_token(value) => format!("{name}={value}"),
+                Some(value) => format!("{name}=\"{}\"", value.replace(['"', '\\'], "")),
+                None => name.clone(),
+            });
+        }
+
+        if preferences.is_empty() {
+            return None;
+        }
+
+        HeaderValue::try_from(preferences.join(", ")).ok()
+    }
+
+    /// Set `Preference-Applied` of the response headers to the applied
+    /// preferences (no-op if empty), and add `Prefer` to `Vary`, for servers
+    /// honoring (part of) the `Prefer` of the request.
+    pub fn apply_to_response(&self, headers: &mut HeaderMap) {
+        let Some(value) = self.to_header_value() else {
+            return;
+        };
+
+        headers.insert(PREFERENCE_APPLIED, value);
+        headers.append(header::VARY, HeaderValue::from_static("prefer"));
+    }
+}
+
+/// Whether the value is a token (RFC 9110, section 5.6.2).
+fn is_token(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+#[cfg(feature = "feat-request-header")]
+impl crate::request::header::TypedHeaderT for Prefer {
+    #[inline]
+    fn name() -> HeaderName {
+        PREFER
+    }
+
+    fn parse(value: &HeaderValue) -> Option<Self> {
+        value.to_str().ok().map(Self::parse)
+    }
+
+    fn encode(&self) -> HeaderValue {
+        self.to_header_value()
+            .unwrap_or_else(|| HeaderValue::from_static(""))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let mut headers = HeaderMap::new();
+        headers.append(
+            PREFER,
+            HeaderValue::from_static("Return=Minimal; foo=bar, wait=10"),
+        );
+        headers.append(
+            PREFER,
+            HeaderValue::from_static(
+                "respond-async, return=representation, odata.maxpagesize=\"50\"",
+            ),
+        );
+
+        assert_eq!(
+            Prefer::from_headers(&headers, &PREFER),
+            Prefer::new()
+                .with_return(PreferReturn::Minimal)
+                .with_wait(Duration::from_secs(10))
+                .with_respond_async()
+                .with_other("odata.maxpagesize", Some("50"))
+        );
+
+        assert!(Prefer::parse("wait=abc, handling=unknown").is_empty());
+    }
+
+    #[test]
+    fn test_apply_to_response() {
+        let applied = Prefer::new()
+            .with_return(PreferReturn::Minimal)
+            .with_handling(PreferHandling::Lenient)
+            .with_other("x-note", Some("a b"));
+
+        let mut headers = HeaderMap::new();
+        applied.apply_to_response(&mut headers);
+
+        assert_eq!(
+            headers[PREFERENCE_APPLIED],
+            "return=minimal, handling=lenient, x-note=\"a b\""
+        );
+        assert_eq!(headers[header::VARY], "prefer");
+        assert_eq!(Prefer::from_headers(&headers, &PREFERENCE_APPLIED), applied);
+
+        let mut headers = HeaderMap::new();
+        Prefer::new().apply_to_response(&mut headers);
+        assert!(headers.is_empty());
+    }
+}
@@ -0,0 +1,269 @@
+//! Request profiles, i.e. per-API defaults defined once, see
+//! [`RequestProfile`].
+
+use std::collections::BTreeMap;
+
+use http::{request::Builder, HeaderName, HeaderValue, Method, Request, Uri};
+
+use super::proxy::ProxyScheme;
+use crate::request::builder::{Md5Signer, Query, SignerT};
+
+#[derive(Debug)]
+#[derive(thiserror::Error)]
+/// Errors building requests from [`RequestProfile`].
+pub enum ProfileError {
+    #[error("invalid header name `{0}`")]
+    /// Invalid default header name.
+    InvalidHeaderName(String),
+
+    #[error("invalid value of header `{0}`")]
+    /// Invalid default header value.
+    InvalidHeaderValue(String),
+
+    #[error("invalid uri: {0}")]
+    /// Invalid URI, see [`http::uri::InvalidUri`] for more details.
+    InvalidUri(#[from] http::uri::InvalidUri),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(serde::Serialize, serde::Deserialize)]
+/// Owned config of [`Md5Signer`], see [`RequestProfile::signer`].
+pub struct ProfileSigner {
+    #[serde(default = "ProfileSigner::default_query_key")]
+    /// The query param key, `sign` by default.
+    pub query_key: String,
+
+    #[serde(default)]
+    /// The salt to be used for signing (prefix).
+    pub prefix_salt: Option<String>,
+
+    #[serde(default)]
+    /// The salt to be used for signing (suffix).
+    pub suffix_salt: Option<String>,
+}
+
+impl ProfileSigner {
+    fn default_query_key() -> String {
+        "sign".to_owned()
+    }
+
+    #[inline]
+    /// Returns the [`Md5Signer`].
+    pub fn as_md5_signer(&self) -> Md5Signer<'_> {
+        Md5Signer::new(
+            &self.query_key,
+            self.prefix_salt.as_deref(),
+            self.suffix_salt.as_deref(),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+/// Per-API defaults for building requests, serializable from config.
+///
+/// # Examples
+///
+/// ```rust
+/// # use http::Method;
+/// # use miku_http_util::request::{builder::Query, misc::profile::RequestProfile};
+/// let profile: RequestProfile = serde_json::from_str(
+///     r#"{
+///         "base_url": "https://api.example.com/v1/",
+///         "headers": { "user-agent": "miku/1.0" },
+///         "query": { "appkey": "123" },
+///         "signer": { "suffix_salt": "salt" }
+///     }"#,
+/// )
+/// .unwrap();
+///
+/// let request = profile
+///     .request(Method::GET, "/users", Query::default().push("id", "1"))
+///     .unwrap()
+///     .body(())
+///     .unwrap();
+///
+/// assert!(request
+///     .uri()
+///     .to_string()
+///     .starts_with("https://api.example.com/v1/users?appkey=123&id=1&sign="));
+/// assert_eq!(request.headers()["user-agent"], "miku/1.0");
+/// ```
+pub struct RequestProfile {
+    /// Base URL, e.g. `https://api.example.com/v1`, joined with the path of
+    /// each request.
+    pub base_url: String,
+
+    /// Default headers.
+    ///
+    /// Note that [`Builder::header`] appends, use [`Builder::headers_mut`] to
+    /// replace them at call sites.
+    pub headers: BTreeMap<String, String>,
+
+    /// Default query params, overridden by the ones of each request.
+    pub query: BTreeMap<String, String>,
+
+    /// Signer of the query string, if any.
+    pub signer: Option<ProfileSigner>,
+
+    /// Proxy, stored in the request extensions.
+    pub proxy: Option<ProxyScheme>,
+}
+
+impl RequestProfile {
+    #[inline]
+    /// Create a new [`RequestProfile`] with the base URL.
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into(),
+            ..Self::default()
+        }
+    }
+
+    #[must_use]
+    /// Add a default header.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.insert(name.into(), value.into());
+        self
+    }
+
+    #[must_use]
+    /// Add a default query param.
+    pub fn with_query(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.query.insert(key.into(), value.into());
+        self
+    }
+
+    #[must_use]
+    #[inline]
+    /// Set the signer.
+    pub fn with_signer(self, signer: Option<ProfileSigner>) -> Self {
+        Self { signer, ..self }
+    }
+
+    #[must_use]
+    #[inline]
+    /// Set the proxy.
+    pub fn with_proxy(self, proxy: Option<ProxyScheme>) -> Self {
+        Self { proxy, ..self }
+    }
+
+    /// Returns the URI of the path, with the default query params merged
+    /// and signed.
+    ///
+    /// # Errors
+    ///
+    /// [`ProfileError::InvalidUri`].
+    pub fn uri(&self, path: &str, query: Query<'_>) -> Result<Uri, ProfileError> {
+        let defaults = self
+            .query
+            .iter()
+            .filter(|&(key, _)| !query.iter().any(|(k, _)| k == key))
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect::<Vec<_>>();
+        let query = defaults
+            .into_iter()
+            .fold(query, |query, (key, value)| query.push(key, value));
+
+        let query = match &self.signer {
+            Some(signer) => match signer.as_md5_signer().build_signed(query) {
+                Ok(query) => query,
+                Err(infallible) => match infallible {},
+            },
+            None => query.build_sorted_stable(),
+        };
+
+        let mut uri = format!(
+            "{}/{}",
+            self.base_url.trim_end_matches('/'),
+            path.trim_start_matches('/')
+        );
+
+        if !query.is_empty() {
+            uri.push('?');
+            uri.push_str(&query);
+        }
+
+        Ok(Uri::try_from(uri)?)
+    }
+
+    /// Returns the pre-configured [`Builder`] of the request: the URI (see
+    /// [`uri`](Self::uri)), the default headers and the proxy.
+    ///
+    /// # Errors
+    ///
+    /// See [`ProfileError`].
+    pub fn request(
+        &self,
+        method: Method,
+        path: &str,
+        query: Query<'_>,
+    ) -> Result<Builder, ProfileError> {
+        let mut builder = Request::builder()
+            .method(method)
+            .uri(self.uri(path, query)?);
+
+        if let Some(headers) = builder.headers_mut() {
+            for (name, value) in &self.headers {
+                let name = HeaderName::try_from(name)
+                    .map_err(|_| ProfileError::InvalidHeaderName(name.clone()))?;
+                let value = HeaderValue::try_from(value)
+                    .map_err(|_| ProfileError::InvalidHeaderValue(name.to_string()))?;
+
+                headers.insert(name, value);
+            }
+        }
+
+        if let Some(proxy) = &self.proxy {
+            builder = builder.extension(proxy.clone());
+        }
+
+        Ok(builder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_profile() {
+        let profile = RequestProfile::new("https://api.example.com/")
+            .with_header("x-api", "1")
+            .with_query("appkey", "123")
+            .with_query("lang", "en")
+            .with_proxy(Some("http://127.0.0.1:7890".parse().unwrap()));
+
+        let request = profile
+            .request(
+                Method::POST,
+                "items",
+                Query::default().push("lang", "zh").push("b", "2"),
+            )
+            .unwrap()
+            .header("x-api", "2")
+            .body(())
+            .unwrap();
+
+        assert_eq!(
+            request.uri(),
+            "https://api.example.com/items?appkey=123&b=2&lang=zh"
+        );
+        assert_eq!(request.headers().get_all("x-api").iter().count(), 2);
+        assert!(request.extensions().get::<ProxyScheme>().is_some());
+
+        let profile: RequestProfile =
+            serde_json::from_str(&serde_json::to_string(&profile).unwrap()).unwrap();
+        assert_eq!(
+            profile.proxy,
+            Some("http://127.0.0.1:7890".parse().unwrap())
+        );
+
+        let profile = RequestProfile::new("https://api.example.com").with_header("bad name", "1");
+        assert!(matches!(
+            profile.request(Method::GET, "/", Query::default()),
+            Err(ProfileError::InvalidHeaderName(_))
+        ));
+    }
+}
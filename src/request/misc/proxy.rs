@@ -1,10 +1,18 @@
 //! Proxy utilities for requests.
 
-use std::{str::FromStr, sync::Arc};
+use std::{future::Future, str::FromStr, sync::Arc};
 
-use anyhow::{anyhow, Context};
+use futures_util::{stream::FuturesUnordered, StreamExt};
 use http::HeaderValue;
 
+use super::host::{HostPort, HostPortError};
+use crate::percent;
+
+pub mod pool;
+
+// re-export
+pub use self::pool::{ProxyPool, ProxyStats};
+
 const DEFAULT_SOCKS5_PROXY_PORT: u16 = 7890;
 
 #[derive(Debug)]
@@ -15,10 +23,18 @@ pub enum Error {
     /// Invalid proxy uri, see [`http::uri::InvalidUri`] for more details.
     InvalidUri(#[from] http::uri::InvalidUri),
 
+    #[error("Invalid proxy uri: {0}")]
+    /// Invalid host or port, see [`HostPortError`] for more details.
+    InvalidHost(#[from] HostPortError),
+
     #[error("Invalid proxy uri: unsupported scheme")]
     /// Unsupported scheme
     UnsupportedScheme,
 
+    #[error("Invalid proxy uri: invalid auth")]
+    /// Invalid auth, e.g. SOCKS5 auth without password
+    InvalidAuth,
+
     #[error("Invalid proxy uri: general error")]
     /// General
     General,
@@ -65,47 +81,38 @@ pub enum ProxyScheme {
 }
 
 impl FromStr for ProxyScheme {
-    type Err = anyhow::Error;
+    type Err = crate::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let uri = fluent_uri::Uri::parse(s).context(Error::General)?;
+        let uri = fluent_uri::Uri::parse(s).map_err(|_| Error::General)?;
 
         let scheme = uri.scheme().as_str();
         let authority = uri.authority().ok_or(Error::General)?;
-        let user_info = authority.userinfo().map(|user_info| {
-            percent_encoding::percent_decode_str(user_info.as_str()).decode_utf8_lossy()
-        });
+        let user_info = authority
+            .userinfo()
+            .map(|user_info| percent::decode(user_info.as_str()));
 
         match scheme {
             "http" | "https" => {
-                let authority = http::uri::Authority::try_from(format!(
-                    "{}:{}",
-                    authority.host(),
-                    authority
-                        .port_to_u16()
-                        .context(Error::General)?
-                        .unwrap_or_else(|| {
-                            if scheme == "http" {
-                                80
-                            } else {
-                                443
-                            }
-                        })
-                ))
-                .map_err(|e| {
-                    #[cfg(debug_assertions)]
-                    {
-                        unreachable!("Rare bug: http::uri::Authority reports error {e:?}");
-                    }
-
-                    #[cfg(all(not(debug_assertions), feature = "feat-tracing"))]
-                    {
-                        tracing::error!("Rare bug: http::uri::Authority reports error {e:?}");
-                    }
-
-                    #[allow(unreachable_code)]
-                    Error::InvalidUri(e)
-                })?;
+                let port = authority.port_to_u16().map_err(|_| Error::General)?;
+
+                let authority = HostPort::new(authority.host().parse().map_err(Error::from)?, port)
+                    .with_default_port(scheme)
+                    .to_authority()
+                    .map_err(|e| {
+                        #[cfg(debug_assertions)]
+                        {
+                            unreachable!("Rare bug: http::uri::Authority reports error {e:?}");
+                        }
+
+                        #[cfg(all(not(debug_assertions), feature = "feat-tracing"))]
+                        {
+                            tracing::error!("Rare bug: http::uri::Authority reports error {e:?}");
+                        }
+
+                        #[allow(unreachable_code)]
+                        Error::InvalidUri(e)
+                    })?;
 
                 let basic_auth = user_info.map(|user_info| match user_info.split_once(':') {
                     Some((user_name, password)) => basic_auth(user_name, Some(password)),
@@ -124,7 +131,7 @@ impl FromStr for ProxyScheme {
                         user_info
                             .split_once(':')
                             .map(|(user_name, password)| (user_name.into(), password.into()))
-                            .context("Invalid socks5 password auth")?,
+                            .ok_or(Error::InvalidAuth)?,
                     ),
                     None => None,
                 };
@@ -135,14 +142,14 @@ impl FromStr for ProxyScheme {
                     host: authority.host().into(),
                     port: authority
                         .port_to_u16()
-                        .context(Error::General)?
+                        .map_err(|_| Error::General)?
                         .unwrap_or(DEFAULT_SOCKS5_PROXY_PORT),
                 })
             }
             _ => {
                 #[cfg(feature = "feat-tracing")]
                 tracing::error!("Unsupported proxy scheme: {scheme}");
-                Err(anyhow!(Error::UnsupportedScheme))
+                Err(Error::UnsupportedScheme.into())
             }
         }
     }
@@ -158,6 +165,82 @@ impl ProxyScheme {
             _ => None,
         }
     }
+
+    /// Whether requests to the URI must be tunneled through the proxy, i.e.
+    /// always for `SOCKS5` proxies, and `CONNECT` for `HTTPS` / `WSS` targets
+    /// of `HTTP` proxies.
+    ///
+    /// Otherwise the request is forwarded by the `HTTP` proxy in the
+    /// absolute-form, with `Proxy-Authorization` if any.
+    pub fn needs_tunnel(&self, uri: &http::Uri) -> bool {
+        match self {
+            ProxyScheme::Http { .. } => uri.scheme_str().is_some_and(|scheme| {
+                scheme.eq_ignore_ascii_case("https") || scheme.eq_ignore_ascii_case("wss")
+            }),
+            ProxyScheme::Socks5 { .. } => true,
+        }
+    }
+}
+
+#[derive(Debug)]
+#[derive(thiserror::Error)]
+#[error("all {} proxy candidates failed", .errors.len())]
+/// Error returned by [`race_proxies`] when no candidate succeeds.
+pub struct RaceError<E> {
+    /// The candidates and their errors, in order of failure.
+    pub errors: Vec<(ProxyScheme, E)>,
+}
+
+/// Establish the connection through the candidate proxies concurrently,
+/// returning the first success along with the proxy, so that a few stalled
+/// proxies of an unreliable pool do not dominate the latency.
+///
+/// At most `concurrency` (at least 1) attempts are in flight, the next
+/// candidate is tried once an attempt fails. The other attempts are cancelled
+/// (dropped) once one succeeds, so `connect` should be cancel-safe. Pair it
+/// with a timeout per attempt, e.g. `tokio::time::timeout`, for proxies
+/// which never respond.
+///
+/// # Errors
+///
+/// [`RaceError`] with the errors of all the candidates, empty if there's no
+/// candidate.
+pub async fn race_proxies<I, F, Fut, T, E>(
+    proxies: I,
+    concurrency: usize,
+    mut connect: F,
+) -> Result<(ProxyScheme, T), RaceError<E>>
+where
+    I: IntoIterator<Item = ProxyScheme>,
+    F: FnMut(&ProxyScheme) -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut proxies = proxies.into_iter();
+    let mut errors = Vec::new();
+
+    let mut attempt = |proxy: ProxyScheme| {
+        let attempt = connect(&proxy);
+
+        async move { (proxy, attempt.await) }
+    };
+
+    let mut attempts: FuturesUnordered<_> = proxies
+        .by_ref()
+        .take(concurrency.max(1))
+        .map(&mut attempt)
+        .collect();
+
+    while let Some((proxy, result)) = attempts.next().await {
+        match result {
+            Ok(conn) => return Ok((proxy, conn)),
+            Err(e) => {
+                errors.push((proxy, e));
+                attempts.extend(proxies.next().map(&mut attempt));
+            }
+        }
+    }
+
+    Err(RaceError { errors })
 }
 
 impl serde::Serialize for ProxyScheme {
@@ -196,22 +279,10 @@ impl serde::Serialize for ProxyScheme {
                         match basic_auth.split_once(':') {
                             Some((user_name, password)) => format!(
                                 "{}:{}@",
-                                percent_encoding::percent_encode(
-                                    user_name.as_bytes(),
-                                    percent_encoding::NON_ALPHANUMERIC
-                                ),
-                                percent_encoding::percent_encode(
-                                    password.as_bytes(),
-                                    percent_encoding::NON_ALPHANUMERIC
-                                )
-                            ),
-                            None => format!(
-                                "{}@",
-                                percent_encoding::percent_encode(
-                                    basic_auth.as_bytes(),
-                                    percent_encoding::NON_ALPHANUMERIC
-                                ),
+                                percent::encode(user_name, percent::USERINFO),
+                                percent::encode(password, percent::USERINFO)
                             ),
+                            None => format!("{}@", percent::encode(&basic_auth, percent::USERINFO)),
                         }
                     })
                     .unwrap_or_default(),
@@ -230,14 +301,8 @@ impl serde::Serialize for ProxyScheme {
                     .map(|(user_name, password)| {
                         format!(
                             "{}:{}@",
-                            percent_encoding::percent_encode(
-                                user_name.as_bytes(),
-                                percent_encoding::NON_ALPHANUMERIC
-                            ),
-                            percent_encoding::percent_encode(
-                                password.as_bytes(),
-                                percent_encoding::NON_ALPHANUMERIC
-                            )
+                            percent::encode(user_name, percent::USERINFO),
+                            percent::encode(password, percent::USERINFO)
                         )
                     })
                     .unwrap_or_default(),
@@ -259,7 +324,7 @@ impl<'de> serde::Deserialize<'de> for ProxyScheme {
     }
 }
 
-fn basic_auth<U, P>(username: U, password: Option<P>) -> HeaderValue
+pub(crate) fn basic_auth<U, P>(username: U, password: Option<P>) -> HeaderValue
 where
     U: std::fmt::Display,
     P: std::fmt::Display,
@@ -321,6 +386,14 @@ mod tests {
                 authority: "127.0.0.1:80".parse().unwrap() // weird but as it is
             }
         );
+        assert_eq!(
+            "https://[::1]".parse::<ProxyScheme>().unwrap(),
+            ProxyScheme::Http {
+                is_https: true,
+                basic_auth: None,
+                authority: "[::1]:443".parse().unwrap()
+            }
+        );
         assert_eq!(
             "https://u:p@127.0.0.1:7890".parse::<ProxyScheme>().unwrap(),
             ProxyScheme::Http {
@@ -380,6 +453,45 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_race_proxies() {
+        let proxies = [
+            "socks5://127.0.0.1:1",
+            "socks5://127.0.0.1:2",
+            "socks5://127.0.0.1:3",
+        ]
+        .map(|proxy| proxy.parse::<ProxyScheme>().unwrap());
+
+        let connect = |proxy: &ProxyScheme| {
+            let port = match proxy {
+                ProxyScheme::Socks5 { port, .. } => *port,
+                ProxyScheme::Http { .. } => 0,
+            };
+
+            async move {
+                match port {
+                    // Stalled, cancelled once another one succeeds.
+                    1 => std::future::pending().await,
+                    2 => Err("connection refused"),
+                    _ => Ok(port),
+                }
+            }
+        };
+
+        let (proxy, conn) = race_proxies(proxies.clone(), 2, connect).await.unwrap();
+        assert_eq!(proxy, proxies[2]);
+        assert_eq!(conn, 3);
+
+        let err = race_proxies(proxies[1..2].to_vec(), 2, connect)
+            .await
+            .unwrap_err();
+        assert_eq!(err.errors, [(proxies[1].clone(), "connection refused")]);
+        assert_eq!(err.to_string(), "all 1 proxy candidates failed");
+
+        let err = race_proxies([], 2, connect).await.unwrap_err();
+        assert!(err.errors.is_empty());
+    }
+
     #[test]
     #[should_panic]
     fn empty_scheme() {
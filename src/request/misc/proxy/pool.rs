@@ -0,0 +1,292 @@
+//! Proxy pool with quality scoring, see [`ProxyPool`].
+
+use std::{
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
+
+use super::ProxyScheme;
+
+/// Default smoothing factor of the EWMA, i.e. the weight of the latest
+/// sample.
+pub const DEFAULT_ALPHA: f64 = 0.2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// Historical stats of a proxy, as exponentially weighted moving averages
+/// (EWMA).
+pub struct ProxyStats {
+    success_rate: f64,
+    latency: Option<Duration>,
+    samples: u64,
+}
+
+impl Default for ProxyStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProxyStats {
+    #[inline]
+    /// Create a new [`ProxyStats`] without any sample, optimistic (success
+    /// rate of 1) so that new proxies get tried.
+    pub const fn new() -> Self {
+        Self {
+            success_rate: 1.0,
+            latency: None,
+            samples: 0,
+        }
+    }
+
+    #[inline]
+    /// Returns the success rate, from 0 to 1.
+    pub const fn success_rate(&self) -> f64 {
+        self.success_rate
+    }
+
+    #[inline]
+    /// Returns the handshake latency of the successful attempts, if any.
+    pub const fn latency(&self) -> Option<Duration> {
+        self.latency
+    }
+
+    #[inline]
+    /// Returns the number of samples recorded.
+    pub const fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    /// Returns the score, the higher the better: the success rate discounted
+    /// by the latency, i.e. `success_rate / (1 + latency in seconds)`.
+    pub fn score(&self) -> f64 {
+        self.success_rate / (1.0 + self.latency.unwrap_or_default().as_secs_f64())
+    }
+
+    /// Record the outcome of an attempt, with the handshake latency if
+    /// succeeded.
+    fn record(&mut self, alpha: f64, latency: Option<Duration>) {
+        let ewma = |average: f64, sample: f64| alpha.mul_add(sample - average, average);
+
+        self.success_rate = ewma(self.success_rate, if latency.is_some() { 1.0 } else { 0.0 });
+
+        if let Some(latency) = latency {
+            self.latency = Some(match self.latency {
+                Some(average) => Duration::try_from_secs_f64(
+                    ewma(average.as_secs_f64(), latency.as_secs_f64()).max(0.0),
+                )
+                .unwrap_or(Duration::MAX),
+                None => latency,
+            });
+        }
+
+        self.samples += 1;
+    }
+}
+
+#[derive(Debug)]
+struct PoolState {
+    stats: Vec<ProxyStats>,
+    cursor: usize,
+}
+
+#[derive(Debug, Clone)]
+/// Pool of proxies, rotated in round-robin, or by the quality scored from
+/// the historical [`ProxyStats`] with [`select_best`](Self::select_best).
+///
+/// Cheap to clone, clones share the stats. With `feat-layer-proxy`, it's a
+/// `ProxySelectorT` selecting the best proxy.
+///
+/// ```rust
+/// # use std::time::Duration;
+/// use miku_http_util::request::misc::proxy::{ProxyPool, ProxyScheme};
+///
+/// let pool = ProxyPool::new(
+///     ["socks5://10.0.0.1:1080", "socks5://10.0.0.2:1080"]
+///         .map(|proxy| proxy.parse::<ProxyScheme>().unwrap()),
+/// );
+///
+/// let slow = pool.select_best().unwrap().clone();
+/// pool.record_success(&slow, Duration::from_secs(2));
+///
+/// let fast = pool.select_best().unwrap().clone();
+/// pool.record_success(&fast, Duration::from_millis(50));
+///
+/// assert_eq!(pool.select_best(), Some(&fast));
+/// ```
+pub struct ProxyPool {
+    proxies: Arc<[ProxyScheme]>,
+    state: Arc<Mutex<PoolState>>,
+    alpha: f64,
+}
+
+impl ProxyPool {
+    /// Create a new [`ProxyPool`], with [`DEFAULT_ALPHA`].
+    pub fn new<I>(proxies: I) -> Self
+    where
+        I: IntoIterator<Item = ProxyScheme>,
+    {
+        let proxies: Arc<[ProxyScheme]> = proxies.into_iter().collect();
+
+        Self {
+            state: Arc::new(Mutex::new(PoolState {
+                stats: vec![ProxyStats::new(); proxies.len()],
+                cursor: 0,
+            })),
+            proxies,
+            alpha: DEFAULT_ALPHA,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Set the smoothing factor of the EWMA (0 to 1), the higher the faster
+    /// the stats follow the latest samples.
+    pub fn with_alpha(self, alpha: f64) -> Self {
+        Self {
+            alpha: alpha.clamp(0.0, 1.0),
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Returns the proxies.
+    pub fn proxies(&self) -> &[ProxyScheme] {
+        &self.proxies
+    }
+
+    /// Returns the next proxy in round-robin.
+    pub fn rotate(&self) -> Option<&ProxyScheme> {
+        if self.proxies.is_empty() {
+            return None;
+        }
+
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let idx = state.cursor % self.proxies.len();
+        state.cursor = idx + 1;
+
+        Some(&self.proxies[idx])
+    }
+
+    /// Returns the proxy of the best [`ProxyStats::score`].
+    ///
+    /// Ties are broken in round-robin, so it's the same as
+    /// [`rotate`](Self::rotate) without any quality data.
+    pub fn select_best(&self) -> Option<&ProxyScheme> {
+        let len = self.proxies.len();
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let best = (0..len)
+            .map(|offset| (state.cursor + offset) % len)
+            .map(|idx| (idx, state.stats[idx].score()))
+            .reduce(|best, current| if current.1 > best.1 { current } else { best })?
+            .0;
+
+        state.cursor = best + 1;
+
+        Some(&self.proxies[best])
+    }
+
+    /// Returns the stats of the proxy, `None` if not in the pool.
+    pub fn stats(&self, proxy: &ProxyScheme) -> Option<ProxyStats> {
+        let idx = self.position(proxy)?;
+
+        Some(
+            self.state
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .stats[idx],
+        )
+    }
+
+    #[inline]
+    /// Record a successful connection through the proxy, with the handshake
+    /// latency. It's a no-op if the proxy is not in the pool.
+    pub fn record_success(&self, proxy: &ProxyScheme, latency: Duration) {
+        self.record(proxy, Some(latency));
+    }
+
+    #[inline]
+    /// Record a failed connection through the proxy, e.g. the errors of
+    /// [`RaceError`](super::RaceError). It's a no-op if the proxy is not in
+    /// the pool.
+    pub fn record_failure(&self, proxy: &ProxyScheme) {
+        self.record(proxy, None);
+    }
+
+    fn record(&self, proxy: &ProxyScheme, latency: Option<Duration>) {
+        if let Some(idx) = self.position(proxy) {
+            self.state
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .stats[idx]
+                .record(self.alpha, latency);
+        }
+    }
+
+    fn position(&self, proxy: &ProxyScheme) -> Option<usize> {
+        self.proxies.iter().position(|p| p == proxy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool() -> ProxyPool {
+        ProxyPool::new(
+            [
+                "socks5://10.0.0.1",
+                "socks5://10.0.0.2",
+                "socks5://10.0.0.3",
+            ]
+            .map(|proxy| proxy.parse::<ProxyScheme>().unwrap()),
+        )
+        .with_alpha(0.5)
+    }
+
+    #[test]
+    fn test_rotate() {
+        let pool = pool();
+
+        let rotated: Vec<_> = (0..4).map(|_| pool.rotate().unwrap().clone()).collect();
+        assert_eq!(rotated[..3], pool.proxies()[..]);
+        assert_eq!(rotated[3], pool.proxies()[0]);
+
+        // Without quality data, the same as round-robin.
+        assert_eq!(pool.select_best(), Some(&pool.proxies()[1]));
+        assert_eq!(ProxyPool::new([]).select_best(), None);
+    }
+
+    #[test]
+    fn test_select_best() {
+        let pool = pool();
+        let [a, b, c] = [0, 1, 2].map(|idx| pool.proxies()[idx].clone());
+
+        pool.record_success(&a, Duration::from_millis(100));
+        pool.record_success(&b, Duration::from_millis(300));
+        pool.record_failure(&c);
+
+        for _ in 0..3 {
+            assert_eq!(pool.select_best(), Some(&a));
+        }
+
+        pool.record_failure(&a);
+        pool.record_failure(&a);
+        assert_eq!(pool.select_best(), Some(&b));
+
+        let stats = pool.stats(&a).unwrap();
+        assert_eq!(stats.samples(), 3);
+        assert!((stats.success_rate() - 0.25).abs() < f64::EPSILON);
+        assert_eq!(stats.latency(), Some(Duration::from_millis(100)));
+
+        let stats = pool.stats(&c).unwrap();
+        assert!((stats.success_rate() - 0.5).abs() < f64::EPSILON);
+        assert_eq!(stats.latency(), None);
+
+        // Saturating rather than panicking.
+        pool.record_success(&c, Duration::MAX);
+        pool.record_success(&c, Duration::MAX);
+        assert_eq!(pool.stats(&c).unwrap().latency(), Some(Duration::MAX));
+    }
+}
@@ -0,0 +1,344 @@
+//! Request canonicalization and replay detection, see
+//! [`canonical_request_fingerprint`] and [`ReplayGuard`].
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Mutex, PoisonError},
+    time::{Duration, SystemTime},
+};
+
+use http::{HeaderMap, HeaderName, HeaderValue, Method, Uri};
+use sha2::{Digest, Sha256};
+
+use crate::percent;
+
+/// SHA-256 fingerprint of the canonical request.
+pub type Fingerprint = [u8; 32];
+
+/// Returns the headers of the names, in the given order, for
+/// [`canonical_request_fingerprint`].
+pub fn select_headers<'a>(
+    headers: &'a HeaderMap,
+    names: &'a [HeaderName],
+) -> impl Iterator<Item = (&'a HeaderName, &'a HeaderValue)> {
+    names
+        .iter()
+        .flat_map(move |name| headers.get_all(name).iter().map(move |value| (name, value)))
+}
+
+/// Compute the SHA-256 fingerprint of the canonical request, i.e. lines of:
+///
+/// - the uppercase method;
+/// - the lowercase authority (empty if missing);
+/// - the path;
+/// - the query pairs, decoded (`+` as space), sorted (by key, then value),
+///   re-encoded with [`percent::QUERY`] and joined with `&`;
+/// - each selected header as `name:value` with the value trimmed, sorted by
+///   name, values of the same name joined with `,` in the original order;
+/// - the lowercase hex of `body_hash`.
+///
+/// It's stable across processes and independent of the query / header order,
+/// so put the nonce or timestamp in the query or selected headers.
+pub fn canonical_request_fingerprint<'a, I>(
+    method: &Method,
+    uri: &Uri,
+    selected_headers: I,
+    body_hash: &[u8],
+) -> Fingerprint
+where
+    I: IntoIterator<Item = (&'a HeaderName, &'a HeaderValue)>,
+{
+    let mut hasher = Sha256::new();
+
+    let mut line = |data: &[u8]| {
+        hasher.update(data);
+        hasher.update(b"\n");
+    };
+
+    line(method.as_str().to_ascii_uppercase().as_bytes());
+    line(
+        uri.authority()
+            .map(|authority| authority.as_str().to_ascii_lowercase())
+            .unwrap_or_default()
+            .as_bytes(),
+    );
+    line(uri.path().as_bytes());
+
+    // Decoded the same way as the signature is verified, so that re-encoding
+    // does not change the fingerprint.
+    let mut query: Vec<_> = uri
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (k, v) = pair.split_once('=').unwrap_or((pair, ""));
+
+            let (mut key, mut value) = (String::new(), String::new());
+            percent::decode_form_to(k, &mut key);
+            percent::decode_form_to(v, &mut value);
+
+            (key, value)
+        })
+        .collect();
+    query.sort_unstable();
+    line(
+        query
+            .iter()
+            .map(|(k, v)| {
+                format!(
+                    "{}={}",
+                    percent::encode(k, percent::QUERY),
+                    percent::encode(v, percent::QUERY)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("&")
+            .as_bytes(),
+    );
+
+    let mut headers: Vec<(&HeaderName, Vec<u8>)> = Vec::new();
+    for (name, value) in selected_headers {
+        let value = value.as_bytes().trim_ascii();
+
+        match headers.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, joined)) => {
+                joined.push(b',');
+                joined.extend_from_slice(value);
+            }
+            None => headers.push((name, value.to_vec())),
+        }
+    }
+    headers.sort_by(|(l, _), (r, _)| l.as_str().cmp(r.as_str()));
+
+    for (name, value) in headers {
+        let mut header = name.as_str().as_bytes().to_vec();
+        header.push(b':');
+        header.extend_from_slice(&value);
+
+        line(&header);
+    }
+
+    let body_hash: String = body_hash.iter().map(|b| format!("{b:02x}")).collect();
+    hasher.update(body_hash.as_bytes());
+
+    hasher.finalize().into()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(thiserror::Error)]
+/// Error returned by [`ReplayGuard::check`].
+pub enum ReplayError {
+    #[error("replayed request")]
+    /// The request has been seen within the window.
+    Replayed,
+
+    #[error("replay guard is full")]
+    /// The guard is full of fingerprints within the window, so the request
+    /// can't be remembered.
+    Full,
+}
+
+#[derive(Debug)]
+/// Anti-replay guard, remembering the fingerprints seen within the window.
+///
+/// At most `capacity` fingerprints are remembered. Fingerprints within the
+/// window are never evicted, so that replays can't slip through by flooding
+/// the guard: when full, new requests are rejected with
+/// [`ReplayError::Full`] until the oldest ones expire. Size it for the peak
+/// request rate times the window.
+pub struct ReplayGuard {
+    window: Duration,
+    capacity: usize,
+    inner: Mutex<ReplayGuardInner>,
+}
+
+#[derive(Debug, Default)]
+struct ReplayGuardInner {
+    seen: HashMap<Fingerprint, SystemTime>,
+    order: VecDeque<(Fingerprint, SystemTime)>,
+}
+
+impl ReplayGuard {
+    #[inline]
+    /// Create a new [`ReplayGuard`].
+    pub fn new(window: Duration, capacity: usize) -> Self {
+        Self {
+            window,
+            capacity: capacity.max(1),
+            inner: Mutex::default(),
+        }
+    }
+
+    #[inline]
+    /// Check the fingerprint, remembering it if not seen within the window.
+    ///
+    /// # Errors
+    ///
+    /// - [`ReplayError::Replayed`] if seen within the window.
+    /// - [`ReplayError::Full`] if the guard is full.
+    pub fn check(&self, fingerprint: Fingerprint) -> Result<(), ReplayError> {
+        self.check_at(fingerprint, crate::time::now())
+    }
+
+    /// Like [`check`](Self::check), at the given time.
+    ///
+    /// # Errors
+    ///
+    /// See [`check`](Self::check).
+    pub fn check_at(&self, fingerprint: Fingerprint, now: SystemTime) -> Result<(), ReplayError> {
+        let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+
+        // Evict the expired ones.
+        while let Some(&(oldest, seen_at)) = inner.order.front() {
+            let expired = now
+                .duration_since(seen_at)
+                .is_ok_and(|elapsed| elapsed >= self.window);
+
+            if !expired {
+                break;
+            }
+
+            inner.order.pop_front();
+
+            if inner.seen.get(&oldest) == Some(&seen_at) {
+                inner.seen.remove(&oldest);
+            }
+        }
+
+        if inner.seen.contains_key(&fingerprint) {
+            return Err(ReplayError::Replayed);
+        }
+
+        // Fail closed, the remaining ones are all within the window.
+        if inner.order.len() >= self.capacity {
+            #[cfg(feature = "feat-tracing")]
+            tracing::warn!("Replay guard is full, rejecting the request");
+
+            return Err(ReplayError::Full);
+        }
+
+        inner.seen.insert(fingerprint, now);
+        inner.order.push_back((fingerprint, now));
+
+        Ok(())
+    }
+
+    /// Returns the number of remembered fingerprints.
+    pub fn len(&self) -> usize {
+        self.inner
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .seen
+            .len()
+    }
+
+    #[inline]
+    /// Returns `true` if nothing is remembered.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::UNIX_EPOCH;
+
+    use super::*;
+
+    #[test]
+    fn test_canonical_request_fingerprint() {
+        let names = [
+            HeaderName::from_static("x-nonce"),
+            HeaderName::from_static("x-date"),
+        ];
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-date", HeaderValue::from_static(" 20240101 "));
+        headers.insert("x-nonce", HeaderValue::from_static("abc"));
+        headers.insert("x-ignored", HeaderValue::from_static("1"));
+
+        let fingerprint = canonical_request_fingerprint(
+            &Method::POST,
+            &Uri::from_static("https://Example.com/a?b=2&a=1"),
+            select_headers(&headers, &names),
+            b"\x01\x02",
+        );
+
+        let mut reordered = HeaderMap::new();
+        reordered.insert("x-nonce", HeaderValue::from_static("abc"));
+        reordered.insert("x-date", HeaderValue::from_static("20240101"));
+
+        assert_eq!(
+            fingerprint,
+            canonical_request_fingerprint(
+                &Method::POST,
+                &Uri::from_static("https://example.com/a?a=1&b=2"),
+                select_headers(&reordered, &names[..]),
+                b"\x01\x02",
+            )
+        );
+        assert_ne!(
+            fingerprint,
+            canonical_request_fingerprint(
+                &Method::POST,
+                &Uri::from_static("https://example.com/a?a=1&b=3"),
+                select_headers(&reordered, &names[..]),
+                b"\x01\x02",
+            )
+        );
+
+        // Re-encoded copies of the same request.
+        let fingerprint = |uri: &'static str| {
+            canonical_request_fingerprint(&Method::GET, &Uri::from_static(uri), [], b"")
+        };
+        let expected = fingerprint("/a?nonce=abc&q=a+b");
+        for uri in [
+            "/a?nonce=%61bc&q=a+b",
+            "/a?n%6Fnce=abc&q=a%20b",
+            "/a?q=a%20b&nonce=abc",
+        ] {
+            assert_eq!(fingerprint(uri), expected, "{uri}");
+        }
+        assert_ne!(fingerprint("/a?nonce=abd&q=a+b"), expected);
+    }
+
+    #[test]
+    fn test_replay_guard() {
+        let guard = ReplayGuard::new(Duration::from_secs(60), 2);
+        let t0 = UNIX_EPOCH + Duration::from_secs(1_000);
+
+        assert_eq!(guard.check_at([1; 32], t0), Ok(()));
+        assert_eq!(guard.check_at([1; 32], t0), Err(ReplayError::Replayed));
+        assert_eq!(
+            guard.check_at([1; 32], t0 + Duration::from_secs(60)),
+            Ok(())
+        );
+
+        assert_eq!(
+            guard.check_at([2; 32], t0 + Duration::from_secs(61)),
+            Ok(())
+        );
+        assert_eq!(guard.len(), 2);
+
+        // Full, never evicted within the window.
+        assert_eq!(
+            guard.check_at([3; 32], t0 + Duration::from_secs(62)),
+            Err(ReplayError::Full)
+        );
+        assert_eq!(
+            guard.check_at([2; 32], t0 + Duration::from_secs(63)),
+            Err(ReplayError::Replayed)
+        );
+
+        // The one seen at `t0 + 60s` expired.
+        assert_eq!(
+            guard.check_at([3; 32], t0 + Duration::from_secs(120)),
+            Ok(())
+        );
+        assert_eq!(
+            guard.check_at([2; 32], t0 + Duration::from_secs(120)),
+            Err(ReplayError::Replayed)
+        );
+    }
+}
@@ -1,16 +1,71 @@
 //! HTTP request utilities: parser related.
 
+#[cfg(feature = "feat-request-parser-ext-coerce")]
+pub mod coerce;
 #[cfg(any(feature = "feat-integrate-axum", feature = "feat-integrate-tower"))]
 pub mod integration;
+pub mod nested;
+#[cfg(feature = "feat-request-parser-ext-serde")]
+pub mod serde_helper;
+#[cfg(feature = "feat-request-parser-ext-signed")]
+pub mod signed;
+#[cfg(any(feature = "feat-integrate-axum", feature = "feat-integrate-tower"))]
+pub mod testing;
+
+use alloc::{borrow::Cow, string::String, sync::Arc, vec::Vec};
+use core::{borrow::Borrow, hash::Hash, ops};
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+#[cfg(not(feature = "std"))]
+use hashbrown::HashMap;
+
+/// Implement [`From`], [`Borrow`], [`Deref`](ops::Deref),
+/// [`DerefMut`](ops::DerefMut), [`AsRef`] and `new` for the wrapper type.
+macro_rules! impl_wrapper {
+    ($name:ident$(<$lt:lifetime>)?, $inner:ty) => {
+        impl$(<$lt>)? From<$inner> for $name$(<$lt>)? {
+            #[inline]
+            fn from(inner: $inner) -> Self {
+                Self { inner }
+            }
+        }
+
+        impl$(<$lt>)? Borrow<$inner> for $name$(<$lt>)? {
+            fn borrow(&self) -> &$inner {
+                &self.inner
+            }
+        }
+
+        impl$(<$lt>)? ops::Deref for $name$(<$lt>)? {
+            type Target = $inner;
 
-use std::{
-    borrow::{Borrow, Cow},
-    collections::HashMap,
-    hash::Hash,
-    sync::Arc,
-};
+            fn deref(&self) -> &Self::Target {
+                &self.inner
+            }
+        }
+
+        impl$(<$lt>)? ops::DerefMut for $name$(<$lt>)? {
+            fn deref_mut(&mut self) -> &mut Self::Target {
+                &mut self.inner
+            }
+        }
+
+        impl$(<$lt>)? AsRef<$inner> for $name$(<$lt>)? {
+            fn as_ref(&self) -> &$inner {
+                &self.inner
+            }
+        }
 
-use macro_toolset::wrapper;
+        impl$(<$lt>)? $name$(<$lt>)? {
+            #[inline]
+            #[doc = concat!("Creates a new instance of [`", stringify!($name), "`]")]
+            pub const fn new(inner: $inner) -> Self {
+                Self { inner }
+            }
+        }
+    };
+}
 
 #[deprecated(
     since = "0.6.0",
@@ -26,14 +81,20 @@ pub type Queries<'q> = Query<'q>;
 /// Renamed and deprecated, use [`OwnedQuery`] instead.
 pub type OwnedQueries = OwnedQuery;
 
-wrapper! {
-    #[derive(Debug, Clone)]
-    /// Helper for query string parsing.
-    ///
-    /// You may also need [`OwnedQuery`].
-    pub Query<'q>(HashMap<Cow<'q, str>, Cow<'q, str>, foldhash::fast::RandomState>)
+#[derive(Debug, Clone)]
+#[repr(transparent)]
+/// Helper for query string parsing.
+///
+/// You may also need [`OwnedQuery`].
+pub struct Query<'q> {
+    inner: HashMap<Cow<'q, str>, Cow<'q, str>, foldhash::fast::RandomState>,
 }
 
+impl_wrapper!(
+    Query<'q>,
+    HashMap<Cow<'q, str>, Cow<'q, str>, foldhash::fast::RandomState>
+);
+
 impl<'q> Query<'q> {
     #[cfg(feature = "feat-integrate-http")]
     #[inline]
@@ -45,44 +106,58 @@ impl<'q> Query<'q> {
     #[inline]
     /// Parse query string.
     pub fn parse(query: &'q str) -> Self {
-        use fluent_uri::encoding::{encoder::IQuery, EStr};
-
         Self {
-            inner: EStr::<IQuery>::new(query)
-                .unwrap_or({
-                    #[cfg(feature = "feat-tracing")]
-                    tracing::warn!("Failed to parse `{query}`");
-
-                    EStr::EMPTY
-                })
-                .split('&')
-                .map(|pair| {
-                    pair.split_once('=').unwrap_or({
-                        #[cfg(feature = "feat-tracing")]
-                        tracing::warn!("Failed to split query pair: {:?}", pair);
-
-                        (pair, EStr::EMPTY)
-                    })
-                })
-                .map(|(k, v)| {
-                    (
-                        k.decode().into_string_lossy(),
-                        v.decode().into_string_lossy(),
-                    )
-                })
-                .collect::<HashMap<_, _, _>>(),
+            inner: QueryPairs::new(query).collect(),
         }
     }
-}
 
-wrapper! {
-    #[derive(Debug, Clone)]
-    /// Helper for query string parsing.
+    #[cfg(feature = "feat-request-builder")]
+    #[inline]
+    /// Convert to the query string builder, see
+    /// [`from_query`](crate::request::builder::Query::from_query).
+    pub fn to_queries(&self) -> crate::request::builder::Query<'q> {
+        crate::request::builder::Query::from_query(self.clone())
+    }
+
+    #[inline]
+    /// Deterministic hash over the sorted pairs whose key is in `include`,
+    /// e.g. for building response caches keyed by normalized query params.
+    ///
+    /// See [`stable_hash`](Self::stable_hash) for the stability guarantee.
+    pub fn cache_key(&self, include: &[&str]) -> u64 {
+        stable_hash(
+            self.iter()
+                .map(|(k, v)| (&**k, &**v))
+                .filter(|(k, _)| include.contains(k)),
+        )
+    }
+
+    #[inline]
+    /// Deterministic hash over all the sorted pairs.
     ///
-    /// You may also need [`Query`] if you just want a borrowed version.
-    pub OwnedQuery(Arc<HashMap<Arc<str>, Arc<str>, foldhash::fast::RandomState>>)
+    /// The hash is independent of the order of pairs in the original query
+    /// string. It's 64-bit FNV-1a of the length-prefixed keys and values, so
+    /// stable across processes, platforms and versions of this crate, e.g.
+    /// for keys of shared or persistent caches.
+    pub fn stable_hash(&self) -> u64 {
+        stable_hash(self.iter().map(|(k, v)| (&**k, &**v)))
+    }
+}
+
+#[derive(Debug, Clone)]
+#[repr(transparent)]
+/// Helper for query string parsing.
+///
+/// You may also need [`Query`] if you just want a borrowed version.
+pub struct OwnedQuery {
+    inner: Arc<HashMap<Arc<str>, Arc<str>, foldhash::fast::RandomState>>,
 }
 
+impl_wrapper!(
+    OwnedQuery,
+    Arc<HashMap<Arc<str>, Arc<str>, foldhash::fast::RandomState>>
+);
+
 impl OwnedQuery {
     #[cfg(feature = "feat-integrate-http")]
     #[inline]
@@ -130,33 +205,331 @@ impl OwnedQuery {
     #[inline]
     /// Parse query string.
     pub fn parse(query: &str) -> Self {
-        use fluent_uri::encoding::{encoder::IQuery, EStr};
+        Self::parse_checked(query).0
+    }
 
-        Self {
-            inner: EStr::<IQuery>::new(query)
-                .unwrap_or({
-                    #[cfg(feature = "feat-tracing")]
-                    tracing::warn!("Failed to parse `{query}`");
+    /// Parse query string, also returning the first duplicate key, if any.
+    pub(crate) fn parse_checked(query: &str) -> (Self, Option<Arc<str>>) {
+        let mut inner = HashMap::default();
+        let mut duplicate = None;
 
-                    EStr::EMPTY
-                })
-                .split('&')
-                .map(|pair| {
-                    pair.split_once('=').unwrap_or({
-                        #[cfg(feature = "feat-tracing")]
-                        tracing::warn!("Failed to split query pair: {:?}", pair);
-
-                        (pair, EStr::EMPTY)
-                    })
+        for (k, v) in QueryPairs::new(query) {
+            let k: Arc<str> = k.into();
+
+            if inner.insert(k.clone(), v.into()).is_some() {
+                duplicate.get_or_insert(k);
+            }
+        }
+
+        (
+            Self {
+                inner: Arc::new(inner),
+            },
+            duplicate,
+        )
+    }
+
+    #[inline]
+    /// Returns a mutable reference to the inner map, cloning it first if it's
+    /// shared (copy-on-write), so the other clones are not affected.
+    ///
+    /// This allows middleware to enrich the parsed query (e.g. inject a tenant
+    /// ID) before the handlers read it, e.g. through
+    /// `req.extensions_mut().get_mut::<ParseQueryResult>()`.
+    pub fn to_mut(&mut self) -> &mut HashMap<Arc<str>, Arc<str>, foldhash::fast::RandomState> {
+        Arc::make_mut(&mut self.inner)
+    }
+
+    #[inline]
+    /// Insert a pair, returning the previous value of the key if any.
+    ///
+    /// See [`to_mut`](Self::to_mut).
+    pub fn insert(&mut self, k: impl Into<Arc<str>>, v: impl Into<Arc<str>>) -> Option<Arc<str>> {
+        self.to_mut().insert(k.into(), v.into())
+    }
+
+    #[allow(clippy::multiple_bound_locations)]
+    #[inline]
+    /// Remove a key, returning its value if any.
+    ///
+    /// The inner map is not cloned if the key is absent. See
+    /// [`to_mut`](Self::to_mut).
+    pub fn remove<Q: ?Sized>(&mut self, k: &Q) -> Option<Arc<str>>
+    where
+        Arc<str>: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        if !self.inner.contains_key(k) {
+            return None;
+        }
+
+        self.to_mut().remove(k)
+    }
+
+    #[cfg(feature = "feat-request-builder")]
+    /// Convert to the query string builder, see [`Query::to_queries`].
+    pub fn to_queries(&self) -> crate::request::builder::Query<'static> {
+        let mut pairs: Vec<(Cow<'static, str>, Cow<'static, str>)> = self
+            .iter()
+            .map(|(k, v)| (Cow::Owned((**k).into()), Cow::Owned((**v).into())))
+            .collect();
+        pairs.sort_unstable();
+
+        pairs.into_iter().fold(
+            crate::request::builder::Query::with_capacity(self.len()),
+            |query, (k, v)| query.push(k, v),
+        )
+    }
+
+    #[inline]
+    /// See [`Query::cache_key`].
+    pub fn cache_key(&self, include: &[&str]) -> u64 {
+        stable_hash(
+            self.iter()
+                .map(|(k, v)| (&**k, &**v))
+                .filter(|(k, _)| include.contains(k)),
+        )
+    }
+
+    #[inline]
+    /// See [`Query::stable_hash`].
+    pub fn stable_hash(&self) -> u64 {
+        stable_hash(self.iter().map(|(k, v)| (&**k, &**v)))
+    }
+}
+
+/// Hash the sorted pairs with 64-bit FNV-1a, each key and value prefixed with
+/// its length (as little-endian `u64`) to avoid ambiguity.
+///
+/// Not with [`Hash`], whose output is not guaranteed to be stable across
+/// versions of Rust or the hasher.
+fn stable_hash<'a>(pairs: impl Iterator<Item = (&'a str, &'a str)>) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    let mut pairs: Vec<_> = pairs.collect();
+    pairs.sort_unstable();
+
+    pairs
+        .iter()
+        .flat_map(|(k, v)| [k.as_bytes(), v.as_bytes()])
+        .fold(OFFSET_BASIS, |hash, bytes| {
+            (bytes.len() as u64)
+                .to_le_bytes()
+                .iter()
+                .chain(bytes)
+                .fold(hash, |hash, &byte| {
+                    (hash ^ u64::from(byte)).wrapping_mul(PRIME)
                 })
-                .map(|(k, v)| {
-                    (
-                        k.decode().into_string_lossy().into(),
-                        v.decode().into_string_lossy().into(),
-                    )
+        })
+}
+
+#[derive(Debug, Clone)]
+/// Iterator over the decoded key-value pairs of a query string.
+///
+/// Scans bytes with `memchr` for `&` and `=`, and only decodes the keys or
+/// values which actually contain `%` or `+` (space, as
+/// `application/x-www-form-urlencoded`), the others are borrowed from the
+/// query string as is.
+struct QueryPairs<'q> {
+    rest: Option<&'q str>,
+}
+
+impl<'q> QueryPairs<'q> {
+    #[inline]
+    fn new(query: &'q str) -> Self {
+        use fluent_uri::encoding::{encoder::IQuery, EStr};
+
+        let query = if EStr::<IQuery>::new(query).is_some() {
+            query
+        } else {
+            #[cfg(feature = "feat-tracing")]
+            tracing::warn!("Failed to parse `{query}`");
+
+            ""
+        };
+
+        Self { rest: Some(query) }
+    }
+
+    #[inline]
+    fn decode(encoded: &'q str) -> Cow<'q, str> {
+        if memchr::memchr2(b'%', b'+', encoded.as_bytes()).is_none() {
+            return Cow::Borrowed(encoded);
+        }
+
+        // The whole query has been validated, no need to do it again here.
+        let mut decoded = String::with_capacity(encoded.len());
+        crate::percent::decode_form_to(encoded, &mut decoded);
+
+        Cow::Owned(decoded)
+    }
+}
+
+impl<'q> Iterator for QueryPairs<'q> {
+    type Item = (Cow<'q, str>, Cow<'q, str>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = self.rest?;
+
+        let pair = match memchr::memchr(b'&', rest.as_bytes()) {
+            Some(idx) => {
+                self.rest = Some(&rest[idx + 1..]);
+                &rest[..idx]
+            }
+            None => {
+                self.rest = None;
+                rest
+            }
+        };
+
+        let (k, v) = match memchr::memchr(b'=', pair.as_bytes()) {
+            Some(idx) => (&pair[..idx], &pair[idx + 1..]),
+            None => {
+                #[cfg(feature = "feat-tracing")]
+                tracing::warn!("Failed to split query pair: {:?}", pair);
+
+                (pair, "")
+            }
+        };
+
+        Some((Self::decode(k), Self::decode(v)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let query = Query::parse("a=1&b=%E4%BD%A0%E5%A5%BD&c&d=x=y&=e");
+
+        assert!(matches!(query.get("a"), Some(Cow::Borrowed("1"))));
+        assert!(matches!(query.get("b"), Some(Cow::Owned(v)) if v == "你好"));
+        assert_eq!(query.get("c").map(|v| &**v), Some(""));
+        assert_eq!(query.get("d").map(|v| &**v), Some("x=y"));
+        assert_eq!(query.get("").map(|v| &**v), Some("e"));
+
+        let query = Query::parse("q=a+b%2B&a+b=1");
+        assert_eq!(query.get("q").map(|v| &**v), Some("a b+"));
+        assert_eq!(query.get("a b").map(|v| &**v), Some("1"));
+
+        let query = OwnedQuery::parse("a=1&b=%E4%BD%A0%E5%A5%BD");
+        assert_eq!(query.get("a"), Some("1"));
+        assert_eq!(query.get("b"), Some("你好"));
+
+        // invalid query
+        assert_eq!(OwnedQuery::parse("a=1&b=[").len(), 1);
+    }
+
+    #[test]
+    fn test_stable_hash() {
+        let query = Query::parse("a=1&b=2&ts=100");
+        let owned_query = OwnedQuery::parse("ts=200&b=2&a=1");
+
+        assert_eq!(
+            query.cache_key(&["a", "b"]),
+            owned_query.cache_key(&["b", "a"])
+        );
+        assert_ne!(query.stable_hash(), owned_query.stable_hash());
+        assert_eq!(
+            query.stable_hash(),
+            OwnedQuery::parse("ts=100&a=1&b=2").stable_hash()
+        );
+        assert_ne!(query.cache_key(&["a"]), query.cache_key(&["b"]));
+
+        // Pinned, must not change across versions.
+        assert_eq!(Query::parse("b=2&a=1").stable_hash(), 0x4fa0_8d85_903c_5b2b);
+        assert_ne!(
+            Query::parse("a=1b").stable_hash(),
+            Query::parse("a1=b").stable_hash()
+        );
+    }
+
+    #[test]
+    fn test_copy_on_write() {
+        let query = OwnedQuery::parse("a=1&b=2");
+
+        let mut enriched = query.clone();
+        assert_eq!(enriched.insert("tenant", "x"), None);
+        assert_eq!(enriched.insert("a", "3").as_deref(), Some("1"));
+        assert_eq!(enriched.remove("b").as_deref(), Some("2"));
+        assert_eq!(enriched.remove("c"), None);
+
+        assert_eq!(enriched.get("tenant"), Some("x"));
+        assert_eq!(enriched.get("a"), Some("3"));
+        assert_eq!(enriched.get("b"), None);
+
+        assert_eq!(query.get("a"), Some("1"));
+        assert_eq!(query.get("b"), Some("2"));
+        assert_eq!(query.get("tenant"), None);
+    }
+
+    #[cfg(feature = "feat-request-builder")]
+    #[test]
+    fn test_round_trip() {
+        use crate::request::builder::Md5Signer;
+
+        // Pseudo-random values covering reserved and non-ASCII characters, keys
+        // are not encoded by the builder.
+        const ALPHABET: &[char] = &['a', 'Z', '0', ' ', '&', '=', '%', '+', '?', '#', '/', '你'];
+
+        let mut seed = 0x2545_f491_u32;
+        let mut next = |len: usize| -> String {
+            (0..len)
+                .map(|_| {
+                    seed ^= seed << 13;
+                    seed ^= seed >> 17;
+                    seed ^= seed << 5;
+                    ALPHABET[seed as usize % ALPHABET.len()]
                 })
-                .collect::<HashMap<_, _, _>>()
-                .into(),
+                .collect()
+        };
+
+        for round in 0..64 {
+            let pairs: Vec<(String, String)> = (0..round % 5 + 1)
+                .map(|idx| (format!("k{idx}_{round}"), next(round % 7)))
+                .collect();
+
+            let built = pairs
+                .iter()
+                .fold(
+                    crate::request::builder::Query::with_capacity(8),
+                    |query, (k, v)| query.push(k.as_str(), v.as_str()),
+                )
+                .build();
+
+            let parsed = Query::parse(&built);
+            assert_eq!(parsed.len(), pairs.len(), "{built}");
+            for (k, v) in &pairs {
+                assert_eq!(parsed.get(k.as_str()).map(|v| &**v), Some(v.as_str()));
+            }
+
+            let rebuilt = parsed.to_queries().build();
+            assert_eq!(Query::parse(&rebuilt).stable_hash(), parsed.stable_hash());
+            assert_eq!(
+                OwnedQuery::parse(&built).to_queries().build(),
+                rebuilt,
+                "sorted, so deterministic"
+            );
         }
+
+        // Modify and re-sign in one chain.
+        let signer = Md5Signer::new_default().with_suffix_salt(Some("0123456789abcdef"));
+        let mut parsed = Query::parse("test2=2&sign=stale");
+        parsed.remove("sign");
+
+        let resigned = parsed
+            .to_queries()
+            .push_any("test1", 1)
+            .sorted()
+            .build_signed(signer)
+            .unwrap();
+
+        assert_eq!(
+            resigned,
+            "test1=1&test2=2&sign=cc4f5844a6a1893a88d648cebba5462f"
+        );
     }
 }
@@ -0,0 +1,303 @@
+//! HTTP request utilities: coercion of raw query values into canonical forms.
+//!
+//! Public APIs usually accept `yes` / `1` / `on` as `true`, and enum values in
+//! any case, while typed deserialization (e.g. serde) is strict. Apply a
+//! [`CoercionTable`] before deserializing:
+//!
+//! ```rust
+//! # use miku_http_util::request::parser::{coerce::{Coercion, CoercionTable}, OwnedQuery};
+//! static TABLE: CoercionTable = CoercionTable::new(&[
+//!     ("verbose", Coercion::Bool),
+//!     ("sort", Coercion::Enum(&["asc", "desc"])),
+//! ]);
+//!
+//! let mut query = OwnedQuery::parse("verbose=Yes&sort=DESC");
+//! TABLE.apply(&mut query).unwrap();
+//!
+//! assert_eq!(query.get("verbose"), Some("true"));
+//! assert_eq!(query.get("sort"), Some("desc"));
+//!
+//! let err = TABLE.apply(&mut OwnedQuery::parse("sort=dessc")).unwrap_err();
+//! assert_eq!(
+//!     err.to_string(),
+//!     "invalid value `dessc` of `sort`, expected one of [\"asc\", \"desc\"], did you mean `desc`?"
+//! );
+//! ```
+
+use std::sync::Arc;
+
+use super::{nested::QueryValue, OwnedQuery};
+
+/// Raw values accepted as `true`, case-insensitive.
+pub const TRUTHY: &[&str] = &["true", "1", "yes", "y", "on"];
+
+/// Raw values accepted as `false`, case-insensitive.
+pub const FALSY: &[&str] = &["false", "0", "no", "n", "off"];
+
+#[derive(Debug)]
+#[derive(thiserror::Error)]
+/// Error coercing the query value.
+pub enum CoercionError {
+    #[error("invalid value `{value}` of `{key}`, expected a boolean")]
+    /// The value is in neither [`TRUTHY`] nor [`FALSY`].
+    InvalidBool {
+        /// The query key
+        key: &'static str,
+
+        /// The raw value
+        value: String,
+    },
+
+    #[error(
+        "invalid value `{value}` of `{key}`, expected one of {expected:?}{}",
+        DidYouMean(*.suggestion)
+    )]
+    /// The value matches none of the variants.
+    UnknownVariant {
+        /// The query key
+        key: &'static str,
+
+        /// The raw value
+        value: String,
+
+        /// The accepted variants
+        expected: &'static [&'static str],
+
+        /// The closest variant, if it's likely a typo
+        suggestion: Option<&'static str>,
+    },
+}
+
+struct DidYouMean(Option<&'static str>);
+
+impl std::fmt::Display for DidYouMean {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(suggestion) => write!(f, ", did you mean `{suggestion}`?"),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// How to coerce the raw value.
+pub enum Coercion {
+    /// [`TRUTHY`] to `true` and [`FALSY`] to `false`.
+    Bool,
+
+    /// Case-insensitive matching of the variants, coerced to the variant as
+    /// is.
+    Enum(&'static [&'static str]),
+}
+
+impl Coercion {
+    /// Coerce the raw value of the given key into the canonical form.
+    ///
+    /// # Errors
+    ///
+    /// See [`CoercionError`].
+    pub fn coerce(&self, key: &'static str, value: &str) -> Result<&'static str, CoercionError> {
+        match *self {
+            Self::Bool => {
+                let matches =
+                    |values: &[&str]| values.iter().any(|v| v.eq_ignore_ascii_case(value));
+
+                if matches(TRUTHY) {
+                    Ok("true")
+                } else if matches(FALSY) {
+                    Ok("false")
+                } else {
+                    Err(CoercionError::InvalidBool {
+                        key,
+                        value: value.to_owned(),
+                    })
+                }
+            }
+            Self::Enum(variants) => variants
+                .iter()
+                .find(|variant| variant.eq_ignore_ascii_case(value))
+                .copied()
+                .ok_or_else(|| CoercionError::UnknownVariant {
+                    key,
+                    value: value.to_owned(),
+                    expected: variants,
+                    suggestion: suggest(value, variants),
+                }),
+        }
+    }
+}
+
+/// The closest variant by (case-insensitive) edit distance, if close enough to
+/// be a typo, i.e. within a third of the length, at least 1.
+fn suggest(value: &str, variants: &'static [&'static str]) -> Option<&'static str> {
+    let value = value.to_ascii_lowercase();
+
+    variants
+        .iter()
+        .map(|variant| (*variant, distance(&value, &variant.to_ascii_lowercase())))
+        .filter(|&(variant, distance)| distance <= (variant.chars().count() / 3).max(1))
+        .min_by_key(|&(_, distance)| distance)
+        .map(|(variant, _)| variant)
+}
+
+/// Levenshtein distance.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+
+        for (j, &cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if ca == cb {
+                prev
+            } else {
+                1 + prev.min(row[j]).min(current)
+            };
+            prev = current;
+        }
+    }
+
+    row[b.len()]
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Table of query keys and how to coerce their values.
+///
+/// Keys not in the table are left untouched.
+pub struct CoercionTable {
+    entries: &'static [(&'static str, Coercion)],
+}
+
+impl CoercionTable {
+    #[inline]
+    /// Create a new [`CoercionTable`].
+    pub const fn new(entries: &'static [(&'static str, Coercion)]) -> Self {
+        Self { entries }
+    }
+
+    #[inline]
+    /// Returns the [`Coercion`] of the given key, if any.
+    pub fn get(&self, key: &str) -> Option<Coercion> {
+        self.entries
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|&(_, coercion)| coercion)
+    }
+
+    /// Coerce the values of [`OwnedQuery`] in place.
+    ///
+    /// # Errors
+    ///
+    /// The first [`CoercionError`], the query is left partially coerced.
+    pub fn apply(&self, query: &mut OwnedQuery) -> Result<(), CoercionError> {
+        for &(key, coercion) in self.entries {
+            let Some(value) = query.get(key) else {
+                continue;
+            };
+
+            let coerced = coercion.coerce(key, value)?;
+
+            if coerced != value {
+                query.insert(key, Arc::<str>::from(coerced));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Coerce the top-level values of the nested query (a
+    /// [`QueryValue::Map`]) in place, including the items of the lists, e.g.
+    /// `status[]=Active&status[]=closed`.
+    ///
+    /// # Errors
+    ///
+    /// The first [`CoercionError`], the query is left partially coerced.
+    pub fn apply_nested(&self, query: &mut QueryValue) -> Result<(), CoercionError> {
+        let QueryValue::Map(map) = query else {
+            return Ok(());
+        };
+
+        for &(key, coercion) in self.entries {
+            let values = match map.get_mut(key) {
+                Some(QueryValue::List(list)) => list.iter_mut().collect(),
+                Some(value) => vec![value],
+                None => continue,
+            };
+
+            for value in values {
+                if let QueryValue::String(s) = value {
+                    *s = coercion.coerce(key, s)?.to_owned();
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TABLE: CoercionTable = CoercionTable::new(&[
+        ("active", Coercion::Bool),
+        ("status", Coercion::Enum(&["Open", "Closed", "Archived"])),
+    ]);
+
+    #[test]
+    fn test_coerce() {
+        let mut query = OwnedQuery::parse("active=ON&status=closed&other=yes");
+        TABLE.apply(&mut query).unwrap();
+        assert_eq!(query.get("active"), Some("true"));
+        assert_eq!(query.get("status"), Some("Closed"));
+        assert_eq!(query.get("other"), Some("yes"));
+
+        assert!(matches!(
+            TABLE
+                .apply(&mut OwnedQuery::parse("active=maybe"))
+                .unwrap_err(),
+            CoercionError::InvalidBool { key: "active", .. }
+        ));
+        assert!(matches!(
+            TABLE
+                .apply(&mut OwnedQuery::parse("status=clsoed"))
+                .unwrap_err(),
+            CoercionError::UnknownVariant {
+                suggestion: Some("Closed"),
+                ..
+            }
+        ));
+        assert!(matches!(
+            TABLE
+                .apply(&mut OwnedQuery::parse("status=pending"))
+                .unwrap_err(),
+            CoercionError::UnknownVariant {
+                suggestion: None,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_coerce_nested() {
+        let mut query = QueryValue::parse("active=0&status[]=open&status[]=ARCHIVED");
+        TABLE.apply_nested(&mut query).unwrap();
+
+        assert_eq!(
+            query.get("active").and_then(QueryValue::as_str),
+            Some("false")
+        );
+        assert_eq!(
+            query.get("status").and_then(QueryValue::as_list),
+            Some(
+                &[
+                    QueryValue::String("Open".to_owned()),
+                    QueryValue::String("Archived".to_owned())
+                ][..]
+            )
+        );
+    }
+}
@@ -1,18 +1,34 @@
 //! Integration into other crates
 
+#[cfg(any(feature = "feat-integrate-axum", feature = "feat-integrate-tower"))]
+pub mod events;
 #[cfg(feature = "feat-integrate-axum")]
 pub mod integrate_axum;
 #[cfg(feature = "feat-integrate-tower")]
 pub mod integrate_tower;
+#[cfg(all(
+    any(feature = "feat-integrate-axum", feature = "feat-integrate-tower"),
+    feature = "feat-request-parser-ext-openapi"
+))]
+pub mod openapi;
 #[cfg(any(feature = "feat-integrate-axum", feature = "feat-integrate-tower"))]
 pub mod utils;
 
+#[cfg(any(feature = "feat-integrate-axum", feature = "feat-integrate-tower"))]
+// re-export
+pub use events::*;
 #[cfg(feature = "feat-integrate-axum")]
 // re-export
 pub use integrate_axum::*;
 #[cfg(feature = "feat-integrate-tower")]
 // re-export
 pub use integrate_tower::*;
+#[cfg(all(
+    any(feature = "feat-integrate-axum", feature = "feat-integrate-tower"),
+    feature = "feat-request-parser-ext-openapi"
+))]
+// re-export
+pub use openapi::*;
 #[cfg(any(feature = "feat-integrate-axum", feature = "feat-integrate-tower"))]
 // re-export
 pub use utils::*;
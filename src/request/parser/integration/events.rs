@@ -0,0 +1,171 @@
+//! Structured events of query parsing, see [`ParseEventSink`].
+
+use std::{
+    fmt,
+    sync::atomic::{AtomicU32, AtomicU64, Ordering},
+    time::UNIX_EPOCH,
+};
+
+use super::ParseQueryError;
+
+#[derive(Debug, Clone, Copy)]
+/// Event of query parsing, reported to the [`ParseEventSink`].
+pub enum ParseEvent<'a> {
+    /// The required key is missing.
+    MissingKey(&'static str),
+
+    /// The [`QueryRule`](super::QueryRule) is not satisfied.
+    UnsatisfiedRule(ParseQueryError),
+
+    /// The query has more pairs than the limit.
+    LimitExceeded {
+        /// The number of pairs.
+        pairs: usize,
+
+        /// The limit.
+        limit: usize,
+    },
+
+    /// The key appears more than once, only one of the values is kept.
+    DuplicateKey(&'a str),
+}
+
+impl fmt::Display for ParseEvent<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingKey(key) => write!(f, "missing query key `{key}`"),
+            Self::UnsatisfiedRule(e) => write!(f, "unsatisfied query rule: {e}"),
+            Self::LimitExceeded { pairs, limit } => {
+                write!(f, "too many query pairs: {pairs}, the limit is {limit}")
+            }
+            Self::DuplicateKey(key) => write!(f, "duplicate query key `{key}`"),
+        }
+    }
+}
+
+/// Sink of [`ParseEvent`]s, e.g. logging or metrics.
+///
+/// Sinks are `&'static`, like the other configs of the integrations, e.g.
+///
+/// ```rust
+/// # use miku_http_util::request::parser::integration::{SampledEventSink, TracingEventSink};
+/// // Report 1 of every 100 events, at most 10 per second.
+/// static SINK: SampledEventSink<TracingEventSink> =
+///     SampledEventSink::new(TracingEventSink, 100, Some(10));
+/// ```
+pub trait ParseEventSink: fmt::Debug + Sync {
+    /// Handle the event.
+    fn on_event(&self, event: ParseEvent<'_>);
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+/// [`ParseEventSink`] logging every event with `tracing::error!` (feature
+/// `feat-tracing`), used when no sink is configured.
+pub struct TracingEventSink;
+
+impl ParseEventSink for TracingEventSink {
+    fn on_event(&self, _event: ParseEvent<'_>) {
+        #[cfg(feature = "feat-tracing")]
+        tracing::error!("{_event}");
+    }
+}
+
+#[derive(Debug)]
+/// [`ParseEventSink`] forwarding only sampled events to the inner sink, so that
+/// attack traffic does not flood the logs.
+pub struct SampledEventSink<S> {
+    inner: S,
+    one_in: u64,
+    max_per_sec: u32,
+    counter: AtomicU64,
+    window: AtomicU64,
+    window_count: AtomicU32,
+}
+
+impl<S> SampledEventSink<S> {
+    #[inline]
+    /// Create a new [`SampledEventSink`], forwarding 1 of every `one_in`
+    /// events (`0` is treated as `1`), and at most `max_per_sec` of them per
+    /// second.
+    pub const fn new(inner: S, one_in: u64, max_per_sec: Option<u32>) -> Self {
+        Self {
+            inner,
+            one_in: if one_in == 0 { 1 } else { one_in },
+            max_per_sec: match max_per_sec {
+                Some(max_per_sec) => max_per_sec,
+                None => u32::MAX,
+            },
+            counter: AtomicU64::new(0),
+            window: AtomicU64::new(0),
+            window_count: AtomicU32::new(0),
+        }
+    }
+
+    #[inline]
+    /// Whether the event is sampled, and within the rate limit.
+    fn sample(&self) -> bool {
+        self.sample_at(
+            crate::time::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        )
+    }
+
+    /// [`sample`](Self::sample) at the given second.
+    fn sample_at(&self, now: u64) -> bool {
+        if self.counter.fetch_add(1, Ordering::Relaxed) % self.one_in != 0 {
+            return false;
+        }
+
+        if self.window.swap(now, Ordering::Relaxed) != now {
+            self.window_count.store(0, Ordering::Relaxed);
+        }
+
+        self.window_count.fetch_add(1, Ordering::Relaxed) < self.max_per_sec
+    }
+}
+
+impl<S: ParseEventSink> ParseEventSink for SampledEventSink<S> {
+    fn on_event(&self, event: ParseEvent<'_>) {
+        if self.sample() {
+            self.inner.on_event(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct Collect(Mutex<Vec<String>>);
+
+    impl ParseEventSink for Collect {
+        fn on_event(&self, event: ParseEvent<'_>) {
+            self.0.lock().unwrap().push(format!("{event:?}"));
+        }
+    }
+
+    #[test]
+    fn test_sampled_event_sink() {
+        let sink = SampledEventSink::new(Collect::default(), 3, None);
+        for _ in 0..9 {
+            sink.on_event(ParseEvent::MissingKey("a"));
+        }
+        assert_eq!(sink.inner.0.lock().unwrap().len(), 3);
+
+        let sink = SampledEventSink::new(Collect::default(), 1, Some(2));
+        for _ in 0..5 {
+            if sink.sample_at(1_000) {
+                sink.inner.on_event(ParseEvent::DuplicateKey("a"));
+            }
+        }
+        assert_eq!(sink.inner.0.lock().unwrap().len(), 2);
+
+        // The next second.
+        assert!(sink.sample_at(1_001));
+    }
+}
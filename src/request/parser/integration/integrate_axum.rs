@@ -1,39 +1,141 @@
 //! `axum` integration for [`OwnedQuery`](OwnedQuery).
 
+use std::{fmt, marker::PhantomData};
+
 use axum::{extract::Request, handler::Handler};
 
-use super::parse_query;
+use super::{parse_query, ParseEventSink, ParseQueryResult, QueryRule};
 
 #[macro_export]
-/// Just [`WithQueryHandler::new`].
+/// Just [`WithQueryHandler::new`], optionally with
+/// [`WithQueryHandler::with_rules`].
 macro_rules! query_keys_required {
     ($handler:expr => $required:expr) => {
         $crate::request::parser::integration::WithQueryHandler::new($handler, $required)
     };
+    ($handler:expr => $required:expr, $rules:expr) => {
+        $crate::request::parser::integration::WithQueryHandler::new($handler, $required)
+            .with_rules($rules)
+    };
 }
 
-#[derive(Debug, Clone, Copy)]
 /// Wrapper over handler
-pub struct WithQueryHandler<H> {
+///
+/// The result is stored as `K`, [`ParseQueryResult`] by default, see
+/// [`with_key`](Self::with_key).
+pub struct WithQueryHandler<H, K = ParseQueryResult> {
     inner: H,
     required: &'static [&'static str],
+    rules: &'static [QueryRule],
+    max_pairs: Option<usize>,
+    sink: Option<&'static dyn ParseEventSink>,
+    _key: PhantomData<fn() -> K>,
 }
 
+impl<H: fmt::Debug, K> fmt::Debug for WithQueryHandler<H, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WithQueryHandler")
+            .field("inner", &self.inner)
+            .field("required", &self.required)
+            .field("rules", &self.rules)
+            .field("max_pairs", &self.max_pairs)
+            .field("sink", &self.sink)
+            .finish()
+    }
+}
+
+// `K` is just a type marker, `#[derive(Clone, Copy)]` would require `K: Clone`
+// and `K: Copy`.
+impl<H: Clone, K> Clone for WithQueryHandler<H, K> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            required: self.required,
+            rules: self.rules,
+            max_pairs: self.max_pairs,
+            sink: self.sink,
+            _key: PhantomData,
+        }
+    }
+}
+
+impl<H: Copy, K> Copy for WithQueryHandler<H, K> {}
+
 impl<H> WithQueryHandler<H> {
     /// Create a new [`WithQueryHandler`].
     pub const fn new(inner: H, required: &'static [&'static str]) -> Self {
-        Self { inner, required }
+        Self {
+            inner,
+            required,
+            rules: &[],
+            max_pairs: None,
+            sink: None,
+            _key: PhantomData,
+        }
+    }
+
+    #[cfg(feature = "feat-request-parser-ext-openapi")]
+    /// Create a new [`WithQueryHandler`] with the required query keys and the
+    /// [`QueryRule`]s of [`QueryRules`](super::QueryRules), so that the
+    /// exported `OpenAPI` parameters always match the validation.
+    pub fn from_rules(inner: H, rules: &super::QueryRules) -> Self {
+        Self::new(inner, rules.required()).with_rules(rules.rules())
     }
 }
 
-impl<H, T, S> Handler<T, S> for WithQueryHandler<H>
+impl<H, K> WithQueryHandler<H, K> {
+    /// Store the result as `K` instead, e.g.
+    /// [`Keyed<Admin>`](super::Keyed), so that multiple query parsers with
+    /// different rules can coexist.
+    ///
+    /// See [`get_query_as`](super::get_query_as).
+    pub fn with_key<K2>(self) -> WithQueryHandler<H, K2> {
+        WithQueryHandler {
+            inner: self.inner,
+            required: self.required,
+            rules: self.rules,
+            max_pairs: self.max_pairs,
+            sink: self.sink,
+            _key: PhantomData,
+        }
+    }
+
+    /// Set extra [`QueryRule`]s, e.g. "at least one of" or conditional
+    /// requirements.
+    pub fn with_rules(self, rules: &'static [QueryRule]) -> Self {
+        Self { rules, ..self }
+    }
+
+    /// Reject queries with more pairs than the limit.
+    pub fn with_max_pairs(self, max_pairs: Option<usize>) -> Self {
+        Self { max_pairs, ..self }
+    }
+
+    /// Set the sink of parse events, e.g. missing keys, instead of logging
+    /// every one of them, see [`ParseEventSink`].
+    pub fn with_event_sink(self, sink: &'static dyn ParseEventSink) -> Self {
+        Self {
+            sink: Some(sink),
+            ..self
+        }
+    }
+}
+
+impl<H, K, T, S> Handler<T, S> for WithQueryHandler<H, K>
 where
     H: Handler<T, S>,
+    K: From<ParseQueryResult> + Clone + Send + Sync + 'static,
 {
     type Future = H::Future;
 
     fn call(self, mut req: Request, state: S) -> Self::Future {
-        parse_query(&mut req, self.required);
+        parse_query::<K, _>(
+            &mut req,
+            self.required,
+            self.rules,
+            self.max_pairs,
+            self.sink,
+        );
 
         self.inner.call(req, state)
     }
@@ -43,11 +145,31 @@ where
 mod test {
     use axum::{extract::Request, response::IntoResponse, routing::get, Router};
 
+    use crate::request::parser::integration::QueryRule;
+
     #[test]
     fn test() {
         let _app: Router<()> = Router::new()
             .route("/", get(test_router))
-            .route("/test", get(query_keys_required!(test_router => &["hey"])));
+            .route("/test", get(query_keys_required!(test_router => &["hey"])))
+            .route(
+                "/test_rules",
+                get(
+                    query_keys_required!(test_router => &["hey"], &[QueryRule::AnyOf(&["a", "b"])]),
+                ),
+            );
+
+        #[cfg(feature = "feat-request-parser-ext-openapi")]
+        {
+            use crate::request::parser::integration::{QueryRules, WithQueryHandler};
+
+            static RULES: QueryRules = QueryRules::new(&["hey"], &[QueryRule::AnyOf(&["a", "b"])]);
+
+            let _app: Router<()> = Router::new().route(
+                "/test_openapi",
+                get(WithQueryHandler::from_rules(test_router, &RULES)),
+            );
+        }
     }
 
     async fn test_router(_request: Request) -> impl IntoResponse {
@@ -1,6 +1,8 @@
 //! `tower` integration for [`OwnedQuery`](OwnedQuery).
 
 use std::{
+    borrow::Borrow,
+    fmt,
     marker::PhantomData,
     task::{Context, Poll},
 };
@@ -10,7 +12,7 @@ use http::Request;
 use tower_layer::Layer;
 use tower_service::Service;
 
-use super::parse_query;
+use super::{parse_query, strip_query_keys, ParseEventSink, ParseQueryResult, QueryRule};
 
 #[deprecated(since = "0.6.0", note = "Renamed, use `WithQueryLayer` instead.")]
 /// Renamed, use [`WithQueryLayer`] instead.
@@ -20,32 +22,55 @@ pub type QueriesLayer<ReqBody> = WithQueryLayer<ReqBody>;
 /// Renamed, use [`WithQueryLayer`] instead.
 pub type QueriesServcie<S, ReqBody> = WithQueryService<S, ReqBody>;
 
-#[derive(Debug, Default, Copy)]
-#[repr(transparent)]
 /// [`Layer`] for parsing [`OwnedQuery`] from a [`Request`] and insert into
 /// the [`Request`] extensions.
-pub struct WithQueryLayer<ReqBody> {
+///
+/// The result is stored as `K`, [`ParseQueryResult`] by default, see
+/// [`with_key`](Self::with_key).
+pub struct WithQueryLayer<ReqBody, K = ParseQueryResult> {
     _req_body: PhantomData<ReqBody>,
+    _key: PhantomData<fn() -> K>,
     required: &'static [&'static str],
+    rules: &'static [QueryRule],
+    stripped: &'static [&'static str],
+    max_pairs: Option<usize>,
+    sink: Option<&'static dyn ParseEventSink>,
+}
+
+impl<ReqBody, K> fmt::Debug for WithQueryLayer<ReqBody, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WithQueryLayer")
+            .field("required", &self.required)
+            .field("rules", &self.rules)
+            .field("stripped", &self.stripped)
+            .field("max_pairs", &self.max_pairs)
+            .field("sink", &self.sink)
+            .finish()
+    }
+}
+
+impl<ReqBody> Default for WithQueryLayer<ReqBody> {
+    fn default() -> Self {
+        Self::new(&[])
+    }
 }
 
 // `ReqBody`, `ResBody` is just type markers, we actually don't care
 // about what actually it is, but the compiler will complain that *`Clone` is
 // needed* if we just `#[derive(Clone)]`
-impl<ReqBody> Clone for WithQueryLayer<ReqBody> {
+impl<ReqBody, K> Clone for WithQueryLayer<ReqBody, K> {
     fn clone(&self) -> Self {
-        Self {
-            _req_body: PhantomData,
-            required: self.required,
-        }
+        *self
     }
 }
 
+impl<ReqBody, K> Copy for WithQueryLayer<ReqBody, K> {}
+
 #[allow(unsafe_code)]
 // SAFETY: `ReqBody`, `ResBody` is just type markers, we actually don't care
 // about what actually it is, but compiler complains about `the type parameter
 // `B` is not constrained by ***`.
-unsafe impl<ReqBody> Sync for WithQueryLayer<ReqBody> {}
+unsafe impl<ReqBody, K> Sync for WithQueryLayer<ReqBody, K> {}
 
 impl<ReqBody> WithQueryLayer<ReqBody> {
     /// Create a new [`WithQueryLayer`].
@@ -56,33 +81,114 @@ impl<ReqBody> WithQueryLayer<ReqBody> {
     pub const fn new(required: &'static [&'static str]) -> Self {
         Self {
             _req_body: PhantomData,
+            _key: PhantomData,
             required,
+            rules: &[],
+            stripped: &[],
+            max_pairs: None,
+            sink: None,
+        }
+    }
+
+    #[cfg(feature = "feat-request-parser-ext-openapi")]
+    /// Create a new [`WithQueryLayer`] with the required query keys and the
+    /// [`QueryRule`]s of [`QueryRules`](super::QueryRules), so that the
+    /// exported `OpenAPI` parameters always match the validation.
+    pub const fn from_rules(rules: &super::QueryRules) -> Self {
+        Self::new(rules.required()).with_rules(rules.rules())
+    }
+}
+
+impl<ReqBody, K> WithQueryLayer<ReqBody, K> {
+    /// Store the result as `K` instead, e.g.
+    /// [`Keyed<Admin>`](super::Keyed), so that multiple instances of the
+    /// layer with different rules can coexist in one stack.
+    ///
+    /// See [`get_query_as`](super::get_query_as).
+    pub const fn with_key<K2>(self) -> WithQueryLayer<ReqBody, K2> {
+        WithQueryLayer {
+            _req_body: PhantomData,
+            _key: PhantomData,
+            required: self.required,
+            rules: self.rules,
+            stripped: self.stripped,
+            max_pairs: self.max_pairs,
+            sink: self.sink,
+        }
+    }
+
+    /// Set extra [`QueryRule`]s, e.g. "at least one of" or conditional
+    /// requirements.
+    pub const fn with_rules(self, rules: &'static [QueryRule]) -> Self {
+        Self { rules, ..self }
+    }
+
+    /// Remove the given query keys (e.g. auth tokens, signatures) from the
+    /// request URI after parsing, so that they will not be passed to the
+    /// downstream services, access logs, etc.
+    pub const fn with_stripped_keys(self, stripped: &'static [&'static str]) -> Self {
+        Self { stripped, ..self }
+    }
+
+    /// Reject queries with more pairs than the limit.
+    pub const fn with_max_pairs(self, max_pairs: Option<usize>) -> Self {
+        Self { max_pairs, ..self }
+    }
+
+    /// Set the sink of parse events, e.g. missing keys, instead of logging
+    /// every one of them, see [`ParseEventSink`].
+    pub const fn with_event_sink(self, sink: &'static dyn ParseEventSink) -> Self {
+        Self {
+            sink: Some(sink),
+            ..self
         }
     }
 }
 
-impl<S, ReqBody> Layer<S> for WithQueryLayer<ReqBody>
+impl<S, ReqBody, K> Layer<S> for WithQueryLayer<ReqBody, K>
 where
     S: Service<Request<ReqBody>> + Send + 'static,
 {
-    type Service = WithQueryService<S, ReqBody>;
+    type Service = WithQueryService<S, ReqBody, K>;
 
     fn layer(&self, inner: S) -> Self::Service {
         WithQueryService {
             inner,
             required: self.required,
+            rules: self.rules,
+            stripped: self.stripped,
+            max_pairs: self.max_pairs,
+            sink: self.sink,
             _req_body: PhantomData,
+            _key: PhantomData,
         }
     }
 }
 
-#[derive(Debug)]
 /// [`Service`] for parsing [`OwnedQuery`] from a [`Request`] and insert into
 /// the [`Request`] extensions.
-pub struct WithQueryService<S, ReqBody> {
+pub struct WithQueryService<S, ReqBody, K = ParseQueryResult> {
     inner: S,
     required: &'static [&'static str],
+    rules: &'static [QueryRule],
+    stripped: &'static [&'static str],
+    max_pairs: Option<usize>,
+    sink: Option<&'static dyn ParseEventSink>,
     _req_body: PhantomData<ReqBody>,
+    _key: PhantomData<fn() -> K>,
+}
+
+impl<S: fmt::Debug, ReqBody, K> fmt::Debug for WithQueryService<S, ReqBody, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WithQueryService")
+            .field("inner", &self.inner)
+            .field("required", &self.required)
+            .field("rules", &self.rules)
+            .field("stripped", &self.stripped)
+            .field("max_pairs", &self.max_pairs)
+            .field("sink", &self.sink)
+            .finish()
+    }
 }
 
 impl<S, ReqBody> WithQueryService<S, ReqBody> {
@@ -95,7 +201,55 @@ impl<S, ReqBody> WithQueryService<S, ReqBody> {
         Self {
             inner,
             required,
+            rules: &[],
+            stripped: &[],
+            max_pairs: None,
+            sink: None,
+            _req_body: PhantomData,
+            _key: PhantomData,
+        }
+    }
+}
+
+impl<S, ReqBody, K> WithQueryService<S, ReqBody, K> {
+    /// Store the result as `K` instead, see [`WithQueryLayer::with_key`].
+    pub fn with_key<K2>(self) -> WithQueryService<S, ReqBody, K2> {
+        WithQueryService {
+            inner: self.inner,
+            required: self.required,
+            rules: self.rules,
+            stripped: self.stripped,
+            max_pairs: self.max_pairs,
+            sink: self.sink,
             _req_body: PhantomData,
+            _key: PhantomData,
+        }
+    }
+
+    /// Set extra [`QueryRule`]s, e.g. "at least one of" or conditional
+    /// requirements.
+    pub fn with_rules(self, rules: &'static [QueryRule]) -> Self {
+        Self { rules, ..self }
+    }
+
+    /// Remove the given query keys (e.g. auth tokens, signatures) from the
+    /// request URI after parsing, so that they will not be passed to the
+    /// downstream services, access logs, etc.
+    pub fn with_stripped_keys(self, stripped: &'static [&'static str]) -> Self {
+        Self { stripped, ..self }
+    }
+
+    /// Reject queries with more pairs than the limit.
+    pub fn with_max_pairs(self, max_pairs: Option<usize>) -> Self {
+        Self { max_pairs, ..self }
+    }
+
+    /// Set the sink of parse events, e.g. missing keys, instead of logging
+    /// every one of them, see [`ParseEventSink`].
+    pub fn with_event_sink(self, sink: &'static dyn ParseEventSink) -> Self {
+        Self {
+            sink: Some(sink),
+            ..self
         }
     }
 }
@@ -103,7 +257,7 @@ impl<S, ReqBody> WithQueryService<S, ReqBody> {
 // `ReqBody`, `ResBody` is just type markers, we actually don't care
 // about what actually it is, but the compiler will complain that *`Clone` is
 // needed* if we just `#[derive(Clone)]`
-impl<S, ReqBody> Clone for WithQueryService<S, ReqBody>
+impl<S, ReqBody, K> Clone for WithQueryService<S, ReqBody, K>
 where
     S: Clone,
 {
@@ -111,7 +265,12 @@ where
         Self {
             inner: self.inner.clone(),
             required: self.required,
+            rules: self.rules,
+            stripped: self.stripped,
+            max_pairs: self.max_pairs,
+            sink: self.sink,
             _req_body: PhantomData,
+            _key: PhantomData,
         }
     }
 }
@@ -120,11 +279,12 @@ where
 // SAFETY: `ReqBody`, `ResBody` is just type markers, we actually don't care
 // about what actually it is, but compiler complains about `the type parameter
 // `B` is not constrained by ***`.
-unsafe impl<S, ReqBody> Sync for WithQueryService<S, ReqBody> where S: Sync {}
+unsafe impl<S, ReqBody, K> Sync for WithQueryService<S, ReqBody, K> where S: Sync {}
 
-impl<S, ReqBody> Service<Request<ReqBody>> for WithQueryService<S, ReqBody>
+impl<S, ReqBody, K> Service<Request<ReqBody>> for WithQueryService<S, ReqBody, K>
 where
     S: Service<Request<ReqBody>> + Send + 'static,
+    K: From<ParseQueryResult> + Borrow<ParseQueryResult> + Clone + Send + Sync + 'static,
 {
     type Error = S::Error;
     type Future = S::Future;
@@ -135,7 +295,17 @@ where
     }
 
     fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
-        parse_query(&mut req, self.required);
+        parse_query::<K, _>(
+            &mut req,
+            self.required,
+            self.rules,
+            self.max_pairs,
+            self.sink,
+        );
+
+        if !self.stripped.is_empty() {
+            strip_query_keys(&mut req, self.stripped);
+        }
 
         self.inner.call(req)
     }
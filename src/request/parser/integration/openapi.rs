@@ -0,0 +1,316 @@
+//! Export of the query validation rules as `OpenAPI` 3.1 parameter objects, so
+//! that the rules double as API documentation.
+//!
+//! ```rust
+//! # use miku_http_util::request::parser::integration::{QueryRule, QueryRules};
+//! static RULES: QueryRules = QueryRules::new(&["id"], &[QueryRule::AnyOf(&["token", "session_id"])]);
+//!
+//! let parameters = serde_json::to_value(RULES.to_openapi_parameters()).unwrap();
+//! assert_eq!(
+//!     parameters[0],
+//!     serde_json::json!({
+//!         "name": "id",
+//!         "in": "query",
+//!         "required": true,
+//!         "schema": { "type": "string" },
+//!     })
+//! );
+//! ```
+
+use serde::Serialize;
+
+use super::QueryRule;
+
+#[derive(Debug, Clone, Copy)]
+/// The required query keys and the [`QueryRule`]s, see
+/// `WithQueryLayer::from_rules` or `WithQueryHandler::from_rules`.
+pub struct QueryRules {
+    required: &'static [&'static str],
+    rules: &'static [QueryRule],
+    #[cfg(feature = "feat-request-parser-ext-coerce")]
+    coercions: Option<&'static crate::request::parser::coerce::CoercionTable>,
+}
+
+impl QueryRules {
+    #[inline]
+    /// Create a new [`QueryRules`].
+    pub const fn new(required: &'static [&'static str], rules: &'static [QueryRule]) -> Self {
+        Self {
+            required,
+            rules,
+            #[cfg(feature = "feat-request-parser-ext-coerce")]
+            coercions: None,
+        }
+    }
+
+    #[cfg(feature = "feat-request-parser-ext-coerce")]
+    #[inline]
+    /// Set the [`CoercionTable`](crate::request::parser::coerce::CoercionTable)
+    /// for the schema types, e.g. `boolean`, or `string` with enum values.
+    pub const fn with_coercions(
+        self,
+        coercions: &'static crate::request::parser::coerce::CoercionTable,
+    ) -> Self {
+        Self {
+            coercions: Some(coercions),
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Returns the required query keys.
+    pub const fn required(&self) -> &'static [&'static str] {
+        self.required
+    }
+
+    #[inline]
+    /// Returns the [`QueryRule`]s.
+    pub const fn rules(&self) -> &'static [QueryRule] {
+        self.rules
+    }
+
+    /// Export as `OpenAPI` 3.1 parameter objects, in order of first
+    /// appearance.
+    ///
+    /// Only the plain required keys are marked `required`, the conditions of
+    /// the other rules are described in `description`.
+    pub fn to_openapi_parameters(&self) -> Vec<OpenApiParameter> {
+        let mut parameters: Vec<OpenApiParameter> = Vec::new();
+
+        let mut add = |name: &'static str, description: Option<String>| {
+            let idx = match parameters.iter().position(|p| p.name == name) {
+                Some(idx) => idx,
+                None => {
+                    parameters.push(OpenApiParameter::new(name, self.schema(name)));
+                    parameters.len() - 1
+                }
+            };
+
+            let parameter = &mut parameters[idx];
+            parameter.required |= self.required.contains(&name);
+
+            if let Some(description) = description {
+                match &mut parameter.description {
+                    Some(existing) => {
+                        existing.push_str(". ");
+                        existing.push_str(&description);
+                    }
+                    None => parameter.description = Some(description),
+                }
+            }
+        };
+
+        for &name in self.required {
+            add(name, None);
+        }
+
+        for rule in self.rules {
+            match *rule {
+                QueryRule::AnyOf(keys) => {
+                    for &name in keys {
+                        add(name, Some(format!("At least one of {keys:?} is required")));
+                    }
+                }
+                QueryRule::RequiredIf {
+                    key,
+                    value,
+                    required,
+                } => {
+                    add(key, None);
+
+                    for &name in required {
+                        add(name, Some(format!("Required when `{key}={value}`")));
+                    }
+                }
+                QueryRule::Alias { key, aliases } => {
+                    add(key, Some(format!("Also accepted as {aliases:?}")));
+                }
+                QueryRule::Equals { key, value } => {
+                    add(key, Some(format!("Must be `{value}`")));
+                }
+                // Not a parameter at all.
+                QueryRule::Absent(_) => {}
+            }
+        }
+
+        parameters
+    }
+
+    #[allow(clippy::unused_self)]
+    fn schema(&self, _name: &str) -> OpenApiSchema {
+        #[cfg(feature = "feat-request-parser-ext-coerce")]
+        {
+            use crate::request::parser::coerce::Coercion;
+
+            match self.coercions.and_then(|coercions| coercions.get(_name)) {
+                Some(Coercion::Bool) => OpenApiSchema {
+                    ty: "boolean",
+                    values: None,
+                },
+                Some(Coercion::Enum(values)) => OpenApiSchema {
+                    ty: "string",
+                    values: Some(values),
+                },
+                None => OpenApiSchema::STRING,
+            }
+        }
+
+        #[cfg(not(feature = "feat-request-parser-ext-coerce"))]
+        OpenApiSchema::STRING
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Serialize)]
+/// `OpenAPI` 3.1 parameter object, see
+/// <https://spec.openapis.org/oas/v3.1.0#parameter-object>.
+pub struct OpenApiParameter {
+    /// The name of the parameter.
+    pub name: &'static str,
+
+    #[serde(rename = "in")]
+    /// The location of the parameter, always `query`.
+    pub location: &'static str,
+
+    /// Whether the parameter is mandatory.
+    pub required: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// The conditions of the rules, if any.
+    pub description: Option<String>,
+
+    /// The schema of the parameter.
+    pub schema: OpenApiSchema,
+}
+
+impl OpenApiParameter {
+    const fn new(name: &'static str, schema: OpenApiSchema) -> Self {
+        Self {
+            name,
+            location: "query",
+            required: false,
+            description: None,
+            schema,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Serialize)]
+/// `OpenAPI` 3.1 schema object, the subset for query parameters.
+pub struct OpenApiSchema {
+    #[serde(rename = "type")]
+    /// The type, e.g. `string` or `boolean`.
+    pub ty: &'static str,
+
+    #[serde(rename = "enum", skip_serializing_if = "Option::is_none")]
+    /// The enum values, if any.
+    pub values: Option<&'static [&'static str]>,
+}
+
+impl OpenApiSchema {
+    /// Plain `string` schema.
+    pub const STRING: Self = Self {
+        ty: "string",
+        values: None,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_to_openapi_parameters() {
+        const RULES: QueryRules = QueryRules::new(
+            &["id"],
+            &[
+                QueryRule::AnyOf(&["token", "id"]),
+                QueryRule::RequiredIf {
+                    key: "type",
+                    value: "upload",
+                    required: &["filename"],
+                },
+                QueryRule::Alias {
+                    key: "per_page",
+                    aliases: &["limit"],
+                },
+            ],
+        );
+
+        assert_eq!(
+            serde_json::to_value(RULES.to_openapi_parameters()).unwrap(),
+            json!([
+                {
+                    "name": "id",
+                    "in": "query",
+                    "required": true,
+                    "description": "At least one of [\"token\", \"id\"] is required",
+                    "schema": { "type": "string" },
+                },
+                {
+                    "name": "token",
+                    "in": "query",
+                    "required": false,
+                    "description": "At least one of [\"token\", \"id\"] is required",
+                    "schema": { "type": "string" },
+                },
+                {
+                    "name": "type",
+                    "in": "query",
+                    "required": false,
+                    "schema": { "type": "string" },
+                },
+                {
+                    "name": "filename",
+                    "in": "query",
+                    "required": false,
+                    "description": "Required when `type=upload`",
+                    "schema": { "type": "string" },
+                },
+                {
+                    "name": "per_page",
+                    "in": "query",
+                    "required": false,
+                    "description": "Also accepted as [\"limit\"]",
+                    "schema": { "type": "string" },
+                },
+            ])
+        );
+    }
+
+    #[cfg(feature = "feat-request-parser-ext-coerce")]
+    #[test]
+    fn test_coercions_schema() {
+        use crate::request::parser::coerce::{Coercion, CoercionTable};
+
+        static TABLE: CoercionTable = CoercionTable::new(&[
+            ("verbose", Coercion::Bool),
+            ("sort", Coercion::Enum(&["asc", "desc"])),
+        ]);
+
+        let parameters = QueryRules::new(&["verbose", "sort"], &[])
+            .with_coercions(&TABLE)
+            .to_openapi_parameters();
+
+        assert_eq!(
+            serde_json::to_value(parameters).unwrap(),
+            json!([
+                {
+                    "name": "verbose",
+                    "in": "query",
+                    "required": true,
+                    "schema": { "type": "boolean" },
+                },
+                {
+                    "name": "sort",
+                    "in": "query",
+                    "required": true,
+                    "schema": { "type": "string", "enum": ["asc", "desc"] },
+                },
+            ])
+        );
+    }
+}
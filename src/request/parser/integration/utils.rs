@@ -1,9 +1,11 @@
 //! Integration with other crates, utils
 
-use anyhow::Result;
+use std::{borrow::Borrow, fmt, marker::PhantomData};
+
 use http::Request;
 
-use crate::request::parser::OwnedQuery;
+use super::{ParseEvent, ParseEventSink, TracingEventSink};
+use crate::{error::Result, request::parser::OwnedQuery};
 
 /// Type alias for [`Result<OwnedQuery, ParseQueryError>`].
 ///
@@ -14,14 +16,88 @@ pub type ParseQueryResult = Result<OwnedQuery, ParseQueryError>;
 #[inline]
 /// Helper function to extract parsed [`Query`](OwnedQuery) from
 /// [`Extensions`](http::Extensions) within given [`Request`].
+///
+/// # Errors
+///
+/// [`Error::QueryParse`](crate::Error::QueryParse) if the query is invalid.
 pub fn get_query<ReqBody>(request: &Request<ReqBody>) -> Result<Option<&OwnedQuery>> {
-    match request.extensions().get::<ParseQueryResult>() {
+    get_query_as::<ParseQueryResult, _>(request)
+}
+
+#[inline]
+/// Like [`get_query`], but extract the result stored under the given key, e.g.
+/// [`Keyed<Admin>`](Keyed), see
+/// [`WithQueryLayer::with_key`](super::WithQueryLayer::with_key).
+///
+/// # Errors
+///
+/// [`Error::QueryParse`](crate::Error::QueryParse) if the query is invalid.
+pub fn get_query_as<K, ReqBody>(request: &Request<ReqBody>) -> Result<Option<&OwnedQuery>>
+where
+    K: Borrow<ParseQueryResult> + Send + Sync + 'static,
+{
+    match request.extensions().get::<K>().map(Borrow::borrow) {
         Some(Ok(data)) => Ok(Some(data)),
         Some(Err(e)) => Err((*e).into()),
         None => Ok(None),
     }
 }
 
+/// [`ParseQueryResult`] stored under the user-defined key `K`, so that
+/// multiple query parsers with different rules can coexist without clobbering
+/// each other's extension entry.
+///
+/// `K` is just a type marker, e.g.
+///
+/// ```rust
+/// # use miku_http_util::request::parser::integration::{Keyed, ParseQueryResult};
+/// enum Admin {}
+///
+/// type AdminQuery = Keyed<Admin>;
+/// ```
+pub struct Keyed<K> {
+    result: ParseQueryResult,
+    _key: PhantomData<fn() -> K>,
+}
+
+impl<K> Keyed<K> {
+    #[inline]
+    /// Returns the inner [`ParseQueryResult`].
+    pub fn into_inner(self) -> ParseQueryResult {
+        self.result
+    }
+}
+
+impl<K> fmt::Debug for Keyed<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Keyed").field(&self.result).finish()
+    }
+}
+
+impl<K> Clone for Keyed<K> {
+    fn clone(&self) -> Self {
+        Self {
+            result: self.result.clone(),
+            _key: PhantomData,
+        }
+    }
+}
+
+impl<K> From<ParseQueryResult> for Keyed<K> {
+    fn from(result: ParseQueryResult) -> Self {
+        Self {
+            result,
+            _key: PhantomData,
+        }
+    }
+}
+
+impl<K> Borrow<ParseQueryResult> for Keyed<K> {
+    fn borrow(&self) -> &ParseQueryResult {
+        &self.result
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 #[derive(thiserror::Error)]
 /// `ParseQueryError`
@@ -29,41 +105,484 @@ pub enum ParseQueryError {
     #[error("missing query key `{0}`")]
     /// Missing required query key
     MissingKey(&'static str),
+
+    #[error("missing query key: at least one of {0:?} is required")]
+    /// None of the keys in the [`QueryRule::AnyOf`] group is present
+    MissingAnyOf(&'static [&'static str]),
+
+    #[error("missing query key `{missing}`, required when `{key}={value}`")]
+    /// Missing query key required by [`QueryRule::RequiredIf`]
+    MissingConditional {
+        /// The condition key
+        key: &'static str,
+
+        /// The condition value
+        value: &'static str,
+
+        /// The missing key
+        missing: &'static str,
+    },
+
+    #[error("query key `{key}` is expected to be `{expected}`")]
+    /// The query key required by [`QueryRule::Equals`] is missing or has
+    /// another value
+    UnexpectedValue {
+        /// The query key
+        key: &'static str,
+
+        /// The expected value
+        expected: &'static str,
+    },
+
+    #[error("unexpected query key `{0}`")]
+    /// Query key present but not allowed by [`QueryRule::Absent`]
+    UnexpectedKey(&'static str),
+
+    #[error("too many query pairs, the limit is {0}")]
+    /// The query has more pairs than the limit
+    TooManyPairs(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Rules for query keys, evaluated after the plain required keys.
+pub enum QueryRule {
+    /// At least one of the keys is required, e.g. `token` or `session_id`.
+    AnyOf(&'static [&'static str]),
+
+    /// If query key `key` equals to `value`, all `required` keys are required,
+    /// e.g. `filename` is required when `type=upload`.
+    RequiredIf {
+        /// The condition key
+        key: &'static str,
+
+        /// The condition value
+        value: &'static str,
+
+        /// Keys required when the condition is met
+        required: &'static [&'static str],
+    },
+
+    /// Accept the `aliases` as the canonical `key`, e.g. `perPage` and `limit`
+    /// as `per_page`, for API compatibility.
+    ///
+    /// The query is normalized before checking the required keys and the other
+    /// rules: the first present one of `key` and `aliases` (in order) wins and
+    /// is stored under `key`, and the aliases are removed.
+    Alias {
+        /// The canonical key
+        key: &'static str,
+
+        /// The aliases, in order of precedence
+        aliases: &'static [&'static str],
+    },
+
+    /// Query key `key` is required to equal to (decoded) `value`, e.g.
+    /// `version=2`.
+    Equals {
+        /// The query key
+        key: &'static str,
+
+        /// The expected value
+        value: &'static str,
+    },
+
+    /// None of the keys is allowed, e.g. `debug` on public endpoints.
+    Absent(&'static [&'static str]),
+}
+
+impl QueryRule {
+    /// Check the rule against the given query (`None` if there's no query at
+    /// all).
+    ///
+    /// # Errors
+    ///
+    /// [`ParseQueryError`] naming the unsatisfied rule.
+    pub fn check(&self, query: Option<&OwnedQuery>) -> Result<(), ParseQueryError> {
+        let contains = |key: &str| query.is_some_and(|query| query.contains_key(key));
+
+        match *self {
+            Self::AnyOf(keys) => {
+                if keys.iter().any(|&key| contains(key)) {
+                    Ok(())
+                } else {
+                    Err(ParseQueryError::MissingAnyOf(keys))
+                }
+            }
+            Self::RequiredIf {
+                key,
+                value,
+                required,
+            } => {
+                if query.and_then(|query| query.get(key)) != Some(value) {
+                    return Ok(());
+                }
+
+                match required.iter().find(|&&missing| !contains(missing)) {
+                    Some(&missing) => Err(ParseQueryError::MissingConditional {
+                        key,
+                        value,
+                        missing,
+                    }),
+                    None => Ok(()),
+                }
+            }
+            Self::Alias { .. } => Ok(()),
+            Self::Equals { key, value } => {
+                if query.and_then(|query| query.get(key)) == Some(value) {
+                    Ok(())
+                } else {
+                    Err(ParseQueryError::UnexpectedValue {
+                        key,
+                        expected: value,
+                    })
+                }
+            }
+            Self::Absent(keys) => match keys.iter().find(|&&key| contains(key)) {
+                Some(&key) => Err(ParseQueryError::UnexpectedKey(key)),
+                None => Ok(()),
+            },
+        }
+    }
+
+    /// Normalize the aliases of [`QueryRule::Alias`] into the canonical key,
+    /// no-op for the other rules.
+    pub fn normalize(&self, query: &mut OwnedQuery) {
+        let Self::Alias { key, aliases } = *self else {
+            return;
+        };
+
+        let mut value = query.remove(key);
+
+        for alias in aliases {
+            if let Some(alias_value) = query.remove(*alias) {
+                value.get_or_insert(alias_value);
+            }
+        }
+
+        if let Some(value) = value {
+            query.insert(key, value);
+        }
+    }
+}
+
+fn check_query(
+    query: Option<&OwnedQuery>,
+    required: &'static [&'static str],
+    rules: &'static [QueryRule],
+    sink: &dyn ParseEventSink,
+) -> Result<(), ParseQueryError> {
+    if let Some(&key) = required
+        .iter()
+        .find(|&&key| !query.is_some_and(|query| query.contains_key(key)))
+    {
+        sink.on_event(ParseEvent::MissingKey(key));
+
+        return Err(ParseQueryError::MissingKey(key));
+    }
+
+    rules.iter().try_for_each(|rule| {
+        rule.check(query)
+            .inspect_err(|&e| sink.on_event(ParseEvent::UnsatisfiedRule(e)))
+    })
+}
+
+/// Parse the query, checking the number of pairs first and reporting the
+/// first duplicate key if any.
+fn parse_checked(
+    query: &str,
+    max_pairs: Option<usize>,
+    sink: &dyn ParseEventSink,
+) -> Result<OwnedQuery, ParseQueryError> {
+    // No need to decode for counting.
+    let pairs = memchr::memchr_iter(b'&', query.as_bytes()).count() + 1;
+
+    if let Some(limit) = max_pairs.filter(|&limit| pairs > limit) {
+        sink.on_event(ParseEvent::LimitExceeded { pairs, limit });
+
+        return Err(ParseQueryError::TooManyPairs(limit));
+    }
+
+    let (parsed, duplicate) = OwnedQuery::parse_checked(query);
+
+    if let Some(key) = duplicate {
+        sink.on_event(ParseEvent::DuplicateKey(&key));
+    }
+
+    Ok(parsed)
 }
 
 #[inline]
-pub(super) fn parse_query<ReqBody>(req: &mut Request<ReqBody>, required: &'static [&'static str]) {
-    match req.uri().query().map(OwnedQuery::parse) {
-        Some(owned_query) => {
+pub(crate) fn parse_query<K, ReqBody>(
+    req: &mut Request<ReqBody>,
+    required: &'static [&'static str],
+    rules: &'static [QueryRule],
+    max_pairs: Option<usize>,
+    sink: Option<&'static dyn ParseEventSink>,
+) where
+    K: From<ParseQueryResult> + Clone + Send + Sync + 'static,
+{
+    let sink = sink.unwrap_or(&TracingEventSink);
+
+    let (mut owned_query, result) = match req.uri().query() {
+        Some(query) => match parse_checked(query, max_pairs, sink) {
+            Ok(parsed) => (Some(parsed), Ok(())),
+            Err(e) => (None, Err(e)),
+        },
+        None => (None, Ok(())),
+    };
+
+    #[cfg(feature = "feat-tracing")]
+    match &owned_query {
+        Some(owned_query) => tracing::trace!("Found query: {:?}", owned_query),
+        None => tracing::trace!("Missing query."),
+    }
+
+    if let (Ok(()), Some(owned_query)) = (&result, &mut owned_query) {
+        rules.iter().for_each(|rule| rule.normalize(owned_query));
+    }
+
+    let result = result.and_then(|()| check_query(owned_query.as_ref(), required, rules, sink));
+
+    match (owned_query, result) {
+        (Some(owned_query), Ok(())) => {
+            req.extensions_mut()
+                .insert::<K>(ParseQueryResult::Ok(owned_query).into());
+        }
+        (_, Err(e)) => {
+            req.extensions_mut()
+                .insert::<K>(ParseQueryResult::Err(e).into());
+        }
+        (None, Ok(())) => {}
+    }
+}
+
+#[cfg(feature = "feat-integrate-tower")]
+/// Remove the given query keys from the request URI, keeping the other pairs
+/// untouched (as is, not re-encoded).
+pub(super) fn strip_query_keys<ReqBody>(req: &mut Request<ReqBody>, keys: &'static [&'static str]) {
+    use http::uri::{PathAndQuery, Uri};
+
+    use crate::percent;
+
+    let Some(query) = req
+        .uri()
+        .query()
+        .and_then(|query| percent::strip_query_keys(query, keys))
+    else {
+        return;
+    };
+
+    let mut path_and_query = req.uri().path().to_owned();
+
+    if !query.is_empty() {
+        path_and_query.push('?');
+        path_and_query.push_str(&query);
+    }
+
+    // Keep the original URI untouched unless rebuilt successfully.
+    let mut parts = req.uri().clone().into_parts();
+
+    match PathAndQuery::try_from(path_and_query) {
+        Ok(path_and_query) => parts.path_and_query = Some(path_and_query),
+        Err(_e) => {
+            #[cfg(feature = "feat-tracing")]
+            tracing::error!("Failed to rebuild path and query: {_e:?}");
+
+            return;
+        }
+    }
+
+    match Uri::from_parts(parts) {
+        Ok(uri) => *req.uri_mut() = uri,
+        Err(_e) => {
             #[cfg(feature = "feat-tracing")]
-            tracing::trace!("Found query: {:?}", owned_query);
-
-            let owned_query = required
-                .iter()
-                .find_map(|&key| {
-                    if !owned_query.contains_key(key) {
-                        #[cfg(feature = "feat-tracing")]
-                        tracing::error!(key, "Missing query key.");
-
-                        Some(ParseQueryResult::Err(ParseQueryError::MissingKey(key)))
-                    } else {
-                        None
-                    }
-                })
-                .unwrap_or(ParseQueryResult::Ok(owned_query));
-
-            req.extensions_mut().insert::<ParseQueryResult>(owned_query);
+            tracing::error!("Failed to rebuild uri: {_e:?}");
         }
-        None => {
-            if !required.is_empty() {
-                #[cfg(feature = "feat-tracing")]
-                tracing::error!("Missing query.");
-
-                req.extensions_mut()
-                    .insert::<ParseQueryResult>(ParseQueryResult::Err(
-                        ParseQueryError::MissingKey(required[0]),
-                    ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request::parser::testing::request_with_query_checked;
+
+    const RULES: &[QueryRule] = &[
+        QueryRule::AnyOf(&["token", "session_id"]),
+        QueryRule::RequiredIf {
+            key: "type",
+            value: "upload",
+            required: &["filename"],
+        },
+    ];
+
+    fn check(query: &str) -> Result<(), crate::Error> {
+        let req = request_with_query_checked(query, &["id"], RULES);
+
+        get_query(&req).map(|_| ())
+    }
+
+    #[test]
+    fn test_rules() {
+        assert!(matches!(check("id=1&token=a"), Ok(())));
+        assert!(matches!(
+            check("token=a"),
+            Err(crate::Error::QueryParse(ParseQueryError::MissingKey("id")))
+        ));
+        assert!(matches!(
+            check("id=1"),
+            Err(crate::Error::QueryParse(ParseQueryError::MissingAnyOf([
+                "token",
+                "session_id"
+            ])))
+        ));
+        assert!(matches!(check("id=1&session_id=a&type=download"), Ok(())));
+        assert!(matches!(
+            check("id=1&session_id=a&type=upload"),
+            Err(crate::Error::QueryParse(
+                ParseQueryError::MissingConditional {
+                    missing: "filename",
+                    ..
+                }
+            ))
+        ));
+        assert!(matches!(
+            check("id=1&session_id=a&type=upload&filename=a.txt"),
+            Ok(())
+        ));
+    }
+
+    #[test]
+    fn test_alias() {
+        const RULES: &[QueryRule] = &[QueryRule::Alias {
+            key: "per_page",
+            aliases: &["perPage", "limit"],
+        }];
+
+        let per_page = |query: &str| {
+            let req = request_with_query_checked(query, &["per_page"], RULES);
+
+            get_query(&req).map(|query| {
+                let query = query.unwrap();
+                assert!(!query.contains_key("perPage") && !query.contains_key("limit"));
+                query.get("per_page").unwrap().to_owned()
+            })
+        };
+
+        assert_eq!(per_page("per_page=1&perPage=2&limit=3").unwrap(), "1");
+        assert_eq!(per_page("limit=3&perPage=2").unwrap(), "2");
+        assert_eq!(per_page("limit=3").unwrap(), "3");
+        assert!(matches!(
+            per_page("page=1").unwrap_err(),
+            crate::Error::QueryParse(ParseQueryError::MissingKey("per_page"))
+        ));
+    }
+
+    #[test]
+    fn test_equals_absent() {
+        const RULES: &[QueryRule] = &[
+            QueryRule::Equals {
+                key: "version",
+                value: "2",
+            },
+            QueryRule::Absent(&["debug"]),
+        ];
+
+        let check = |query: &str| {
+            let req = request_with_query_checked(query, &[], RULES);
+
+            get_query(&req).map(|_| ())
+        };
+
+        assert!(matches!(check("version=2"), Ok(())));
+        assert!(matches!(
+            check("version=1"),
+            Err(crate::Error::QueryParse(ParseQueryError::UnexpectedValue {
+                key: "version",
+                expected: "2"
+            }))
+        ));
+        assert!(matches!(
+            check("page=1"),
+            Err(crate::Error::QueryParse(
+                ParseQueryError::UnexpectedValue { .. }
+            ))
+        ));
+        assert!(matches!(
+            check("version=2&debug=1"),
+            Err(crate::Error::QueryParse(ParseQueryError::UnexpectedKey(
+                "debug"
+            )))
+        ));
+    }
+
+    #[test]
+    fn test_parse_events() {
+        use std::sync::Mutex;
+
+        #[derive(Debug)]
+        struct Collect(Mutex<Vec<String>>);
+
+        impl ParseEventSink for Collect {
+            fn on_event(&self, event: ParseEvent<'_>) {
+                self.0.lock().unwrap().push(format!("{event:?}"));
             }
         }
+
+        static SINK: Collect = Collect(Mutex::new(Vec::new()));
+
+        let parse = |query: &str| {
+            let mut req = Request::builder()
+                .uri(format!("/?{query}"))
+                .body(())
+                .unwrap();
+            parse_query::<ParseQueryResult, _>(&mut req, &["id"], &[], Some(3), Some(&SINK));
+            get_query(&req).map(|_| ()).map_err(|e| e.to_string())
+        };
+
+        parse("id=1&a=1&a=2").unwrap();
+        parse("a=1").unwrap_err();
+        parse("id=1&a=1&b=1&c=1").unwrap_err();
+
+        assert_eq!(
+            *SINK.0.lock().unwrap(),
+            [
+                r#"DuplicateKey("a")"#,
+                r#"MissingKey("id")"#,
+                "LimitExceeded { pairs: 4, limit: 3 }",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_keyed() {
+        enum Admin {}
+
+        let mut req = Request::get("/?id=1").body(()).unwrap();
+        parse_query::<ParseQueryResult, _>(&mut req, &["id"], &[], None, None);
+        parse_query::<Keyed<Admin>, _>(&mut req, &["id", "token"], &[], None, None);
+
+        assert!(get_query(&req).unwrap().is_some());
+        assert!(matches!(
+            get_query_as::<Keyed<Admin>, _>(&req).unwrap_err(),
+            crate::Error::QueryParse(ParseQueryError::MissingKey("token"))
+        ));
+    }
+
+    #[cfg(feature = "feat-integrate-tower")]
+    #[test]
+    fn test_strip_query_keys() {
+        let strip = |uri: &str| {
+            let mut req = Request::builder().uri(uri).body(()).unwrap();
+            strip_query_keys(&mut req, &["token", "sign"]);
+            req.uri().to_string()
+        };
+
+        assert_eq!(
+            strip("https://example.com/a?token=x&b=%20&sig%6E=y&c"),
+            "https://example.com/a?b=%20&c"
+        );
+        assert_eq!(strip("/a?token=x&sign=y"), "/a");
+        assert_eq!(strip("/a?b=1"), "/a?b=1");
+        assert_eq!(strip("/a"), "/a");
     }
 }
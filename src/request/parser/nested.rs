@@ -0,0 +1,439 @@
+//! HTTP request utilities: nested (bracket syntax) query parsing.
+//!
+//! Parses queries like `filter[status]=active&filter[tags][]=a` into a tree of
+//! [`QueryValue`], which is what `OpenAPI` `deepObject` style parameters and
+//! Rails / PHP clients send.
+
+use alloc::{
+    borrow::{Cow, ToOwned},
+    string::String,
+    vec,
+    vec::Vec,
+};
+
+use super::HashMap;
+
+/// Type alias for the map variant of [`QueryValue`].
+pub type QueryMap = HashMap<String, QueryValue, foldhash::fast::RandomState>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A nested query value.
+///
+/// - `key=value` → [`QueryValue::String`]
+/// - `key[]=a&key[]=b` → [`QueryValue::List`]
+/// - `key[sub]=value` → [`QueryValue::Map`]
+pub enum QueryValue {
+    /// Plain string value.
+    String(String),
+
+    /// List value, built from `key[]=...` pairs, in order of appearance.
+    List(Vec<QueryValue>),
+
+    /// Map value, built from `key[sub]=...` pairs.
+    Map(QueryMap),
+}
+
+impl Default for QueryValue {
+    fn default() -> Self {
+        Self::Map(QueryMap::default())
+    }
+}
+
+impl QueryValue {
+    #[cfg(feature = "feat-integrate-http")]
+    #[inline]
+    /// Parse nested query string from [`http::Uri`].
+    pub fn parse_uri(uri: &http::Uri) -> Option<Self> {
+        uri.query().map(Self::parse)
+    }
+
+    /// Parse nested query string.
+    ///
+    /// The result is always a [`QueryValue::Map`]. When the same path is
+    /// assigned values of different shapes (e.g. `a=1&a[b]=2`), the last one
+    /// wins.
+    pub fn parse(query: &str) -> Self {
+        use percent_encoding::percent_decode_str;
+
+        let mut root = Self::default();
+
+        // Clients seldom percent-encode brackets, which are not allowed in query
+        // component by RFC 3986, so here we don't validate the query with
+        // `fluent_uri` as `Query::parse` does.
+        query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| pair.split_once('=').unwrap_or((pair, "")))
+            .for_each(|(k, v)| {
+                let key = percent_decode_str(k).decode_utf8_lossy();
+                let value = percent_decode_str(v).decode_utf8_lossy().into_owned();
+
+                root.insert(&key_path(&key), value);
+            });
+
+        root
+    }
+
+    #[inline]
+    /// Returns the string value, if this is a [`QueryValue::String`].
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    /// Returns the list value, if this is a [`QueryValue::List`].
+    pub fn as_list(&self) -> Option<&[QueryValue]> {
+        match self {
+            Self::List(l) => Some(l),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    /// Returns the map value, if this is a [`QueryValue::Map`].
+    pub const fn as_map(&self) -> Option<&QueryMap> {
+        match self {
+            Self::Map(m) => Some(m),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    /// Get the child value with given key, if this is a [`QueryValue::Map`].
+    pub fn get(&self, key: &str) -> Option<&QueryValue> {
+        self.as_map().and_then(|m| m.get(key))
+    }
+
+    #[cfg(feature = "feat-request-parser-ext-serde")]
+    #[inline]
+    /// Deserialize the nested query value into `T`.
+    ///
+    /// Primitives (numbers, booleans, etc) are parsed from the string values.
+    ///
+    /// # Errors
+    ///
+    /// See [`Error`].
+    pub fn deserialize_into<T>(self) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        T::deserialize(self)
+    }
+
+    fn insert(&mut self, path: &[Segment<'_>], value: String) {
+        let Some((segment, rest)) = path.split_first() else {
+            *self = Self::String(value);
+            return;
+        };
+
+        match segment {
+            Segment::Key(key) => {
+                if !matches!(self, Self::Map(_)) {
+                    *self = Self::default();
+                }
+
+                if let Self::Map(map) = self {
+                    map.entry((**key).to_owned())
+                        .or_insert_with(|| Self::String(String::new()))
+                        .insert(rest, value);
+                }
+            }
+            Segment::Push => {
+                if !matches!(self, Self::List(_)) {
+                    *self = Self::List(Vec::new());
+                }
+
+                if let Self::List(list) = self {
+                    let mut item = Self::String(String::new());
+                    item.insert(rest, value);
+                    list.push(item);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Segment<'k> {
+    /// `[key]`, or the leading key
+    Key(Cow<'k, str>),
+
+    /// `[]`
+    Push,
+}
+
+/// Split `a[b][]` into `["a", "b", Push]`.
+///
+/// Malformed keys (e.g. unclosed bracket) are treated as a plain key.
+fn key_path(key: &str) -> Vec<Segment<'_>> {
+    let Some(start) = key.find('[').filter(|&start| start > 0) else {
+        return vec![Segment::Key(key.into())];
+    };
+
+    let mut path = vec![Segment::Key(key[..start].into())];
+    let mut rest = &key[start..];
+
+    while !rest.is_empty() {
+        let Some(end) = rest.strip_prefix('[').and_then(|r| r.find(']')) else {
+            return vec![Segment::Key(key.into())];
+        };
+
+        path.push(match &rest[1..=end] {
+            "" => Segment::Push,
+            sub => Segment::Key(sub.into()),
+        });
+        rest = &rest[end + 2..];
+    }
+
+    path
+}
+
+#[cfg(feature = "feat-request-parser-ext-serde")]
+mod de {
+    //! serde deserialization support for [`QueryValue`].
+
+    use serde::de::{
+        self,
+        value::{MapDeserializer, SeqDeserializer},
+        IntoDeserializer, Visitor,
+    };
+
+    use super::QueryValue;
+
+    #[derive(Debug, Clone)]
+    #[derive(thiserror::Error)]
+    /// Errors when deserializing from [`QueryValue`].
+    pub enum Error {
+        #[error("{0}")]
+        /// Custom error from the deserialized type.
+        Custom(String),
+
+        #[error("invalid value `{value}`, expected {expected}")]
+        /// The string value cannot be parsed as the expected primitive.
+        InvalidValue {
+            /// The original string value
+            value: String,
+
+            /// The expected type
+            expected: &'static str,
+        },
+    }
+
+    impl de::Error for Error {
+        fn custom<T: std::fmt::Display>(msg: T) -> Self {
+            Self::Custom(msg.to_string())
+        }
+    }
+
+    impl<'de> IntoDeserializer<'de, Error> for QueryValue {
+        type Deserializer = Self;
+
+        #[inline]
+        fn into_deserializer(self) -> Self::Deserializer {
+            self
+        }
+    }
+
+    macro_rules! deserialize_parsed {
+        ($($method:ident => $visit:ident($ty:ty)),* $(,)?) => {
+            $(
+                fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+                where
+                    V: Visitor<'de>,
+                {
+                    match self {
+                        QueryValue::String(s) => match s.parse::<$ty>() {
+                            Ok(v) => visitor.$visit(v),
+                            Err(_) => Err(Error::InvalidValue {
+                                value: s,
+                                expected: stringify!($ty),
+                            }),
+                        },
+                        other => other.deserialize_any(visitor),
+                    }
+                }
+            )*
+        };
+    }
+
+    impl<'de> de::Deserializer<'de> for QueryValue {
+        type Error = Error;
+
+        deserialize_parsed! {
+            deserialize_bool => visit_bool(bool),
+            deserialize_i8 => visit_i8(i8),
+            deserialize_i16 => visit_i16(i16),
+            deserialize_i32 => visit_i32(i32),
+            deserialize_i64 => visit_i64(i64),
+            deserialize_i128 => visit_i128(i128),
+            deserialize_u8 => visit_u8(u8),
+            deserialize_u16 => visit_u16(u16),
+            deserialize_u32 => visit_u32(u32),
+            deserialize_u64 => visit_u64(u64),
+            deserialize_u128 => visit_u128(u128),
+            deserialize_f32 => visit_f32(f32),
+            deserialize_f64 => visit_f64(f64),
+            deserialize_char => visit_char(char),
+        }
+
+        serde::forward_to_deserialize_any! {
+            str string bytes byte_buf unit unit_struct tuple tuple_struct
+            map struct identifier ignored_any
+        }
+
+        fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            match self {
+                QueryValue::String(s) => visitor.visit_string(s),
+                QueryValue::List(l) => visitor.visit_seq(SeqDeserializer::new(l.into_iter())),
+                QueryValue::Map(m) => visitor.visit_map(MapDeserializer::new(m.into_iter())),
+            }
+        }
+
+        #[inline]
+        fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            visitor.visit_some(self)
+        }
+
+        #[inline]
+        fn deserialize_newtype_struct<V>(
+            self,
+            _name: &'static str,
+            visitor: V,
+        ) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            visitor.visit_newtype_struct(self)
+        }
+
+        fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            match self {
+                // `key=a` is accepted as a single element sequence.
+                QueryValue::String(_) => {
+                    visitor.visit_seq(SeqDeserializer::new(std::iter::once(self)))
+                }
+                other => other.deserialize_any(visitor),
+            }
+        }
+
+        fn deserialize_enum<V>(
+            self,
+            _name: &'static str,
+            _variants: &'static [&'static str],
+            visitor: V,
+        ) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            match self {
+                QueryValue::String(s) => {
+                    visitor.visit_enum(<String as IntoDeserializer<'de, Error>>::into_deserializer(
+                        s,
+                    ))
+                }
+                other => other.deserialize_any(visitor),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "feat-request-parser-ext-serde")]
+pub use de::Error;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_nested() {
+        let value =
+            QueryValue::parse("filter[status]=active&filter[tags][]=a&filter[tags][]=b&page=1");
+
+        assert_eq!(
+            value.get("filter").and_then(|f| f.get("status")),
+            Some(&QueryValue::String("active".to_owned()))
+        );
+        assert_eq!(
+            value.get("filter").and_then(|f| f.get("tags")),
+            Some(&QueryValue::List(vec![
+                QueryValue::String("a".to_owned()),
+                QueryValue::String("b".to_owned())
+            ]))
+        );
+        assert_eq!(value.get("page").and_then(QueryValue::as_str), Some("1"));
+    }
+
+    #[test]
+    fn test_parse_encoded_and_malformed() {
+        let value = QueryValue::parse("filter%5Bstatus%5D=active&broken[key=1&[x]=2");
+
+        assert_eq!(
+            value
+                .get("filter")
+                .and_then(|f| f.get("status"))
+                .and_then(QueryValue::as_str),
+            Some("active")
+        );
+        assert_eq!(
+            value.get("broken[key").and_then(QueryValue::as_str),
+            Some("1")
+        );
+        assert_eq!(value.get("[x]").and_then(QueryValue::as_str), Some("2"));
+    }
+
+    #[cfg(feature = "feat-request-parser-ext-serde")]
+    #[test]
+    fn test_deserialize() {
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        #[serde(rename_all = "lowercase")]
+        enum Status {
+            Active,
+        }
+
+        #[derive(Debug, serde::Deserialize)]
+        struct Filter {
+            status: Status,
+            tags: Vec<String>,
+            limit: Option<u32>,
+        }
+
+        #[derive(Debug, serde::Deserialize)]
+        struct Params {
+            filter: Filter,
+            page: u64,
+        }
+
+        let params: Params =
+            QueryValue::parse("filter[status]=active&filter[tags]=a&filter[limit]=10&page=2")
+                .deserialize_into()
+                .unwrap();
+
+        assert_eq!(params.filter.status, Status::Active);
+        assert_eq!(params.filter.tags, ["a"]);
+        assert_eq!(params.filter.limit, Some(10));
+        assert_eq!(params.page, 2);
+
+        let err = QueryValue::parse("filter[status]=active&filter[tags][]=a&page=x")
+            .deserialize_into::<Params>()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidValue {
+                expected: "u64",
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,178 @@
+//! HTTP request utilities: serde support for [`OwnedQuery`].
+//!
+//! [`OwnedQuery`] is (de)serialized as a map by default. To (de)serialize it as
+//! the query string instead, use [`as_query_string`]:
+//!
+//! ```rust
+//! # use miku_http_util::request::parser::OwnedQuery;
+//! #[derive(serde::Serialize, serde::Deserialize)]
+//! struct AuditRecord {
+//!     #[serde(with = "miku_http_util::request::parser::serde_helper::as_query_string")]
+//!     query: OwnedQuery,
+//! }
+//! ```
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::OwnedQuery;
+
+impl Serialize for OwnedQuery {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        as_map::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for OwnedQuery {
+    #[inline]
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        as_map::deserialize(deserializer)
+    }
+}
+
+pub mod as_map {
+    //! (De)serialize [`OwnedQuery`] as a map of strings, the default one.
+
+    use std::{collections::HashMap, fmt, sync::Arc};
+
+    use serde::{
+        de::{MapAccess, Visitor},
+        Deserializer, Serializer,
+    };
+
+    use super::OwnedQuery;
+
+    /// Serialize [`OwnedQuery`] as a map, keys sorted for stable output.
+    ///
+    /// # Errors
+    ///
+    /// Serializer errors.
+    pub fn serialize<S>(query: &OwnedQuery, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut pairs: Vec<_> = query.iter().map(|(k, v)| (&**k, &**v)).collect();
+        pairs.sort_unstable_by_key(|&(k, _)| k);
+
+        serializer.collect_map(pairs)
+    }
+
+    /// Deserialize [`OwnedQuery`] from a map.
+    ///
+    /// # Errors
+    ///
+    /// Deserializer errors.
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<OwnedQuery, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct QueryVisitor;
+
+        impl<'de> Visitor<'de> for QueryVisitor {
+            type Value = OwnedQuery;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a map of query pairs")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut inner = HashMap::with_capacity_and_hasher(
+                    map.size_hint().unwrap_or_default(),
+                    foldhash::fast::RandomState::default(),
+                );
+
+                while let Some((k, v)) = map.next_entry::<String, String>()? {
+                    inner.insert(Arc::from(k), Arc::from(v));
+                }
+
+                Ok(OwnedQuery::new(Arc::new(inner)))
+            }
+        }
+
+        deserializer.deserialize_map(QueryVisitor)
+    }
+}
+
+pub mod as_query_string {
+    //! (De)serialize [`OwnedQuery`] as the query string.
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::OwnedQuery;
+    use crate::percent;
+
+    /// Serialize [`OwnedQuery`] as the query string, keys sorted for stable
+    /// output.
+    ///
+    /// # Errors
+    ///
+    /// Serializer errors.
+    pub fn serialize<S>(query: &OwnedQuery, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut pairs: Vec<_> = query.iter().collect();
+        pairs.sort_unstable_by_key(|&(k, _)| k);
+
+        let mut buf = String::with_capacity(64);
+        for (idx, (k, v)) in pairs.into_iter().enumerate() {
+            if idx != 0 {
+                buf.push('&');
+            }
+
+            percent::encode_to(k, percent::QUERY, &mut buf);
+            buf.push('=');
+            percent::encode_to(v, percent::QUERY, &mut buf);
+        }
+
+        serializer.serialize_str(&buf)
+    }
+
+    /// Deserialize [`OwnedQuery`] from the query string.
+    ///
+    /// # Errors
+    ///
+    /// Deserializer errors.
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<OwnedQuery, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer).map(|query| OwnedQuery::parse(&query))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Record {
+        #[serde(with = "as_query_string")]
+        query: OwnedQuery,
+    }
+
+    #[test]
+    fn test_serde() {
+        let query = OwnedQuery::parse("b=2&a=%E4%BD%A0%20%26");
+
+        let json = serde_json::to_string(&query).unwrap();
+        assert_eq!(json, r#"{"a":"你 &","b":"2"}"#);
+        let query: OwnedQuery = serde_json::from_str(&json).unwrap();
+        assert_eq!(query.get("a"), Some("你 &"));
+
+        let json = serde_json::to_string(&Record { query }).unwrap();
+        assert_eq!(json, r#"{"query":"a=%E4%BD%A0%20%26&b=2"}"#);
+        let record: Record = serde_json::from_str(&json).unwrap();
+        assert_eq!(record.query.get("a"), Some("你 &"));
+        assert_eq!(record.query.get("b"), Some("2"));
+    }
+}
@@ -0,0 +1,326 @@
+//! Signed query validation: signature, expiry and clock skew, see
+//! [`validate_signed_query`].
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::Query;
+use crate::request::builder::{self, Md5Signer, SignerT};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(thiserror::Error)]
+/// Errors validating the signed query.
+pub enum SignedQueryError {
+    #[error("missing signature")]
+    /// The signature param is missing.
+    MissingSignature,
+
+    #[error("bad signature")]
+    /// The signature mismatches.
+    BadSignature,
+
+    #[error("missing timestamp")]
+    /// Neither the timestamp nor the expiry param is present, while required.
+    MissingTimestamp,
+
+    #[error("invalid timestamp `{0}`")]
+    /// The timestamp or expiry is not a Unix timestamp in seconds.
+    InvalidTimestamp(String),
+
+    #[error("expired at {0} (unix timestamp)")]
+    /// The URL has expired.
+    Expired(u64),
+
+    #[error("not valid until {0} (unix timestamp)")]
+    /// The timestamp is in the future beyond the tolerated skew.
+    NotYetValid(u64),
+}
+
+/// Verifier of the query signature.
+pub trait QueryVerifierT {
+    /// Returns the signature param key, like `sign`.
+    fn signature_key(&self) -> &str;
+
+    /// Verify the signature against the query without the signature param.
+    fn verify(&self, query: &Query<'_>, signature: &str) -> bool;
+}
+
+impl QueryVerifierT for Md5Signer<'_> {
+    #[inline]
+    fn signature_key(&self) -> &str {
+        self.query_key
+    }
+
+    fn verify(&self, query: &Query<'_>, signature: &str) -> bool {
+        let query = query
+            .iter()
+            .fold(builder::Query::with_capacity(query.len()), |acc, (k, v)| {
+                acc.push(k.as_ref(), v.as_ref())
+            });
+
+        let signed = match self.build_signed(query) {
+            Ok(signed) => signed,
+            Err(infallible) => match infallible {},
+        };
+
+        signed
+            .rsplit('=')
+            .next()
+            .is_some_and(|expected| constant_time_eq(expected, signature))
+    }
+}
+
+/// Compare without short-circuiting, against timing attacks.
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Tolerances of the signed query timestamps.
+pub struct SkewPolicy {
+    /// The param of the signing time.
+    ts_key: &'static str,
+
+    /// The param of the expiry time.
+    expires_key: &'static str,
+
+    /// How long the URL is valid after the signing time, `None` to check the
+    /// expiry param only.
+    max_age: Option<Duration>,
+
+    /// The tolerated clock skew, in both directions.
+    max_skew: Duration,
+
+    /// Whether either timestamp param is required.
+    require_timestamp: bool,
+}
+
+impl Default for SkewPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SkewPolicy {
+    #[inline]
+    /// Create a new [`SkewPolicy`]: params `ts` and `expires`, a max age of 5
+    /// minutes, a clock skew of 30 seconds and timestamps required.
+    pub const fn new() -> Self {
+        Self {
+            ts_key: "ts",
+            expires_key: "expires",
+            max_age: Some(Duration::from_secs(300)),
+            max_skew: Duration::from_secs(30),
+            require_timestamp: true,
+        }
+    }
+
+    #[inline]
+    /// Set the param of the signing time.
+    pub const fn with_ts_key(self, ts_key: &'static str) -> Self {
+        Self { ts_key, ..self }
+    }
+
+    #[inline]
+    /// Set the param of the expiry time.
+    pub const fn with_expires_key(self, expires_key: &'static str) -> Self {
+        Self {
+            expires_key,
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Set the max age after the signing time.
+    pub const fn with_max_age(self, max_age: Option<Duration>) -> Self {
+        Self { max_age, ..self }
+    }
+
+    #[inline]
+    /// Set the tolerated clock skew.
+    pub const fn with_max_skew(self, max_skew: Duration) -> Self {
+        Self { max_skew, ..self }
+    }
+
+    #[inline]
+    /// Set whether either timestamp param is required.
+    pub const fn with_require_timestamp(self, require_timestamp: bool) -> Self {
+        Self {
+            require_timestamp,
+            ..self
+        }
+    }
+
+    /// Check the timestamps against `now`.
+    fn check(&self, query: &Query<'_>, now: SystemTime) -> Result<(), SignedQueryError> {
+        let now = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let skew = self.max_skew.as_secs();
+
+        let timestamp = |key: &str| {
+            query
+                .get(key)
+                .map(|value| {
+                    value
+                        .parse::<u64>()
+                        .map_err(|_| SignedQueryError::InvalidTimestamp(value.to_string()))
+                })
+                .transpose()
+        };
+
+        let ts = timestamp(self.ts_key)?;
+        let expires = timestamp(self.expires_key)?;
+
+        if self.require_timestamp && ts.is_none() && expires.is_none() {
+            return Err(SignedQueryError::MissingTimestamp);
+        }
+
+        if let Some(ts) = ts {
+            if ts > now.saturating_add(skew) {
+                return Err(SignedQueryError::NotYetValid(ts));
+            }
+
+            if let Some(max_age) = self.max_age {
+                let expires_at = ts.saturating_add(max_age.as_secs());
+
+                if now > expires_at.saturating_add(skew) {
+                    return Err(SignedQueryError::Expired(expires_at));
+                }
+            }
+        }
+
+        match expires {
+            Some(expires) if now > expires.saturating_add(skew) => {
+                Err(SignedQueryError::Expired(expires))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Validate the signed query string: the signature first, then the
+/// timestamps against the current time, see [`SkewPolicy`].
+///
+/// The timestamps are Unix timestamps in seconds, and must be covered by the
+/// signature.
+///
+/// # Errors
+///
+/// See [`SignedQueryError`].
+pub fn validate_signed_query<V>(
+    query: &str,
+    verifier: &V,
+    policy: SkewPolicy,
+) -> Result<(), SignedQueryError>
+where
+    V: QueryVerifierT + ?Sized,
+{
+    validate_signed_query_at(query, verifier, policy, crate::time::now())
+}
+
+/// Like [`validate_signed_query`], against the given time.
+///
+/// # Errors
+///
+/// See [`SignedQueryError`].
+pub fn validate_signed_query_at<V>(
+    query: &str,
+    verifier: &V,
+    policy: SkewPolicy,
+    now: SystemTime,
+) -> Result<(), SignedQueryError>
+where
+    V: QueryVerifierT + ?Sized,
+{
+    let mut query = Query::parse(query);
+
+    let signature = query
+        .remove(verifier.signature_key())
+        .ok_or(SignedQueryError::MissingSignature)?;
+
+    if !verifier.verify(&query, &signature) {
+        return Err(SignedQueryError::BadSignature);
+    }
+
+    policy.check(&query, now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIGNER: Md5Signer<'static> = Md5Signer::new_default().with_suffix_salt(Some("salt"));
+
+    fn sign(pairs: &[(&'static str, &'static str)]) -> String {
+        pairs
+            .iter()
+            .fold(builder::Query::with_capacity(4), |acc, &(k, v)| {
+                acc.push(k, v)
+            })
+            .build_signed(SIGNER)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_validate_signed_query() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let policy = SkewPolicy::new();
+
+        let query = sign(&[("a", "1"), ("ts", "1000000")]);
+        assert_eq!(
+            validate_signed_query_at(&query, &SIGNER, policy, now),
+            Ok(())
+        );
+
+        let tampered = query.replace("a=1", "a=2");
+        assert_eq!(
+            validate_signed_query_at(&tampered, &SIGNER, policy, now),
+            Err(SignedQueryError::BadSignature)
+        );
+
+        assert_eq!(
+            validate_signed_query_at("a=1", &SIGNER, policy, now),
+            Err(SignedQueryError::MissingSignature)
+        );
+
+        let query = sign(&[("ts", "999000")]);
+        assert_eq!(
+            validate_signed_query_at(&query, &SIGNER, policy, now),
+            Err(SignedQueryError::Expired(999_300))
+        );
+
+        let query = sign(&[("ts", "1000020")]);
+        assert_eq!(
+            validate_signed_query_at(&query, &SIGNER, policy, now),
+            Ok(())
+        );
+
+        let query = sign(&[("ts", "1000100")]);
+        assert_eq!(
+            validate_signed_query_at(&query, &SIGNER, policy, now),
+            Err(SignedQueryError::NotYetValid(1_000_100))
+        );
+
+        let query = sign(&[("expires", "999990")]);
+        assert_eq!(
+            validate_signed_query_at(&query, &SIGNER, policy, now),
+            Ok(())
+        );
+        assert_eq!(
+            validate_signed_query_at(&query, &SIGNER, policy.with_max_skew(Duration::ZERO), now),
+            Err(SignedQueryError::Expired(999_990))
+        );
+
+        let query = sign(&[("a", "1")]);
+        assert_eq!(
+            validate_signed_query_at(&query, &SIGNER, policy, now),
+            Err(SignedQueryError::MissingTimestamp)
+        );
+        assert_eq!(
+            validate_signed_query_at(&query, &SIGNER, policy.with_require_timestamp(false), now),
+            Ok(())
+        );
+    }
+}
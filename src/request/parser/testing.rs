@@ -0,0 +1,44 @@
+//! HTTP request utilities: helpers for testing handlers which rely on the
+//! query parsing layers.
+//!
+//! The requests built here have their extensions pre-populated exactly as
+//! [`WithQueryLayer`](super::integration::WithQueryLayer) or
+//! [`WithQueryHandler`](super::integration::WithQueryHandler) would do, so
+//! there's no need to spin up the full middleware stack in unit tests.
+
+use http::Request;
+
+use super::integration::{parse_query, ParseQueryResult, QueryRule};
+
+#[inline]
+/// Build a `GET /?{query}` request, with the parsed query inserted into the
+/// extensions.
+///
+/// # Panics
+///
+/// Panics if `/?{query}` is not a valid URI.
+pub fn request_with_query(query: &str) -> Request<()> {
+    request_with_query_checked(query, &[], &[])
+}
+
+/// Build a `GET /?{query}` request, with the parsed query (or the error
+/// reported due to given `required` keys and `rules`) inserted into the
+/// extensions.
+///
+/// # Panics
+///
+/// Panics if `/?{query}` is not a valid URI.
+pub fn request_with_query_checked(
+    query: &str,
+    required: &'static [&'static str],
+    rules: &'static [QueryRule],
+) -> Request<()> {
+    let mut req = Request::builder()
+        .uri(format!("/?{query}"))
+        .body(())
+        .expect("invalid query");
+
+    parse_query::<ParseQueryResult, _>(&mut req, required, rules, None, None);
+
+    req
+}
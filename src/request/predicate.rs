@@ -0,0 +1,269 @@
+//! HTTP request utilities: request matching, see [`Predicate`].
+
+use std::ops;
+
+use http::{HeaderName, HeaderValue, Method, Request};
+use regex::Regex;
+
+use crate::request::parser::OwnedQuery;
+
+#[derive(Debug)]
+#[derive(thiserror::Error)]
+/// Errors building [`Predicate`].
+pub enum PredicateError {
+    #[error("invalid header name `{0}`")]
+    /// Invalid header name.
+    InvalidHeaderName(String),
+
+    #[error("invalid header value `{0}`")]
+    /// Invalid header value.
+    InvalidHeaderValue(String),
+
+    #[error("invalid regex: {0}")]
+    /// Invalid regex, see [`regex::Error`] for more details.
+    InvalidRegex(#[from] regex::Error),
+}
+
+#[derive(Debug, Clone)]
+/// Request predicate, built (and compiled, e.g. the regexes) once and
+/// evaluated against each request, see [`matches`](Self::matches).
+///
+/// # Examples
+///
+/// ```rust
+/// # use http::{Method, Request};
+/// # use miku_http_util::request::predicate::Predicate;
+/// let predicate = Predicate::path_prefix("/api")
+///     .and(!Predicate::method(Method::POST))
+///     .and(
+///         Predicate::header_equals("x-canary", "1")
+///             .unwrap()
+///             .or(Predicate::query_has("canary")),
+///     );
+///
+/// let request = Request::get("/api/users?canary").body(()).unwrap();
+/// assert!(predicate.matches(&request));
+///
+/// let request = Request::get("/apis?canary").body(()).unwrap();
+/// assert!(!predicate.matches(&request));
+/// ```
+pub enum Predicate {
+    /// Matches the method.
+    Method(Method),
+
+    /// Matches the path prefix, segment by segment, e.g. `/api` matches `/api`
+    /// and `/api/users`, but not `/apis`.
+    PathPrefix(String),
+
+    /// Any value of the header equals the value.
+    HeaderEquals(HeaderName, HeaderValue),
+
+    /// Any value of the header contains the string.
+    HeaderContains(HeaderName, String),
+
+    /// Any value of the header matches the regex.
+    HeaderRegex(HeaderName, Regex),
+
+    /// The query contains the key.
+    QueryHas(String),
+
+    /// All the predicates match, `true` when empty.
+    All(Vec<Predicate>),
+
+    /// Any of the predicates matches, `false` when empty.
+    Any(Vec<Predicate>),
+
+    /// The predicate does not match.
+    Not(Box<Predicate>),
+}
+
+impl Predicate {
+    #[inline]
+    /// See [`Predicate::Method`].
+    pub const fn method(method: Method) -> Self {
+        Self::Method(method)
+    }
+
+    #[inline]
+    /// See [`Predicate::PathPrefix`].
+    pub fn path_prefix(prefix: impl Into<String>) -> Self {
+        Self::PathPrefix(prefix.into())
+    }
+
+    /// See [`Predicate::HeaderEquals`].
+    ///
+    /// # Errors
+    ///
+    /// Invalid header name or value.
+    pub fn header_equals(name: &str, value: &str) -> Result<Self, PredicateError> {
+        let value = HeaderValue::from_str(value)
+            .map_err(|_| PredicateError::InvalidHeaderValue(value.to_owned()))?;
+
+        Ok(Self::HeaderEquals(header_name(name)?, value))
+    }
+
+    /// See [`Predicate::HeaderContains`].
+    ///
+    /// # Errors
+    ///
+    /// Invalid header name.
+    pub fn header_contains(name: &str, needle: impl Into<String>) -> Result<Self, PredicateError> {
+        Ok(Self::HeaderContains(header_name(name)?, needle.into()))
+    }
+
+    /// See [`Predicate::HeaderRegex`].
+    ///
+    /// # Errors
+    ///
+    /// Invalid header name or regex.
+    pub fn header_regex(name: &str, regex: &str) -> Result<Self, PredicateError> {
+        Ok(Self::HeaderRegex(header_name(name)?, Regex::new(regex)?))
+    }
+
+    #[inline]
+    /// See [`Predicate::QueryHas`].
+    pub fn query_has(key: impl Into<String>) -> Self {
+        Self::QueryHas(key.into())
+    }
+
+    #[must_use]
+    /// Both predicates match.
+    pub fn and(self, other: Self) -> Self {
+        match self {
+            Self::All(mut all) => {
+                all.push(other);
+                Self::All(all)
+            }
+            this => Self::All(vec![this, other]),
+        }
+    }
+
+    #[must_use]
+    /// Either predicate matches.
+    pub fn or(self, other: Self) -> Self {
+        match self {
+            Self::Any(mut any) => {
+                any.push(other);
+                Self::Any(any)
+            }
+            this => Self::Any(vec![this, other]),
+        }
+    }
+
+    #[inline]
+    /// Evaluate the predicate against the request.
+    ///
+    /// The query is parsed (as [`OwnedQuery`]) at most once, and only when
+    /// needed.
+    pub fn matches<B>(&self, request: &Request<B>) -> bool {
+        self.eval(request, &mut None)
+    }
+
+    fn eval<B>(&self, request: &Request<B>, query: &mut Option<OwnedQuery>) -> bool {
+        match self {
+            Self::Method(method) => request.method() == method,
+            Self::PathPrefix(prefix) => {
+                let prefix = prefix.trim_end_matches('/');
+
+                request
+                    .uri()
+                    .path()
+                    .strip_prefix(prefix)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+            }
+            Self::HeaderEquals(name, value) => {
+                request.headers().get_all(name).iter().any(|v| v == value)
+            }
+            Self::HeaderContains(name, needle) => request
+                .headers()
+                .get_all(name)
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .any(|v| v.contains(needle.as_str())),
+            Self::HeaderRegex(name, regex) => request
+                .headers()
+                .get_all(name)
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .any(|v| regex.is_match(v)),
+            Self::QueryHas(key) => query
+                .get_or_insert_with(|| OwnedQuery::parse(request.uri().query().unwrap_or_default()))
+                .get(key.as_str())
+                .is_some(),
+            Self::All(all) => all.iter().all(|p| p.eval(request, query)),
+            Self::Any(any) => any.iter().any(|p| p.eval(request, query)),
+            Self::Not(predicate) => !predicate.eval(request, query),
+        }
+    }
+}
+
+impl ops::Not for Predicate {
+    type Output = Self;
+
+    fn not(self) -> Self::Output {
+        match self {
+            Self::Not(predicate) => *predicate,
+            predicate => Self::Not(Box::new(predicate)),
+        }
+    }
+}
+
+fn header_name(name: &str) -> Result<HeaderName, PredicateError> {
+    HeaderName::from_bytes(name.as_bytes())
+        .map_err(|_| PredicateError::InvalidHeaderName(name.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_predicate() {
+        let request = Request::post("https://example.com/api/v2/users?id=1&debug")
+            .header("user-agent", "curl/8.0")
+            .header("x-tenant", "a")
+            .header("x-tenant", "b")
+            .body(())
+            .unwrap();
+
+        for (predicate, expected) in [
+            (Predicate::method(Method::POST), true),
+            (Predicate::path_prefix("/api/"), true),
+            (Predicate::path_prefix("/api/v2/users"), true),
+            (Predicate::path_prefix("/api/v"), false),
+            (Predicate::header_equals("x-tenant", "b").unwrap(), true),
+            (Predicate::header_equals("X-Tenant", "c").unwrap(), false),
+            (
+                Predicate::header_contains("user-agent", "curl").unwrap(),
+                true,
+            ),
+            (
+                Predicate::header_regex("user-agent", r"^curl/[0-7]\.").unwrap(),
+                false,
+            ),
+            (Predicate::query_has("debug"), true),
+            (Predicate::query_has("page"), false),
+            (
+                Predicate::query_has("page").or(Predicate::query_has("id")),
+                true,
+            ),
+            (
+                Predicate::query_has("id").and(!Predicate::method(Method::POST)),
+                false,
+            ),
+            (Predicate::All(Vec::new()), true),
+            (Predicate::Any(Vec::new()), false),
+        ] {
+            assert_eq!(predicate.matches(&request), expected, "{predicate:?}");
+        }
+
+        assert!(matches!(
+            Predicate::header_regex("x", "("),
+            Err(PredicateError::InvalidRegex(_))
+        ));
+        assert!(matches!(
+            Predicate::header_equals("bad name", ""),
+            Err(PredicateError::InvalidHeaderName(_))
+        ));
+    }
+}
@@ -0,0 +1,260 @@
+//! HTTP request utilities: URI related, see [`UriExtT`].
+
+use http::{uri::InvalidUri, Uri};
+
+use crate::{percent, request::builder::Query};
+
+/// Extension trait for editing [`Uri`] without string surgery.
+///
+/// All methods return the rebuilt [`Uri`], leaving the original untouched.
+pub trait UriExtT {
+    /// Replace the query with the built one, see [`Query::build`].
+    ///
+    /// An empty query removes the query component.
+    ///
+    /// # Errors
+    ///
+    /// [`InvalidUri`], e.g. keys with characters not allowed (the builder does
+    /// not encode keys).
+    fn with_query(&self, query: Query<'_>) -> Result<Uri, InvalidUri>;
+
+    /// Append the query pair, both percent-encoded with [`percent::QUERY`].
+    ///
+    /// # Errors
+    ///
+    /// [`InvalidUri`], which should not happen.
+    fn append_query(&self, key: &str, value: &str) -> Result<Uri, InvalidUri>;
+
+    /// Remove the query pairs of the keys (compared after percent-decoding),
+    /// keeping the others as is.
+    ///
+    /// # Errors
+    ///
+    /// [`InvalidUri`], which should not happen.
+    fn without_query_keys(&self, keys: &[&str]) -> Result<Uri, InvalidUri>;
+
+    /// Normalize the URI (RFC 3986, section 6.2.2):
+    ///
+    /// - lowercase the scheme and host;
+    /// - remove the default port of `http`, `https`, `ws` and `wss`;
+    /// - decode percent-encoded unreserved characters, and uppercase the hex
+    ///   digits of the others, in the path and query;
+    /// - remove dot segments of the path, and use `/` for the empty path of
+    ///   absolute URIs (with the scheme).
+    ///
+    /// # Errors
+    ///
+    /// [`InvalidUri`], which should not happen.
+    fn normalized(&self) -> Result<Uri, InvalidUri>;
+}
+
+impl UriExtT for Uri {
+    fn with_query(&self, query: Query<'_>) -> Result<Uri, InvalidUri> {
+        rebuild(self, self.path(), Some(&query.build()))
+    }
+
+    fn append_query(&self, key: &str, value: &str) -> Result<Uri, InvalidUri> {
+        let mut query = self.query().unwrap_or_default().to_owned();
+
+        if !query.is_empty() {
+            query.push('&');
+        }
+
+        percent::encode_to(key, percent::QUERY, &mut query);
+        query.push('=');
+        percent::encode_to(value, percent::QUERY, &mut query);
+
+        rebuild(self, self.path(), Some(&query))
+    }
+
+    fn without_query_keys(&self, keys: &[&str]) -> Result<Uri, InvalidUri> {
+        match self
+            .query()
+            .and_then(|query| percent::strip_query_keys(query, keys))
+        {
+            Some(query) => rebuild(self, self.path(), Some(&query)),
+            None => Ok(self.clone()),
+        }
+    }
+
+    fn normalized(&self) -> Result<Uri, InvalidUri> {
+        let scheme = self.scheme_str().map(str::to_ascii_lowercase);
+
+        let authority = self.authority().map(|authority| {
+            let (user_info, host_port) = match authority.as_str().rsplit_once('@') {
+                Some((user_info, host_port)) => (Some(user_info), host_port),
+                None => (None, authority.as_str()),
+            };
+
+            let is_default_port = matches!(
+                (scheme.as_deref(), authority.port_u16()),
+                (Some("http" | "ws"), Some(80)) | (Some("https" | "wss"), Some(443))
+            );
+
+            let host_port = if is_default_port {
+                host_port
+                    .rsplit_once(':')
+                    .map_or(host_port, |(host, _)| host)
+            } else {
+                host_port
+            };
+
+            match user_info {
+                Some(user_info) => format!("{user_info}@{}", host_port.to_ascii_lowercase()),
+                None => host_port.to_ascii_lowercase(),
+            }
+        });
+
+        let mut path = remove_dot_segments(&percent::normalize(self.path()));
+        if path.is_empty() && scheme.is_some() {
+            path.push('/');
+        }
+
+        let query = self.query().map(percent::normalize);
+
+        let mut uri = String::with_capacity(64);
+
+        if let Some(scheme) = &scheme {
+            uri.push_str(scheme);
+            uri.push_str("://");
+        }
+
+        if let Some(authority) = &authority {
+            uri.push_str(authority);
+        }
+
+        uri.push_str(&path);
+
+        if let Some(query) = &query {
+            uri.push('?');
+            uri.push_str(query);
+        }
+
+        Uri::try_from(uri)
+    }
+}
+
+/// Rebuild the URI with the path and query (`None` or empty to remove).
+fn rebuild(uri: &Uri, path: &str, query: Option<&str>) -> Result<Uri, InvalidUri> {
+    let mut rebuilt = String::new();
+
+    if let Some(scheme) = uri.scheme_str() {
+        rebuilt.push_str(scheme);
+        rebuilt.push_str("://");
+    }
+
+    if let Some(authority) = uri.authority() {
+        rebuilt.push_str(authority.as_str());
+    }
+
+    rebuilt.push_str(path);
+
+    if let Some(query) = query.filter(|query| !query.is_empty()) {
+        rebuilt.push('?');
+        rebuilt.push_str(query);
+    }
+
+    Uri::try_from(rebuilt)
+}
+
+/// Remove the dot segments of the path (RFC 3986, section 5.2.4).
+fn remove_dot_segments(path: &str) -> String {
+    let is_absolute = path.starts_with('/');
+
+    let mut segments = Vec::new();
+    let mut trailing_slash = false;
+
+    for segment in path.split('/').skip(usize::from(is_absolute)) {
+        match segment {
+            "." => trailing_slash = true,
+            ".." => {
+                segments.pop();
+                trailing_slash = true;
+            }
+            segment => {
+                segments.push(segment);
+                trailing_slash = false;
+            }
+        }
+    }
+
+    let mut normalized = segments.join("/");
+
+    if is_absolute {
+        normalized.insert(0, '/');
+    }
+
+    if trailing_slash && !normalized.ends_with('/') {
+        normalized.push('/');
+    }
+
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_query() {
+        let uri = Uri::from_static("https://example.com/a?b=1&c=2&b=3&d");
+
+        assert_eq!(
+            uri.with_query(Query::with_capacity(1).push("x", "1 2"))
+                .unwrap(),
+            "https://example.com/a?x=1%202"
+        );
+        assert_eq!(
+            uri.with_query(Query::new()).unwrap(),
+            "https://example.com/a"
+        );
+        assert_eq!(
+            uri.append_query("k&", "v=").unwrap(),
+            "https://example.com/a?b=1&c=2&b=3&d&k%26=v%3D"
+        );
+        assert_eq!(
+            Uri::from_static("/a").append_query("k", "v").unwrap(),
+            "/a?k=v"
+        );
+        assert_eq!(
+            uri.without_query_keys(&["b", "d"]).unwrap(),
+            "https://example.com/a?c=2"
+        );
+        assert_eq!(
+            uri.without_query_keys(&["b", "c", "d"]).unwrap(),
+            "https://example.com/a"
+        );
+        assert_eq!(
+            Uri::from_static("/a?b%20=1&c")
+                .without_query_keys(&["b "])
+                .unwrap(),
+            "/a?c"
+        );
+
+        // Authority-form, without the scheme.
+        let uri = Uri::from_static("example.com:443");
+        assert_eq!(uri.with_query(Query::new()).unwrap(), uri);
+        assert_eq!(uri.without_query_keys(&["b"]).unwrap(), uri);
+    }
+
+    #[test]
+    fn test_normalized() {
+        for (uri, expected) in [
+            (
+                "HTTP://User@Example.COM:80/a/./b/../%7e%2fc?%7Ex=%2f",
+                "http://User@example.com/a/~%2Fc?~x=%2F",
+            ),
+            ("https://example.com:443", "https://example.com/"),
+            ("https://example.com:8443/a/..", "https://example.com:8443/"),
+            ("/a/b/../../../c/.", "/c/"),
+            ("/a//b", "/a//b"),
+            ("example.com:443", "example.com:443"),
+        ] {
+            assert_eq!(
+                Uri::try_from(uri).unwrap().normalized().unwrap(),
+                expected,
+                "{uri}"
+            );
+        }
+    }
+}
@@ -1,8 +1,111 @@
 //! HTTP response utilities
 
+pub mod builder;
+pub mod cache;
+#[cfg(feature = "feat-response-ext-cbor")]
+pub mod cbor;
+pub mod content_type;
+pub mod decompress;
+pub mod deprecation;
+#[cfg(feature = "feat-response-ext-digest")]
+pub mod digest;
+#[cfg(feature = "feat-response-ext-download")]
+pub mod download;
+#[cfg(feature = "feat-response-ext-json")]
+pub mod envelope;
+#[cfg(feature = "feat-response-ext-grpc")]
+pub mod grpc;
+pub mod hints;
+#[cfg(feature = "feat-response-ext-json")]
+pub mod json;
+#[cfg(feature = "feat-response-ext-msgpack")]
+pub mod msgpack;
+pub mod pagination;
+#[cfg(feature = "feat-response-ext-json")]
+pub mod problem;
+pub mod rate_limit;
+pub mod redirect;
+pub mod retry;
+#[cfg(feature = "feat-response-ext-save")]
+pub mod save;
+pub mod security;
+#[cfg(feature = "feat-response-ext-snapshot")]
+pub mod snapshot;
+pub mod status;
+pub mod text;
+pub mod timings;
+pub mod transform;
+
 use bytes::Bytes;
 use http::response::Parts;
 
+// re-export
+pub use self::builder::{BuildError, ResponseExtBuilder, Trailers};
+// re-export
+pub use self::cache::{CacheControl, CachePolicy};
+#[cfg(feature = "feat-response-ext-cbor")]
+// re-export
+pub use self::cbor::CborError;
+// re-export
+pub use self::content_type::ContentTypeError;
+// re-export
+pub use self::decompress::{DecompressError, Encoding};
+// re-export
+pub use self::deprecation::DeprecationInfo;
+#[cfg(feature = "feat-response-ext-digest")]
+// re-export
+pub use self::digest::{DigestAlgorithm, DigestError};
+#[cfg(feature = "feat-response-ext-download")]
+// re-export
+pub use self::download::{
+    ByteRange, DownloadError, Downloaded, RangeFetch, RangePlanner, RangedDownloader,
+};
+#[cfg(feature = "feat-response-ext-json")]
+// re-export
+pub use self::envelope::{Envelope, EnvelopeError};
+#[cfg(feature = "feat-response-ext-grpc")]
+// re-export
+pub use self::grpc::{GrpcCode, GrpcStatus};
+// re-export
+pub use self::hints::{AltSvc, EarlyHints, PreloadHint};
+#[cfg(feature = "feat-response-ext-json-stream")]
+// re-export
+pub use self::json::{json_array_stream, JsonStreamError};
+#[cfg(feature = "feat-response-ext-json")]
+// re-export
+pub use self::json::{JsonArrayDecoder, JsonCheckedError, JsonError, Ndjson, NdjsonDecoder};
+#[cfg(feature = "feat-response-ext-msgpack")]
+// re-export
+pub use self::msgpack::MsgpackError;
+#[cfg(feature = "feat-response-ext-paginate")]
+// re-export
+pub use self::pagination::paginate;
+#[cfg(feature = "feat-response-ext-json")]
+// re-export
+pub use self::problem::ProblemDetails;
+// re-export
+pub use self::pagination::{NextPage, Pagination};
+// re-export
+pub use self::rate_limit::RateLimitInfo;
+// re-export
+pub use self::redirect::{RedirectError, RedirectHop, RedirectTrace};
+// re-export
+pub use self::retry::{RetryHint, RetryReason};
+#[cfg(feature = "feat-response-ext-save")]
+// re-export
+pub use self::save::SaveOptions;
+// re-export
+pub use self::security::SecurityHeaders;
+#[cfg(feature = "feat-response-ext-snapshot")]
+// re-export
+pub use self::snapshot::{ResponseSnapshot, SnapshotBody, SnapshotError, SnapshotOptions};
+// re-export
+pub use self::status::StatusError;
+// re-export
+pub use self::timings::Timings;
+// re-export
+pub use self::transform::{BodyPipeline, BodyTransform, TransformError};
+
 #[derive(Debug, Clone)]
 /// Response (Extended)
 pub struct ResponseExt<B = Bytes> {
@@ -14,16 +117,493 @@ pub struct ResponseExt<B = Bytes> {
 }
 
 impl ResponseExt {
+    #[inline]
+    /// Create a [`ResponseExtBuilder`], mainly for unit tests.
+    pub fn builder() -> ResponseExtBuilder {
+        ResponseExtBuilder::new()
+    }
+
+    #[inline]
+    /// Returns the trailers, if any were put into the extensions as
+    /// [`Trailers`].
+    pub fn trailers(&self) -> Option<&http::HeaderMap> {
+        self.response_parts
+            .extensions
+            .get::<Trailers>()
+            .map(|trailers| &trailers.0)
+    }
+
+    #[inline]
+    /// Returns the header value as `&str`, `None` if missing or not visible
+    /// ASCII.
+    pub fn header_str<K>(&self, key: K) -> Option<&str>
+    where
+        K: http::header::AsHeaderName,
+    {
+        self.response_parts.headers.get(key)?.to_str().ok()
+    }
+
+    #[inline]
+    /// Returns the `Content-Type` header value, parameters included.
+    pub fn content_type(&self) -> Option<&str> {
+        self.header_str(http::header::CONTENT_TYPE)
+    }
+
+    #[inline]
+    /// Returns the `Content-Length` header value, `None` if missing or
+    /// invalid.
+    pub fn content_length(&self) -> Option<u64> {
+        self.header_str(http::header::CONTENT_LENGTH)?
+            .trim()
+            .parse()
+            .ok()
+    }
+
+    #[inline]
+    /// Returns the `charset` parameter of `Content-Type`, see
+    /// [`content_type_charset`](text::content_type_charset).
+    pub fn charset(&self) -> Option<&str> {
+        text::content_type_charset(&self.response_parts.headers)
+    }
+
+    #[inline]
+    /// Whether `Content-Type` is JSON, including the `+json` ones like
+    /// `application/problem+json`.
+    pub fn is_json(&self) -> bool {
+        self.content_type()
+            .is_some_and(|v| content_type::mime_matches(v, "application/json"))
+    }
+
+    #[inline]
+    /// Whether `Content-Type` is HTML.
+    pub fn is_html(&self) -> bool {
+        self.content_type()
+            .is_some_and(|v| content_type::mime_matches(v, "text/html"))
+    }
+
+    /// Verify the `Content-Type` matches the expected media type, see
+    /// [`mime_matches`](content_type::mime_matches) for the matching rules.
+    ///
+    /// # Errors
+    ///
+    /// [`ContentTypeError`] carrying the actual type, a body snippet and the
+    /// original response.
+    pub fn expect_content_type(self, mime: &str) -> Result<Self, ContentTypeError> {
+        let matches = self
+            .content_type()
+            .is_some_and(|v| content_type::mime_matches(v, mime));
+
+        if matches {
+            Ok(self)
+        } else {
+            #[cfg(feature = "feat-tracing")]
+            tracing::error!(
+                "Unexpected content type {:?}, expected `{mime}`",
+                self.response_parts.headers.get(http::header::CONTENT_TYPE)
+            );
+
+            Err(ContentTypeError::new(mime, self))
+        }
+    }
+
+    #[inline]
+    /// Returns the [`Timings`], if set by the client integration.
+    pub fn timings(&self) -> Option<&Timings> {
+        self.response_parts.extensions.get::<Timings>()
+    }
+
+    #[inline]
+    /// Set the [`Timings`], kept in the extensions.
+    pub fn set_timings(&mut self, timings: Timings) {
+        self.response_parts.extensions.insert(timings);
+    }
+
+    #[inline]
+    /// Returns the [`RedirectTrace`], if the client integration put it into
+    /// the extensions.
+    pub fn redirect_trace(&self) -> Option<&RedirectTrace> {
+        self.response_parts.extensions.get::<RedirectTrace>()
+    }
+
+    #[inline]
+    /// Parse [`RateLimitInfo`] from the `RateLimit-*` or `X-RateLimit-*`
+    /// headers.
+    pub fn rate_limit(&self) -> Option<RateLimitInfo> {
+        RateLimitInfo::from_headers(&self.response_parts.headers, crate::time::now())
+    }
+
+    #[inline]
+    /// Compute the [`RetryHint`] from the status, `Retry-After` and rate
+    /// limit headers.
+    pub fn retry_hint(&self) -> RetryHint {
+        RetryHint::from_parts(&self.response_parts, crate::time::now())
+    }
+
+    #[inline]
+    /// Compute the [`CachePolicy`] of this response for the given request.
+    ///
+    /// `response_time` is when the response was received.
+    pub fn cache_policy(
+        &self,
+        request: &http::request::Parts,
+        response_time: std::time::SystemTime,
+    ) -> CachePolicy {
+        CachePolicy::new(request, &self.response_parts, response_time)
+    }
+
+    #[inline]
+    /// Compute the canonical secondary cache key from `Vary` and the
+    /// corresponding request header values, see [`cache::vary_key`].
+    ///
+    /// Returns `None` for `Vary: *`.
+    pub fn vary_key(&self, request_headers: &http::HeaderMap) -> Option<String> {
+        cache::vary_key(&self.response_parts.headers, request_headers)
+    }
+
+    #[inline]
+    /// Extract pagination info from `Link` headers and common header
+    /// conventions (`X-Total-Count`, `X-Next-Cursor`).
+    ///
+    /// See also [`paginate`](pagination::paginate) (feature
+    /// `feat-response-ext-paginate`) for walking all the pages.
+    pub fn pagination(&self) -> Pagination {
+        Pagination::from_headers(&self.response_parts.headers)
+    }
+
+    #[inline]
+    /// Parse [`DeprecationInfo`] from the `Deprecation`, `Sunset` and `Link`
+    /// headers.
+    ///
+    /// With feature `feat-tracing`, see also
+    /// [`DeprecationInfo::warn_once`].
+    pub fn deprecation(&self) -> Option<DeprecationInfo> {
+        DeprecationInfo::from_headers(&self.response_parts.headers)
+    }
+
+    #[cfg(feature = "feat-version")]
+    #[inline]
+    /// Returns the HTTP version the response arrived on, see
+    /// [`VersionInfo`](crate::version::VersionInfo).
+    pub const fn version_info(&self) -> crate::version::VersionInfo {
+        crate::version::VersionInfo::from_parts(&self.response_parts)
+    }
+
+    #[inline]
+    /// Parse the alternative services from `Alt-Svc`, see
+    /// [`AltSvc::from_headers`].
+    pub fn alt_svc(&self) -> Option<Vec<AltSvc>> {
+        AltSvc::from_headers(&self.response_parts.headers)
+    }
+
+    /// Turn a response with client or server error status (4xx, 5xx) into
+    /// [`StatusError`].
+    ///
+    /// # Errors
+    ///
+    /// [`StatusError`] with the status, selected headers and the (truncated)
+    /// body text.
+    pub fn error_for_status(self) -> Result<Self, StatusError> {
+        let status = self.response_parts.status;
+
+        if status.is_client_error() || status.is_server_error() {
+            Err(StatusError::new(
+                status,
+                &self.response_parts.headers,
+                &self.body,
+            ))
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Decompress the body according to the `Content-Encoding` header, so
+    /// that [`json`](Self::json), [`text`](Self::text), etc work against
+    /// compressed upstreams.
+    ///
+    /// `Content-Encoding` is removed and `Content-Length` (if any) is updated
+    /// after decompression. It's a no-op if there's no `Content-Encoding` or
+    /// it's `identity`.
+    ///
+    /// Each codec is gated behind its own feature: `feat-response-ext-gzip`,
+    /// `feat-response-ext-deflate`, `feat-response-ext-brotli` and
+    /// `feat-response-ext-zstd`.
+    ///
+    /// # Errors
+    ///
+    /// [`DecompressError`] carrying the original response, if the encoding is
+    /// not supported or the body is corrupted.
+    pub fn decompressed(mut self) -> Result<Self, DecompressError> {
+        use http::header::{CONTENT_ENCODING, CONTENT_LENGTH};
+
+        let Some(encodings) = self.response_parts.headers.get(CONTENT_ENCODING) else {
+            return Ok(self);
+        };
+
+        let encodings = match encodings.to_str() {
+            Ok(encodings) => encodings.to_ascii_lowercase(),
+            Err(e) => {
+                return Err(DecompressError::new(
+                    self,
+                    std::io::Error::new(std::io::ErrorKind::InvalidData, e),
+                ))
+            }
+        };
+
+        let mut body = None::<Vec<u8>>;
+
+        // Codings are listed in the order they were applied.
+        for encoding in encodings
+            .rsplit(',')
+            .map(str::trim)
+            .filter(|encoding| !encoding.is_empty() && *encoding != "identity")
+        {
+            match decompress::decompress(encoding, body.as_deref().unwrap_or(&self.body)) {
+                Ok(decompressed) => body = Some(decompressed),
+                Err(e) => {
+                    #[cfg(feature = "feat-tracing")]
+                    tracing::error!("Failed to decompress response body: {e:?}");
+
+                    return Err(DecompressError::new(self, e));
+                }
+            }
+        }
+
+        self.response_parts.headers.remove(CONTENT_ENCODING);
+
+        if let Some(body) = body {
+            if self.response_parts.headers.contains_key(CONTENT_LENGTH) {
+                self.response_parts
+                    .headers
+                    .insert(CONTENT_LENGTH, body.len().into());
+            }
+
+            self.body = Bytes::from(body);
+        }
+
+        Ok(self)
+    }
+
+    /// Decode the body according to the `Content-Encoding` header (see
+    /// [`decompressed`](Self::decompressed)), and re-encode it with the target
+    /// content coding, e.g. for caching proxies to normalize the stored bodies.
+    ///
+    /// `level` is the compression level of the codec, `None` for the default
+    /// one, see [`Encoding`] for the ranges.
+    ///
+    /// `Content-Encoding` and `Content-Length` (if any) are updated, and the
+    /// strong `ETag` (if any) is weakened since the bytes are changed.
+    ///
+    /// # Errors
+    ///
+    /// [`DecompressError`] if the decoding fails (carrying the original
+    /// response), or the target encoding is not supported (carrying the
+    /// decoded response).
+    pub fn recompress(self, target: Encoding, level: Option<u32>) -> Result<Self, DecompressError> {
+        use http::header::{CONTENT_ENCODING, CONTENT_LENGTH, ETAG};
+
+        let mut response = self.decompressed()?;
+
+        if target == Encoding::Identity {
+            return Ok(response);
+        }
+
+        let body = match decompress::compress(target, level, &response.body) {
+            Ok(body) => body,
+            Err(e) => return Err(DecompressError::new(response, e)),
+        };
+
+        let headers = &mut response.response_parts.headers;
+
+        headers.insert(
+            CONTENT_ENCODING,
+            http::HeaderValue::from_static(target.as_str()),
+        );
+
+        if headers.contains_key(CONTENT_LENGTH) {
+            headers.insert(CONTENT_LENGTH, body.len().into());
+        }
+
+        let weakened = headers
+            .get(ETAG)
+            .filter(|etag| etag.as_bytes().starts_with(b"\""))
+            .and_then(|etag| {
+                let mut weak = b"W/".to_vec();
+                weak.extend_from_slice(etag.as_bytes());
+                http::HeaderValue::from_bytes(&weak).ok()
+            });
+
+        if let Some(weakened) = weakened {
+            headers.insert(ETAG, weakened);
+        }
+
+        response.body = Bytes::from(body);
+
+        Ok(response)
+    }
+
+    #[cfg(feature = "feat-response-ext-digest")]
+    #[inline]
+    /// Verify the body against the `Content-Digest` / `Repr-Digest`
+    /// (`sha-256`, `sha-512`) and `Content-MD5` headers, all present ones with
+    /// supported algorithms are checked.
+    ///
+    /// Since the body is checked as is, call this before
+    /// [`decompressed`](Self::decompressed).
+    ///
+    /// # Errors
+    ///
+    /// See [`DigestError`].
+    pub fn verify_digest(&self) -> Result<(), DigestError> {
+        digest::verify_headers(&self.response_parts.headers, &self.body)
+    }
+
+    #[cfg(feature = "feat-response-ext-digest")]
+    #[inline]
+    /// Verify the body against a user-supplied expected digest, e.g. the one
+    /// published along with the artifact.
+    ///
+    /// # Errors
+    ///
+    /// [`DigestError::Mismatch`].
+    pub fn verify_digest_with(
+        &self,
+        algorithm: DigestAlgorithm,
+        expected: &[u8],
+    ) -> Result<(), DigestError> {
+        digest::verify(algorithm, expected, &self.body)
+    }
+
+    #[cfg(feature = "feat-response-ext-save")]
+    #[inline]
+    /// Save the body to `path`.
+    ///
+    /// The body is written to a temporary file in the same directory first,
+    /// then renamed to the destination, so that readers never see a partially
+    /// written file. See [`SaveOptions`] for fsync and `Content-Disposition`
+    /// handling.
+    ///
+    /// `on_progress` is called with `(written, total)` bytes after each chunk.
+    ///
+    /// Returns the final path of the saved file.
+    ///
+    /// # Errors
+    ///
+    /// IO errors. The temporary file is removed on failure.
+    pub async fn save_to<P, F>(
+        &self,
+        path: P,
+        options: SaveOptions,
+        on_progress: F,
+    ) -> std::io::Result<std::path::PathBuf>
+    where
+        P: AsRef<std::path::Path>,
+        F: FnMut(u64, u64),
+    {
+        save::save(
+            &self.response_parts.headers,
+            &self.body,
+            path.as_ref(),
+            options,
+            on_progress,
+        )
+        .await
+    }
+
+    #[cfg(feature = "feat-response-ext-grpc")]
+    /// Read [`GrpcStatus`] from the [`trailers`](Self::trailers), or the
+    /// headers (Trailers-Only response).
+    ///
+    /// # Errors
+    ///
+    /// See [`GrpcStatus::from_headers`].
+    pub fn grpc_status(&self) -> crate::Result<Option<GrpcStatus>> {
+        if let Some(status) = self
+            .trailers()
+            .map(GrpcStatus::from_headers)
+            .transpose()?
+            .flatten()
+        {
+            return Ok(Some(status));
+        }
+
+        GrpcStatus::from_headers(&self.response_parts.headers)
+    }
+
+    #[cfg(feature = "feat-response-ext-snapshot")]
+    #[inline]
+    /// Take a serializable [`ResponseSnapshot`] of this response, for
+    /// structured logging and recorded test fixtures.
+    ///
+    /// Sensitive headers are redacted and the body can be truncated, see
+    /// [`SnapshotOptions`].
+    pub fn snapshot(&self, options: SnapshotOptions<'_>) -> ResponseSnapshot {
+        ResponseSnapshot::new(self, options)
+    }
+
+    #[cfg(feature = "feat-response-ext-snapshot")]
+    #[inline]
+    /// Restore a response from [`ResponseSnapshot`].
+    ///
+    /// Redacted header values and truncated body are restored as is.
+    ///
+    /// # Errors
+    ///
+    /// See [`SnapshotError`].
+    pub fn from_snapshot(snapshot: ResponseSnapshot) -> Result<Self, SnapshotError> {
+        snapshot.try_into()
+    }
+
+    #[inline]
+    /// Apply the [`BodyPipeline`] (e.g. decompress, charset decode, decrypt)
+    /// to the body.
+    ///
+    /// # Errors
+    ///
+    /// [`TransformError`] of the first failed stage.
+    pub fn apply(self, pipeline: &BodyPipeline) -> Result<Self, TransformError> {
+        pipeline.apply(self)
+    }
+
+    /// Decode the body as text, according to the charset of `Content-Type`.
+    ///
+    /// Without feature `feat-response-ext-charset`, only UTF-8, US-ASCII and
+    /// ISO-8859-1 are supported, and other charsets fallback to UTF-8. With it,
+    /// all charsets defined by the Encoding Standard are supported, and BOM /
+    /// HTML `<meta>` sniffing is performed too.
+    ///
+    /// Invalid sequences are replaced with `U+FFFD`.
+    pub fn text(&self) -> String {
+        text::decode(&self.response_parts.headers, &self.body)
+    }
+
     #[cfg(feature = "feat-response-ext-json")]
     /// Convert the body to a JSON value
     ///
-    /// If the body is not valid JSON, the original response is returned as an
-    /// error.
-    pub fn json<T>(self) -> Result<ResponseExt<T>, Self>
+    /// # Errors
+    ///
+    /// If the body is not valid JSON, [`JsonError`] carrying the original
+    /// response and the reason of failure is returned.
+    pub fn json<T>(self) -> Result<ResponseExt<T>, JsonError>
     where
         T: for<'a> serde::Deserialize<'a>,
     {
-        match serde_json::from_slice(&self.body) {
+        #[cfg(not(feature = "feat-response-ext-json-path"))]
+        let result = serde_json::from_slice(&self.body);
+
+        #[cfg(feature = "feat-response-ext-json-path")]
+        let result = {
+            let mut de = serde_json::Deserializer::from_slice(&self.body);
+
+            match serde_path_to_error::deserialize(&mut de) {
+                Ok(body) => de.end().map(|()| body).map_err(|e| (e, ".".to_owned())),
+                Err(e) => {
+                    let path = e.path().to_string();
+                    Err((e.into_inner(), path))
+                }
+            }
+        };
+
+        match result {
             Ok(body) => Ok(ResponseExt {
                 response_parts: self.response_parts,
                 body,
@@ -31,7 +611,131 @@ impl ResponseExt {
             Err(e) => {
                 #[cfg(feature = "feat-tracing")]
                 tracing::error!("Failed to parse JSON: {e:?}");
-                Err(self)
+
+                #[cfg(not(feature = "feat-response-ext-json-path"))]
+                return Err(JsonError::new(self, e));
+
+                #[cfg(feature = "feat-response-ext-json-path")]
+                return Err(JsonError::new(self, e.0, e.1));
+            }
+        }
+    }
+
+    #[cfg(feature = "feat-response-ext-json")]
+    #[inline]
+    /// Like [`json`](Self::json), but verify the `Content-Type` is
+    /// `application/json` (or `application/*+json`) first.
+    ///
+    /// # Errors
+    ///
+    /// See [`JsonCheckedError`].
+    pub fn json_checked<T>(self) -> Result<ResponseExt<T>, JsonCheckedError>
+    where
+        T: for<'a> serde::Deserialize<'a>,
+    {
+        Ok(self.expect_content_type("application/json")?.json()?)
+    }
+
+    #[cfg(feature = "feat-response-ext-json")]
+    #[inline]
+    /// Iterate over the newline-delimited JSON values in the body, common for
+    /// bulk / export endpoints.
+    ///
+    /// For streaming bodies, see [`NdjsonDecoder`], or [`JsonArrayDecoder`] for
+    /// huge top-level JSON arrays.
+    pub fn ndjson<T>(&self) -> Ndjson<'_, T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        Ndjson::new(&self.body)
+    }
+
+    #[cfg(feature = "feat-response-ext-json")]
+    /// Decode the body as an API envelope (e.g. `{code, msg, data}`), and
+    /// returns the typed data if the code indicates success.
+    ///
+    /// # Errors
+    ///
+    /// See [`EnvelopeError`].
+    pub fn json_envelope<T>(self, envelope: Envelope<'_>) -> Result<ResponseExt<T>, EnvelopeError>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let ResponseExt {
+            response_parts,
+            body,
+        } = self.json::<serde_json::Map<String, serde_json::Value>>()?;
+
+        Ok(ResponseExt {
+            response_parts,
+            body: envelope.extract(body)?,
+        })
+    }
+
+    #[cfg(feature = "feat-response-ext-json")]
+    /// Decode the body as [`ProblemDetails`], if the `Content-Type` is
+    /// `application/problem+json`.
+    ///
+    /// Returns `None` if it's not, or the body is not a valid JSON object.
+    pub fn problem_details(&self) -> Option<ProblemDetails> {
+        if !problem::is_problem_json(&self.response_parts.headers) {
+            return None;
+        }
+
+        ProblemDetails::from_slice(&self.body)
+            .inspect_err(|_e| {
+                #[cfg(feature = "feat-tracing")]
+                tracing::warn!("Invalid problem details: {_e:?}");
+            })
+            .ok()
+    }
+
+    #[cfg(feature = "feat-response-ext-msgpack")]
+    /// Convert the body from `MessagePack` to a value
+    ///
+    /// # Errors
+    ///
+    /// If the body is not valid `MessagePack`, [`MsgpackError`] carrying the
+    /// original response and the reason of failure is returned.
+    pub fn msgpack<T>(self) -> Result<ResponseExt<T>, MsgpackError>
+    where
+        T: for<'a> serde::Deserialize<'a>,
+    {
+        match rmp_serde::from_slice(&self.body) {
+            Ok(body) => Ok(ResponseExt {
+                response_parts: self.response_parts,
+                body,
+            }),
+            Err(e) => {
+                #[cfg(feature = "feat-tracing")]
+                tracing::error!("Failed to parse MessagePack: {e:?}");
+
+                Err(MsgpackError::new(self, e))
+            }
+        }
+    }
+
+    #[cfg(feature = "feat-response-ext-cbor")]
+    /// Convert the body from CBOR to a value
+    ///
+    /// # Errors
+    ///
+    /// If the body is not valid CBOR, [`CborError`] carrying the original
+    /// response and the reason of failure is returned.
+    pub fn cbor<T>(self) -> Result<ResponseExt<T>, CborError>
+    where
+        T: for<'a> serde::Deserialize<'a>,
+    {
+        match ciborium::from_reader(&*self.body) {
+            Ok(body) => Ok(ResponseExt {
+                response_parts: self.response_parts,
+                body,
+            }),
+            Err(e) => {
+                #[cfg(feature = "feat-tracing")]
+                tracing::error!("Failed to parse CBOR: {e:?}");
+
+                Err(CborError::new(self, e))
             }
         }
     }
@@ -0,0 +1,238 @@
+//! HTTP response utilities: [`ResponseExt`] builder related.
+
+use bytes::Bytes;
+use http::{header, response, HeaderMap, HeaderName, HeaderValue, StatusCode, Version};
+
+use super::ResponseExt;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// Trailers of the response, kept in the extensions of
+/// [`ResponseExt::response_parts`], see [`ResponseExt::trailers`].
+pub struct Trailers(pub HeaderMap);
+
+#[derive(Debug)]
+#[derive(thiserror::Error)]
+/// Error returned by [`ResponseExtBuilder::build`].
+pub enum BuildError {
+    #[error(transparent)]
+    /// Invalid status, header name or value.
+    Http(#[from] http::Error),
+
+    #[cfg(feature = "feat-response-ext-json")]
+    #[error("failed to serialize JSON body: {0}")]
+    /// Failed to serialize the body.
+    Json(#[from] serde_json::Error),
+}
+
+#[derive(Debug)]
+/// Builder of [`ResponseExt`], see [`ResponseExt::builder`].
+///
+/// Mainly for unit tests of code consuming [`ResponseExt`], so that
+/// [`http::response::Parts`] don't have to be constructed by hand.
+///
+/// ```rust
+/// # use miku_http_util::response::ResponseExt;
+/// let response = ResponseExt::builder()
+///     .status(404)
+///     .header("x-request-id", "abc")
+///     .text("not found")
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(response.response_parts.status, 404);
+/// assert_eq!(response.text(), "not found");
+/// ```
+pub struct ResponseExtBuilder {
+    inner: response::Builder,
+    trailers: Result<HeaderMap, http::Error>,
+    body: Result<Bytes, BuildError>,
+}
+
+impl Default for ResponseExtBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ResponseExtBuilder {
+    #[inline]
+    /// Create a new [`ResponseExtBuilder`], with status `200 OK` and empty
+    /// body.
+    pub fn new() -> Self {
+        Self {
+            inner: response::Builder::new(),
+            trailers: Ok(HeaderMap::new()),
+            body: Ok(Bytes::new()),
+        }
+    }
+
+    #[inline]
+    /// Set the status code.
+    pub fn status<T>(self, status: T) -> Self
+    where
+        StatusCode: TryFrom<T>,
+        <StatusCode as TryFrom<T>>::Error: Into<http::Error>,
+    {
+        Self {
+            inner: self.inner.status(status),
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Set the HTTP version.
+    pub fn version(self, version: Version) -> Self {
+        Self {
+            inner: self.inner.version(version),
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Append a header.
+    pub fn header<K, V>(self, key: K, value: V) -> Self
+    where
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: Into<http::Error>,
+        HeaderValue: TryFrom<V>,
+        <HeaderValue as TryFrom<V>>::Error: Into<http::Error>,
+    {
+        Self {
+            inner: self.inner.header(key, value),
+            ..self
+        }
+    }
+
+    /// Modify the headers with given closure, e.g. with the
+    /// [`HeaderMapExtT`](crate::request::header::HeaderMapExtT) helpers.
+    ///
+    /// No-op if there's any error before.
+    pub fn headers_with<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut HeaderMap),
+    {
+        if let Some(headers) = self.inner.headers_mut() {
+            f(headers);
+        }
+
+        self
+    }
+
+    /// Append a trailer.
+    pub fn trailer<K, V>(self, key: K, value: V) -> Self
+    where
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: Into<http::Error>,
+        HeaderValue: TryFrom<V>,
+        <HeaderValue as TryFrom<V>>::Error: Into<http::Error>,
+    {
+        let trailers = self.trailers.and_then(|mut trailers| {
+            let key = HeaderName::try_from(key).map_err(Into::into)?;
+            let value = HeaderValue::try_from(value).map_err(Into::into)?;
+
+            trailers.append(key, value);
+
+            Ok(trailers)
+        });
+
+        Self { trailers, ..self }
+    }
+
+    #[inline]
+    /// Set the body bytes.
+    pub fn bytes(self, body: impl Into<Bytes>) -> Self {
+        Self {
+            body: Ok(body.into()),
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Set the body text, with `Content-Type: text/plain; charset=utf-8`.
+    pub fn text(self, body: impl Into<String>) -> Self {
+        self.header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+            .bytes(body.into())
+    }
+
+    #[cfg(feature = "feat-response-ext-json")]
+    #[inline]
+    /// Set the body as the serialized JSON value, with `Content-Type:
+    /// application/json`.
+    pub fn json<T>(self, body: &T) -> Self
+    where
+        T: serde::Serialize + ?Sized,
+    {
+        Self {
+            body: serde_json::to_vec(body)
+                .map(Bytes::from)
+                .map_err(Into::into),
+            ..self.header(header::CONTENT_TYPE, "application/json")
+        }
+    }
+
+    /// Build the [`ResponseExt`].
+    ///
+    /// # Errors
+    ///
+    /// [`BuildError`] if the status, any header name or value is invalid, or
+    /// failed to serialize the body.
+    pub fn build(self) -> Result<ResponseExt, BuildError> {
+        let (mut response_parts, ()) = self.inner.body(())?.into_parts();
+
+        let trailers = self.trailers?;
+        if !trailers.is_empty() {
+            response_parts.extensions.insert(Trailers(trailers));
+        }
+
+        Ok(ResponseExt {
+            response_parts,
+            body: self.body?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder() {
+        let response = ResponseExtBuilder::new()
+            .status(StatusCode::CREATED)
+            .header("x-a", "1")
+            .headers_with(|headers| {
+                headers.insert("x-b", HeaderValue::from_static("2"));
+            })
+            .trailer("grpc-status", "0")
+            .bytes(&b"raw"[..])
+            .build()
+            .unwrap();
+
+        assert_eq!(response.response_parts.status, StatusCode::CREATED);
+        assert_eq!(response.response_parts.headers["x-a"], "1");
+        assert_eq!(response.response_parts.headers["x-b"], "2");
+        assert_eq!(response.trailers().unwrap()["grpc-status"], "0");
+        assert_eq!(response.body, "raw");
+
+        let _ = ResponseExtBuilder::new()
+            .trailer("invalid key", "0")
+            .build()
+            .unwrap_err();
+        let _ = ResponseExtBuilder::new().status(1000).build().unwrap_err();
+    }
+
+    #[cfg(feature = "feat-response-ext-json")]
+    #[test]
+    fn test_builder_json() {
+        let response = ResponseExtBuilder::new()
+            .json(&serde_json::json!({"a": 1}))
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            response.response_parts.headers[header::CONTENT_TYPE],
+            "application/json"
+        );
+        assert_eq!(response.body, r#"{"a":1}"#);
+    }
+}
@@ -0,0 +1,459 @@
+//! HTTP response utilities: cache policy (RFC 9111 subset) related.
+
+use std::time::{Duration, SystemTime};
+
+use http::{header, request, response, HeaderMap, HeaderValue, Method, StatusCode};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// Parsed `Cache-Control` directives, only the ones used by [`CachePolicy`].
+pub struct CacheControl {
+    /// `no-store`
+    pub no_store: bool,
+
+    /// `no-cache`
+    pub no_cache: bool,
+
+    /// `private`
+    pub private: bool,
+
+    /// `public`
+    pub public: bool,
+
+    /// `must-revalidate`
+    pub must_revalidate: bool,
+
+    /// `max-age`
+    pub max_age: Option<Duration>,
+
+    /// `s-maxage`
+    pub s_maxage: Option<Duration>,
+}
+
+impl CacheControl {
+    /// Parse all the `Cache-Control` headers.
+    ///
+    /// Unknown or malformed directives are ignored.
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let mut cache_control = Self::default();
+
+        headers
+            .get_all(header::CACHE_CONTROL)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .for_each(|directive| {
+                let (name, value) = match directive.split_once('=') {
+                    Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+                    None => (directive.trim(), None),
+                };
+
+                let seconds = || value.and_then(|v| v.parse().ok()).map(Duration::from_secs);
+
+                match name.to_ascii_lowercase().as_str() {
+                    "no-store" => cache_control.no_store = true,
+                    "no-cache" => cache_control.no_cache = true,
+                    "private" => cache_control.private = true,
+                    "public" => cache_control.public = true,
+                    "must-revalidate" => cache_control.must_revalidate = true,
+                    "max-age" => cache_control.max_age = seconds(),
+                    "s-maxage" => cache_control.s_maxage = seconds(),
+                    _ => {}
+                }
+            });
+
+        cache_control
+    }
+}
+
+#[cfg(feature = "feat-request-header")]
+impl crate::request::header::TypedHeaderT for CacheControl {
+    #[inline]
+    fn name() -> header::HeaderName {
+        header::CACHE_CONTROL
+    }
+
+    fn parse(value: &HeaderValue) -> Option<Self> {
+        let mut headers = HeaderMap::with_capacity(1);
+        headers.insert(header::CACHE_CONTROL, value.clone());
+
+        Some(Self::from_headers(&headers))
+    }
+
+    fn encode(&self) -> HeaderValue {
+        let mut directives = Vec::new();
+
+        for (enabled, directive) in [
+            (self.public, "public"),
+            (self.private, "private"),
+            (self.no_cache, "no-cache"),
+            (self.no_store, "no-store"),
+            (self.must_revalidate, "must-revalidate"),
+        ] {
+            if enabled {
+                directives.push(directive.to_owned());
+            }
+        }
+
+        if let Some(max_age) = self.max_age {
+            directives.push(format!("max-age={}", max_age.as_secs()));
+        }
+
+        if let Some(s_maxage) = self.s_maxage {
+            directives.push(format!("s-maxage={}", s_maxage.as_secs()));
+        }
+
+        HeaderValue::try_from(directives.join(", ")).expect("directives must be valid")
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Cache policy computed from the request and response headers, a subset of
+/// [RFC 9111](https://www.rfc-editor.org/rfc/rfc9111).
+///
+/// It's for building HTTP caches, defaults to a private cache, see
+/// [`shared`](Self::shared).
+pub struct CachePolicy {
+    method: Method,
+    status: StatusCode,
+    has_authorization: bool,
+    request_cache_control: CacheControl,
+    cache_control: CacheControl,
+    response_time: SystemTime,
+    date: Option<SystemTime>,
+    expires: Option<SystemTime>,
+    last_modified: Option<SystemTime>,
+    age: Duration,
+    etag: Option<HeaderValue>,
+    last_modified_raw: Option<HeaderValue>,
+    vary_key: Option<String>,
+    shared: bool,
+}
+
+impl CachePolicy {
+    /// Create a new [`CachePolicy`].
+    ///
+    /// `response_time` is when the response was received.
+    pub fn new(
+        request: &request::Parts,
+        response: &response::Parts,
+        response_time: SystemTime,
+    ) -> Self {
+        let headers = &response.headers;
+
+        Self {
+            method: request.method.clone(),
+            status: response.status,
+            has_authorization: request.headers.contains_key(header::AUTHORIZATION),
+            request_cache_control: CacheControl::from_headers(&request.headers),
+            cache_control: CacheControl::from_headers(headers),
+            response_time,
+            date: http_date(headers, header::DATE),
+            // Invalid `Expires` (e.g. `0`) means already expired.
+            expires: headers
+                .get(header::EXPIRES)
+                .map(|v| parse_http_date(v).unwrap_or(SystemTime::UNIX_EPOCH)),
+            last_modified: http_date(headers, header::LAST_MODIFIED),
+            age: headers
+                .get(header::AGE)
+                .and_then(|v| v.to_str().ok()?.trim().parse().ok())
+                .map(Duration::from_secs)
+                .unwrap_or_default(),
+            etag: headers.get(header::ETAG).cloned(),
+            last_modified_raw: headers.get(header::LAST_MODIFIED).cloned(),
+            vary_key: vary_key(headers, &request.headers),
+            shared: false,
+        }
+    }
+
+    #[inline]
+    /// Set whether the cache is a shared one (e.g. a proxy), which respects
+    /// `s-maxage` and `private`.
+    pub fn shared(self, shared: bool) -> Self {
+        Self { shared, ..self }
+    }
+
+    /// Whether the response can be stored, see RFC 9111, section 3.
+    pub fn is_storable(&self) -> bool {
+        if !matches!(self.method, Method::GET | Method::HEAD)
+            || self.status.is_informational()
+            || self.status == StatusCode::PARTIAL_CONTENT
+            || self.request_cache_control.no_store
+            || self.cache_control.no_store
+            || self.vary_key.is_none()
+        {
+            return false;
+        }
+
+        if self.shared {
+            if self.cache_control.private {
+                return false;
+            }
+
+            if self.has_authorization
+                && !(self.cache_control.public
+                    || self.cache_control.must_revalidate
+                    || self.cache_control.s_maxage.is_some())
+            {
+                return false;
+            }
+        }
+
+        self.expires.is_some()
+            || self.cache_control.max_age.is_some()
+            || (self.shared && self.cache_control.s_maxage.is_some())
+            || self.cache_control.public
+            || is_heuristically_cacheable(self.status)
+    }
+
+    /// The freshness lifetime, see RFC 9111, section 4.2.1.
+    pub fn freshness_lifetime(&self) -> Duration {
+        if self.shared {
+            if let Some(s_maxage) = self.cache_control.s_maxage {
+                return s_maxage;
+            }
+        }
+
+        if let Some(max_age) = self.cache_control.max_age {
+            return max_age;
+        }
+
+        let date = self.date.unwrap_or(self.response_time);
+
+        if let Some(expires) = self.expires {
+            return expires.duration_since(date).unwrap_or_default();
+        }
+
+        // Heuristic freshness: 10% of the time since last modified.
+        match self.last_modified {
+            Some(last_modified) if is_heuristically_cacheable(self.status) => {
+                date.duration_since(last_modified).unwrap_or_default() / 10
+            }
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// The current age of the response, see RFC 9111, section 4.2.3.
+    pub fn age(&self, now: SystemTime) -> Duration {
+        let apparent_age = self
+            .date
+            .and_then(|date| self.response_time.duration_since(date).ok())
+            .unwrap_or_default();
+        let resident_time = now.duration_since(self.response_time).unwrap_or_default();
+
+        apparent_age.max(self.age) + resident_time
+    }
+
+    /// Whether the stored response can be used without revalidation.
+    pub fn is_fresh(&self, now: SystemTime) -> bool {
+        !self.cache_control.no_cache
+            && !self.request_cache_control.no_cache
+            && self.freshness_lifetime() > self.age(now)
+    }
+
+    #[inline]
+    /// The secondary cache key computed from `Vary`, see [`vary_key`].
+    ///
+    /// `None` for `Vary: *`, which makes the response not storable.
+    pub fn vary_key(&self) -> Option<&str> {
+        self.vary_key.as_deref()
+    }
+
+    /// Headers to send for revalidating the stored response, i.e.
+    /// `If-None-Match` and `If-Modified-Since`.
+    pub fn revalidation_headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+
+        if let Some(etag) = &self.etag {
+            headers.insert(header::IF_NONE_MATCH, etag.clone());
+        }
+
+        if let Some(last_modified) = &self.last_modified_raw {
+            headers.insert(header::IF_MODIFIED_SINCE, last_modified.clone());
+        }
+
+        headers
+    }
+}
+
+/// Compute the canonical secondary cache key from the `Vary` header of the
+/// response and the corresponding request header values.
+///
+/// The key is made of `name:value` lines of the varied (lowercase, sorted and
+/// deduplicated) header names, where multiple values are joined with `,` and
+/// whitespaces are normalized. It's empty if there's no `Vary`.
+///
+/// Returns `None` for `Vary: *`, which never matches.
+pub fn vary_key(response_headers: &HeaderMap, request_headers: &HeaderMap) -> Option<String> {
+    let mut names = Vec::new();
+
+    for name in response_headers
+        .get_all(header::VARY)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+    {
+        if name == "*" {
+            return None;
+        }
+
+        names.push(name.to_ascii_lowercase());
+    }
+
+    names.sort_unstable();
+    names.dedup();
+
+    let mut key = String::new();
+
+    for name in names {
+        if !key.is_empty() {
+            key.push('\n');
+        }
+
+        key.push_str(&name);
+        key.push(':');
+
+        let values = request_headers
+            .get_all(name.as_str())
+            .iter()
+            .map(|v| {
+                String::from_utf8_lossy(v.as_bytes())
+                    .split_ascii_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect::<Vec<_>>();
+
+        key.push_str(&values.join(","));
+    }
+
+    Some(key)
+}
+
+/// Status codes defined as heuristically cacheable, see RFC 9110, section
+/// 15.1.
+const fn is_heuristically_cacheable(status: StatusCode) -> bool {
+    matches!(
+        status.as_u16(),
+        200 | 203 | 204 | 206 | 300 | 301 | 308 | 404 | 405 | 410 | 414 | 501
+    )
+}
+
+#[inline]
+fn http_date(headers: &HeaderMap, key: header::HeaderName) -> Option<SystemTime> {
+    headers.get(key).and_then(parse_http_date)
+}
+
+#[inline]
+pub(super) fn parse_http_date(value: &HeaderValue) -> Option<SystemTime> {
+    httpdate::parse_http_date(value.to_str().ok()?.trim()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(
+        request_headers: &[(&'static str, &'static str)],
+        response_headers: &[(&'static str, &'static str)],
+        response_time: SystemTime,
+    ) -> CachePolicy {
+        let mut request = http::Request::get("/");
+        for (k, v) in request_headers {
+            request = request.header(*k, *v);
+        }
+
+        let mut response = http::Response::builder();
+        for (k, v) in response_headers {
+            response = response.header(*k, *v);
+        }
+
+        CachePolicy::new(
+            &request.body(()).unwrap().into_parts().0,
+            &response.body(()).unwrap().into_parts().0,
+            response_time,
+        )
+    }
+
+    #[test]
+    fn test_cache_policy() {
+        let now = httpdate::parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT").unwrap();
+
+        let p = policy(
+            &[],
+            &[
+                ("date", "Sun, 06 Nov 1994 08:49:37 GMT"),
+                ("cache-control", "public, max-age=60"),
+                ("etag", "\"abc\""),
+            ],
+            now,
+        );
+        assert!(p.is_storable());
+        assert_eq!(p.freshness_lifetime(), Duration::from_secs(60));
+        assert!(p.is_fresh(now + Duration::from_secs(59)));
+        assert!(!p.is_fresh(now + Duration::from_secs(60)));
+        assert_eq!(p.revalidation_headers()[header::IF_NONE_MATCH], "\"abc\"");
+
+        let p = policy(
+            &[("authorization", "Bearer x")],
+            &[("cache-control", "private, max-age=60"), ("age", "30")],
+            now,
+        );
+        assert!(p.is_storable());
+        assert!(!p.clone().shared(true).is_storable());
+        assert!(p.is_fresh(now + Duration::from_secs(29)));
+        assert!(!p.is_fresh(now + Duration::from_secs(30)));
+
+        let p = policy(
+            &[],
+            &[
+                ("date", "Sun, 06 Nov 1994 08:49:37 GMT"),
+                ("last-modified", "Sun, 06 Nov 1994 07:49:37 GMT"),
+            ],
+            now,
+        );
+        assert_eq!(p.freshness_lifetime(), Duration::from_secs(360));
+        assert!(p
+            .revalidation_headers()
+            .contains_key(header::IF_MODIFIED_SINCE));
+
+        let p = policy(&[], &[("cache-control", "no-store")], now);
+        assert!(!p.is_storable());
+
+        let p = policy(&[], &[("expires", "0")], now);
+        assert!(p.is_storable());
+        assert!(!p.is_fresh(now));
+    }
+
+    #[test]
+    fn test_vary_key() {
+        let now = SystemTime::now();
+
+        let p = policy(
+            &[
+                ("accept-language", "en-US,  en;q=0.9"),
+                ("accept-encoding", "gzip"),
+                ("accept-encoding", "br"),
+            ],
+            &[
+                ("cache-control", "max-age=60"),
+                ("vary", "Accept-Language, accept-encoding"),
+                ("vary", "Accept-Language, X-Tenant"),
+            ],
+            now,
+        );
+        assert!(p.is_storable());
+        assert_eq!(
+            p.vary_key(),
+            Some("accept-encoding:gzip,br\naccept-language:en-US, en;q=0.9\nx-tenant:")
+        );
+
+        let p = policy(&[], &[("cache-control", "max-age=60")], now);
+        assert_eq!(p.vary_key(), Some(""));
+
+        let p = policy(&[], &[("cache-control", "max-age=60"), ("vary", "*")], now);
+        assert!(!p.is_storable());
+        assert_eq!(p.vary_key(), None);
+    }
+}
@@ -0,0 +1,96 @@
+//! HTTP response utilities: CBOR related.
+
+use super::ResponseExt;
+
+#[derive(Debug)]
+#[derive(thiserror::Error)]
+#[error("failed to parse CBOR: {source}")]
+/// Error returned by [`ResponseExt::cbor`], carrying the original response
+/// and the reason of failure.
+pub struct CborError {
+    response: Box<ResponseExt>,
+
+    #[source]
+    source: ciborium::de::Error<std::io::Error>,
+}
+
+impl CborError {
+    pub(super) fn new(response: ResponseExt, source: ciborium::de::Error<std::io::Error>) -> Self {
+        Self {
+            response: Box::new(response),
+            source,
+        }
+    }
+
+    #[inline]
+    /// Returns the original response.
+    pub fn response(&self) -> &ResponseExt {
+        &self.response
+    }
+
+    #[inline]
+    /// Consumes the error and returns the original response.
+    pub fn into_response(self) -> ResponseExt {
+        *self.response
+    }
+
+    #[inline]
+    /// Returns the underlying [`ciborium::de::Error`].
+    pub const fn cbor_error(&self) -> &ciborium::de::Error<std::io::Error> {
+        &self.source
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Data {
+        items: Vec<u32>,
+    }
+
+    fn response(body: Vec<u8>) -> ResponseExt {
+        let (response_parts, ()) = http::Response::new(()).into_parts();
+
+        ResponseExt {
+            response_parts,
+            body: Bytes::from(body),
+        }
+    }
+
+    #[test]
+    fn test_cbor() {
+        let data = Data {
+            items: vec![1, 2, 3],
+        };
+
+        let mut body = Vec::new();
+        ciborium::into_writer(&data, &mut body).unwrap();
+
+        assert_eq!(response(body).cbor::<Data>().unwrap().body, data);
+    }
+
+    #[test]
+    fn test_cbor_error() {
+        let mut body = Vec::new();
+        ciborium::into_writer(&serde_json::json!({ "items": [1, "2"] }), &mut body).unwrap();
+
+        let err = response(body.clone()).cbor::<Data>().unwrap_err();
+        assert!(matches!(
+            err.cbor_error(),
+            ciborium::de::Error::Semantic(..)
+        ));
+        assert_eq!(err.into_response().body, body);
+
+        // Truncated
+        let mut body = Vec::new();
+        ciborium::into_writer(&Data { items: vec![1, 2] }, &mut body).unwrap();
+        body.pop();
+
+        let err = response(body).cbor::<Data>().unwrap_err();
+        assert!(matches!(err.cbor_error(), ciborium::de::Error::Io(_)));
+    }
+}
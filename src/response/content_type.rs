@@ -0,0 +1,147 @@
+//! HTTP response utilities: `Content-Type` assertion related.
+
+use http::header::CONTENT_TYPE;
+
+use super::ResponseExt;
+
+/// Max length of the body snippet kept in [`ContentTypeError`].
+pub const CONTENT_TYPE_ERROR_SNIPPET_LIMIT: usize = 256;
+
+#[derive(Debug)]
+#[derive(thiserror::Error)]
+#[error("unexpected content type {actual:?}, expected `{expected}`: {snippet}")]
+/// Error returned by
+/// [`ResponseExt::expect_content_type`](super::ResponseExt::expect_content_type),
+/// carrying the actual type, a body snippet and the original response.
+///
+/// It's common that a HTML error page masquerades as an API response.
+pub struct ContentTypeError {
+    expected: String,
+    actual: Option<String>,
+    snippet: String,
+    response: Box<ResponseExt>,
+}
+
+impl ContentTypeError {
+    pub(super) fn new(expected: &str, response: ResponseExt) -> Self {
+        let actual = response
+            .response_parts
+            .headers
+            .get(CONTENT_TYPE)
+            .map(|v| String::from_utf8_lossy(v.as_bytes()).into_owned());
+
+        let snippet = {
+            let body = &response.body[..response.body.len().min(CONTENT_TYPE_ERROR_SNIPPET_LIMIT)];
+            super::text::decode(&response.response_parts.headers, body)
+        };
+
+        Self {
+            expected: expected.to_owned(),
+            actual,
+            snippet,
+            response: Box::new(response),
+        }
+    }
+
+    #[inline]
+    /// Returns the expected media type.
+    pub fn expected(&self) -> &str {
+        &self.expected
+    }
+
+    #[inline]
+    /// Returns the actual `Content-Type`, if any.
+    pub fn actual(&self) -> Option<&str> {
+        self.actual.as_deref()
+    }
+
+    #[inline]
+    /// Returns the snippet of the body text.
+    pub fn snippet(&self) -> &str {
+        &self.snippet
+    }
+
+    #[inline]
+    /// Returns the original response.
+    pub fn response(&self) -> &ResponseExt {
+        &self.response
+    }
+
+    #[inline]
+    /// Consumes the error and returns the original response.
+    pub fn into_response(self) -> ResponseExt {
+        *self.response
+    }
+}
+
+/// Check if the media type (`Content-Type` value, parameters are ignored)
+/// matches the expected one.
+///
+/// The expected one can be `*/*` or `type/*`. Also, `application/json`
+/// matches structured syntax suffixed ones like `application/problem+json`.
+pub fn mime_matches(actual: &str, expected: &str) -> bool {
+    let actual = actual.split(';').next().unwrap_or_default().trim();
+
+    let Some((actual_type, actual_subtype)) = actual.split_once('/') else {
+        return false;
+    };
+    let Some((expected_type, expected_subtype)) = expected.split_once('/') else {
+        return false;
+    };
+
+    if expected_type == "*" {
+        return true;
+    }
+
+    if !actual_type.eq_ignore_ascii_case(expected_type) {
+        return false;
+    }
+
+    expected_subtype == "*"
+        || actual_subtype.eq_ignore_ascii_case(expected_subtype)
+        || actual_subtype
+            .rsplit_once('+')
+            .is_some_and(|(_, suffix)| suffix.eq_ignore_ascii_case(expected_subtype))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_response_helpers() {
+        let response = ResponseExt::builder()
+            .header(
+                "content-type",
+                "application/problem+json; charset=\"UTF-8\"",
+            )
+            .header("content-length", "2")
+            .bytes("{}")
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            response.content_type(),
+            Some("application/problem+json; charset=\"UTF-8\"")
+        );
+        assert_eq!(response.content_length(), Some(2));
+        assert_eq!(response.charset(), Some("UTF-8"));
+        assert!(response.is_json());
+        assert!(!response.is_html());
+        assert_eq!(response.header_str("x-missing"), None);
+    }
+
+    #[test]
+    fn test_mime_matches() {
+        assert!(mime_matches(
+            "application/json; charset=utf-8",
+            "application/json"
+        ));
+        assert!(mime_matches("Application/JSON", "application/json"));
+        assert!(mime_matches("application/problem+json", "application/json"));
+        assert!(mime_matches("text/html", "text/*"));
+        assert!(mime_matches("text/html", "*/*"));
+        assert!(!mime_matches("text/html", "application/json"));
+        assert!(!mime_matches("", "application/json"));
+    }
+}
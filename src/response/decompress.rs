@@ -0,0 +1,258 @@
+//! HTTP response utilities: `Content-Encoding` decompression related.
+
+use std::io;
+
+use super::ResponseExt;
+
+#[derive(Debug)]
+#[derive(thiserror::Error)]
+#[error("failed to decompress response body: {source}")]
+/// Error returned by [`ResponseExt::decompressed`] (and
+/// [`ResponseExt::recompress`]), carrying the original response and the reason
+/// of failure.
+pub struct DecompressError {
+    response: Box<ResponseExt>,
+
+    #[source]
+    source: io::Error,
+}
+
+impl DecompressError {
+    pub(super) fn new(response: ResponseExt, source: io::Error) -> Self {
+        Self {
+            response: Box::new(response),
+            source,
+        }
+    }
+
+    #[inline]
+    /// Returns the original response.
+    pub fn response(&self) -> &ResponseExt {
+        &self.response
+    }
+
+    #[inline]
+    /// Consumes the error and returns the original response.
+    pub fn into_response(self) -> ResponseExt {
+        *self.response
+    }
+
+    #[inline]
+    /// Returns the underlying [`io::Error`].
+    ///
+    /// For unsupported encodings, the error kind is
+    /// [`io::ErrorKind::Unsupported`].
+    pub const fn io_error(&self) -> &io::Error {
+        &self.source
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Content coding, see [`ResponseExt::recompress`].
+///
+/// Each codec is gated behind its own feature like decompression.
+pub enum Encoding {
+    /// No encoding.
+    Identity,
+
+    /// `gzip`, level 0-9.
+    Gzip,
+
+    /// `deflate` (zlib-wrapped), level 0-9.
+    Deflate,
+
+    /// `br`, level (quality) 0-11.
+    Brotli,
+
+    /// `zstd`, level 1-22.
+    Zstd,
+}
+
+impl Encoding {
+    #[inline]
+    /// Returns the content coding name, e.g. `gzip`.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Identity => "identity",
+            Self::Gzip => "gzip",
+            Self::Deflate => "deflate",
+            Self::Brotli => "br",
+            Self::Zstd => "zstd",
+        }
+    }
+}
+
+/// Compress the body with given content coding, `None` level for the default
+/// one of the codec.
+pub(super) fn compress(encoding: Encoding, level: Option<u32>, body: &[u8]) -> io::Result<Vec<u8>> {
+    #[allow(unused_imports, reason = "unused if no codec feature is enabled")]
+    use std::io::Write;
+
+    match encoding {
+        Encoding::Identity => Ok(body.to_vec()),
+        #[cfg(feature = "feat-response-ext-gzip")]
+        Encoding::Gzip => {
+            let mut encoder = flate2::write::GzEncoder::new(
+                Vec::with_capacity(body.len() / 2),
+                level.map_or_else(flate2::Compression::default, |level| {
+                    flate2::Compression::new(level.min(9))
+                }),
+            );
+            encoder.write_all(body)?;
+            encoder.finish()
+        }
+        #[cfg(feature = "feat-response-ext-deflate")]
+        Encoding::Deflate => {
+            let mut encoder = flate2::write::ZlibEncoder::new(
+                Vec::with_capacity(body.len() / 2),
+                level.map_or_else(flate2::Compression::default, |level| {
+                    flate2::Compression::new(level.min(9))
+                }),
+            );
+            encoder.write_all(body)?;
+            encoder.finish()
+        }
+        #[cfg(feature = "feat-response-ext-brotli")]
+        Encoding::Brotli => {
+            let mut encoder = brotli::CompressorWriter::new(
+                Vec::with_capacity(body.len() / 2),
+                4096,
+                level.map_or(5, |level| level.min(11)),
+                22,
+            );
+            encoder.write_all(body)?;
+            Ok(encoder.into_inner())
+        }
+        #[cfg(feature = "feat-response-ext-zstd")]
+        Encoding::Zstd => {
+            zstd::stream::encode_all(body, level.map_or(0, |level| level.clamp(1, 22) as i32))
+        }
+        #[allow(unreachable_patterns, reason = "all codec features are enabled")]
+        _ => {
+            let _ = (level, body);
+
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("unsupported content encoding `{}`", encoding.as_str()),
+            ))
+        }
+    }
+}
+
+/// Decompress the body with given content coding.
+pub(super) fn decompress(encoding: &str, body: &[u8]) -> io::Result<Vec<u8>> {
+    match encoding {
+        #[cfg(feature = "feat-response-ext-gzip")]
+        "gzip" | "x-gzip" => read_to_end(flate2::read::MultiGzDecoder::new(body), body.len()),
+        #[cfg(feature = "feat-response-ext-deflate")]
+        "deflate" => {
+            // Per RFC 9110, `deflate` is zlib-wrapped, but some servers send
+            // raw deflate data.
+            read_to_end(flate2::read::ZlibDecoder::new(body), body.len())
+                .or_else(|_| read_to_end(flate2::read::DeflateDecoder::new(body), body.len()))
+        }
+        #[cfg(feature = "feat-response-ext-brotli")]
+        "br" => read_to_end(brotli::Decompressor::new(body, 4096), body.len()),
+        #[cfg(feature = "feat-response-ext-zstd")]
+        "zstd" => read_to_end(zstd::stream::read::Decoder::new(body)?, body.len()),
+        _ => {
+            let _ = body;
+
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("unsupported content encoding `{encoding}`"),
+            ))
+        }
+    }
+}
+
+#[allow(dead_code, reason = "unused if no codec feature is enabled")]
+#[inline]
+fn read_to_end(mut decoder: impl io::Read, compressed_len: usize) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::with_capacity(compressed_len.saturating_mul(4));
+    decoder.read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use http::header::{CONTENT_ENCODING, CONTENT_LENGTH};
+
+    use super::*;
+
+    fn response(encoding: &'static str, body: Vec<u8>) -> ResponseExt {
+        let (response_parts, ()) = http::Response::builder()
+            .header(CONTENT_ENCODING, encoding)
+            .header(CONTENT_LENGTH, body.len())
+            .body(())
+            .unwrap()
+            .into_parts();
+
+        ResponseExt {
+            response_parts,
+            body: Bytes::from(body),
+        }
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let err = response("unknown", b"hello".to_vec())
+            .decompressed()
+            .unwrap_err();
+
+        assert_eq!(err.io_error().kind(), io::ErrorKind::Unsupported);
+        assert_eq!(&err.into_response().body[..], b"hello");
+
+        let response = response("identity", b"hello".to_vec())
+            .decompressed()
+            .unwrap();
+        assert!(!response
+            .response_parts
+            .headers
+            .contains_key(CONTENT_ENCODING));
+    }
+
+    #[cfg(feature = "feat-response-ext-gzip")]
+    #[test]
+    fn test_gzip() {
+        use std::io::Write;
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        encoder.write_all(b"hello world").unwrap();
+
+        let response = response("gzip", encoder.finish().unwrap())
+            .decompressed()
+            .unwrap();
+
+        assert_eq!(&response.body[..], b"hello world");
+        assert_eq!(response.response_parts.headers[CONTENT_LENGTH], "11");
+        assert!(!response
+            .response_parts
+            .headers
+            .contains_key(CONTENT_ENCODING));
+    }
+
+    #[cfg(feature = "feat-response-ext-gzip")]
+    #[test]
+    fn test_recompress() {
+        use http::header::ETAG;
+
+        let mut response = response("identity", b"hello world".to_vec());
+        response
+            .response_parts
+            .headers
+            .insert(ETAG, http::HeaderValue::from_static("\"abc\""));
+
+        let response = response.recompress(Encoding::Gzip, Some(9)).unwrap();
+        let headers = &response.response_parts.headers;
+        assert_eq!(headers[CONTENT_ENCODING], "gzip");
+        assert_eq!(headers[CONTENT_LENGTH], response.body.len().to_string());
+        assert_eq!(headers[ETAG], "W/\"abc\"");
+
+        let response = response.recompress(Encoding::Identity, None).unwrap();
+        assert_eq!(&response.body[..], b"hello world");
+        assert_eq!(response.response_parts.headers[CONTENT_LENGTH], "11");
+        assert_eq!(response.response_parts.headers[ETAG], "W/\"abc\"");
+    }
+}
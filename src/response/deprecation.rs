@@ -0,0 +1,155 @@
+//! HTTP response utilities: `Deprecation` and `Sunset` headers related.
+
+use std::time::{Duration, SystemTime};
+
+use http::{header, HeaderMap};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// Deprecation info parsed from the response headers, helping client teams
+/// notice upcoming API removals.
+///
+/// - `Deprecation: @1688169599` (RFC 9745), or legacy `Deprecation: true` and
+///   HTTP-date
+/// - `Sunset: Sat, 31 Dec 2025 23:59:59 GMT` (RFC 8594)
+/// - `Link: <...>; rel="deprecation"` / `rel="sunset"` for the documentation
+pub struct DeprecationInfo {
+    /// Whether the resource is (or will be) deprecated.
+    pub deprecated: bool,
+
+    /// When the resource is (or will be) deprecated, if known.
+    pub deprecated_at: Option<SystemTime>,
+
+    /// When the resource will become unavailable.
+    pub sunset: Option<SystemTime>,
+
+    /// Link to the deprecation or sunset documentation.
+    pub link: Option<String>,
+}
+
+impl DeprecationInfo {
+    /// Parse deprecation info from the headers.
+    ///
+    /// Returns `None` if there's neither `Deprecation` nor `Sunset`.
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let deprecation = headers
+            .get("deprecation")
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim);
+        let sunset = headers
+            .get("sunset")
+            .and_then(super::cache::parse_http_date);
+
+        if deprecation.is_none() && sunset.is_none() {
+            return None;
+        }
+
+        let (deprecated, deprecated_at) = match deprecation {
+            None => (false, None),
+            Some(v) if v.eq_ignore_ascii_case("false") => (false, None),
+            Some(v) if v.eq_ignore_ascii_case("true") => (true, None),
+            Some(v) => (
+                true,
+                v.strip_prefix('@')
+                    .and_then(|secs| secs.parse::<u64>().ok())
+                    .map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+                    .or_else(|| httpdate::parse_http_date(v).ok()),
+            ),
+        };
+
+        let mut link = None;
+        headers
+            .get_all(header::LINK)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .for_each(|v| {
+                super::pagination::parse_link(v, |uri, rel| {
+                    if link.is_none()
+                        && (rel.eq_ignore_ascii_case("deprecation")
+                            || rel.eq_ignore_ascii_case("sunset"))
+                    {
+                        link = Some(uri.to_owned());
+                    }
+                });
+            });
+
+        Some(Self {
+            deprecated,
+            deprecated_at,
+            sunset,
+            link,
+        })
+    }
+
+    #[cfg(feature = "feat-tracing")]
+    /// Emit a `tracing::warn!` for the endpoint, once per endpoint in the
+    /// process lifetime.
+    pub fn warn_once(&self, endpoint: &str) {
+        use std::{
+            collections::HashSet,
+            sync::{Mutex, OnceLock, PoisonError},
+        };
+
+        static WARNED: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+
+        let first = WARNED
+            .get_or_init(Mutex::default)
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(endpoint.to_owned());
+
+        if first {
+            tracing::warn!(
+                endpoint,
+                deprecated_at = ?self.deprecated_at.map(httpdate::fmt_http_date),
+                sunset = ?self.sunset.map(httpdate::fmt_http_date),
+                link = ?self.link,
+                "Calling deprecated API endpoint"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_from_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(DeprecationInfo::from_headers(&headers), None);
+
+        headers.insert("deprecation", HeaderValue::from_static("@1688169599"));
+        headers.insert(
+            "sunset",
+            HeaderValue::from_static("Wed, 11 Nov 2026 11:11:11 GMT"),
+        );
+        headers.insert(
+            header::LINK,
+            HeaderValue::from_static(
+                r#"<https://api.example.com/items?page=2>; rel="next", <https://developer.example.com/deprecation>; rel="deprecation""#,
+            ),
+        );
+
+        let info = DeprecationInfo::from_headers(&headers).unwrap();
+        assert!(info.deprecated);
+        assert_eq!(
+            info.deprecated_at,
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_688_169_599))
+        );
+        assert_eq!(
+            info.sunset,
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_794_395_471))
+        );
+        assert_eq!(
+            info.link.as_deref(),
+            Some("https://developer.example.com/deprecation")
+        );
+
+        headers.insert("deprecation", HeaderValue::from_static("true"));
+        let info = DeprecationInfo::from_headers(&headers).unwrap();
+        assert!(info.deprecated);
+        assert_eq!(info.deprecated_at, None);
+    }
+}
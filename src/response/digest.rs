@@ -0,0 +1,223 @@
+//! HTTP response utilities: body digest verification related.
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use http::{HeaderMap, HeaderName, HeaderValue};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Supported digest algorithms.
+pub enum DigestAlgorithm {
+    /// MD5, from `Content-MD5`.
+    Md5,
+
+    /// SHA-256, from `Content-Digest` / `Repr-Digest`.
+    Sha256,
+
+    /// SHA-512, from `Content-Digest` / `Repr-Digest`.
+    Sha512,
+}
+
+impl DigestAlgorithm {
+    /// Compute the digest of given data.
+    pub fn digest(self, data: &[u8]) -> Vec<u8> {
+        use md5::Digest;
+
+        match self {
+            Self::Md5 => md5::Md5::digest(data).to_vec(),
+            Self::Sha256 => sha2::Sha256::digest(data).to_vec(),
+            Self::Sha512 => sha2::Sha512::digest(data).to_vec(),
+        }
+    }
+
+    #[inline]
+    /// Returns the algorithm key used in `Content-Digest` (RFC 9530).
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Md5 => "md5",
+            Self::Sha256 => "sha-256",
+            Self::Sha512 => "sha-512",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(thiserror::Error)]
+/// Error returned by
+/// [`ResponseExt::verify_digest`](super::ResponseExt::verify_digest).
+pub enum DigestError {
+    #[error("no supported digest header found")]
+    /// No `Content-MD5`, `Content-Digest` or `Repr-Digest` with supported
+    /// algorithms.
+    Missing,
+
+    #[error("invalid digest header `{0}`")]
+    /// The digest header is malformed.
+    Invalid(&'static str),
+
+    #[error(
+        "{} digest mismatch, expected `{}`, actual `{}`",
+        algorithm.as_str(),
+        BASE64_STANDARD.encode(expected),
+        BASE64_STANDARD.encode(actual)
+    )]
+    /// The digest does not match.
+    Mismatch {
+        /// The algorithm
+        algorithm: DigestAlgorithm,
+
+        /// The expected digest
+        expected: Vec<u8>,
+
+        /// The actual digest of the body
+        actual: Vec<u8>,
+    },
+}
+
+/// Compute the digest header of the body, i.e. `Content-Digest` (RFC 9530)
+/// like `sha-256=:base64:`, or `Content-MD5` for [`DigestAlgorithm::Md5`].
+#[allow(
+    clippy::missing_panics_doc,
+    reason = "base64 is always valid header value"
+)]
+pub fn digest_header(algorithm: DigestAlgorithm, body: &[u8]) -> (HeaderName, HeaderValue) {
+    let digest = BASE64_STANDARD.encode(algorithm.digest(body));
+
+    let (name, value) = match algorithm {
+        DigestAlgorithm::Md5 => (HeaderName::from_static("content-md5"), digest),
+        _ => (
+            HeaderName::from_static("content-digest"),
+            format!("{}=:{digest}:", algorithm.as_str()),
+        ),
+    };
+
+    (
+        name,
+        HeaderValue::try_from(value).expect("base64 is valid header value"),
+    )
+}
+
+/// Verify the body against the expected digest.
+///
+/// # Errors
+///
+/// [`DigestError::Mismatch`].
+pub fn verify(algorithm: DigestAlgorithm, expected: &[u8], body: &[u8]) -> Result<(), DigestError> {
+    let actual = algorithm.digest(body);
+
+    if actual == expected {
+        Ok(())
+    } else {
+        #[cfg(feature = "feat-tracing")]
+        tracing::error!("{} digest mismatch", algorithm.as_str());
+
+        Err(DigestError::Mismatch {
+            algorithm,
+            expected: expected.to_vec(),
+            actual,
+        })
+    }
+}
+
+/// Verify the body against all the digests of supported algorithms found in
+/// `Content-Digest`, `Repr-Digest` and `Content-MD5` headers.
+///
+/// # Errors
+///
+/// See [`DigestError`].
+pub fn verify_headers(headers: &HeaderMap, body: &[u8]) -> Result<(), DigestError> {
+    let mut verified = false;
+
+    for key in ["content-digest", "repr-digest"] {
+        for value in headers.get_all(key) {
+            let value = value.to_str().map_err(|_| DigestError::Invalid(key))?;
+
+            for (algorithm, expected) in
+                value.split(',').filter_map(|member| member.split_once('='))
+            {
+                let algorithm = match algorithm.trim().to_ascii_lowercase().as_str() {
+                    "sha-256" => DigestAlgorithm::Sha256,
+                    "sha-512" => DigestAlgorithm::Sha512,
+                    _ => continue,
+                };
+
+                // Byte sequence in structured field values: `:base64:`
+                let expected = expected
+                    .trim()
+                    .strip_prefix(':')
+                    .and_then(|v| v.strip_suffix(':'))
+                    .and_then(|v| BASE64_STANDARD.decode(v).ok())
+                    .ok_or(DigestError::Invalid(key))?;
+
+                verify(algorithm, &expected, body)?;
+                verified = true;
+            }
+        }
+    }
+
+    if let Some(value) = headers.get("content-md5") {
+        let expected = value
+            .to_str()
+            .ok()
+            .and_then(|v| BASE64_STANDARD.decode(v.trim()).ok())
+            .ok_or(DigestError::Invalid("content-md5"))?;
+
+        verify(DigestAlgorithm::Md5, &expected, body)?;
+        verified = true;
+    }
+
+    if verified {
+        Ok(())
+    } else {
+        Err(DigestError::Missing)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_digest_header() {
+        const BODY: &[u8] = b"{\"hello\": \"world\"}\n";
+
+        for algorithm in [
+            DigestAlgorithm::Md5,
+            DigestAlgorithm::Sha256,
+            DigestAlgorithm::Sha512,
+        ] {
+            let (name, value) = digest_header(algorithm, BODY);
+
+            let mut headers = HeaderMap::new();
+            headers.insert(name, value);
+            assert_eq!(verify_headers(&headers, BODY), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_verify_headers() {
+        const BODY: &[u8] = b"{\"hello\": \"world\"}\n";
+
+        let mut headers = HeaderMap::new();
+        assert_eq!(verify_headers(&headers, BODY), Err(DigestError::Missing));
+
+        headers.insert(
+            "content-digest",
+            HeaderValue::from_static(
+                "unixsum=:AAAA:, sha-256=:RK/0qy18MlBSVnWgjwz6lZEWjP/lF5HF9bvEF8FabDg=:",
+            ),
+        );
+        assert_eq!(verify_headers(&headers, BODY), Ok(()));
+        assert!(matches!(
+            verify_headers(&headers, b"tampered"),
+            Err(DigestError::Mismatch {
+                algorithm: DigestAlgorithm::Sha256,
+                ..
+            })
+        ));
+
+        headers.insert("content-md5", HeaderValue::from_static("not base64!"));
+        assert_eq!(
+            verify_headers(&headers, BODY),
+            Err(DigestError::Invalid("content-md5"))
+        );
+    }
+}
@@ -0,0 +1,802 @@
+//! HTTP response utilities: parallel, resumable ranged download related.
+
+use std::{future::Future, io, ops::Range, path::Path};
+
+use bytes::Bytes;
+use futures_util::{stream, StreamExt};
+use http::{header, HeaderMap, HeaderValue, Request, StatusCode};
+use tokio::io::{AsyncSeekExt, AsyncWrite, AsyncWriteExt};
+
+use super::ResponseExt;
+
+/// Default size of each range fetched.
+pub const DEFAULT_CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Default number of ranges fetched concurrently.
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Request to be sent by the fetch closure of [`RangedDownloader`].
+pub enum RangeFetch {
+    /// Probing request, typically `HEAD`, for `Accept-Ranges`,
+    /// `Content-Length`, `ETag` and `Last-Modified`.
+    Probe,
+
+    /// Request for the whole resource, when ranges are not supported.
+    Full,
+
+    /// Request for the byte range, see [`apply_to`](Self::apply_to).
+    Range {
+        /// The first byte position.
+        start: u64,
+
+        /// The last byte position (inclusive).
+        end: u64,
+
+        /// The validator for `If-Range` from the probing response, i.e. the
+        /// strong `ETag`, or `Last-Modified` if without one.
+        if_range: Option<HeaderValue>,
+    },
+}
+
+impl RangeFetch {
+    /// Set `Range` (and `If-Range`, if with a validator) to the request.
+    ///
+    /// It's a no-op unless [`RangeFetch::Range`].
+    pub fn apply_to<B>(&self, req: &mut Request<B>) {
+        if let Self::Range {
+            start,
+            end,
+            if_range,
+        } = self
+        {
+            let headers = req.headers_mut();
+
+            headers.insert(
+                header::RANGE,
+                ByteRange {
+                    start: *start,
+                    end: *end,
+                }
+                .to_header_value(),
+            );
+
+            if let Some(if_range) = if_range {
+                headers.insert(header::IF_RANGE, if_range.clone());
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Byte range, both ends inclusive as in the `Range` header.
+pub struct ByteRange {
+    /// The first byte position.
+    pub start: u64,
+
+    /// The last byte position (inclusive).
+    pub end: u64,
+}
+
+impl ByteRange {
+    #[inline]
+    /// Returns the number of bytes in the range.
+    pub const fn size(&self) -> u64 {
+        self.end - self.start + 1
+    }
+
+    #[allow(clippy::missing_panics_doc, reason = "the range is always valid")]
+    /// Returns the `Range` header value, e.g. `bytes=0-499`.
+    pub fn to_header_value(&self) -> HeaderValue {
+        HeaderValue::try_from(format!("bytes={}-{}", self.start, self.end))
+            .expect("must be valid header value")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Planner of the byte ranges to fetch, for writing download loops (it's what
+/// [`RangedDownloader`] uses).
+///
+/// Ranges are planned in order, in chunks, skipping the downloaded ones.
+///
+/// ```rust
+/// # use miku_http_util::response::download::RangePlanner;
+/// let mut planner = RangePlanner::new(10, 4).with_downloaded([2..5]);
+///
+/// let range = planner.next_range().unwrap();
+/// assert_eq!(range.to_header_value(), "bytes=0-1");
+/// planner.complete(range);
+///
+/// assert_eq!(planner.next_range().unwrap().to_header_value(), "bytes=5-8");
+/// assert_eq!(planner.next_range().unwrap().to_header_value(), "bytes=9-9");
+/// assert_eq!(planner.next_range(), None);
+/// assert!(!planner.is_complete());
+/// ```
+pub struct RangePlanner {
+    total: u64,
+    chunk_size: u64,
+    /// Sorted, disjoint and non-adjacent.
+    downloaded: Vec<Range<u64>>,
+    cursor: u64,
+}
+
+impl RangePlanner {
+    #[inline]
+    /// Create a new [`RangePlanner`] for the resource of `total` bytes.
+    pub const fn new(total: u64, chunk_size: u64) -> Self {
+        Self {
+            total,
+            chunk_size: if chunk_size == 0 { 1 } else { chunk_size },
+            downloaded: Vec::new(),
+            cursor: 0,
+        }
+    }
+
+    #[must_use]
+    /// Mark the (half-open) ranges as already downloaded, e.g. restored from
+    /// the previous attempt.
+    pub fn with_downloaded(mut self, ranges: impl IntoIterator<Item = Range<u64>>) -> Self {
+        for range in ranges {
+            self.mark(range);
+        }
+        self
+    }
+
+    #[inline]
+    /// Returns the total length of the resource.
+    pub const fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Returns the next range to fetch, `None` if all the remaining ones have
+    /// been planned.
+    ///
+    /// Planned ranges are not planned again until [`rewind`](Self::rewind),
+    /// unless [`complete`](Self::complete)d.
+    pub fn next_range(&mut self) -> Option<ByteRange> {
+        let mut start = self.cursor;
+        let mut limit = self.total;
+
+        for range in &self.downloaded {
+            if range.end <= start {
+                continue;
+            }
+
+            if range.start <= start {
+                start = range.end;
+            } else {
+                limit = range.start;
+                break;
+            }
+        }
+
+        if start >= self.total {
+            self.cursor = self.total;
+            return None;
+        }
+
+        let end = start.saturating_add(self.chunk_size).min(limit);
+        self.cursor = end;
+
+        Some(ByteRange {
+            start,
+            end: end - 1,
+        })
+    }
+
+    /// Mark the range as downloaded.
+    pub fn complete(&mut self, range: ByteRange) {
+        self.mark(range.start..range.end.saturating_add(1));
+    }
+
+    /// Plan again from the beginning, e.g. to retry the failed ranges. The
+    /// downloaded ones are still skipped.
+    pub fn rewind(&mut self) {
+        self.cursor = 0;
+    }
+
+    /// Returns the downloaded (half-open) ranges, sorted and merged.
+    pub fn downloaded(&self) -> &[Range<u64>] {
+        &self.downloaded
+    }
+
+    /// Returns the number of bytes not downloaded yet.
+    pub fn remaining(&self) -> u64 {
+        self.total
+            - self
+                .downloaded
+                .iter()
+                .map(|range| range.end - range.start)
+                .sum::<u64>()
+    }
+
+    #[inline]
+    /// Whether all the bytes have been downloaded.
+    pub fn is_complete(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    fn mark(&mut self, range: Range<u64>) {
+        let mut start = range.start.min(self.total);
+        let mut end = range.end.min(self.total);
+
+        if start >= end {
+            return;
+        }
+
+        self.downloaded.retain(|range| {
+            if range.end < start || range.start > end {
+                true
+            } else {
+                start = start.min(range.start);
+                end = end.max(range.end);
+                false
+            }
+        });
+
+        let idx = self.downloaded.partition_point(|range| range.start < start);
+        self.downloaded.insert(idx, start..end);
+    }
+}
+
+#[derive(Debug)]
+#[derive(thiserror::Error)]
+/// Error returned by [`RangedDownloader`].
+pub enum DownloadError<E> {
+    #[error("fetch error: {0}")]
+    /// Error returned by the fetch closure.
+    Fetch(E),
+
+    #[error("unexpected status: {0}")]
+    /// Unexpected status, e.g. `200 OK` for range request (the resource may be
+    /// changed).
+    Status(StatusCode),
+
+    #[error("invalid Content-Range, expected bytes {start}-{end}/{total}")]
+    /// `Content-Range` does not match the requested range.
+    ContentRange {
+        /// The first byte position requested.
+        start: u64,
+
+        /// The last byte position requested.
+        end: u64,
+
+        /// The total length from the probing response.
+        total: u64,
+    },
+
+    #[error("ETag mismatch, expected {expected:?}, found {found:?}")]
+    /// `ETag` differs across the ranges.
+    EtagMismatch {
+        /// `ETag` of the probing response.
+        expected: Option<HeaderValue>,
+
+        /// `ETag` of the range response.
+        found: Option<HeaderValue>,
+    },
+
+    #[error("body length mismatch, expected {expected}, found {found}")]
+    /// Body length differs from the requested range.
+    Length {
+        /// The length expected.
+        expected: u64,
+
+        /// The actual length.
+        found: u64,
+    },
+
+    #[error(transparent)]
+    /// IO error writing the file.
+    Io(#[from] io::Error),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Result of [`RangedDownloader`].
+pub struct Downloaded {
+    /// The total length of the resource.
+    pub total: u64,
+
+    /// The offset resumed from, `0` if not resumed.
+    pub resumed_from: u64,
+
+    /// `ETag` of the resource, persist it for validating later resumption
+    /// (see [`RangedDownloader::with_etag`]).
+    pub etag: Option<HeaderValue>,
+}
+
+#[derive(Debug, Clone)]
+/// Result of the probing request.
+struct Probe {
+    total: Option<u64>,
+    accept_ranges: bool,
+    etag: Option<HeaderValue>,
+    last_modified: Option<HeaderValue>,
+}
+
+impl Probe {
+    fn from_headers(headers: &HeaderMap) -> Self {
+        Self {
+            total: headers
+                .get(header::CONTENT_LENGTH)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse().ok()),
+            accept_ranges: headers
+                .get_all(header::ACCEPT_RANGES)
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .flat_map(|v| v.split(','))
+                .any(|v| v.trim().eq_ignore_ascii_case("bytes")),
+            etag: headers.get(header::ETAG).cloned(),
+            last_modified: headers.get(header::LAST_MODIFIED).cloned(),
+        }
+    }
+
+    /// Returns the total length if ranges are supported.
+    fn ranged_total(&self) -> Option<u64> {
+        self.total.filter(|_| self.accept_ranges)
+    }
+
+    /// The validator usable in `If-Range`: the strong `ETag`, or
+    /// `Last-Modified` if without one.
+    fn if_range(&self) -> Option<HeaderValue> {
+        self.etag
+            .clone()
+            .filter(|etag| !etag.as_bytes().starts_with(b"W/"))
+            .or_else(|| self.last_modified.clone())
+    }
+}
+
+/// Parse `Content-Range` like `bytes 0-499/1234`.
+fn parse_content_range(value: &str) -> Option<(u64, u64, Option<u64>)> {
+    let (unit, rest) = value.trim().split_once(' ')?;
+
+    if !unit.eq_ignore_ascii_case("bytes") {
+        return None;
+    }
+
+    let (range, total) = rest.split_once('/')?;
+    let (start, end) = range.split_once('-')?;
+
+    let total = match total.trim() {
+        "*" => None,
+        total => Some(total.parse().ok()?),
+    };
+
+    Some((start.trim().parse().ok()?, end.trim().parse().ok()?, total))
+}
+
+#[derive(Debug, Clone)]
+/// Client-side downloader fetching a resource in parallel byte ranges.
+///
+/// The resource is probed first (see [`RangeFetch::Probe`]). If the server
+/// declares `Accept-Ranges: bytes` with `Content-Length`, the resource is
+/// fetched in ranges concurrently, each validated against the probing
+/// response (`206 Partial Content`, `Content-Range`, `ETag`). Otherwise it's
+/// fetched as a whole.
+///
+/// Ranges are written in order, so a partial file is always a prefix of the
+/// resource and can be resumed, see [`with_resume`](Self::with_resume).
+pub struct RangedDownloader<F> {
+    fetch: F,
+    chunk_size: u64,
+    concurrency: usize,
+    resume: bool,
+    etag: Option<HeaderValue>,
+}
+
+impl<F, Fut, E> RangedDownloader<F>
+where
+    F: Fn(RangeFetch) -> Fut,
+    Fut: Future<Output = Result<ResponseExt, E>>,
+{
+    #[inline]
+    /// Create a new [`RangedDownloader`] with the fetch closure, which sends
+    /// the request described by [`RangeFetch`].
+    pub const fn new(fetch: F) -> Self {
+        Self {
+            fetch,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            concurrency: DEFAULT_CONCURRENCY,
+            resume: false,
+            etag: None,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Set the size of each range.
+    pub fn with_chunk_size(self, chunk_size: u64) -> Self {
+        Self {
+            chunk_size: chunk_size.max(1),
+            ..self
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Set the number of ranges fetched concurrently.
+    pub fn with_concurrency(self, concurrency: usize) -> Self {
+        Self {
+            concurrency: concurrency.max(1),
+            ..self
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Whether to resume from the existing (partial) file in
+    /// [`download_to`](Self::download_to).
+    ///
+    /// Resuming requires a validator for `If-Range` from the probing
+    /// response, i.e. a strong `ETag` or `Last-Modified`, otherwise the
+    /// download restarts from the beginning.
+    pub fn with_resume(self, resume: bool) -> Self {
+        Self { resume, ..self }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Set the `ETag` of the existing (partial) file, from
+    /// [`Downloaded::etag`] of the previous attempt.
+    ///
+    /// If set and differs from the current one, the download restarts from
+    /// the beginning instead of resuming.
+    pub fn with_etag(self, etag: Option<HeaderValue>) -> Self {
+        Self { etag, ..self }
+    }
+
+    /// Download the resource into memory.
+    ///
+    /// # Errors
+    ///
+    /// See [`DownloadError`].
+    pub async fn download(&self) -> Result<(Bytes, Downloaded), DownloadError<E>> {
+        let probe = self.probe().await?;
+
+        let mut buf = Vec::with_capacity(
+            probe
+                .total
+                .and_then(|total| usize::try_from(total).ok())
+                .unwrap_or_default(),
+        );
+        let downloaded = self.transfer(&probe, 0, &mut buf).await?;
+
+        Ok((Bytes::from(buf), downloaded))
+    }
+
+    /// Download the resource to the file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// See [`DownloadError`]. The partial file is kept for resumption.
+    pub async fn download_to<P>(&self, path: P) -> Result<Downloaded, DownloadError<E>>
+    where
+        P: AsRef<Path>,
+    {
+        let probe = self.probe().await?;
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(false)
+            .open(path.as_ref())
+            .await?;
+
+        let mut offset = if self.resume {
+            file.metadata().await?.len()
+        } else {
+            0
+        };
+
+        // Without a validator for `If-Range`, the partial file can't be told
+        // apart from a changed resource.
+        let resumable = probe.ranged_total().is_some_and(|total| offset <= total)
+            && probe.if_range().is_some()
+            && (self.etag.is_none() || self.etag == probe.etag);
+
+        if !resumable {
+            offset = 0;
+        }
+
+        file.set_len(offset).await?;
+        file.seek(io::SeekFrom::Start(offset)).await?;
+
+        let downloaded = self.transfer(&probe, offset, &mut file).await?;
+
+        file.flush().await?;
+
+        Ok(downloaded)
+    }
+
+    async fn probe(&self) -> Result<Probe, DownloadError<E>> {
+        let response = (self.fetch)(RangeFetch::Probe)
+            .await
+            .map_err(DownloadError::Fetch)?;
+
+        if !response.response_parts.status.is_success() {
+            return Err(DownloadError::Status(response.response_parts.status));
+        }
+
+        Ok(Probe::from_headers(&response.response_parts.headers))
+    }
+
+    /// Fetch the resource from `offset` and write to `writer`.
+    async fn transfer<W>(
+        &self,
+        probe: &Probe,
+        offset: u64,
+        writer: &mut W,
+    ) -> Result<Downloaded, DownloadError<E>>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let Some(total) = probe.ranged_total() else {
+            let response = (self.fetch)(RangeFetch::Full)
+                .await
+                .map_err(DownloadError::Fetch)?;
+
+            if !response.response_parts.status.is_success() {
+                return Err(DownloadError::Status(response.response_parts.status));
+            }
+
+            writer.write_all(&response.body).await?;
+
+            return Ok(Downloaded {
+                total: response.body.len() as u64,
+                resumed_from: 0,
+                etag: response.response_parts.headers.get(header::ETAG).cloned(),
+            });
+        };
+
+        let if_range = probe.if_range();
+
+        let mut planner =
+            RangePlanner::new(total, self.chunk_size).with_downloaded(std::iter::once(0..offset));
+
+        let mut chunks = stream::iter(std::iter::from_fn(|| planner.next_range()))
+            .map(|ByteRange { start, end }| {
+                self.fetch_range(probe, start, end, total, if_range.clone())
+            })
+            .buffered(self.concurrency);
+
+        while let Some(chunk) = chunks.next().await {
+            writer.write_all(&chunk?).await?;
+        }
+
+        Ok(Downloaded {
+            total,
+            resumed_from: offset,
+            etag: probe.etag.clone(),
+        })
+    }
+
+    /// Fetch and validate the range.
+    async fn fetch_range(
+        &self,
+        probe: &Probe,
+        start: u64,
+        end: u64,
+        total: u64,
+        if_range: Option<HeaderValue>,
+    ) -> Result<Bytes, DownloadError<E>> {
+        let response = (self.fetch)(RangeFetch::Range {
+            start,
+            end,
+            if_range,
+        })
+        .await
+        .map_err(DownloadError::Fetch)?;
+
+        let parts = &response.response_parts;
+
+        if parts.status != StatusCode::PARTIAL_CONTENT {
+            return Err(DownloadError::Status(parts.status));
+        }
+
+        let content_range = parts
+            .headers
+            .get(header::CONTENT_RANGE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_content_range);
+
+        if content_range != Some((start, end, Some(total))) {
+            return Err(DownloadError::ContentRange { start, end, total });
+        }
+
+        let found = parts.headers.get(header::ETAG);
+        if probe.etag.is_some() && probe.etag.as_ref() != found {
+            return Err(DownloadError::EtagMismatch {
+                expected: probe.etag.clone(),
+                found: found.cloned(),
+            });
+        }
+
+        let expected = end - start + 1;
+        let found = response.body.len() as u64;
+        if found != expected {
+            return Err(DownloadError::Length { expected, found });
+        }
+
+        Ok(response.body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use super::*;
+
+    const DATA: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+    /// Serve [`DATA`] with ranges and given `ETag`.
+    fn serve(fetch: RangeFetch, etag: &'static str) -> ResponseExt {
+        let builder = ResponseExt::builder()
+            .header(header::ACCEPT_RANGES, "bytes")
+            .header(header::ETAG, etag);
+
+        match fetch {
+            RangeFetch::Probe => builder
+                .header(header::CONTENT_LENGTH, DATA.len())
+                .build()
+                .unwrap(),
+            RangeFetch::Full => builder.bytes(DATA).build().unwrap(),
+            RangeFetch::Range { start, end, .. } => {
+                #[allow(clippy::cast_possible_truncation, reason = "test data is small")]
+                let range = &DATA[start as usize..=end as usize];
+
+                builder
+                    .status(StatusCode::PARTIAL_CONTENT)
+                    .header(
+                        header::CONTENT_RANGE,
+                        format!("bytes {start}-{end}/{}", DATA.len()),
+                    )
+                    .bytes(range)
+                    .build()
+                    .unwrap()
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_content_range() {
+        assert_eq!(
+            parse_content_range("bytes 0-499/1234"),
+            Some((0, 499, Some(1234)))
+        );
+        assert_eq!(parse_content_range("bytes 0-499/*"), Some((0, 499, None)));
+        assert_eq!(parse_content_range("items 0-1/2"), None);
+    }
+
+    #[test]
+    fn test_apply_to() {
+        let mut req = Request::new(());
+        RangeFetch::Range {
+            start: 0,
+            end: 9,
+            if_range: Some(HeaderValue::from_static("\"v1\"")),
+        }
+        .apply_to(&mut req);
+
+        assert_eq!(req.headers()[header::RANGE], "bytes=0-9");
+        assert_eq!(req.headers()[header::IF_RANGE], "\"v1\"");
+    }
+
+    #[test]
+    fn test_range_planner() {
+        let mut planner = RangePlanner::new(20, 5).with_downloaded([3..7, 6..9, 15..30]);
+        assert_eq!(planner.downloaded(), &[3..9, 15..20]);
+        assert_eq!(planner.remaining(), 9);
+
+        let ranges: Vec<_> = std::iter::from_fn(|| planner.next_range()).collect();
+        assert_eq!(
+            ranges,
+            [
+                ByteRange { start: 0, end: 2 },
+                ByteRange { start: 9, end: 13 },
+                ByteRange { start: 14, end: 14 },
+            ]
+        );
+
+        // The second range failed, plan it again.
+        planner.complete(ranges[0]);
+        planner.complete(ranges[2]);
+        planner.rewind();
+        assert_eq!(planner.next_range(), Some(ranges[1]));
+        assert_eq!(planner.next_range(), None);
+        assert!(!planner.is_complete());
+
+        planner.complete(ranges[1]);
+        assert!(planner.is_complete());
+        let all = 0..20;
+        assert_eq!(planner.downloaded(), [all]);
+    }
+
+    #[tokio::test]
+    async fn test_download() {
+        let (body, downloaded) =
+            RangedDownloader::new(|fetch| async move { Ok::<_, ()>(serve(fetch, "\"v1\"")) })
+                .with_chunk_size(5)
+                .download()
+                .await
+                .unwrap();
+
+        assert_eq!(body, DATA);
+        assert_eq!(downloaded.total, DATA.len() as u64);
+        assert_eq!(downloaded.etag.unwrap(), "\"v1\"");
+
+        // `ETag` changed after probing.
+        let calls = Arc::new(AtomicUsize::new(0));
+        let err = RangedDownloader::new(|fetch| {
+            let calls = calls.clone();
+
+            async move {
+                let etag = if calls.fetch_add(1, Ordering::Relaxed) == 0 {
+                    "\"v1\""
+                } else {
+                    "\"v2\""
+                };
+
+                Ok::<_, ()>(serve(fetch, etag))
+            }
+        })
+        .download()
+        .await
+        .unwrap_err();
+
+        assert!(matches!(err, DownloadError::EtagMismatch { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_download_to_resume() {
+        let path = std::env::temp_dir().join(format!(
+            "miku-http-util-download-{}.bin",
+            std::process::id()
+        ));
+        tokio::fs::write(&path, &DATA[..12]).await.unwrap();
+
+        let downloaded =
+            RangedDownloader::new(|fetch| async move { Ok::<_, ()>(serve(fetch, "\"v1\"")) })
+                .with_chunk_size(7)
+                .with_resume(true)
+                .with_etag(Some(HeaderValue::from_static("\"v1\"")))
+                .download_to(&path)
+                .await
+                .unwrap();
+
+        assert_eq!(downloaded.resumed_from, 12);
+        assert_eq!(tokio::fs::read(&path).await.unwrap(), DATA);
+
+        // Restart if the `ETag` differs.
+        tokio::fs::write(&path, b"stale").await.unwrap();
+
+        let downloaded =
+            RangedDownloader::new(|fetch| async move { Ok::<_, ()>(serve(fetch, "\"v2\"")) })
+                .with_resume(true)
+                .with_etag(Some(HeaderValue::from_static("\"v1\"")))
+                .download_to(&path)
+                .await
+                .unwrap();
+
+        assert_eq!(downloaded.resumed_from, 0);
+        assert_eq!(tokio::fs::read(&path).await.unwrap(), DATA);
+
+        // Restart without a validator, e.g. with weak `ETag` only.
+        tokio::fs::write(&path, b"stale").await.unwrap();
+
+        let downloaded =
+            RangedDownloader::new(|fetch| async move { Ok::<_, ()>(serve(fetch, "W/\"v1\"")) })
+                .with_resume(true)
+                .download_to(&path)
+                .await
+                .unwrap();
+
+        assert_eq!(downloaded.resumed_from, 0);
+        assert_eq!(tokio::fs::read(&path).await.unwrap(), DATA);
+
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+}
@@ -0,0 +1,203 @@
+//! HTTP response utilities: API envelope (e.g. `{code, msg, data}`) related.
+
+use serde_json::{Map, Value};
+
+use super::JsonError;
+
+#[derive(Debug, Clone, Copy)]
+/// Configuration of the API envelope, like `{"code": 0, "message": "ok",
+/// "data": {...}}`.
+///
+/// See [`ResponseExt::json_envelope`](super::ResponseExt::json_envelope).
+pub struct Envelope<'e> {
+    /// The field name of the code.
+    ///
+    /// The default is `"code"`.
+    pub code_key: &'e str,
+
+    /// The field name of the message.
+    ///
+    /// The default is `"message"`.
+    pub message_key: &'e str,
+
+    /// The field name of the data.
+    ///
+    /// The default is `"data"`.
+    pub data_key: &'e str,
+
+    /// Codes indicating success.
+    ///
+    /// The default is `[0]`.
+    pub success_codes: &'e [i64],
+}
+
+impl Default for Envelope<'_> {
+    fn default() -> Self {
+        Self::new_default()
+    }
+}
+
+impl<'e> Envelope<'e> {
+    #[inline]
+    /// Create a new [`Envelope`].
+    pub const fn new(
+        code_key: &'e str,
+        message_key: &'e str,
+        data_key: &'e str,
+        success_codes: &'e [i64],
+    ) -> Self {
+        Self {
+            code_key,
+            message_key,
+            data_key,
+            success_codes,
+        }
+    }
+
+    #[inline]
+    /// Create a new [`Envelope`] with the default field names and success
+    /// codes.
+    pub const fn new_default() -> Self {
+        Self {
+            code_key: "code",
+            message_key: "message",
+            data_key: "data",
+            success_codes: &[0],
+        }
+    }
+
+    #[inline]
+    /// Set the field name of the code.
+    pub const fn with_code_key(self, code_key: &'e str) -> Self {
+        Self { code_key, ..self }
+    }
+
+    #[inline]
+    /// Set the field name of the message.
+    pub const fn with_message_key(self, message_key: &'e str) -> Self {
+        Self {
+            message_key,
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Set the field name of the data.
+    pub const fn with_data_key(self, data_key: &'e str) -> Self {
+        Self { data_key, ..self }
+    }
+
+    #[inline]
+    /// Set the codes indicating success.
+    pub const fn with_success_codes(self, success_codes: &'e [i64]) -> Self {
+        Self {
+            success_codes,
+            ..self
+        }
+    }
+
+    /// Extract the typed data from the envelope.
+    ///
+    /// # Errors
+    ///
+    /// See [`EnvelopeError`].
+    pub fn extract<T>(&self, mut envelope: Map<String, Value>) -> Result<T, EnvelopeError>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let code = match envelope.get(self.code_key) {
+            Some(Value::Number(code)) => code.as_i64(),
+            Some(Value::String(code)) => code.parse().ok(),
+            _ => None,
+        }
+        .ok_or_else(|| EnvelopeError::InvalidCode(self.code_key.to_owned()))?;
+
+        if !self.success_codes.contains(&code) {
+            let message = match envelope.remove(self.message_key) {
+                Some(Value::String(message)) => message,
+                Some(other) => other.to_string(),
+                None => String::new(),
+            };
+
+            #[cfg(feature = "feat-tracing")]
+            tracing::error!(code, message, "API returned error.");
+
+            return Err(EnvelopeError::Api { code, message });
+        }
+
+        serde_json::from_value(envelope.remove(self.data_key).unwrap_or_default())
+            .map_err(EnvelopeError::Data)
+    }
+}
+
+#[derive(Debug)]
+#[derive(thiserror::Error)]
+/// Error returned by
+/// [`ResponseExt::json_envelope`](super::ResponseExt::json_envelope).
+pub enum EnvelopeError {
+    #[error(transparent)]
+    /// The body is not a valid JSON object.
+    Json(#[from] JsonError),
+
+    #[error("missing or invalid code field `{0}`")]
+    /// The code field is missing, or not an integer.
+    InvalidCode(String),
+
+    #[error("API error {code}: {message}")]
+    /// The code is not in the success set.
+    Api {
+        /// The code
+        code: i64,
+
+        /// The message, empty if missing.
+        message: String,
+    },
+
+    #[error("invalid data: {0}")]
+    /// Failed to deserialize the data field.
+    Data(#[source] serde_json::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::response::ResponseExt;
+
+    fn response(body: &'static str) -> ResponseExt {
+        let (response_parts, ()) = http::Response::new(()).into_parts();
+
+        ResponseExt {
+            response_parts,
+            body: Bytes::from_static(body.as_bytes()),
+        }
+    }
+
+    #[test]
+    fn test_json_envelope() {
+        const ENVELOPE: Envelope<'static> = Envelope::new_default().with_message_key("msg");
+
+        let data = response(r#"{"code":0,"msg":"ok","data":[1,2]}"#)
+            .json_envelope::<Vec<u32>>(ENVELOPE)
+            .unwrap();
+        assert_eq!(data.body, [1, 2]);
+
+        assert!(matches!(
+            response(r#"{"code":"-400","msg":"bad request"}"#).json_envelope::<Vec<u32>>(ENVELOPE),
+            Err(EnvelopeError::Api { code: -400, message }) if message == "bad request"
+        ));
+        assert!(matches!(
+            response(r#"{"msg":"ok"}"#).json_envelope::<Vec<u32>>(ENVELOPE),
+            Err(EnvelopeError::InvalidCode(_))
+        ));
+        assert!(matches!(
+            response(r#"{"code":0,"data":{}}"#).json_envelope::<Vec<u32>>(ENVELOPE),
+            Err(EnvelopeError::Data(_))
+        ));
+        assert!(matches!(
+            response(r#"not json"#).json_envelope::<Vec<u32>>(ENVELOPE),
+            Err(EnvelopeError::Json(_))
+        ));
+    }
+}
@@ -0,0 +1,239 @@
+//! HTTP response utilities: gRPC status related.
+
+use http::HeaderMap;
+
+use crate::{
+    error::{Error, Result},
+    request::header::{BinaryKeyWrapper, HeaderMapExtT},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u32)]
+/// gRPC status codes.
+///
+/// See <https://grpc.github.io/grpc/core/md_doc_statuscodes.html>.
+pub enum GrpcCode {
+    /// Not an error.
+    Ok = 0,
+
+    /// The operation was cancelled.
+    Cancelled = 1,
+
+    /// Unknown error, also used for unrecognized codes.
+    Unknown = 2,
+
+    /// The client specified an invalid argument.
+    InvalidArgument = 3,
+
+    /// The deadline expired before the operation could complete.
+    DeadlineExceeded = 4,
+
+    /// Some requested entity was not found.
+    NotFound = 5,
+
+    /// The entity that a client attempted to create already exists.
+    AlreadyExists = 6,
+
+    /// The caller does not have permission.
+    PermissionDenied = 7,
+
+    /// Some resource has been exhausted.
+    ResourceExhausted = 8,
+
+    /// The system is not in a state required for the operation.
+    FailedPrecondition = 9,
+
+    /// The operation was aborted.
+    Aborted = 10,
+
+    /// The operation was attempted past the valid range.
+    OutOfRange = 11,
+
+    /// The operation is not implemented or supported.
+    Unimplemented = 12,
+
+    /// Internal errors.
+    Internal = 13,
+
+    /// The service is currently unavailable.
+    Unavailable = 14,
+
+    /// Unrecoverable data loss or corruption.
+    DataLoss = 15,
+
+    /// The request does not have valid authentication credentials.
+    Unauthenticated = 16,
+}
+
+impl From<u32> for GrpcCode {
+    fn from(code: u32) -> Self {
+        match code {
+            0 => Self::Ok,
+            1 => Self::Cancelled,
+            3 => Self::InvalidArgument,
+            4 => Self::DeadlineExceeded,
+            5 => Self::NotFound,
+            6 => Self::AlreadyExists,
+            7 => Self::PermissionDenied,
+            8 => Self::ResourceExhausted,
+            9 => Self::FailedPrecondition,
+            10 => Self::Aborted,
+            11 => Self::OutOfRange,
+            12 => Self::Unimplemented,
+            13 => Self::Internal,
+            14 => Self::Unavailable,
+            15 => Self::DataLoss,
+            16 => Self::Unauthenticated,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// gRPC status, from `grpc-status`, `grpc-message` and
+/// `grpc-status-details-bin`.
+pub struct GrpcStatus {
+    /// The status code.
+    pub code: GrpcCode,
+
+    /// The percent-decoded status message.
+    pub message: String,
+
+    /// The raw (base64-decoded) `grpc-status-details-bin`, usually an encoded
+    /// `google.rpc.Status`, see [`details`](Self::details).
+    pub details: Option<Vec<u8>>,
+}
+
+impl GrpcStatus {
+    /// Read gRPC status from the trailers, or the headers of a Trailers-Only
+    /// response.
+    ///
+    /// Returns `Ok(None)` if there's no `grpc-status`.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::ResponseDecode`], invalid `grpc-status`.
+    /// - [`Error::Header`], invalid base64 string in `grpc-status-details-bin`.
+    pub fn from_headers(headers: &HeaderMap) -> Result<Option<Self>> {
+        let Some(code) = headers.get_ascii("grpc-status") else {
+            return Ok(None);
+        };
+
+        let code = code
+            .trim()
+            .parse::<u32>()
+            .map_err(|e| Error::response_decode(format!("invalid grpc-status `{code}`: {e}")))?
+            .into();
+
+        let message = headers
+            .get_exact("grpc-message")
+            .map(|v| {
+                percent_encoding::percent_decode(v.as_bytes())
+                    .decode_utf8_lossy()
+                    .into_owned()
+            })
+            .unwrap_or_default();
+
+        let details = headers.get_bin(BinaryKeyWrapper {
+            inner: "grpc-status-details-bin",
+        })?;
+
+        Ok(Some(Self {
+            code,
+            message,
+            details,
+        }))
+    }
+
+    #[inline]
+    /// Returns if the status code is [`GrpcCode::Ok`].
+    pub fn is_ok(&self) -> bool {
+        self.code == GrpcCode::Ok
+    }
+
+    /// Decode the status details (usually a `google.rpc.Status`).
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::ResponseDecode`], see [`prost::DecodeError`].
+    pub fn details<T>(&self) -> Result<Option<T>>
+    where
+        T: prost::Message + Default,
+    {
+        self.details
+            .as_deref()
+            .map(T::decode)
+            .transpose()
+            .map_err(Error::response_decode)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::HeaderValue;
+
+    use super::*;
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct Status {
+        #[prost(int32, tag = "1")]
+        code: i32,
+        #[prost(string, tag = "2")]
+        message: String,
+    }
+
+    #[test]
+    fn test_from_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(GrpcStatus::from_headers(&headers).unwrap(), None);
+
+        headers.insert("grpc-status", HeaderValue::from_static("5"));
+        headers.insert(
+            "grpc-message",
+            HeaderValue::from_static("user%20%E4%BD%A0 not found"),
+        );
+        headers.insert(
+            "grpc-status-details-bin",
+            // Status { code: 5, message: "nf" }
+            HeaderValue::from_static("CAUSAm5m"),
+        );
+
+        let status = GrpcStatus::from_headers(&headers).unwrap().unwrap();
+        assert_eq!(status.code, GrpcCode::NotFound);
+        assert_eq!(status.message, "user 你 not found");
+        assert!(!status.is_ok());
+        assert_eq!(
+            status.details::<Status>().unwrap(),
+            Some(Status {
+                code: 5,
+                message: "nf".to_owned()
+            })
+        );
+
+        headers.insert("grpc-status", HeaderValue::from_static("99"));
+        assert_eq!(
+            GrpcStatus::from_headers(&headers).unwrap().unwrap().code,
+            GrpcCode::Unknown
+        );
+
+        headers.insert("grpc-status", HeaderValue::from_static("ok"));
+        assert!(matches!(
+            GrpcStatus::from_headers(&headers),
+            Err(Error::ResponseDecode(_))
+        ));
+    }
+
+    #[test]
+    fn test_grpc_status_from_trailers() {
+        let response = crate::response::ResponseExt::builder()
+            .header("grpc-status", "0")
+            .trailer("grpc-status", "14")
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            response.grpc_status().unwrap().unwrap().code,
+            GrpcCode::Unavailable
+        );
+    }
+}
@@ -0,0 +1,274 @@
+//! HTTP response utilities: connection planning hints, i.e. `Alt-Svc`
+//! (RFC 7838) and `Link: <...>; rel=preload` of `103 Early Hints` (RFC 8297).
+
+use std::time::Duration;
+
+use http::{header, HeaderMap, StatusCode};
+
+use super::pagination::{link_param, parse_link_params};
+use crate::percent;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Alternative service advertised by `Alt-Svc`, like
+/// `h3=":443"; ma=3600; persist=1`.
+///
+/// See [`ResponseExt::alt_svc`](super::ResponseExt::alt_svc).
+pub struct AltSvc {
+    /// The ALPN protocol ID (percent-decoded), e.g. `h3`.
+    pub protocol: String,
+
+    /// The alternative authority, e.g. `alt.example.com:443`, or `:443` for
+    /// the same host.
+    pub authority: String,
+
+    /// How long the alternative is fresh, 24 hours by default.
+    pub max_age: Duration,
+
+    /// Whether the alternative persists across network changes.
+    pub persist: bool,
+}
+
+impl AltSvc {
+    /// The default freshness lifetime, 24 hours.
+    pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+    /// Parse the `Alt-Svc` headers.
+    ///
+    /// Returns `None` without `Alt-Svc`, and an empty list for `Alt-Svc:
+    /// clear`, meaning all the alternatives of the origin are invalidated.
+    /// Invalid entries are skipped.
+    pub fn from_headers(headers: &HeaderMap) -> Option<Vec<Self>> {
+        let mut values = headers
+            .get_all(header::ALT_SVC)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .peekable();
+
+        values.peek()?;
+
+        let mut services = Vec::new();
+
+        for value in values {
+            if value.trim().eq_ignore_ascii_case("clear") {
+                return Some(Vec::new());
+            }
+
+            services.extend(value.split(',').filter_map(Self::parse));
+        }
+
+        Some(services)
+    }
+
+    /// Parse a single entry, like `h3=":443"; ma=3600`.
+    fn parse(entry: &str) -> Option<Self> {
+        let mut params = entry.split(';');
+
+        let (protocol, authority) = params.next()?.split_once('=')?;
+        let protocol = percent::decode(protocol.trim()).into_owned();
+        let authority = authority.trim().strip_prefix('"')?.strip_suffix('"')?;
+
+        if protocol.is_empty() || !authority.contains(':') {
+            return None;
+        }
+
+        let mut service = Self {
+            protocol,
+            authority: authority.to_owned(),
+            max_age: Self::DEFAULT_MAX_AGE,
+            persist: false,
+        };
+
+        for (key, value) in params.filter_map(|param| param.split_once('=')) {
+            let value = value.trim().trim_matches('"');
+
+            match key.trim() {
+                "ma" => {
+                    if let Ok(secs) = value.parse() {
+                        service.max_age = Duration::from_secs(secs);
+                    }
+                }
+                "persist" => service.persist = value == "1",
+                _ => {}
+            }
+        }
+
+        Some(service)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Preload hint, from `Link: <...>; rel=preload`.
+pub struct PreloadHint {
+    /// The URI (reference) of the resource.
+    pub uri: String,
+
+    /// The destination (`as`), e.g. `style`, `script` or `fetch`.
+    pub destination: Option<String>,
+
+    /// The `crossorigin` attribute, `anonymous` when present without value.
+    pub crossorigin: Option<String>,
+}
+
+impl PreloadHint {
+    /// Parse the preload hints from the `Link` headers.
+    pub fn from_headers(headers: &HeaderMap) -> Vec<Self> {
+        let mut hints = Vec::new();
+
+        headers
+            .get_all(header::LINK)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .for_each(|v| {
+                parse_link_params(v, |uri, params| {
+                    let is_preload = link_param(params, "rel").is_some_and(|rel| {
+                        rel.split_ascii_whitespace()
+                            .any(|rel| rel.eq_ignore_ascii_case("preload"))
+                    });
+
+                    if !is_preload {
+                        return;
+                    }
+
+                    let crossorigin = link_param(params, "crossorigin")
+                        .map(str::to_owned)
+                        .or_else(|| {
+                            params
+                                .split(';')
+                                .any(|param| param.trim().eq_ignore_ascii_case("crossorigin"))
+                                .then(|| "anonymous".to_owned())
+                        });
+
+                    hints.push(Self {
+                        uri: uri.to_owned(),
+                        destination: link_param(params, "as").map(str::to_owned),
+                        crossorigin,
+                    });
+                });
+            });
+
+        hints
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// Collector of the [`PreloadHint`]s of `103 Early Hints` responses, which may
+/// be sent more than once before the final response.
+pub struct EarlyHints {
+    hints: Vec<PreloadHint>,
+}
+
+impl EarlyHints {
+    #[inline]
+    /// Create a new, empty [`EarlyHints`].
+    pub const fn new() -> Self {
+        Self { hints: Vec::new() }
+    }
+
+    /// Collect the preload hints of the informational response, ignored
+    /// unless `103 Early Hints`.
+    ///
+    /// Hints of the same URI are collected once.
+    pub fn observe(&mut self, status: StatusCode, headers: &HeaderMap) {
+        if status != StatusCode::EARLY_HINTS {
+            return;
+        }
+
+        for hint in PreloadHint::from_headers(headers) {
+            if !self.hints.iter().any(|h| h.uri == hint.uri) {
+                self.hints.push(hint);
+            }
+        }
+    }
+
+    #[inline]
+    /// Returns the collected hints, in order.
+    pub fn hints(&self) -> &[PreloadHint] {
+        &self.hints
+    }
+
+    #[inline]
+    /// Returns the collected hints, in order.
+    pub fn into_hints(self) -> Vec<PreloadHint> {
+        self.hints
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_alt_svc() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(AltSvc::from_headers(&headers), None);
+
+        headers.append(
+            header::ALT_SVC,
+            HeaderValue::from_static(
+                r#"h3=":443"; ma=3600; persist=1, h3%2D29="alt.example.com:8443", bad, h2=443"#,
+            ),
+        );
+        headers.append(header::ALT_SVC, HeaderValue::from_static(r#"h2=":443""#));
+
+        assert_eq!(
+            AltSvc::from_headers(&headers).unwrap(),
+            [
+                AltSvc {
+                    protocol: "h3".to_owned(),
+                    authority: ":443".to_owned(),
+                    max_age: Duration::from_secs(3600),
+                    persist: true,
+                },
+                AltSvc {
+                    protocol: "h3-29".to_owned(),
+                    authority: "alt.example.com:8443".to_owned(),
+                    max_age: AltSvc::DEFAULT_MAX_AGE,
+                    persist: false,
+                },
+                AltSvc {
+                    protocol: "h2".to_owned(),
+                    authority: ":443".to_owned(),
+                    max_age: AltSvc::DEFAULT_MAX_AGE,
+                    persist: false,
+                },
+            ]
+        );
+
+        headers.insert(header::ALT_SVC, HeaderValue::from_static("clear"));
+        assert_eq!(AltSvc::from_headers(&headers), Some(Vec::new()));
+    }
+
+    #[test]
+    fn test_early_hints() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::LINK,
+            HeaderValue::from_static(
+                r#"</style.css>; rel=preload; as=style, </font.woff2>; rel="preload"; as=font; crossorigin, </next>; rel=next"#,
+            ),
+        );
+
+        let mut hints = EarlyHints::new();
+        hints.observe(StatusCode::EARLY_HINTS, &headers);
+        hints.observe(StatusCode::EARLY_HINTS, &headers);
+        hints.observe(StatusCode::OK, &HeaderMap::new());
+
+        assert_eq!(
+            hints.hints(),
+            [
+                PreloadHint {
+                    uri: "/style.css".to_owned(),
+                    destination: Some("style".to_owned()),
+                    crossorigin: None,
+                },
+                PreloadHint {
+                    uri: "/font.woff2".to_owned(),
+                    destination: Some("font".to_owned()),
+                    crossorigin: Some("anonymous".to_owned()),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,608 @@
+//! HTTP response utilities: JSON related.
+
+use std::marker::PhantomData;
+
+use super::ResponseExt;
+
+#[derive(Debug)]
+#[derive(thiserror::Error)]
+#[cfg_attr(
+    feature = "feat-response-ext-json-path",
+    error("failed to parse JSON at `{path}`: {source}")
+)]
+#[cfg_attr(
+    not(feature = "feat-response-ext-json-path"),
+    error("failed to parse JSON: {source}")
+)]
+/// Error returned by [`ResponseExt::json`], carrying the original response
+/// and the reason of failure.
+pub struct JsonError {
+    response: Box<ResponseExt>,
+
+    #[source]
+    source: serde_json::Error,
+
+    #[cfg(feature = "feat-response-ext-json-path")]
+    path: String,
+}
+
+impl JsonError {
+    #[cfg(not(feature = "feat-response-ext-json-path"))]
+    pub(super) fn new(response: ResponseExt, source: serde_json::Error) -> Self {
+        Self {
+            response: Box::new(response),
+            source,
+        }
+    }
+
+    #[cfg(feature = "feat-response-ext-json-path")]
+    pub(super) fn new(response: ResponseExt, source: serde_json::Error, path: String) -> Self {
+        Self {
+            response: Box::new(response),
+            source,
+            path,
+        }
+    }
+
+    #[inline]
+    /// Returns the original response.
+    pub fn response(&self) -> &ResponseExt {
+        &self.response
+    }
+
+    #[inline]
+    /// Consumes the error and returns the original response.
+    pub fn into_response(self) -> ResponseExt {
+        *self.response
+    }
+
+    #[inline]
+    /// Returns the underlying [`serde_json::Error`].
+    pub const fn json_error(&self) -> &serde_json::Error {
+        &self.source
+    }
+
+    #[cfg(feature = "feat-response-ext-json-path")]
+    #[inline]
+    /// Returns the path to the field failed to be deserialized, e.g.
+    /// `data.items[2].id`.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+#[derive(Debug)]
+#[derive(thiserror::Error)]
+/// Error returned by [`ResponseExt::json_checked`].
+pub enum JsonCheckedError {
+    #[error(transparent)]
+    /// The `Content-Type` is not JSON.
+    ContentType(#[from] super::ContentTypeError),
+
+    #[error(transparent)]
+    /// The body is not valid JSON.
+    Json(#[from] JsonError),
+}
+
+#[derive(Debug)]
+/// Iterator over newline-delimited JSON values in the buffered body, see
+/// [`ResponseExt::ndjson`].
+///
+/// Blank lines are skipped.
+pub struct Ndjson<'b, T> {
+    rest: &'b [u8],
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<'b, T> Ndjson<'b, T> {
+    #[inline]
+    /// Create a new [`Ndjson`] iterator over given bytes.
+    pub const fn new(bytes: &'b [u8]) -> Self {
+        Self {
+            rest: bytes,
+            _value: PhantomData,
+        }
+    }
+}
+
+impl<T> Iterator for Ndjson<'_, T>
+where
+    T: serde::de::DeserializeOwned,
+{
+    type Item = Result<T, serde_json::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.rest.is_empty() {
+                return None;
+            }
+
+            let line = match memchr::memchr(b'\n', self.rest) {
+                Some(idx) => {
+                    let line = &self.rest[..idx];
+                    self.rest = &self.rest[idx + 1..];
+                    line
+                }
+                None => std::mem::take(&mut self.rest),
+            };
+
+            if let Some(value) = decode_line(line) {
+                return Some(value);
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+/// Incremental decoder of newline-delimited JSON, for streaming bodies.
+///
+/// Push the received chunks with [`push`](Self::push), then take the decoded
+/// values with [`decode_next`](Self::decode_next) until `None` is returned.
+/// When the stream ends, call [`finish`](Self::finish) to decode the last
+/// line which is not terminated by a newline.
+pub struct NdjsonDecoder<T> {
+    buf: Vec<u8>,
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<T> Default for NdjsonDecoder<T> {
+    fn default() -> Self {
+        Self {
+            buf: Vec::new(),
+            _value: PhantomData,
+        }
+    }
+}
+
+impl<T> NdjsonDecoder<T>
+where
+    T: serde::de::DeserializeOwned,
+{
+    #[inline]
+    /// Create a new [`NdjsonDecoder`].
+    pub const fn new() -> Self {
+        Self {
+            buf: Vec::new(),
+            _value: PhantomData,
+        }
+    }
+
+    #[inline]
+    /// Push a received chunk into the decoder.
+    pub fn push(&mut self, chunk: &[u8]) {
+        self.buf.extend_from_slice(chunk);
+    }
+
+    /// Decode the next complete line, returns `None` if more data is needed.
+    pub fn decode_next(&mut self) -> Option<Result<T, serde_json::Error>> {
+        while let Some(idx) = memchr::memchr(b'\n', &self.buf) {
+            let value = decode_line(&self.buf[..idx]);
+            self.buf.drain(..=idx);
+
+            if value.is_some() {
+                return value;
+            }
+        }
+
+        None
+    }
+
+    /// Decode the remaining data as the last line.
+    pub fn finish(&mut self) -> Option<Result<T, serde_json::Error>> {
+        let value = decode_line(&self.buf);
+        self.buf.clear();
+        value
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArrayState {
+    /// Before the opening `[`.
+    Start,
+
+    /// Scanning the elements.
+    Elements,
+
+    /// After the closing `]`, with whether non-whitespace data follows.
+    End { trailing: bool },
+
+    /// Failed, the following data is ignored.
+    Failed,
+}
+
+#[derive(Debug)]
+/// Incremental decoder of the elements of a top-level JSON array, for
+/// streaming bodies (e.g. export endpoints returning hundreds of megabytes)
+/// which are not worth buffering as a whole.
+///
+/// Use it like [`NdjsonDecoder`]: push the received chunks with
+/// [`push`](Self::push), then take the decoded elements with
+/// [`decode_next`](Self::decode_next) until `None` is returned. When the
+/// stream ends, call [`finish`](Self::finish) to check the array is complete.
+/// See also `json_array_stream` (feature `feat-response-ext-json-stream`)
+/// for `http_body::Body`s.
+///
+/// Only the current element is buffered.
+pub struct JsonArrayDecoder<T> {
+    buf: Vec<u8>,
+    /// Scanned position of the current element in `buf`.
+    pos: usize,
+    state: ArrayState,
+    first: bool,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<T> Default for JsonArrayDecoder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> JsonArrayDecoder<T> {
+    #[inline]
+    /// Create a new [`JsonArrayDecoder`].
+    pub const fn new() -> Self {
+        Self {
+            buf: Vec::new(),
+            pos: 0,
+            state: ArrayState::Start,
+            first: true,
+            depth: 0,
+            in_string: false,
+            escaped: false,
+            _value: PhantomData,
+        }
+    }
+
+    #[inline]
+    /// Push a received chunk into the decoder.
+    pub fn push(&mut self, chunk: &[u8]) {
+        match &mut self.state {
+            ArrayState::Start | ArrayState::Elements => self.buf.extend_from_slice(chunk),
+            ArrayState::End { trailing } => {
+                *trailing |= !chunk.iter().all(u8::is_ascii_whitespace);
+            }
+            ArrayState::Failed => {}
+        }
+    }
+
+    /// Check that the closing `]` has been reached and followed by nothing
+    /// but whitespace, when the stream ends.
+    ///
+    /// # Errors
+    ///
+    /// The array is incomplete, followed by trailing characters, or the
+    /// decoder has failed.
+    pub fn finish(&self) -> Result<(), serde_json::Error> {
+        match self.state {
+            ArrayState::End { trailing: false } => Ok(()),
+            ArrayState::End { trailing: true } => Err(serde::de::Error::custom(
+                "trailing characters after JSON array",
+            )),
+            ArrayState::Failed => Err(serde::de::Error::custom("invalid JSON array")),
+            ArrayState::Start | ArrayState::Elements => {
+                Err(serde::de::Error::custom("unexpected end of JSON array"))
+            }
+        }
+    }
+
+    #[cfg(feature = "feat-response-ext-json-stream")]
+    #[inline]
+    const fn is_failed(&self) -> bool {
+        matches!(self.state, ArrayState::Failed)
+    }
+
+    /// Fail the decoder, the following data is ignored.
+    fn fail(&mut self, msg: &str) -> serde_json::Error {
+        self.state = ArrayState::Failed;
+        self.buf = Vec::new();
+
+        serde::de::Error::custom(msg)
+    }
+
+    /// Scan the current element, returns the position of the terminating `,`
+    /// or `]`.
+    fn scan(&mut self) -> Option<usize> {
+        while let Some(&b) = self.buf.get(self.pos) {
+            let pos = self.pos;
+            self.pos += 1;
+
+            match (self.in_string, self.escaped, b) {
+                (true, true, _) => self.escaped = false,
+                (true, false, b'\\') => self.escaped = true,
+                (true, false, b'"') => self.in_string = false,
+                (true, false, _) => {}
+                (false, _, b'"') => self.in_string = true,
+                (false, _, b'[' | b'{') => self.depth += 1,
+                (false, _, b',' | b']') if self.depth == 0 => return Some(pos),
+                (false, _, b']' | b'}') => self.depth = self.depth.saturating_sub(1),
+                (false, _, _) => {}
+            }
+        }
+
+        None
+    }
+}
+
+impl<T> JsonArrayDecoder<T>
+where
+    T: serde::de::DeserializeOwned,
+{
+    /// Decode the next complete element, returns `None` if more data is
+    /// needed, or the array has ended.
+    ///
+    /// After an error of malformed array (not of decoding the element), the
+    /// decoder fails and always returns `None`.
+    pub fn decode_next(&mut self) -> Option<Result<T, serde_json::Error>> {
+        if self.state == ArrayState::Start {
+            let start = self.buf.iter().position(|b| !b.is_ascii_whitespace());
+
+            match start.map(|idx| (idx, self.buf[idx])) {
+                Some((idx, b'[')) => {
+                    self.buf.drain(..=idx);
+                    self.state = ArrayState::Elements;
+                }
+                Some(_) => return Some(Err(self.fail("expected `[` at the start of JSON array"))),
+                None => {
+                    self.buf.clear();
+                    return None;
+                }
+            }
+        }
+
+        if self.state != ArrayState::Elements {
+            return None;
+        }
+
+        let end = self.scan()?;
+        let closing = self.buf[end] == b']';
+        let element = self.buf[..end].trim_ascii();
+
+        let value = match (element.is_empty(), closing && self.first) {
+            (true, true) => None,
+            (true, false) => return Some(Err(self.fail("expected value in JSON array"))),
+            (false, _) => Some(serde_json::from_slice(element)),
+        };
+
+        self.buf.drain(..=end);
+        self.pos = 0;
+        self.first = false;
+
+        if closing {
+            self.state = ArrayState::End {
+                trailing: !self.buf.iter().all(u8::is_ascii_whitespace),
+            };
+            self.buf = Vec::new();
+        }
+
+        value
+    }
+}
+
+#[cfg(feature = "feat-response-ext-json-stream")]
+#[derive(Debug)]
+#[derive(thiserror::Error)]
+/// Error returned by the stream of [`json_array_stream`].
+pub enum JsonStreamError<E> {
+    #[error("body error: {0}")]
+    /// Error reading the body.
+    Body(E),
+
+    #[error(transparent)]
+    /// The body is not a valid JSON array, or the element fails to decode.
+    Json(#[from] serde_json::Error),
+}
+
+#[cfg(feature = "feat-response-ext-json-stream")]
+/// Decode the elements of the top-level JSON array in the streaming body, see
+/// [`JsonArrayDecoder`].
+///
+/// The stream ends after the body ends, or after any error except the ones
+/// decoding a single element.
+pub fn json_array_stream<T, B>(
+    body: B,
+) -> impl futures_util::Stream<Item = Result<T, JsonStreamError<B::Error>>>
+where
+    T: serde::de::DeserializeOwned,
+    B: http_body::Body,
+{
+    use bytes::Buf;
+    use http_body_util::BodyExt;
+
+    let state = (Box::pin(body), JsonArrayDecoder::new());
+
+    futures_util::stream::unfold(Some(state), |state| async move {
+        let (mut body, mut decoder) = state?;
+
+        loop {
+            if let Some(value) = decoder.decode_next() {
+                let state = (!decoder.is_failed()).then_some((body, decoder));
+
+                return Some((value.map_err(JsonStreamError::Json), state));
+            }
+
+            match body.frame().await {
+                Some(Ok(frame)) => {
+                    if let Ok(mut data) = frame.into_data() {
+                        while data.has_remaining() {
+                            let chunk = data.chunk();
+                            let len = chunk.len();
+
+                            decoder.push(chunk);
+                            data.advance(len);
+                        }
+                    }
+                }
+                Some(Err(e)) => return Some((Err(JsonStreamError::Body(e)), None)),
+                None => return decoder.finish().err().map(|e| (Err(e.into()), None)),
+            }
+        }
+    })
+}
+
+#[inline]
+fn decode_line<T>(line: &[u8]) -> Option<Result<T, serde_json::Error>>
+where
+    T: serde::de::DeserializeOwned,
+{
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+    if line.iter().all(u8::is_ascii_whitespace) {
+        None
+    } else {
+        Some(serde_json::from_slice(line))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+
+    #[derive(Debug, serde::Deserialize)]
+    struct Data {
+        #[allow(dead_code, reason = "test")]
+        items: Vec<u32>,
+    }
+
+    #[test]
+    fn test_json_error() {
+        let (response_parts, ()) = http::Response::new(()).into_parts();
+        let response = ResponseExt {
+            response_parts,
+            body: Bytes::from_static(br#"{"items":[1,"2"]}"#),
+        };
+
+        let err = response.json::<Data>().unwrap_err();
+
+        assert!(err.json_error().is_data());
+        #[cfg(feature = "feat-response-ext-json-path")]
+        assert_eq!(err.path(), "items[1]");
+        assert_eq!(&err.into_response().body[..], br#"{"items":[1,"2"]}"#);
+    }
+
+    #[test]
+    fn test_ndjson() {
+        const DATA: &[u8] = b"{\"id\":1}\r\n\n{\"id\":2}\n{\"id\":\n{\"id\":3}";
+
+        let values: Vec<_> = Ndjson::<serde_json::Value>::new(DATA).collect();
+        assert_eq!(values.len(), 4);
+        assert_eq!(values[1].as_ref().unwrap()["id"], 2);
+        assert!(values[2].as_ref().unwrap_err().is_eof());
+        assert_eq!(values[3].as_ref().unwrap()["id"], 3);
+
+        let mut decoder = NdjsonDecoder::<serde_json::Value>::new();
+        let mut values = Vec::new();
+        for chunk in DATA.chunks(3) {
+            decoder.push(chunk);
+            while let Some(value) = decoder.decode_next() {
+                values.push(value);
+            }
+        }
+        values.extend(decoder.finish());
+        assert_eq!(values.len(), 4);
+        assert_eq!(values[3].as_ref().unwrap()["id"], 3);
+    }
+
+    #[test]
+    fn test_json_array_decoder() {
+        const DATA: &[u8] = br#" [{"id":1,"tags":["a,]","b\"]"]}, 2 ,"x]" , [[3]], {"id":"}"}] "#;
+
+        for chunk_size in [1, 3, DATA.len()] {
+            let mut decoder = JsonArrayDecoder::<serde_json::Value>::new();
+            let mut values = Vec::new();
+            for chunk in DATA.chunks(chunk_size) {
+                decoder.push(chunk);
+                while let Some(value) = decoder.decode_next() {
+                    values.push(value.unwrap());
+                }
+            }
+            decoder.finish().unwrap();
+
+            assert_eq!(
+                values,
+                [
+                    serde_json::json!({"id": 1, "tags": ["a,]", "b\"]"]}),
+                    serde_json::json!(2),
+                    serde_json::json!("x]"),
+                    serde_json::json!([[3]]),
+                    serde_json::json!({"id": "}"}),
+                ]
+            );
+        }
+
+        let mut decoder = JsonArrayDecoder::<u32>::new();
+        decoder.push(b"[]");
+        assert!(decoder.decode_next().is_none());
+        decoder.finish().unwrap();
+
+        let mut decoder = JsonArrayDecoder::<u32>::new();
+        decoder.push(b"[1,,2]");
+        assert_eq!(decoder.decode_next().unwrap().unwrap(), 1);
+        decoder.decode_next().unwrap().unwrap_err();
+        assert!(decoder.decode_next().is_none());
+
+        let mut decoder = JsonArrayDecoder::<u32>::new();
+        decoder.push(b"{\"a\":1}");
+        decoder.decode_next().unwrap().unwrap_err();
+
+        let mut decoder = JsonArrayDecoder::<u32>::new();
+        decoder.push(b"[1,");
+        assert_eq!(decoder.decode_next().unwrap().unwrap(), 1);
+        decoder.finish().unwrap_err();
+
+        let mut decoder = JsonArrayDecoder::<u32>::new();
+        decoder.push(b"[1] \n");
+        assert_eq!(decoder.decode_next().unwrap().unwrap(), 1);
+        decoder.push(b"\r\n");
+        decoder.finish().unwrap();
+
+        for (chunks, value) in [(&[&b"[1]x"[..]][..], 1), (&[b"[1] ", b" ]"], 1)] {
+            let mut decoder = JsonArrayDecoder::<u32>::new();
+            for chunk in chunks {
+                decoder.push(chunk);
+            }
+            assert_eq!(decoder.decode_next().unwrap().unwrap(), value);
+            assert!(decoder.decode_next().is_none());
+            decoder.finish().unwrap_err();
+        }
+    }
+
+    #[cfg(feature = "feat-response-ext-json-stream")]
+    #[tokio::test]
+    async fn test_json_array_stream() {
+        use std::convert::Infallible;
+
+        use futures_util::StreamExt;
+        use http_body::Frame;
+        use http_body_util::StreamBody;
+
+        let body = |chunks: &'static [&'static str]| {
+            StreamBody::new(futures_util::stream::iter(chunks.iter().map(|chunk| {
+                Ok::<_, Infallible>(Frame::data(Bytes::from_static(chunk.as_bytes())))
+            })))
+        };
+
+        let values: Vec<_> = json_array_stream::<u32, _>(body(&["[1, 2,", "\"3\", 4", "] "]))
+            .collect()
+            .await;
+
+        assert_eq!(values.len(), 4);
+        assert_eq!(*values[0].as_ref().unwrap(), 1);
+        assert!(matches!(values[2], Err(JsonStreamError::Json(_))));
+        assert_eq!(*values[3].as_ref().unwrap(), 4);
+
+        // Incomplete, then malformed.
+        for chunks in [&["[1, 2"][..], &["[1,", ",2]"]] {
+            let values: Vec<_> = json_array_stream::<u32, _>(body(chunks)).collect().await;
+
+            assert_eq!(values.len(), 2);
+            assert_eq!(*values[0].as_ref().unwrap(), 1);
+            assert!(matches!(values[1], Err(JsonStreamError::Json(_))));
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! HTTP response utilities: `MessagePack` related.
+
+use super::ResponseExt;
+
+#[derive(Debug)]
+#[derive(thiserror::Error)]
+#[error("failed to parse MessagePack: {source}")]
+/// Error returned by [`ResponseExt::msgpack`], carrying the original response
+/// and the reason of failure.
+pub struct MsgpackError {
+    response: Box<ResponseExt>,
+
+    #[source]
+    source: rmp_serde::decode::Error,
+}
+
+impl MsgpackError {
+    pub(super) fn new(response: ResponseExt, source: rmp_serde::decode::Error) -> Self {
+        Self {
+            response: Box::new(response),
+            source,
+        }
+    }
+
+    #[inline]
+    /// Returns the original response.
+    pub fn response(&self) -> &ResponseExt {
+        &self.response
+    }
+
+    #[inline]
+    /// Consumes the error and returns the original response.
+    pub fn into_response(self) -> ResponseExt {
+        *self.response
+    }
+
+    #[inline]
+    /// Returns the underlying [`rmp_serde::decode::Error`].
+    pub const fn msgpack_error(&self) -> &rmp_serde::decode::Error {
+        &self.source
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Data {
+        items: Vec<u32>,
+    }
+
+    fn response(body: Vec<u8>) -> ResponseExt {
+        let (response_parts, ()) = http::Response::new(()).into_parts();
+
+        ResponseExt {
+            response_parts,
+            body: Bytes::from(body),
+        }
+    }
+
+    #[test]
+    fn test_msgpack() {
+        let data = Data {
+            items: vec![1, 2, 3],
+        };
+
+        let response = response(rmp_serde::to_vec(&data).unwrap());
+        assert_eq!(response.msgpack::<Data>().unwrap().body, data);
+    }
+
+    #[test]
+    fn test_msgpack_error() {
+        let body = rmp_serde::to_vec(&("items", [1])).unwrap();
+
+        let err = response(body.clone()).msgpack::<Data>().unwrap_err();
+        assert!(matches!(
+            err.msgpack_error(),
+            rmp_serde::decode::Error::Syntax(_) | rmp_serde::decode::Error::TypeMismatch(_)
+        ));
+        assert_eq!(err.into_response().body, body);
+    }
+}
@@ -0,0 +1,201 @@
+//! HTTP response utilities: pagination related.
+
+use http::HeaderMap;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// Pagination info extracted from the response headers.
+///
+/// See [`ResponseExt::pagination`](super::ResponseExt::pagination).
+pub struct Pagination {
+    /// URI of the first page, from `Link: <...>; rel="first"`.
+    pub first: Option<String>,
+
+    /// URI of the previous page, from `Link: <...>; rel="prev"`.
+    pub prev: Option<String>,
+
+    /// URI of the next page, from `Link: <...>; rel="next"`.
+    pub next: Option<String>,
+
+    /// URI of the last page, from `Link: <...>; rel="last"`.
+    pub last: Option<String>,
+
+    /// Total count of items, from `X-Total-Count` or `X-Total`.
+    pub total_count: Option<u64>,
+
+    /// Cursor of the next page, from `X-Next-Cursor`.
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// How to fetch the next page.
+pub enum NextPage {
+    /// The URI (reference) of the next page.
+    Uri(String),
+
+    /// The cursor of the next page.
+    Cursor(String),
+}
+
+impl Pagination {
+    /// Extract pagination info from the headers.
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let mut pagination = Self::default();
+
+        headers
+            .get_all(http::header::LINK)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .for_each(|v| {
+                parse_link(v, |uri, rel| {
+                    let slot = match rel {
+                        "first" => &mut pagination.first,
+                        "prev" | "previous" => &mut pagination.prev,
+                        "next" => &mut pagination.next,
+                        "last" => &mut pagination.last,
+                        _ => return,
+                    };
+
+                    slot.get_or_insert_with(|| uri.to_owned());
+                });
+            });
+
+        pagination.total_count = ["x-total-count", "x-total"]
+            .into_iter()
+            .find_map(|key| headers.get(key)?.to_str().ok()?.trim().parse().ok());
+
+        pagination.next_cursor = headers
+            .get("x-next-cursor")
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(str::to_owned);
+
+        pagination
+    }
+
+    #[inline]
+    /// Returns how to fetch the next page, prefer `Link` over the cursor.
+    pub fn next_page(&self) -> Option<NextPage> {
+        self.next
+            .clone()
+            .map(NextPage::Uri)
+            .or_else(|| self.next_cursor.clone().map(NextPage::Cursor))
+    }
+}
+
+/// Parse `Link` header value, like `<uri>; rel="next", <uri>; rel="last"`.
+pub(super) fn parse_link(value: &str, mut f: impl FnMut(&str, &str)) {
+    parse_link_params(value, |uri, params| {
+        link_param(params, "rel")
+            .into_iter()
+            .flat_map(str::split_ascii_whitespace)
+            .for_each(|rel| f(uri, rel));
+    });
+}
+
+/// Returns the (unquoted) value of the `Link` param, like `rel` of
+/// `; rel="next"; as=style`.
+pub(super) fn link_param<'a>(params: &'a str, key: &str) -> Option<&'a str> {
+    params
+        .split(';')
+        .filter_map(|param| param.split_once('='))
+        .find(|(k, _)| k.trim().eq_ignore_ascii_case(key))
+        .map(|(_, v)| v.trim().trim_matches('"'))
+}
+
+/// Parse `Link` header value into the URIs and the raw params of each.
+pub(super) fn parse_link_params(value: &str, mut f: impl FnMut(&str, &str)) {
+    let mut rest = value;
+
+    while let Some(start) = rest.find('<') {
+        let Some(end) = rest[start..].find('>').map(|end| start + end) else {
+            return;
+        };
+
+        let uri = &rest[start + 1..end];
+        rest = &rest[end + 1..];
+
+        let params_end = rest.find(',').unwrap_or(rest.len());
+        let params = &rest[..params_end];
+        rest = &rest[params_end..];
+
+        f(uri, params);
+    }
+}
+
+#[cfg(feature = "feat-response-ext-paginate")]
+/// Walk all the pages, yielding each page's typed body.
+///
+/// `fetch` is called with `None` for the first page, then with the
+/// [`NextPage`] extracted from the previous response, until there's no next
+/// page.
+pub fn paginate<T, E, F, Fut>(fetch: F) -> impl futures_util::Stream<Item = Result<T, E>>
+where
+    T: serde::de::DeserializeOwned,
+    E: From<super::JsonError>,
+    F: FnMut(Option<NextPage>) -> Fut,
+    Fut: std::future::Future<Output = Result<super::ResponseExt, E>>,
+{
+    futures_util::stream::unfold((fetch, Some(None)), |(mut fetch, next_page)| async move {
+        let response = match fetch(next_page?).await {
+            Ok(response) => response,
+            Err(e) => return Some((Err(e), (fetch, None))),
+        };
+
+        let next_page = response.pagination().next_page().map(Some);
+
+        match response.json::<T>() {
+            Ok(response) => Some((Ok(response.body), (fetch, next_page))),
+            Err(e) => Some((Err(e.into()), (fetch, None))),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use http::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_pagination() {
+        let mut headers = HeaderMap::new();
+        headers.append(
+            http::header::LINK,
+            HeaderValue::from_static(
+                r#"<https://api.example.com/items?page=2&a=1,2>; rel="next", <https://api.example.com/items?page=5>; rel="last""#,
+            ),
+        );
+        headers.append(
+            http::header::LINK,
+            HeaderValue::from_static(r#"<https://api.example.com/items?page=1>; rel="prev first""#),
+        );
+        headers.insert("x-total-count", HeaderValue::from_static("100"));
+        headers.insert("x-next-cursor", HeaderValue::from_static("abc"));
+
+        let pagination = Pagination::from_headers(&headers);
+
+        assert_eq!(
+            pagination.next.as_deref(),
+            Some("https://api.example.com/items?page=2&a=1,2")
+        );
+        assert_eq!(
+            pagination.last.as_deref(),
+            Some("https://api.example.com/items?page=5")
+        );
+        assert_eq!(pagination.prev, pagination.first);
+        assert_eq!(pagination.total_count, Some(100));
+        assert_eq!(
+            pagination.next_page(),
+            Some(NextPage::Uri(
+                "https://api.example.com/items?page=2&a=1,2".to_owned()
+            ))
+        );
+
+        headers.remove(http::header::LINK);
+        assert_eq!(
+            Pagination::from_headers(&headers).next_page(),
+            Some(NextPage::Cursor("abc".to_owned()))
+        );
+    }
+}
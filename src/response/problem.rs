@@ -0,0 +1,135 @@
+//! HTTP response utilities: problem details (RFC 9457) related.
+
+use std::fmt;
+
+use http::{header::CONTENT_TYPE, HeaderMap};
+use serde_json::{Map, Value};
+
+/// Media type of problem details in JSON.
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+#[derive(Debug, Clone, PartialEq)]
+/// Problem details for HTTP APIs, see [RFC 9457](https://www.rfc-editor.org/rfc/rfc9457).
+///
+/// Members with unexpected types are ignored, as the RFC requires.
+pub struct ProblemDetails {
+    /// URI reference identifying the problem type, defaults to
+    /// `about:blank`.
+    pub problem_type: String,
+
+    /// Short, human-readable summary of the problem type.
+    pub title: Option<String>,
+
+    /// The HTTP status code generated by the origin server.
+    pub status: Option<u16>,
+
+    /// Human-readable explanation specific to this occurrence of the problem.
+    pub detail: Option<String>,
+
+    /// URI reference identifying the specific occurrence of the problem.
+    pub instance: Option<String>,
+
+    /// Extension members.
+    pub extensions: Map<String, Value>,
+}
+
+impl fmt::Display for ProblemDetails {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.title.as_deref().unwrap_or(&self.problem_type))?;
+
+        if let Some(status) = self.status {
+            write!(f, " ({status})")?;
+        }
+
+        if let Some(detail) = &self.detail {
+            write!(f, ": {detail}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for ProblemDetails {}
+
+impl ProblemDetails {
+    /// Parse problem details from JSON bytes.
+    ///
+    /// # Errors
+    ///
+    /// [`serde_json::Error`] if the bytes is not a JSON object.
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, serde_json::Error> {
+        let mut members: Map<String, Value> = serde_json::from_slice(bytes)?;
+
+        let mut take_string = |key: &str| match members.remove(key) {
+            Some(Value::String(value)) => Some(value),
+            _ => None,
+        };
+
+        let problem_type = take_string("type").unwrap_or_else(|| "about:blank".to_owned());
+        let title = take_string("title");
+        let detail = take_string("detail");
+        let instance = take_string("instance");
+        let status = members
+            .remove("status")
+            .and_then(|status| status.as_u64())
+            .and_then(|status| u16::try_from(status).ok());
+
+        Ok(Self {
+            problem_type,
+            title,
+            status,
+            detail,
+            instance,
+            extensions: members,
+        })
+    }
+}
+
+#[inline]
+/// Returns if the `Content-Type` is `application/problem+json`.
+pub fn is_problem_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(';').next())
+        .is_some_and(|v| v.trim().eq_ignore_ascii_case(PROBLEM_JSON))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_problem_details() {
+        let problem = ProblemDetails::from_slice(
+            br#"{
+                "type": "https://example.com/probs/out-of-credit",
+                "title": "You do not have enough credit.",
+                "status": 403,
+                "detail": "Your current balance is 30, but that costs 50.",
+                "instance": 12,
+                "balance": 30
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            problem.problem_type,
+            "https://example.com/probs/out-of-credit"
+        );
+        assert_eq!(problem.status, Some(403));
+        assert_eq!(problem.instance, None);
+        assert_eq!(problem.extensions["balance"], 30);
+        assert_eq!(
+            problem.to_string(),
+            "You do not have enough credit. (403): Your current balance is 30, but that costs \
+             50."
+        );
+
+        assert_eq!(
+            ProblemDetails::from_slice(b"{}").unwrap().problem_type,
+            "about:blank"
+        );
+        assert!(ProblemDetails::from_slice(b"[]").unwrap_err().is_data());
+    }
+}
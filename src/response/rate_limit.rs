@@ -0,0 +1,176 @@
+//! HTTP response utilities: rate limit headers related.
+
+use std::time::{Duration, SystemTime};
+
+use http::{HeaderMap, HeaderValue};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// Rate limit info parsed from the response headers, so that clients can pace
+/// themselves.
+///
+/// Supported headers, in order of precedence:
+///
+/// - `RateLimit: limit=100, remaining=50, reset=30` or `RateLimit:
+///   "default";r=50;t=30` (IETF draft, structured)
+/// - `RateLimit-Limit`, `RateLimit-Remaining`, `RateLimit-Reset` (IETF draft)
+/// - `X-RateLimit-Limit`, `X-RateLimit-Remaining`, `X-RateLimit-Reset`
+///   (de-facto)
+///
+/// The reset value is accepted both as delta seconds and Unix timestamp (e.g.
+/// GitHub).
+pub struct RateLimitInfo {
+    /// The request quota.
+    pub limit: Option<u64>,
+
+    /// The remaining quota.
+    pub remaining: Option<u64>,
+
+    /// Time until the quota resets.
+    pub reset: Option<Duration>,
+
+    /// The raw quota policy, from `RateLimit-Policy`.
+    pub policy: Option<String>,
+}
+
+impl RateLimitInfo {
+    #[inline]
+    /// Parse rate limit info from the headers.
+    ///
+    /// `now` is used when the reset value is a Unix timestamp.
+    ///
+    /// Returns `None` if there's no rate limit header at all.
+    pub fn from_headers(headers: &HeaderMap, now: SystemTime) -> Option<Self> {
+        Self::from_fn(|key| headers.get(key), now)
+    }
+
+    /// Parse rate limit info with given header getter, see
+    /// [`from_headers`](Self::from_headers).
+    pub fn from_fn<'h, F>(get: F, now: SystemTime) -> Option<Self>
+    where
+        F: Fn(&'static str) -> Option<&'h HeaderValue>,
+    {
+        let get_str = |key| get(key).and_then(|v| v.to_str().ok()).map(str::trim);
+        let get_u64 = |keys: [&'static str; 2]| {
+            keys.into_iter()
+                .find_map(|key| get_str(key)?.parse::<u64>().ok())
+        };
+
+        let mut info = get_str("ratelimit")
+            .map(parse_structured)
+            .unwrap_or_default();
+
+        info.limit = info
+            .limit
+            .or_else(|| get_u64(["ratelimit-limit", "x-ratelimit-limit"]));
+        info.remaining = info
+            .remaining
+            .or_else(|| get_u64(["ratelimit-remaining", "x-ratelimit-remaining"]));
+
+        let reset = info
+            .reset
+            .map(|reset| reset.as_secs())
+            .or_else(|| get_u64(["ratelimit-reset", "x-ratelimit-reset"]));
+        info.reset = reset.and_then(|reset| {
+            // Seconds since 2001-09-09, which is obviously a Unix timestamp.
+            if reset >= 1_000_000_000 {
+                // Treat the overflowing one as no reset.
+                SystemTime::UNIX_EPOCH
+                    .checked_add(Duration::from_secs(reset))
+                    .map(|reset| reset.duration_since(now).unwrap_or_default())
+            } else {
+                Some(Duration::from_secs(reset))
+            }
+        });
+
+        info.policy = get_str("ratelimit-policy").map(str::to_owned);
+
+        if info == Self::default() {
+            None
+        } else {
+            Some(info)
+        }
+    }
+
+    #[inline]
+    /// Whether the quota is exhausted.
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining == Some(0)
+    }
+}
+
+/// Parse the structured `RateLimit` header.
+fn parse_structured(value: &str) -> RateLimitInfo {
+    let mut info = RateLimitInfo::default();
+
+    value
+        .split([',', ';'])
+        .filter_map(|param| param.split_once('='))
+        .for_each(|(k, v)| {
+            let Ok(v) = v.trim().trim_matches('"').parse::<u64>() else {
+                return;
+            };
+
+            match k.trim() {
+                "limit" | "q" => info.limit = Some(v),
+                "remaining" | "r" => info.remaining = Some(v),
+                "reset" | "t" => info.reset = Some(Duration::from_secs(v)),
+                _ => {}
+            }
+        });
+
+    info
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(headers: &[(&'static str, &'static str)]) -> Option<RateLimitInfo> {
+        let mut map = HeaderMap::new();
+        for (k, v) in headers {
+            map.insert(*k, HeaderValue::from_static(v));
+        }
+
+        RateLimitInfo::from_headers(
+            &map,
+            SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+        )
+    }
+
+    #[test]
+    fn test_rate_limit_info() {
+        assert_eq!(info(&[]), None);
+
+        let i = info(&[
+            ("x-ratelimit-limit", "60"),
+            ("x-ratelimit-remaining", "0"),
+            ("x-ratelimit-reset", "1700000030"),
+        ])
+        .unwrap();
+        assert_eq!(i.limit, Some(60));
+        assert!(i.is_exhausted());
+        assert_eq!(i.reset, Some(Duration::from_secs(30)));
+
+        let i = info(&[
+            ("ratelimit", "limit=100, remaining=50, reset=10"),
+            ("ratelimit-policy", "100;w=60"),
+        ])
+        .unwrap();
+        assert_eq!(i.limit, Some(100));
+        assert_eq!(i.remaining, Some(50));
+        assert_eq!(i.reset, Some(Duration::from_secs(10)));
+        assert_eq!(i.policy.as_deref(), Some("100;w=60"));
+
+        let i = info(&[("ratelimit", "\"default\";r=0;t=5")]).unwrap();
+        assert!(i.is_exhausted());
+        assert_eq!(i.reset, Some(Duration::from_secs(5)));
+
+        // `u64::MAX`, overflowing `SystemTime`.
+        const MAX: &str = "18446744073709551615";
+        assert_eq!(info(&[("x-ratelimit-reset", MAX)]), None);
+
+        let i = info(&[("x-ratelimit-remaining", "1"), ("x-ratelimit-reset", MAX)]).unwrap();
+        assert_eq!(i.remaining, Some(1));
+        assert_eq!(i.reset, None);
+    }
+}
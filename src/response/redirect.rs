@@ -0,0 +1,241 @@
+//! HTTP response utilities: redirect chain tracking related.
+
+use std::time::Duration;
+
+use http::{header, response, HeaderMap, HeaderName, StatusCode, Uri};
+
+/// Default max number of redirect hops, the same as most browsers.
+pub const DEFAULT_MAX_REDIRECTS: usize = 20;
+
+/// Headers kept in [`RedirectHop`].
+const SELECTED_HEADERS: [HeaderName; 4] = [
+    header::LOCATION,
+    header::CACHE_CONTROL,
+    header::RETRY_AFTER,
+    HeaderName::from_static("x-request-id"),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A redirect hop.
+pub struct RedirectHop {
+    /// The requested URI.
+    pub uri: Uri,
+
+    /// Status of the response.
+    pub status: StatusCode,
+
+    /// Selected headers of the response: `Location`, `Cache-Control`,
+    /// `Retry-After` and `X-Request-Id`.
+    pub headers: HeaderMap,
+
+    /// Time elapsed for this hop.
+    pub elapsed: Duration,
+}
+
+impl RedirectHop {
+    /// Create a new [`RedirectHop`] from the requested URI and the response.
+    pub fn new(uri: Uri, response: &response::Parts, elapsed: Duration) -> Self {
+        Self {
+            uri,
+            status: response.status,
+            headers: SELECTED_HEADERS
+                .iter()
+                .filter_map(|key| Some((key.clone(), response.headers.get(key)?.clone())))
+                .collect(),
+            elapsed,
+        }
+    }
+
+    /// Returns the redirect target, resolved against the requested URI.
+    pub fn location(&self) -> Option<Uri> {
+        let location = self.headers.get(header::LOCATION)?.to_str().ok()?;
+
+        resolve_location(&self.uri, location.trim())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(thiserror::Error)]
+/// Error returned by [`RedirectTrace::push`].
+pub enum RedirectError {
+    #[error("too many redirects (max {0})")]
+    /// The max number of redirects is exceeded.
+    TooMany(usize),
+
+    #[error("redirect loop detected at {0}")]
+    /// The URI has been visited before.
+    Loop(Uri),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The redirect chain of a request, so that client integrations built on
+/// [`ResponseExt`](super::ResponseExt) can expose where a request actually
+/// ended up.
+///
+/// Client integrations should put it into the extensions of the final
+/// response, see [`ResponseExt::redirect_trace`](super::ResponseExt::redirect_trace).
+pub struct RedirectTrace {
+    hops: Vec<RedirectHop>,
+    max_redirects: usize,
+}
+
+impl Default for RedirectTrace {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_REDIRECTS)
+    }
+}
+
+impl RedirectTrace {
+    #[inline]
+    /// Create a new [`RedirectTrace`] allowing at most `max_redirects` hops.
+    pub const fn new(max_redirects: usize) -> Self {
+        Self {
+            hops: Vec::new(),
+            max_redirects,
+        }
+    }
+
+    /// Append a hop.
+    ///
+    /// # Errors
+    ///
+    /// - [`RedirectError::TooMany`] if there're already `max_redirects` hops.
+    /// - [`RedirectError::Loop`] if the URI has been visited before.
+    pub fn push(&mut self, hop: RedirectHop) -> Result<(), RedirectError> {
+        if self.hops.len() >= self.max_redirects {
+            return Err(RedirectError::TooMany(self.max_redirects));
+        }
+
+        if self.hops.iter().any(|visited| visited.uri == hop.uri) {
+            #[cfg(feature = "feat-tracing")]
+            tracing::warn!("Redirect loop detected at {}", hop.uri);
+
+            return Err(RedirectError::Loop(hop.uri));
+        }
+
+        self.hops.push(hop);
+
+        Ok(())
+    }
+
+    #[inline]
+    /// Returns the hops, in order.
+    pub fn hops(&self) -> &[RedirectHop] {
+        &self.hops
+    }
+
+    #[inline]
+    /// Returns the number of hops.
+    pub fn len(&self) -> usize {
+        self.hops.len()
+    }
+
+    #[inline]
+    /// Returns if there's no hop.
+    pub fn is_empty(&self) -> bool {
+        self.hops.is_empty()
+    }
+
+    #[inline]
+    /// Returns the URI where the request ended up, i.e. the target of the last
+    /// hop.
+    pub fn final_uri(&self) -> Option<Uri> {
+        self.hops.last()?.location()
+    }
+
+    #[inline]
+    /// Returns the total time elapsed of all hops.
+    pub fn elapsed(&self) -> Duration {
+        self.hops.iter().map(|hop| hop.elapsed).sum()
+    }
+}
+
+/// Resolve the `Location` value against the requested URI.
+///
+/// Absolute URIs, scheme-relative (`//host/path`), absolute-path and relative
+/// references are supported. Dot segments are not removed.
+pub fn resolve_location(base: &Uri, location: &str) -> Option<Uri> {
+    if let Ok(uri) = location.parse::<Uri>() {
+        if uri.scheme().is_some() {
+            return Some(uri);
+        }
+    }
+
+    let scheme = base.scheme_str().unwrap_or("http");
+
+    if let Some(rest) = location.strip_prefix("//") {
+        return format!("{scheme}://{rest}").parse().ok();
+    }
+
+    let authority = base.authority()?;
+
+    if location.starts_with('/') {
+        return format!("{scheme}://{authority}{location}").parse().ok();
+    }
+
+    let dir = base.path().rsplit_once('/').map_or("", |(dir, _)| dir);
+
+    if let Some(query) = location.strip_prefix('?') {
+        return format!("{scheme}://{authority}{}?{query}", base.path())
+            .parse()
+            .ok();
+    }
+
+    format!("{scheme}://{authority}{dir}/{location}")
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hop(uri: &'static str, location: &'static str) -> RedirectHop {
+        let (response, ()) = http::Response::builder()
+            .status(StatusCode::FOUND)
+            .header("location", location)
+            .header("set-cookie", "secret")
+            .body(())
+            .unwrap()
+            .into_parts();
+
+        RedirectHop::new(Uri::from_static(uri), &response, Duration::from_millis(10))
+    }
+
+    #[test]
+    fn test_resolve_location() {
+        let base = Uri::from_static("https://a.com/x/y?q=1");
+
+        for (location, expected) in [
+            ("https://b.com/z", "https://b.com/z"),
+            ("//c.com/z", "https://c.com/z"),
+            ("/z", "https://a.com/z"),
+            ("z?p=2", "https://a.com/x/z?p=2"),
+            ("?p=2", "https://a.com/x/y?p=2"),
+        ] {
+            assert_eq!(resolve_location(&base, location).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_redirect_trace() {
+        let mut trace = RedirectTrace::new(2);
+
+        trace.push(hop("http://a.com/", "https://a.com/")).unwrap();
+        assert!(!trace.hops()[0].headers.contains_key("set-cookie"));
+
+        assert_eq!(
+            trace.push(hop("http://a.com/", "https://a.com/")),
+            Err(RedirectError::Loop(Uri::from_static("http://a.com/")))
+        );
+
+        trace.push(hop("https://a.com/", "/home")).unwrap();
+        assert_eq!(trace.final_uri().unwrap(), "https://a.com/home");
+        assert_eq!(trace.elapsed(), Duration::from_millis(20));
+
+        assert_eq!(
+            trace.push(hop("https://a.com/home", "/")),
+            Err(RedirectError::TooMany(2))
+        );
+    }
+}
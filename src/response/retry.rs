@@ -0,0 +1,152 @@
+//! HTTP response utilities: retry related.
+
+use std::time::{Duration, SystemTime};
+
+use http::{header, response, HeaderMap, StatusCode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Why (or why not) to retry, see [`RetryHint`].
+pub enum RetryReason {
+    /// Not an error response (1xx, 2xx or 3xx).
+    NotError,
+
+    /// `408 Request Timeout` or `425 Too Early`.
+    Timeout,
+
+    /// `429 Too Many Requests`, or rate limit exhausted.
+    RateLimited,
+
+    /// `503 Service Unavailable`.
+    Unavailable,
+
+    /// `500 Internal Server Error`, `502 Bad Gateway` or `504 Gateway
+    /// Timeout`.
+    ServerError,
+
+    /// Other client errors, retrying does not help.
+    ClientError,
+
+    /// Other server errors (e.g. `501 Not Implemented`), retrying does not
+    /// help.
+    NotRetryable,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Retry hint computed from the response, which client loops can feed into
+/// their backoff logic.
+///
+/// See [`ResponseExt::retry_hint`](super::ResponseExt::retry_hint).
+pub struct RetryHint {
+    /// Whether the request should be retried.
+    pub should_retry: bool,
+
+    /// How long to wait before retrying, if the server tells.
+    pub after: Option<Duration>,
+
+    /// The reason.
+    pub reason: RetryReason,
+}
+
+impl RetryHint {
+    /// Compute the retry hint from the response parts.
+    ///
+    /// `now` is used for `Retry-After` in HTTP-date format, when the response
+    /// has no `Date` header.
+    pub fn from_parts(parts: &response::Parts, now: SystemTime) -> Self {
+        let reason = match parts.status {
+            status if !(status.is_client_error() || status.is_server_error()) => {
+                return Self {
+                    should_retry: false,
+                    after: None,
+                    reason: RetryReason::NotError,
+                }
+            }
+            StatusCode::REQUEST_TIMEOUT | StatusCode::TOO_EARLY => RetryReason::Timeout,
+            StatusCode::TOO_MANY_REQUESTS => RetryReason::RateLimited,
+            StatusCode::SERVICE_UNAVAILABLE => RetryReason::Unavailable,
+            StatusCode::INTERNAL_SERVER_ERROR
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::GATEWAY_TIMEOUT => RetryReason::ServerError,
+            status if status.is_client_error() => RetryReason::ClientError,
+            _ => RetryReason::NotRetryable,
+        };
+
+        let should_retry = !matches!(reason, RetryReason::ClientError | RetryReason::NotRetryable);
+
+        let after = should_retry
+            .then(|| {
+                retry_after(&parts.headers, now).or_else(|| rate_limit_reset(&parts.headers, now))
+            })
+            .flatten();
+
+        Self {
+            should_retry,
+            after,
+            reason,
+        }
+    }
+}
+
+/// Parse `Retry-After`, in delta-seconds or HTTP-date format.
+fn retry_after(headers: &HeaderMap, now: SystemTime) -> Option<Duration> {
+    let value = headers.get(header::RETRY_AFTER)?;
+
+    if let Some(seconds) = value.to_str().ok().and_then(|v| v.trim().parse().ok()) {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = super::cache::parse_http_date(value)?;
+    let now = headers
+        .get(header::DATE)
+        .and_then(super::cache::parse_http_date)
+        .unwrap_or(now);
+
+    Some(date.duration_since(now).unwrap_or_default())
+}
+
+/// Time until the rate limit resets, only if it's exhausted.
+fn rate_limit_reset(headers: &HeaderMap, now: SystemTime) -> Option<Duration> {
+    super::RateLimitInfo::from_headers(headers, now)
+        .filter(super::RateLimitInfo::is_exhausted)?
+        .reset
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hint(status: u16, headers: &[(&'static str, &'static str)]) -> RetryHint {
+        let mut response = http::Response::builder().status(status);
+        for (k, v) in headers {
+            response = response.header(*k, *v);
+        }
+
+        RetryHint::from_parts(
+            &response.body(()).unwrap().into_parts().0,
+            httpdate::parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT").unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_retry_hint() {
+        assert_eq!(hint(200, &[]).reason, RetryReason::NotError);
+        assert!(!hint(404, &[("retry-after", "10")]).should_retry);
+        assert_eq!(hint(404, &[("retry-after", "10")]).after, None);
+
+        let h = hint(429, &[("retry-after", "10")]);
+        assert!(h.should_retry);
+        assert_eq!(h.after, Some(Duration::from_secs(10)));
+
+        let h = hint(503, &[("retry-after", "Sun, 06 Nov 1994 08:50:37 GMT")]);
+        assert_eq!(h.reason, RetryReason::Unavailable);
+        assert_eq!(h.after, Some(Duration::from_secs(60)));
+
+        let h = hint(
+            429,
+            &[("x-ratelimit-remaining", "0"), ("x-ratelimit-reset", "30")],
+        );
+        assert_eq!(h.after, Some(Duration::from_secs(30)));
+
+        assert_eq!(hint(501, &[]).reason, RetryReason::NotRetryable);
+    }
+}
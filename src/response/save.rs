@@ -0,0 +1,285 @@
+//! HTTP response utilities: saving body to file related.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use http::{header, HeaderMap};
+use tokio::io::AsyncWriteExt;
+
+/// Default size of each chunk written, between which progress is reported.
+pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy)]
+/// Options for [`ResponseExt::save_to`](super::ResponseExt::save_to).
+pub struct SaveOptions {
+    fsync: bool,
+    content_disposition: bool,
+    chunk_size: usize,
+}
+
+impl Default for SaveOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SaveOptions {
+    #[inline]
+    /// Create a new [`SaveOptions`] with default settings: no fsync, ignore
+    /// `Content-Disposition`, and [`DEFAULT_CHUNK_SIZE`].
+    pub const fn new() -> Self {
+        Self {
+            fsync: false,
+            content_disposition: false,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+        }
+    }
+
+    #[inline]
+    /// Whether to fsync the file before renaming it to the destination.
+    pub const fn with_fsync(self, fsync: bool) -> Self {
+        Self { fsync, ..self }
+    }
+
+    #[inline]
+    /// Whether to honor the filename in `Content-Disposition`.
+    ///
+    /// When enabled and the header carries a filename, the given path is
+    /// treated as the directory and the (sanitized) filename is joined to it.
+    pub const fn with_content_disposition(self, content_disposition: bool) -> Self {
+        Self {
+            content_disposition,
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Set the size of each chunk written, between which progress is reported.
+    pub const fn with_chunk_size(self, chunk_size: usize) -> Self {
+        Self {
+            chunk_size: if chunk_size == 0 { 1 } else { chunk_size },
+            ..self
+        }
+    }
+}
+
+/// Extract the filename from `Content-Disposition`, preferring `filename*`
+/// (RFC 6266 / RFC 8187) over `filename`.
+///
+/// The filename is sanitized: only the last path component is kept, and `.`,
+/// `..` or empty ones are rejected.
+pub fn content_disposition_filename(headers: &HeaderMap) -> Option<String> {
+    let value = headers.get(header::CONTENT_DISPOSITION)?.to_str().ok()?;
+
+    let mut filename = None;
+    let mut filename_ext = None;
+
+    for param in value.split(';').skip(1) {
+        let Some((key, value)) = param.split_once('=') else {
+            continue;
+        };
+
+        let value = value.trim();
+
+        match key.trim().to_ascii_lowercase().as_str() {
+            "filename" => {
+                filename = Some(
+                    value
+                        .strip_prefix('"')
+                        .and_then(|v| v.strip_suffix('"'))
+                        .unwrap_or(value)
+                        .replace("\\\"", "\""),
+                );
+            }
+            "filename*" => {
+                // charset'language'value-chars, only UTF-8 is supported.
+                filename_ext = value
+                    .split_once('\'')
+                    .filter(|(charset, _)| charset.eq_ignore_ascii_case("utf-8"))
+                    .and_then(|(_, rest)| rest.split_once('\''))
+                    .and_then(|(_, encoded)| {
+                        percent_encoding::percent_decode_str(encoded)
+                            .decode_utf8()
+                            .ok()
+                            .map(|v| v.into_owned())
+                    });
+            }
+            _ => {}
+        }
+    }
+
+    filename_ext
+        .or(filename)
+        .as_deref()
+        .and_then(|filename| filename.rsplit(['/', '\\']).next())
+        .filter(|filename| !matches!(*filename, "" | "." | ".."))
+        .map(str::to_owned)
+}
+
+/// Write `body` to `path` atomically, see
+/// [`ResponseExt::save_to`](super::ResponseExt::save_to).
+pub(super) async fn save<F>(
+    headers: &HeaderMap,
+    body: &[u8],
+    path: &Path,
+    options: SaveOptions,
+    mut on_progress: F,
+) -> io::Result<PathBuf>
+where
+    F: FnMut(u64, u64),
+{
+    let path = match options
+        .content_disposition
+        .then(|| content_disposition_filename(headers))
+        .flatten()
+    {
+        Some(filename) => path.join(filename),
+        None => path.to_owned(),
+    };
+
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+
+    let nonce = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(file_name);
+    temp_name.push(format!(".{}.{nonce}.tmp", std::process::id()));
+
+    let temp_path = path.with_file_name(temp_name);
+
+    let result = async {
+        let mut file = tokio::fs::File::create(&temp_path).await?;
+
+        let total = body.len() as u64;
+        let mut written = 0;
+
+        on_progress(written, total);
+
+        for chunk in body.chunks(options.chunk_size) {
+            file.write_all(chunk).await?;
+
+            written += chunk.len() as u64;
+            on_progress(written, total);
+        }
+
+        file.flush().await?;
+
+        if options.fsync {
+            file.sync_all().await?;
+        }
+
+        drop(file);
+
+        tokio::fs::rename(&temp_path, &path).await
+    }
+    .await;
+
+    if let Err(e) = result {
+        #[cfg(feature = "feat-tracing")]
+        tracing::error!("Failed to save response body to {}: {e:?}", path.display());
+
+        let _ = tokio::fs::remove_file(&temp_path).await;
+
+        return Err(e);
+    }
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use http::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_content_disposition_filename() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(content_disposition_filename(&headers), None);
+
+        headers.insert(
+            header::CONTENT_DISPOSITION,
+            HeaderValue::from_static("attachment; filename=\"report.csv\""),
+        );
+        assert_eq!(
+            content_disposition_filename(&headers).as_deref(),
+            Some("report.csv")
+        );
+
+        headers.insert(
+            header::CONTENT_DISPOSITION,
+            HeaderValue::from_static(
+                "attachment; filename=\"fallback.txt\"; filename*=UTF-8''%E4%BD%A0%E5%A5%BD.txt",
+            ),
+        );
+        assert_eq!(
+            content_disposition_filename(&headers).as_deref(),
+            Some("你好.txt")
+        );
+
+        headers.insert(
+            header::CONTENT_DISPOSITION,
+            HeaderValue::from_static("attachment; filename=\"../../etc/passwd\""),
+        );
+        assert_eq!(
+            content_disposition_filename(&headers).as_deref(),
+            Some("passwd")
+        );
+
+        headers.insert(
+            header::CONTENT_DISPOSITION,
+            HeaderValue::from_static("attachment; filename=\"..\""),
+        );
+        assert_eq!(content_disposition_filename(&headers), None);
+    }
+
+    #[tokio::test]
+    async fn test_save() {
+        let dir = std::env::temp_dir().join(format!("miku-http-util-save-{}", std::process::id()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_DISPOSITION,
+            HeaderValue::from_static("attachment; filename=\"data.bin\""),
+        );
+
+        let body = vec![7u8; 10];
+        let mut progress = Vec::new();
+
+        let path = save(
+            &headers,
+            &body,
+            &dir,
+            SaveOptions::new()
+                .with_content_disposition(true)
+                .with_fsync(true)
+                .with_chunk_size(4),
+            |written, total| progress.push((written, total)),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(path, dir.join("data.bin"));
+        assert_eq!(tokio::fs::read(&path).await.unwrap(), body);
+        assert_eq!(progress, [(0, 10), (4, 10), (8, 10), (10, 10)]);
+
+        // Only the saved file is left, no temp files.
+        let mut entries = tokio::fs::read_dir(&dir).await.unwrap();
+        let mut count = 0;
+        while entries.next_entry().await.unwrap().is_some() {
+            count += 1;
+        }
+        assert_eq!(count, 1);
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
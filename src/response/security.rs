@@ -0,0 +1,154 @@
+//! HTTP response utilities: security headers, see [`SecurityHeaders`].
+
+use std::time::Duration;
+
+use http::{header, HeaderMap, HeaderName, HeaderValue};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// Builder of the security headers of responses, e.g. `Strict-Transport-Security`
+/// and `X-Content-Type-Options`.
+///
+/// ```rust
+/// # use http::HeaderMap;
+/// # use miku_http_util::response::SecurityHeaders;
+/// let mut headers = HeaderMap::new();
+/// headers.insert("x-frame-options", "SAMEORIGIN".parse().unwrap());
+///
+/// SecurityHeaders::recommended().apply(&mut headers);
+///
+/// assert_eq!(headers["x-content-type-options"], "nosniff");
+/// // Existing ones are kept.
+/// assert_eq!(headers["x-frame-options"], "SAMEORIGIN");
+/// ```
+pub struct SecurityHeaders {
+    headers: Vec<(HeaderName, HeaderValue)>,
+}
+
+impl SecurityHeaders {
+    #[inline]
+    /// Create an empty [`SecurityHeaders`].
+    pub const fn new() -> Self {
+        Self {
+            headers: Vec::new(),
+        }
+    }
+
+    /// The recommended ones:
+    ///
+    /// - `Strict-Transport-Security: max-age=31536000; includeSubDomains`
+    /// - `X-Content-Type-Options: nosniff`
+    /// - `X-Frame-Options: DENY`
+    /// - `Referrer-Policy: strict-origin-when-cross-origin`
+    pub fn recommended() -> Self {
+        Self::new()
+            .with_hsts(Duration::from_secs(365 * 24 * 60 * 60), true, false)
+            .with_content_type_options()
+            .with_frame_options(HeaderValue::from_static("DENY"))
+            .with_referrer_policy(HeaderValue::from_static("strict-origin-when-cross-origin"))
+    }
+
+    #[must_use]
+    /// Set the header, replacing the previous one of the same name.
+    pub fn with_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        match self.headers.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, v)) => *v = value,
+            None => self.headers.push((name, value)),
+        }
+        self
+    }
+
+    #[must_use]
+    /// Set `Strict-Transport-Security`.
+    ///
+    /// # Panics
+    ///
+    /// Panic if the value is not a valid header value (for the HSTS string,
+    /// it's not possible).
+    pub fn with_hsts(self, max_age: Duration, include_subdomains: bool, preload: bool) -> Self {
+        let mut value = format!("max-age={}", max_age.as_secs());
+
+        if include_subdomains {
+            value.push_str("; includeSubDomains");
+        }
+
+        if preload {
+            value.push_str("; preload");
+        }
+
+        self.with_header(
+            header::STRICT_TRANSPORT_SECURITY,
+            HeaderValue::try_from(value).expect("HSTS string should be valid header value"),
+        )
+    }
+
+    #[must_use]
+    /// Set `X-Content-Type-Options: nosniff`.
+    pub fn with_content_type_options(self) -> Self {
+        self.with_header(
+            header::X_CONTENT_TYPE_OPTIONS,
+            HeaderValue::from_static("nosniff"),
+        )
+    }
+
+    #[must_use]
+    /// Set `X-Frame-Options`, e.g. `DENY` or `SAMEORIGIN`.
+    pub fn with_frame_options(self, value: HeaderValue) -> Self {
+        self.with_header(header::X_FRAME_OPTIONS, value)
+    }
+
+    #[must_use]
+    /// Set `Referrer-Policy`, e.g. `no-referrer`.
+    pub fn with_referrer_policy(self, value: HeaderValue) -> Self {
+        self.with_header(header::REFERRER_POLICY, value)
+    }
+
+    #[must_use]
+    /// Set `Content-Security-Policy`.
+    pub fn with_content_security_policy(self, value: HeaderValue) -> Self {
+        self.with_header(header::CONTENT_SECURITY_POLICY, value)
+    }
+
+    #[inline]
+    /// Returns the headers, in order.
+    pub fn headers(&self) -> &[(HeaderName, HeaderValue)] {
+        &self.headers
+    }
+
+    /// Insert the headers missing from the map, the existing ones are kept.
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        for (name, value) in &self.headers {
+            if !headers.contains_key(name) {
+                headers.insert(name.clone(), value.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_security_headers() {
+        let security = SecurityHeaders::recommended()
+            .with_hsts(Duration::from_secs(60), false, true)
+            .with_content_security_policy(HeaderValue::from_static("default-src 'self'"));
+
+        let mut headers = HeaderMap::new();
+        security.apply(&mut headers);
+
+        assert_eq!(headers.len(), 5);
+        assert_eq!(
+            headers[header::STRICT_TRANSPORT_SECURITY],
+            "max-age=60; preload"
+        );
+        assert_eq!(
+            headers[header::CONTENT_SECURITY_POLICY],
+            "default-src 'self'"
+        );
+        assert_eq!(
+            headers[header::REFERRER_POLICY],
+            "strict-origin-when-cross-origin"
+        );
+    }
+}
@@ -0,0 +1,274 @@
+//! HTTP response utilities: serializable snapshot related.
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use bytes::Bytes;
+use http::{HeaderMap, HeaderName, HeaderValue, StatusCode, Version};
+use serde::{Deserialize, Serialize};
+
+use super::{ResponseExt, Timings};
+
+/// Placeholder of redacted header values.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Headers redacted by default.
+pub const DEFAULT_REDACTED_HEADERS: &[&str] = &[
+    "authorization",
+    "cookie",
+    "proxy-authorization",
+    "set-cookie",
+    "x-api-key",
+];
+
+#[derive(Debug, Clone, Copy)]
+/// Options for [`ResponseExt::snapshot`].
+pub struct SnapshotOptions<'a> {
+    redacted_headers: &'a [&'a str],
+    body_limit: Option<usize>,
+}
+
+impl Default for SnapshotOptions<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> SnapshotOptions<'a> {
+    #[inline]
+    /// Create a new [`SnapshotOptions`], with [`DEFAULT_REDACTED_HEADERS`] and
+    /// no body limit.
+    pub const fn new() -> Self {
+        Self {
+            redacted_headers: DEFAULT_REDACTED_HEADERS,
+            body_limit: None,
+        }
+    }
+
+    #[inline]
+    /// Set the headers (lowercase) whose values are replaced with
+    /// [`REDACTED`].
+    pub const fn with_redacted_headers(self, redacted_headers: &'a [&'a str]) -> Self {
+        Self {
+            redacted_headers,
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Set the max length of the body kept in the snapshot.
+    pub const fn with_body_limit(self, body_limit: Option<usize>) -> Self {
+        Self { body_limit, ..self }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+/// Serializable snapshot of [`ResponseExt`], for structured logging and
+/// recorded test fixtures.
+pub struct ResponseSnapshot {
+    /// Status code.
+    pub status: u16,
+
+    /// HTTP version, like `HTTP/1.1`.
+    pub version: String,
+
+    /// Headers in order, with sensitive values redacted.
+    pub headers: Vec<(String, String)>,
+
+    /// The (maybe truncated) body.
+    pub body: SnapshotBody,
+
+    /// Length of the original body.
+    pub body_size: usize,
+
+    /// Whether the body is truncated.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+
+    /// The [`Timings`], if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timings: Option<Timings>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "encoding", content = "data", rename_all = "lowercase")]
+/// Body in [`ResponseSnapshot`].
+pub enum SnapshotBody {
+    /// Valid UTF-8 body, kept as is.
+    Utf8(String),
+
+    /// Binary body, base64-encoded.
+    Base64(String),
+}
+
+#[derive(Debug)]
+#[derive(thiserror::Error)]
+/// Error returned by [`ResponseExt::from_snapshot`].
+pub enum SnapshotError {
+    #[error(transparent)]
+    /// Invalid status code.
+    Status(#[from] http::status::InvalidStatusCode),
+
+    #[error("invalid HTTP version `{0}`")]
+    /// Invalid HTTP version.
+    Version(String),
+
+    #[error(transparent)]
+    /// Invalid header name.
+    HeaderName(#[from] http::header::InvalidHeaderName),
+
+    #[error(transparent)]
+    /// Invalid header value.
+    HeaderValue(#[from] http::header::InvalidHeaderValue),
+
+    #[error(transparent)]
+    /// Invalid base64 body.
+    Body(#[from] base64::DecodeError),
+}
+
+impl SnapshotBody {
+    /// Create a [`SnapshotBody`] truncated to `limit` bytes (at a char boundary
+    /// for UTF-8 body), returns if it's truncated.
+    pub fn truncate(body: &[u8], limit: Option<usize>) -> (Self, bool) {
+        let mut end = limit.map_or(body.len(), |limit| limit.min(body.len()));
+
+        let snapshot = match std::str::from_utf8(body) {
+            Ok(text) => {
+                while !text.is_char_boundary(end) {
+                    end -= 1;
+                }
+
+                Self::Utf8(text[..end].to_owned())
+            }
+            Err(_) => Self::Base64(BASE64_STANDARD.encode(&body[..end])),
+        };
+
+        (snapshot, end < body.len())
+    }
+}
+
+/// Returns the headers in order, with the values of `redacted_headers`
+/// replaced with [`REDACTED`].
+pub fn redact_headers(headers: &HeaderMap, redacted_headers: &[&str]) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(k, v)| {
+            let value = if redacted_headers
+                .iter()
+                .any(|redacted| k.as_str().eq_ignore_ascii_case(redacted))
+            {
+                REDACTED.to_owned()
+            } else {
+                String::from_utf8_lossy(v.as_bytes()).into_owned()
+            };
+
+            (k.as_str().to_owned(), value)
+        })
+        .collect()
+}
+
+impl ResponseSnapshot {
+    pub(super) fn new(response: &ResponseExt, options: SnapshotOptions<'_>) -> Self {
+        let headers = redact_headers(&response.response_parts.headers, options.redacted_headers);
+
+        let body_size = response.body.len();
+        let (body, truncated) = SnapshotBody::truncate(&response.body, options.body_limit);
+
+        Self {
+            status: response.response_parts.status.as_u16(),
+            version: format!("{:?}", response.response_parts.version),
+            headers,
+            body,
+            body_size,
+            truncated,
+            timings: response.timings().copied(),
+        }
+    }
+}
+
+impl TryFrom<ResponseSnapshot> for ResponseExt {
+    type Error = SnapshotError;
+
+    fn try_from(snapshot: ResponseSnapshot) -> Result<Self, Self::Error> {
+        let mut response = http::Response::new(());
+
+        *response.status_mut() = StatusCode::from_u16(snapshot.status)?;
+        *response.version_mut() = match snapshot.version.as_str() {
+            "HTTP/0.9" => Version::HTTP_09,
+            "HTTP/1.0" => Version::HTTP_10,
+            "HTTP/1.1" => Version::HTTP_11,
+            "HTTP/2.0" => Version::HTTP_2,
+            "HTTP/3.0" => Version::HTTP_3,
+            _ => return Err(SnapshotError::Version(snapshot.version)),
+        };
+
+        for (k, v) in snapshot.headers {
+            response
+                .headers_mut()
+                .append(HeaderName::try_from(k)?, HeaderValue::try_from(v)?);
+        }
+
+        let body = match snapshot.body {
+            SnapshotBody::Utf8(text) => Bytes::from(text),
+            SnapshotBody::Base64(encoded) => Bytes::from(BASE64_STANDARD.decode(encoded)?),
+        };
+
+        if let Some(timings) = snapshot.timings {
+            response.extensions_mut().insert(timings);
+        }
+
+        Ok(Self {
+            response_parts: response.into_parts().0,
+            body,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(body: &'static [u8]) -> ResponseExt {
+        let (response_parts, ()) = http::Response::builder()
+            .status(StatusCode::CREATED)
+            .header("content-type", "text/plain")
+            .header("set-cookie", "session=secret")
+            .body(())
+            .unwrap()
+            .into_parts();
+
+        ResponseExt {
+            response_parts,
+            body: Bytes::from_static(body),
+        }
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let snapshot = ResponseSnapshot::new(&response(b"hello"), SnapshotOptions::new());
+
+        assert_eq!(
+            serde_json::to_string(&snapshot).unwrap(),
+            r#"{"status":201,"version":"HTTP/1.1","headers":[["content-type","text/plain"],["set-cookie","[REDACTED]"]],"body":{"encoding":"utf8","data":"hello"},"body_size":5}"#
+        );
+
+        let restored = ResponseExt::try_from(snapshot).unwrap();
+        assert_eq!(restored.response_parts.status, StatusCode::CREATED);
+        assert_eq!(restored.response_parts.headers["set-cookie"], REDACTED);
+        assert_eq!(restored.body, "hello");
+    }
+
+    #[test]
+    fn test_snapshot_truncated() {
+        let options = SnapshotOptions::new().with_body_limit(Some(4));
+
+        let snapshot = ResponseSnapshot::new(&response("你好".as_bytes()), options);
+        assert_eq!(snapshot.body, SnapshotBody::Utf8("你".to_owned()));
+        assert_eq!(snapshot.body_size, 6);
+        assert!(snapshot.truncated);
+
+        let snapshot = ResponseSnapshot::new(&response(b"\xff\x00\x01\x02\x03"), options);
+        assert_eq!(snapshot.body, SnapshotBody::Base64("/wABAg==".to_owned()));
+        assert!(snapshot.truncated);
+    }
+}
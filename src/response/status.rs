@@ -0,0 +1,130 @@
+//! HTTP response utilities: status related.
+
+use http::{header, HeaderMap, HeaderName, StatusCode};
+
+/// Max length of the body text kept in [`StatusError`].
+pub const STATUS_ERROR_BODY_LIMIT: usize = 1024;
+
+/// Headers kept in [`StatusError`].
+const SELECTED_HEADERS: [HeaderName; 5] = [
+    header::CONTENT_TYPE,
+    header::RETRY_AFTER,
+    header::WWW_AUTHENTICATE,
+    HeaderName::from_static("x-request-id"),
+    HeaderName::from_static("x-trace-id"),
+];
+
+#[derive(Debug, Clone)]
+#[derive(thiserror::Error)]
+#[error("HTTP status {}: {}", .0.status, .0.body)]
+/// Error returned by [`ResponseExt::error_for_status`](super::ResponseExt::error_for_status).
+///
+/// Keeps the status, selected headers (`Content-Type`, `Retry-After`,
+/// `WWW-Authenticate`, `X-Request-Id` and `X-Trace-Id`) and the body text
+/// (truncated to [`STATUS_ERROR_BODY_LIMIT`] bytes), so that the server's
+/// explanation is not lost when the error is bubbled up.
+///
+/// Boxed inside, so that `Result<_, StatusError>` stays small.
+pub struct StatusError(Box<StatusErrorInner>);
+
+#[derive(Debug, Clone)]
+struct StatusErrorInner {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: String,
+}
+
+impl StatusError {
+    pub(super) fn new(status: StatusCode, headers: &HeaderMap, body: &[u8]) -> Self {
+        // Only decode the part kept, the body may be huge.
+        let mut text =
+            super::text::decode(headers, &body[..body.len().min(STATUS_ERROR_BODY_LIMIT)]);
+
+        if body.len() > STATUS_ERROR_BODY_LIMIT {
+            // The last character may be cut in the middle.
+            if text.ends_with(char::REPLACEMENT_CHARACTER) {
+                text.pop();
+            }
+
+            // Decoding may expand the text, e.g. from ISO-8859-1.
+            let mut end = text.len().min(STATUS_ERROR_BODY_LIMIT);
+            while !text.is_char_boundary(end) {
+                end -= 1;
+            }
+
+            text.truncate(end);
+            text.push_str("...");
+        }
+
+        Self(Box::new(StatusErrorInner {
+            status,
+            headers: SELECTED_HEADERS
+                .iter()
+                .filter_map(|key| Some((key.clone(), headers.get(key)?.clone())))
+                .collect(),
+            body: text,
+        }))
+    }
+
+    #[inline]
+    /// Returns the status code.
+    pub const fn status(&self) -> StatusCode {
+        self.0.status
+    }
+
+    #[inline]
+    /// Returns the selected headers.
+    pub const fn headers(&self) -> &HeaderMap {
+        &self.0.headers
+    }
+
+    #[inline]
+    /// Returns the (truncated) body text.
+    pub fn body(&self) -> &str {
+        &self.0.body
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::response::ResponseExt;
+
+    #[test]
+    fn test_error_for_status() {
+        let response = |status: u16, body: String| {
+            let (response_parts, ()) = http::Response::builder()
+                .status(status)
+                .header(header::CONTENT_TYPE, "text/plain")
+                .header(header::SERVER, "test")
+                .body(())
+                .unwrap()
+                .into_parts();
+
+            ResponseExt {
+                response_parts,
+                body: Bytes::from(body),
+            }
+        };
+
+        let ok = response(200, String::new()).error_for_status().unwrap();
+        assert_eq!(ok.response_parts.status, StatusCode::OK);
+
+        let err = response(429, "slow down".to_owned())
+            .error_for_status()
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(
+            err.to_string(),
+            "HTTP status 429 Too Many Requests: slow down"
+        );
+        assert_eq!(err.headers().len(), 1);
+
+        let err = response(500, "你".repeat(1000))
+            .error_for_status()
+            .unwrap_err();
+        assert_eq!(err.body().len(), 1023 + 3);
+    }
+}
@@ -0,0 +1,149 @@
+//! HTTP response utilities: text decoding related.
+
+use http::{header::CONTENT_TYPE, HeaderMap};
+
+/// Returns the `charset` parameter of the `Content-Type` header, if any.
+pub fn content_type_charset(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(CONTENT_TYPE)?
+        .to_str()
+        .ok()?
+        .split(';')
+        .skip(1)
+        .filter_map(|param| param.split_once('='))
+        .find(|(k, _)| k.trim().eq_ignore_ascii_case("charset"))
+        .map(|(_, v)| v.trim().trim_matches('"'))
+}
+
+#[cfg(not(feature = "feat-response-ext-charset"))]
+/// Decode the body according to the charset.
+///
+/// Only UTF-8, US-ASCII and ISO-8859-1 are supported, enable feature
+/// `feat-response-ext-charset` for more.
+pub(super) fn decode(headers: &HeaderMap, body: &[u8]) -> String {
+    match content_type_charset(headers) {
+        Some(charset)
+            if charset.eq_ignore_ascii_case("iso-8859-1")
+                || charset.eq_ignore_ascii_case("latin1") =>
+        {
+            body.iter().map(|&b| char::from(b)).collect()
+        }
+        _charset => {
+            #[cfg(feature = "feat-tracing")]
+            if let Some(charset) = _charset.filter(|charset| {
+                !charset.eq_ignore_ascii_case("utf-8") && !charset.eq_ignore_ascii_case("us-ascii")
+            }) {
+                tracing::warn!("Unsupported charset `{charset}`, fallback to UTF-8");
+            }
+
+            let body = body.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(body);
+
+            String::from_utf8_lossy(body).into_owned()
+        }
+    }
+}
+
+#[cfg(feature = "feat-response-ext-charset")]
+/// Decode the body according to the BOM, charset of `Content-Type` or the
+/// `<meta>` tag in HTML document, in order. Defaults to UTF-8.
+pub(super) fn decode(headers: &HeaderMap, body: &[u8]) -> String {
+    use encoding_rs::{Encoding, UTF_8};
+
+    let encoding = content_type_charset(headers)
+        .or_else(|| sniff_meta_charset(body))
+        .and_then(|label| Encoding::for_label(label.as_bytes()))
+        .unwrap_or(UTF_8);
+
+    // BOM sniffing is done by `decode`.
+    let (text, _encoding, _had_errors) = encoding.decode(body);
+
+    #[cfg(feature = "feat-tracing")]
+    if _had_errors {
+        tracing::warn!("Malformed {} text", _encoding.name());
+    }
+
+    text.into_owned()
+}
+
+#[cfg(feature = "feat-response-ext-charset")]
+/// Find `charset=...` within the first 1024 bytes of the body, which is how
+/// HTML `<meta charset="...">` or `<meta http-equiv="Content-Type" ...>`
+/// declares the charset.
+fn sniff_meta_charset(body: &[u8]) -> Option<&str> {
+    const PATTERN: &[u8] = b"charset=";
+
+    let head = &body[..body.len().min(1024)];
+
+    let start = head
+        .windows(PATTERN.len())
+        .position(|window| window.eq_ignore_ascii_case(PATTERN))?
+        + PATTERN.len();
+
+    let value = &head[start..];
+    let value = value
+        .strip_prefix(b"\"")
+        .or_else(|| value.strip_prefix(b"'"))
+        .unwrap_or(value);
+    let end = value
+        .iter()
+        .position(|b| matches!(b, b'"' | b'\'' | b';' | b'>' | b'/') || b.is_ascii_whitespace())
+        .unwrap_or(value.len());
+
+    std::str::from_utf8(&value[..end])
+        .ok()
+        .filter(|label| !label.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use http::HeaderValue;
+
+    use super::*;
+
+    fn headers(content_type: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+        headers
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(
+            content_type_charset(&headers("text/plain; Charset=\"UTF-8\"")),
+            Some("UTF-8")
+        );
+        assert_eq!(content_type_charset(&headers("text/plain")), None);
+
+        assert_eq!(
+            decode(
+                &headers("text/plain; charset=utf-8"),
+                "\u{FEFF}你好".as_bytes()
+            ),
+            "你好"
+        );
+        assert_eq!(
+            decode(&headers("text/plain; charset=iso-8859-1"), b"caf\xE9"),
+            "café"
+        );
+    }
+
+    #[cfg(feature = "feat-response-ext-charset")]
+    #[test]
+    fn test_decode_sniffing() {
+        assert_eq!(
+            decode(&headers("text/plain; charset=gbk"), b"\xC4\xE3\xBA\xC3"),
+            "你好"
+        );
+        assert_eq!(
+            decode(
+                &headers("text/html"),
+                b"<html><head><meta charset=\"gbk\"></head>\xC4\xE3\xBA\xC3"
+            ),
+            "<html><head><meta charset=\"gbk\"></head>你好"
+        );
+        assert_eq!(
+            decode(&headers("text/plain"), b"\xFF\xFE\x60\x4F\x7D\x59"),
+            "你好"
+        );
+    }
+}
@@ -0,0 +1,171 @@
+//! HTTP response utilities: timing metadata related.
+
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "feat-response-ext-snapshot",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+/// Timing metadata of the response, kept in the extensions of
+/// [`ResponseExt::response_parts`](super::ResponseExt::response_parts), so
+/// that latency data travels with the response through logging and retry
+/// layers.
+///
+/// Client integrations set it with
+/// [`ResponseExt::set_timings`](super::ResponseExt::set_timings). With
+/// feature `feat-response-ext-snapshot`, it's (de)serialized in milliseconds
+/// and included in the snapshot.
+pub struct Timings {
+    /// DNS resolution.
+    #[cfg_attr(
+        feature = "feat-response-ext-snapshot",
+        serde(default, skip_serializing_if = "Option::is_none", with = "as_millis")
+    )]
+    pub dns: Option<Duration>,
+
+    /// TCP connecting.
+    #[cfg_attr(
+        feature = "feat-response-ext-snapshot",
+        serde(default, skip_serializing_if = "Option::is_none", with = "as_millis")
+    )]
+    pub connect: Option<Duration>,
+
+    /// TLS handshake.
+    #[cfg_attr(
+        feature = "feat-response-ext-snapshot",
+        serde(default, skip_serializing_if = "Option::is_none", with = "as_millis")
+    )]
+    pub tls: Option<Duration>,
+
+    /// Time to first byte, from the request being sent.
+    #[cfg_attr(
+        feature = "feat-response-ext-snapshot",
+        serde(default, skip_serializing_if = "Option::is_none", with = "as_millis")
+    )]
+    pub ttfb: Option<Duration>,
+
+    /// Total time, till the body is fully received.
+    #[cfg_attr(
+        feature = "feat-response-ext-snapshot",
+        serde(default, skip_serializing_if = "Option::is_none", with = "as_millis")
+    )]
+    pub total: Option<Duration>,
+}
+
+impl Timings {
+    #[inline]
+    /// Create an empty [`Timings`].
+    pub const fn new() -> Self {
+        Self {
+            dns: None,
+            connect: None,
+            tls: None,
+            ttfb: None,
+            total: None,
+        }
+    }
+
+    #[inline]
+    /// Set the DNS resolution time.
+    pub const fn with_dns(self, dns: Duration) -> Self {
+        Self {
+            dns: Some(dns),
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Set the TCP connecting time.
+    pub const fn with_connect(self, connect: Duration) -> Self {
+        Self {
+            connect: Some(connect),
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Set the TLS handshake time.
+    pub const fn with_tls(self, tls: Duration) -> Self {
+        Self {
+            tls: Some(tls),
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Set the time to first byte.
+    pub const fn with_ttfb(self, ttfb: Duration) -> Self {
+        Self {
+            ttfb: Some(ttfb),
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Set the total time.
+    pub const fn with_total(self, total: Duration) -> Self {
+        Self {
+            total: Some(total),
+            ..self
+        }
+    }
+}
+
+#[cfg(feature = "feat-response-ext-snapshot")]
+mod as_millis {
+    //! (De)serialize `Option<Duration>` as milliseconds.
+
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    #[allow(
+        clippy::ref_option,
+        reason = "signature required by `serde(with = ...)`"
+    )]
+    pub(super) fn serialize<S>(value: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match value {
+            Some(value) => serializer.serialize_f64(value.as_secs_f64() * 1000.0),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub(super) fn deserialize<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Option::<f64>::deserialize(deserializer)?
+            .map(|millis| {
+                Duration::try_from_secs_f64(millis / 1000.0).map_err(serde::de::Error::custom)
+            })
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timings() {
+        let mut response = super::super::ResponseExt::builder().build().unwrap();
+        assert_eq!(response.timings(), None);
+
+        let timings = Timings::new()
+            .with_dns(Duration::from_millis(3))
+            .with_total(Duration::from_micros(42_500));
+        response.set_timings(timings);
+        assert_eq!(response.timings(), Some(&timings));
+
+        #[cfg(feature = "feat-response-ext-snapshot")]
+        {
+            let json = serde_json::to_string(&timings).unwrap();
+            assert_eq!(json, r#"{"dns":3.0,"total":42.5}"#);
+            assert_eq!(serde_json::from_str::<Timings>(&json).unwrap(), timings);
+        }
+    }
+}
@@ -0,0 +1,243 @@
+//! HTTP response utilities: body transform pipeline related.
+//!
+//! A [`BodyPipeline`] is a chain of [`BodyTransform`] stages configured once
+//! and applied via [`ResponseExt::apply`], so that organizations with
+//! encrypted / wrapped response bodies can plug their own stage into the
+//! standard flow:
+//!
+//! ```rust
+//! # use miku_http_util::response::{BodyPipeline, ResponseExt};
+//! let pipeline = BodyPipeline::new()
+//!     .decompress()
+//!     .charset()
+//!     .then(|mut response: ResponseExt| {
+//!         // decrypt the body here
+//!         response.body = response.body.slice(1..);
+//!         Ok(response)
+//!     });
+//!
+//! let response = ResponseExt::builder()
+//!     .bytes(&b"#{\"a\":1}"[..])
+//!     .build()
+//!     .unwrap()
+//!     .apply(&pipeline)
+//!     .unwrap();
+//! assert_eq!(response.body, r#"{"a":1}"#);
+//! ```
+//!
+//! Deserialization stays the final step, with [`ResponseExt::json`], etc.
+
+use std::{fmt, sync::Arc};
+
+use http::{header::CONTENT_TYPE, HeaderValue};
+
+use super::ResponseExt;
+use crate::error::BoxError;
+
+/// A stage of [`BodyPipeline`].
+///
+/// Implemented for closures
+/// `Fn(ResponseExt) -> Result<ResponseExt, BoxError>`.
+pub trait BodyTransform: Send + Sync {
+    /// Name of the stage, for error reporting.
+    fn name(&self) -> &'static str {
+        "custom"
+    }
+
+    /// Transform the response.
+    ///
+    /// # Errors
+    ///
+    /// Any error of the stage.
+    fn transform(&self, response: ResponseExt) -> Result<ResponseExt, BoxError>;
+}
+
+impl<F> BodyTransform for F
+where
+    F: Fn(ResponseExt) -> Result<ResponseExt, BoxError> + Send + Sync,
+{
+    #[inline]
+    fn transform(&self, response: ResponseExt) -> Result<ResponseExt, BoxError> {
+        self(response)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+/// Stage decompressing the body, see [`ResponseExt::decompressed`].
+pub struct Decompress;
+
+impl BodyTransform for Decompress {
+    fn name(&self) -> &'static str {
+        "decompress"
+    }
+
+    fn transform(&self, response: ResponseExt) -> Result<ResponseExt, BoxError> {
+        Ok(response.decompressed()?)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+/// Stage decoding the body as text (see [`ResponseExt::text`]) and
+/// re-encoding it as UTF-8, with the charset of `Content-Type` updated.
+pub struct CharsetDecode;
+
+impl BodyTransform for CharsetDecode {
+    fn name(&self) -> &'static str {
+        "charset"
+    }
+
+    fn transform(&self, mut response: ResponseExt) -> Result<ResponseExt, BoxError> {
+        let Some(content_type) = response
+            .response_parts
+            .headers
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+        else {
+            return Ok(response);
+        };
+
+        let content_type = content_type
+            .split(';')
+            .filter(|param| {
+                !param
+                    .split_once('=')
+                    .is_some_and(|(k, _)| k.trim().eq_ignore_ascii_case("charset"))
+            })
+            .chain(std::iter::once(" charset=utf-8"))
+            .collect::<Vec<_>>()
+            .join(";");
+
+        response.body = response.text().into();
+        response
+            .response_parts
+            .headers
+            .insert(CONTENT_TYPE, HeaderValue::try_from(content_type)?);
+
+        Ok(response)
+    }
+}
+
+#[derive(Debug)]
+#[derive(thiserror::Error)]
+#[error("body transform stage `{stage}` failed: {source}")]
+/// Error returned by [`ResponseExt::apply`].
+pub struct TransformError {
+    stage: &'static str,
+
+    #[source]
+    source: BoxError,
+}
+
+impl TransformError {
+    #[inline]
+    /// Returns the name of the failed stage.
+    pub const fn stage(&self) -> &'static str {
+        self.stage
+    }
+
+    #[inline]
+    /// Returns the underlying error.
+    pub fn source_error(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+        &*self.source
+    }
+}
+
+#[derive(Clone, Default)]
+/// A chain of [`BodyTransform`] stages, see the module documentation.
+pub struct BodyPipeline {
+    stages: Vec<Arc<dyn BodyTransform>>,
+}
+
+impl fmt::Debug for BodyPipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.stages.iter().map(|stage| stage.name()))
+            .finish()
+    }
+}
+
+impl BodyPipeline {
+    #[inline]
+    /// Create an empty [`BodyPipeline`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    /// Append a stage.
+    pub fn then<T>(mut self, stage: T) -> Self
+    where
+        T: BodyTransform + 'static,
+    {
+        self.stages.push(Arc::new(stage));
+        self
+    }
+
+    #[inline]
+    /// Append the [`Decompress`] stage.
+    pub fn decompress(self) -> Self {
+        self.then(Decompress)
+    }
+
+    #[inline]
+    /// Append the [`CharsetDecode`] stage.
+    pub fn charset(self) -> Self {
+        self.then(CharsetDecode)
+    }
+
+    /// Apply the stages in order.
+    ///
+    /// # Errors
+    ///
+    /// [`TransformError`] of the first failed stage.
+    pub fn apply(&self, response: ResponseExt) -> Result<ResponseExt, TransformError> {
+        self.stages.iter().try_fold(response, |response, stage| {
+            stage.transform(response).map_err(|source| {
+                #[cfg(feature = "feat-tracing")]
+                tracing::error!("Body transform stage `{}` failed: {source:?}", stage.name());
+
+                TransformError {
+                    stage: stage.name(),
+                    source,
+                }
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pipeline() {
+        let pipeline = BodyPipeline::new()
+            .charset()
+            .then(|mut response: ResponseExt| {
+                response.body = response.body.iter().rev().copied().collect();
+                Ok(response)
+            });
+
+        let response = ResponseExt::builder()
+            .header(CONTENT_TYPE, "text/plain; charset=latin1")
+            .bytes(&b"\xe9t\xe9"[..])
+            .build()
+            .unwrap()
+            .apply(&pipeline)
+            .unwrap();
+
+        assert_eq!(
+            response.response_parts.headers[CONTENT_TYPE],
+            "text/plain; charset=utf-8"
+        );
+        assert_eq!(response.body, b"\xa9\xc3t\xa9\xc3"[..]);
+        assert_eq!(format!("{pipeline:?}"), r#"["charset", "custom"]"#);
+
+        let err = BodyPipeline::new()
+            .then(|_: ResponseExt| Err("bad key".into()))
+            .apply(ResponseExt::builder().build().unwrap())
+            .unwrap_err();
+        assert_eq!(err.stage(), "custom");
+        assert_eq!(err.source_error().to_string(), "bad key");
+    }
+}
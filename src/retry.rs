@@ -0,0 +1,452 @@
+//! Retry subsystem: [`RetryPolicy`], the [`retry`] executor and the tower
+//! [`RetryLayer`].
+//!
+//! ```rust,no_run
+//! # async fn fetch() -> Result<miku_http_util::response::ResponseExt, std::io::Error> {
+//! # Err(std::io::ErrorKind::ConnectionReset.into())
+//! # }
+//! # async fn example() -> Result<(), std::io::Error> {
+//! use std::time::Duration;
+//!
+//! use miku_http_util::retry::{retry, RetryPolicy};
+//!
+//! let policy = RetryPolicy::new()
+//!     .with_max_attempts(5)
+//!     .with_base_delay(Duration::from_millis(200));
+//!
+//! let response = retry(&policy, &http::Method::GET, || fetch()).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use bytes::Bytes;
+use http::{Method, Request};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::response::{ResponseExt, RetryHint};
+
+#[derive(Debug, Clone, Copy)]
+/// Outcome of an attempt, passed to the retry-on predicate, see
+/// [`RetryPolicy::with_retry_on`].
+pub enum RetryOutcome<'a> {
+    /// A response is received, with its [`RetryHint`].
+    Response(&'a ResponseExt, RetryHint),
+
+    /// The request failed, e.g. connection errors.
+    Error(&'a dyn fmt::Debug),
+}
+
+/// The default retry-on predicate: errors, and responses whose
+/// [`RetryHint::should_retry`] is true.
+pub fn default_retry_on(outcome: &RetryOutcome<'_>) -> bool {
+    match outcome {
+        RetryOutcome::Response(_, hint) => hint.should_retry,
+        RetryOutcome::Error(_) => true,
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Retry policy: max attempts, exponential backoff with full jitter, the
+/// retry-on predicate, `Retry-After` awareness and idempotent-method gating.
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    jitter: bool,
+    respect_retry_after: bool,
+    retry_non_idempotent: bool,
+    retry_on: fn(&RetryOutcome<'_>) -> bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RetryPolicy {
+    #[inline]
+    /// Create a new [`RetryPolicy`] with default settings:
+    ///
+    /// - 3 attempts at most (including the first one)
+    /// - backoff from 100ms, doubled each time, up to 10s, with full jitter
+    /// - respect `Retry-After`
+    /// - retry idempotent methods only
+    /// - [`default_retry_on`]
+    pub const fn new() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(10),
+            jitter: true,
+            respect_retry_after: true,
+            retry_non_idempotent: false,
+            retry_on: default_retry_on,
+        }
+    }
+
+    #[inline]
+    /// Set the max attempts, including the first one.
+    pub const fn with_max_attempts(self, max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Set the base delay of the exponential backoff.
+    pub const fn with_base_delay(self, base_delay: Duration) -> Self {
+        Self { base_delay, ..self }
+    }
+
+    #[inline]
+    /// Set the max delay between attempts.
+    ///
+    /// If the server asks to wait longer than this via `Retry-After`, no more
+    /// retries will be made.
+    pub const fn with_max_delay(self, max_delay: Duration) -> Self {
+        Self { max_delay, ..self }
+    }
+
+    #[inline]
+    /// Whether to apply full jitter to the backoff.
+    pub const fn with_jitter(self, jitter: bool) -> Self {
+        Self { jitter, ..self }
+    }
+
+    #[inline]
+    /// Whether to respect `Retry-After` (and exhausted rate limit reset).
+    pub const fn with_respect_retry_after(self, respect_retry_after: bool) -> Self {
+        Self {
+            respect_retry_after,
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Whether to retry non-idempotent methods, e.g. `POST`.
+    pub const fn with_retry_non_idempotent(self, retry_non_idempotent: bool) -> Self {
+        Self {
+            retry_non_idempotent,
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Set the retry-on predicate.
+    pub const fn with_retry_on(self, retry_on: fn(&RetryOutcome<'_>) -> bool) -> Self {
+        Self { retry_on, ..self }
+    }
+
+    /// Whether requests with the method can be retried.
+    pub fn is_method_retryable(&self, method: &Method) -> bool {
+        self.retry_non_idempotent
+            || matches!(
+                *method,
+                Method::GET
+                    | Method::HEAD
+                    | Method::OPTIONS
+                    | Method::TRACE
+                    | Method::PUT
+                    | Method::DELETE
+            )
+    }
+
+    /// The backoff before the next attempt, after `attempt` (starting from 1)
+    /// attempts, without `Retry-After` considered.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .base_delay
+            .saturating_mul(1 << attempt.saturating_sub(1).min(31))
+            .min(self.max_delay);
+
+        if self.jitter {
+            backoff.mul_f64(random_f64())
+        } else {
+            backoff
+        }
+    }
+
+    /// Decide whether to retry after `attempt` (starting from 1) attempts, and
+    /// returns the delay before the next attempt if so.
+    pub fn next_delay(
+        &self,
+        attempt: u32,
+        method: &Method,
+        outcome: &RetryOutcome<'_>,
+    ) -> Option<Duration> {
+        if attempt >= self.max_attempts
+            || !self.is_method_retryable(method)
+            || !(self.retry_on)(outcome)
+        {
+            return None;
+        }
+
+        match outcome {
+            RetryOutcome::Response(
+                _,
+                RetryHint {
+                    after: Some(after), ..
+                },
+            ) if self.respect_retry_after => (*after <= self.max_delay).then_some(*after),
+            _ => Some(self.backoff(attempt)),
+        }
+    }
+}
+
+/// Run `f` with retries according to the [`RetryPolicy`].
+///
+/// `method` is the method of the request, for idempotent-method gating.
+///
+/// # Errors
+///
+/// The error of the last attempt.
+pub async fn retry<F, Fut, E>(
+    policy: &RetryPolicy,
+    method: &Method,
+    mut f: F,
+) -> Result<ResponseExt, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<ResponseExt, E>>,
+    E: fmt::Debug,
+{
+    let mut attempt = 0;
+
+    loop {
+        attempt += 1;
+
+        let result = f().await;
+
+        let delay = match &result {
+            Ok(response) => policy.next_delay(
+                attempt,
+                method,
+                &RetryOutcome::Response(response, response.retry_hint()),
+            ),
+            Err(e) => policy.next_delay(attempt, method, &RetryOutcome::Error(e)),
+        };
+
+        let Some(delay) = delay else {
+            return result;
+        };
+
+        #[cfg(feature = "feat-tracing")]
+        match &result {
+            Ok(response) => tracing::warn!(
+                "Attempt {attempt} got {}, retrying in {delay:?}",
+                response.response_parts.status
+            ),
+            Err(e) => tracing::warn!("Attempt {attempt} failed: {e:?}, retrying in {delay:?}"),
+        }
+
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Returns a random number in `[0, 1)`.
+fn random_f64() -> f64 {
+    use std::{collections::hash_map::RandomState, hash::BuildHasher};
+
+    (RandomState::new().hash_one(crate::time::now()) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+/// [`Layer`] retrying requests according to the [`RetryPolicy`].
+pub struct RetryLayer {
+    policy: RetryPolicy,
+}
+
+impl RetryLayer {
+    #[inline]
+    /// Create a new [`RetryLayer`].
+    pub const fn new(policy: RetryPolicy) -> Self {
+        Self { policy }
+    }
+}
+
+impl<S> Layer<S> for RetryLayer {
+    type Service = RetryService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RetryService {
+            inner,
+            policy: self.policy,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// [`Service`] retrying requests according to the [`RetryPolicy`], see
+/// [`RetryLayer`].
+///
+/// The request is rebuilt for each attempt, with the method, URI, version,
+/// headers and body. Extensions are not kept.
+pub struct RetryService<S> {
+    inner: S,
+    policy: RetryPolicy,
+}
+
+impl<S> Service<Request<Bytes>> for RetryService<S>
+where
+    S: Service<Request<Bytes>, Response = ResponseExt> + Clone + Send + 'static,
+    S::Future: Send,
+    S::Error: fmt::Debug + Send,
+{
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<ResponseExt, Self::Error>> + Send>>;
+    type Response = ResponseExt;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Bytes>) -> Self::Future {
+        let clone = self.inner.clone();
+        // The ready one is used for the first attempt.
+        let mut ready = Some(std::mem::replace(&mut self.inner, clone.clone()));
+        let policy = self.policy;
+
+        Box::pin(async move {
+            let method = req.method().clone();
+
+            retry(&policy, &method, move || {
+                let mut request = Request::new(req.body().clone());
+                *request.method_mut() = req.method().clone();
+                *request.uri_mut() = req.uri().clone();
+                *request.version_mut() = req.version();
+                *request.headers_mut() = req.headers().clone();
+
+                let (mut inner, first) = match ready.take() {
+                    Some(inner) => (inner, true),
+                    None => (clone.clone(), false),
+                };
+
+                async move {
+                    if !first {
+                        std::future::poll_fn(|cx| inner.poll_ready(cx)).await?;
+                    }
+
+                    inner.call(request).await
+                }
+            })
+            .await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    };
+
+    use super::*;
+
+    const POLICY: RetryPolicy = RetryPolicy::new()
+        .with_base_delay(Duration::from_millis(1))
+        .with_jitter(false);
+
+    fn response(status: u16, retry_after: Option<&'static str>) -> ResponseExt {
+        let mut builder = ResponseExt::builder().status(status);
+        if let Some(retry_after) = retry_after {
+            builder = builder.header("retry-after", retry_after);
+        }
+        builder.build().unwrap()
+    }
+
+    #[test]
+    fn test_next_delay() {
+        let policy = POLICY.with_max_delay(Duration::from_secs(5));
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(1));
+        assert_eq!(policy.backoff(4), Duration::from_millis(8));
+        assert_eq!(policy.backoff(100), Duration::from_secs(5));
+
+        let r = response(503, Some("2"));
+        let outcome = RetryOutcome::Response(&r, r.retry_hint());
+        assert_eq!(
+            policy.next_delay(1, &Method::GET, &outcome),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(policy.next_delay(1, &Method::POST, &outcome), None);
+        assert_eq!(policy.next_delay(3, &Method::GET, &outcome), None);
+
+        let r = response(503, Some("60"));
+        let outcome = RetryOutcome::Response(&r, r.retry_hint());
+        assert_eq!(policy.next_delay(1, &Method::GET, &outcome), None);
+
+        let r = response(404, None);
+        let outcome = RetryOutcome::Response(&r, r.retry_hint());
+        assert_eq!(policy.next_delay(1, &Method::GET, &outcome), None);
+
+        let jittered = POLICY.with_jitter(true).backoff(3);
+        assert!(jittered <= Duration::from_millis(4));
+    }
+
+    #[tokio::test]
+    async fn test_retry() {
+        let attempts = AtomicU32::new(0);
+
+        let result = retry(&POLICY, &Method::GET, || async {
+            match attempts.fetch_add(1, Ordering::Relaxed) {
+                0 => Err("connection reset"),
+                1 => Ok(response(502, None)),
+                _ => Ok(response(200, None)),
+            }
+        })
+        .await;
+
+        assert_eq!(result.unwrap().response_parts.status, 200);
+        assert_eq!(attempts.load(Ordering::Relaxed), 3);
+
+        let attempts = AtomicU32::new(0);
+        let result = retry(&POLICY, &Method::GET, || async {
+            attempts.fetch_add(1, Ordering::Relaxed);
+            Err::<ResponseExt, _>("connection reset")
+        })
+        .await;
+        assert_eq!(result.unwrap_err(), "connection reset");
+        assert_eq!(attempts.load(Ordering::Relaxed), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_layer() {
+        let attempts = Arc::new(AtomicU32::new(0));
+        let flaky = tower::service_fn({
+            let attempts = attempts.clone();
+
+            move |req: Request<Bytes>| {
+                assert_eq!(req.body(), "payload");
+
+                let status = if attempts.fetch_add(1, Ordering::Relaxed) == 0 {
+                    503
+                } else {
+                    200
+                };
+
+                std::future::ready(Ok::<_, String>(response(status, None)))
+            }
+        });
+        let mut service = RetryLayer::new(POLICY).layer(flaky);
+
+        let response = service
+            .call(Request::new(Bytes::from_static(b"payload")))
+            .await
+            .unwrap();
+
+        assert_eq!(response.response_parts.status, 200);
+        assert_eq!(attempts.load(Ordering::Relaxed), 2);
+    }
+}
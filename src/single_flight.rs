@@ -0,0 +1,211 @@
+//! Single-flight request deduplication, see [`SingleFlight`].
+
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    future::Future,
+    hash::Hash,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use http::{request, HeaderName};
+use tokio::sync::OnceCell;
+
+use crate::{error::BoxError, response::ResponseExt};
+
+type Flight<E> = Arc<OnceCell<Result<ResponseExt, Arc<E>>>>;
+
+#[derive(Debug)]
+/// Coalesces concurrent identical requests, so that only one upstream call is
+/// made and all waiters receive a clone of the [`ResponseExt`], useful for
+/// cache-stampede protection.
+///
+/// Requests are identical if they have the same key, see [`request_key`].
+///
+/// If the caller making the upstream call is cancelled, one of the waiters
+/// takes over and makes its own call.
+///
+/// ```rust,no_run
+/// # async fn fetch() -> Result<miku_http_util::response::ResponseExt, std::io::Error> {
+/// # Err(std::io::ErrorKind::ConnectionReset.into())
+/// # }
+/// # async fn example(parts: http::request::Parts) {
+/// use miku_http_util::single_flight::{request_key, SingleFlight};
+///
+/// let single_flight = SingleFlight::<String, std::io::Error>::new();
+///
+/// let key = request_key(&parts, &[http::header::ACCEPT]);
+/// let response = single_flight.run(key, || fetch()).await;
+/// # }
+/// ```
+pub struct SingleFlight<K, E = BoxError> {
+    flights: Mutex<HashMap<K, Flight<E>>>,
+}
+
+impl<K, E> Default for SingleFlight<K, E> {
+    fn default() -> Self {
+        Self {
+            flights: Mutex::default(),
+        }
+    }
+}
+
+impl<K, E> SingleFlight<K, E>
+where
+    K: Hash + Eq + Clone,
+{
+    #[inline]
+    /// Create a new [`SingleFlight`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `f` for `key`, or wait for the in-flight one with the same key.
+    ///
+    /// # Errors
+    ///
+    /// The error of the upstream call, shared among all waiters.
+    pub async fn run<F, Fut>(&self, key: K, f: F) -> Result<ResponseExt, Arc<E>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<ResponseExt, E>>,
+    {
+        let flight = self
+            .flights
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(key.clone())
+            .or_default()
+            .clone();
+
+        let result = flight
+            .get_or_init(|| async { f().await.map_err(Arc::new) })
+            .await
+            .clone();
+
+        {
+            let mut flights = self.flights.lock().unwrap_or_else(PoisonError::into_inner);
+
+            // A new flight may have started already.
+            if flights
+                .get(&key)
+                .is_some_and(|current| Arc::ptr_eq(current, &flight))
+            {
+                flights.remove(&key);
+            }
+        }
+
+        result
+    }
+
+    /// Returns the number of in-flight keys.
+    pub fn len(&self) -> usize {
+        self.flights
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    /// Returns if there's no in-flight key.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Compute the key of the request for [`SingleFlight`]: the method, the
+/// normalized URL and the values of the given vary headers.
+///
+/// The scheme and host are lowercased, and the default port is removed.
+pub fn request_key(parts: &request::Parts, vary: &[HeaderName]) -> String {
+    let uri = &parts.uri;
+
+    let scheme = uri.scheme_str().unwrap_or("http").to_ascii_lowercase();
+    let host = uri.host().unwrap_or_default().to_ascii_lowercase();
+    let port = uri
+        .port_u16()
+        .filter(|port| !matches!((scheme.as_str(), port), ("http", 80) | ("https", 443)));
+    let path = match uri.path() {
+        "" => "/",
+        path => path,
+    };
+
+    let mut key = format!("{} {scheme}://{host}", parts.method);
+    if let Some(port) = port {
+        let _ = write!(key, ":{port}");
+    }
+    key.push_str(path);
+    if let Some(query) = uri.query() {
+        let _ = write!(key, "?{query}");
+    }
+
+    for name in vary {
+        let _ = write!(key, "\n{name}:");
+
+        for (idx, value) in parts.headers.get_all(name).iter().enumerate() {
+            if idx != 0 {
+                key.push(',');
+            }
+            key.push_str(&String::from_utf8_lossy(value.as_bytes()));
+        }
+    }
+
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use http::header::ACCEPT;
+
+    use super::*;
+
+    #[test]
+    fn test_request_key() {
+        let parts = |uri: &str| {
+            http::Request::get(uri)
+                .header(ACCEPT, "text/html")
+                .header("x-other", "1")
+                .body(())
+                .unwrap()
+                .into_parts()
+                .0
+        };
+
+        assert_eq!(
+            request_key(&parts("HTTPS://A.com:443/x?b=1"), &[ACCEPT]),
+            "GET https://a.com/x?b=1\naccept:text/html"
+        );
+        assert_eq!(
+            request_key(&parts("http://a.com:8080"), &[]),
+            "GET http://a.com:8080/"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_single_flight() {
+        let single_flight = SingleFlight::<&str, String>::new();
+        let calls = AtomicU32::new(0);
+
+        let call = || async {
+            calls.fetch_add(1, Ordering::Relaxed);
+            tokio::task::yield_now().await;
+            ResponseExt::builder()
+                .text("ok")
+                .build()
+                .map_err(|e| e.to_string())
+        };
+
+        let (a, b) = tokio::join!(single_flight.run("k", call), single_flight.run("k", call));
+        assert_eq!(a.unwrap().body, "ok");
+        assert_eq!(b.unwrap().body, "ok");
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        assert!(single_flight.is_empty());
+
+        let err = single_flight
+            .run("k", || async { Err("boom".to_owned()) })
+            .await
+            .unwrap_err();
+        assert_eq!(*err, "boom");
+    }
+}
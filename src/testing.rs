@@ -0,0 +1,6 @@
+//! Testing utilities
+
+#[cfg(feature = "feat-testing-mock-server")]
+pub mod mock_server;
+#[cfg(feature = "feat-testing-vcr")]
+pub mod vcr;
@@ -0,0 +1,444 @@
+//! Testing utilities: in-memory (loopback) mock server.
+//!
+//! [`MockServer`] listens on a random local port, serving the canned
+//! [`ResponseExt`]s of the registered [`Matcher`]s, so that client code can be
+//! tested hermetically against a real HTTP/1.1 server.
+//!
+//! ```rust,no_run
+//! # async fn example() -> std::io::Result<()> {
+//! use miku_http_util::{
+//!     response::ResponseExt,
+//!     testing::mock_server::{Matcher, MockServer},
+//! };
+//!
+//! let server = MockServer::start().await?;
+//!
+//! let mock = server.mock(
+//!     Matcher::get("/users/*").with_query("page", "2"),
+//!     ResponseExt::builder().text("[]").build().unwrap(),
+//! );
+//!
+//! // ... call `server.url("/users/1?page=2")` with the client under test ...
+//!
+//! mock.assert_called_once();
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    convert::Infallible,
+    io,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+};
+
+use bytes::Bytes;
+use http::{Method, Request, Response, StatusCode};
+use http_body_util::{BodyExt, Full};
+use hyper::{body::Incoming, server::conn::http1, service::service_fn};
+use hyper_util::rt::TokioIo;
+use tokio::{net::TcpListener, task::JoinHandle};
+
+use crate::{
+    request::parser::{integration::QueryRule, OwnedQuery},
+    response::ResponseExt,
+};
+
+#[derive(Debug, Clone)]
+/// Matcher of the requests.
+///
+/// The path pattern is matched segment by segment, where `*` matches any
+/// single segment and a trailing `**` matches the rest (zero or more
+/// segments).
+pub struct Matcher {
+    method: Option<Method>,
+    path: String,
+    query: Vec<QueryRule>,
+}
+
+impl Matcher {
+    #[inline]
+    /// Create a new [`Matcher`] of the path pattern, with any method.
+    pub fn any(path: impl Into<String>) -> Self {
+        Self {
+            method: None,
+            path: path.into(),
+            query: Vec::new(),
+        }
+    }
+
+    #[inline]
+    /// Create a new [`Matcher`] of the method and path pattern.
+    pub fn new(method: Method, path: impl Into<String>) -> Self {
+        Self {
+            method: Some(method),
+            ..Self::any(path)
+        }
+    }
+
+    #[inline]
+    /// Create a new [`Matcher`] of `GET` and the path pattern.
+    pub fn get(path: impl Into<String>) -> Self {
+        Self::new(Method::GET, path)
+    }
+
+    #[inline]
+    /// Create a new [`Matcher`] of `POST` and the path pattern.
+    pub fn post(path: impl Into<String>) -> Self {
+        Self::new(Method::POST, path)
+    }
+
+    #[inline]
+    /// Require the query parameter to equal to the given (decoded) value, see
+    /// [`QueryRule::Equals`].
+    pub fn with_query(self, key: &'static str, value: &'static str) -> Self {
+        self.with_query_rule(QueryRule::Equals { key, value })
+    }
+
+    #[inline]
+    /// Add a [`QueryRule`] of the query, e.g. [`QueryRule::AnyOf`] for the
+    /// presence of a key, or [`QueryRule::Absent`].
+    pub fn with_query_rule(mut self, rule: QueryRule) -> Self {
+        self.query.push(rule);
+        self
+    }
+
+    /// Whether the request matches.
+    pub fn matches<B>(&self, req: &Request<B>) -> bool {
+        if self
+            .method
+            .as_ref()
+            .is_some_and(|method| method != req.method())
+        {
+            return false;
+        }
+
+        if !match_path(&self.path, req.uri().path()) {
+            return false;
+        }
+
+        if self.query.is_empty() {
+            return true;
+        }
+
+        let mut query = req.uri().query().map(OwnedQuery::parse);
+
+        if let Some(query) = &mut query {
+            self.query.iter().for_each(|rule| rule.normalize(query));
+        }
+
+        self.query
+            .iter()
+            .all(|rule| rule.check(query.as_ref()).is_ok())
+    }
+}
+
+/// Match the path against the pattern, see [`Matcher`].
+fn match_path(pattern: &str, path: &str) -> bool {
+    let mut patterns = pattern.trim_start_matches('/').split('/');
+    let mut segments = path.trim_start_matches('/').split('/');
+
+    loop {
+        match (patterns.next(), segments.next()) {
+            (Some("**"), _) => return patterns.next().is_none(),
+            (Some(pattern), Some(segment)) if pattern == "*" || pattern == segment => {}
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Handle of a registered mock, for call-count assertions.
+pub struct Mock {
+    matcher: Arc<Matcher>,
+    calls: Arc<AtomicUsize>,
+}
+
+impl Mock {
+    #[inline]
+    /// Returns the number of matched requests.
+    pub fn calls(&self) -> usize {
+        self.calls.load(Ordering::Acquire)
+    }
+
+    #[track_caller]
+    /// Assert the number of matched requests.
+    ///
+    /// # Panics
+    ///
+    /// If the number differs.
+    pub fn assert_called(&self, expected: usize) {
+        let calls = self.calls();
+
+        assert!(
+            calls == expected,
+            "mock {:?} expected to be called {expected} time(s), but called {calls} time(s)",
+            self.matcher
+        );
+    }
+
+    #[inline]
+    #[track_caller]
+    /// Assert the mock is matched exactly once.
+    ///
+    /// # Panics
+    ///
+    /// If not matched exactly once.
+    pub fn assert_called_once(&self) {
+        self.assert_called(1);
+    }
+}
+
+#[derive(Debug)]
+struct Route {
+    mock: Mock,
+    response: ResponseExt,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    routes: Mutex<Vec<Route>>,
+    unmatched: Mutex<Vec<Request<Bytes>>>,
+}
+
+impl State {
+    /// Find the response, the most recently registered mock first.
+    fn respond(&self, req: Request<Bytes>) -> Response<Full<Bytes>> {
+        let routes = self.routes.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let Some(route) = routes
+            .iter()
+            .rev()
+            .find(|route| route.mock.matcher.matches(&req))
+        else {
+            drop(routes);
+
+            self.unmatched
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(req);
+
+            let mut response = Response::new(Full::new(Bytes::from_static(b"no mock matched")));
+            *response.status_mut() = StatusCode::NOT_FOUND;
+
+            return response;
+        };
+
+        route.mock.calls.fetch_add(1, Ordering::AcqRel);
+
+        let mut response = Response::new(Full::new(route.response.body.clone()));
+        *response.status_mut() = route.response.response_parts.status;
+        *response.headers_mut() = route.response.response_parts.headers.clone();
+
+        response
+    }
+}
+
+#[derive(Debug)]
+/// In-memory mock server listening on a random local port, see the
+/// [module-level documentation](self).
+///
+/// The server is shut down when dropped.
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<State>,
+    handle: JoinHandle<()>,
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+impl MockServer {
+    /// Start the server on `127.0.0.1` with a random port.
+    ///
+    /// Must be called within a tokio runtime.
+    ///
+    /// # Errors
+    ///
+    /// IO error binding the port.
+    pub async fn start() -> io::Result<Self> {
+        let listener = TcpListener::bind((std::net::Ipv4Addr::LOCALHOST, 0)).await?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(State::default());
+
+        let handle = tokio::spawn({
+            let state = state.clone();
+
+            async move {
+                loop {
+                    let stream = match listener.accept().await {
+                        Ok((stream, _)) => stream,
+                        Err(_e) => {
+                            #[cfg(feature = "feat-tracing")]
+                            tracing::warn!("Failed to accept the connection: {_e:?}");
+
+                            // Back off a little, e.g. on `EMFILE`.
+                            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+
+                            continue;
+                        }
+                    };
+
+                    let state = state.clone();
+
+                    tokio::spawn(async move {
+                        let service = service_fn(move |req: Request<Incoming>| {
+                            let state = state.clone();
+
+                            async move {
+                                let (parts, body) = req.into_parts();
+                                let body = body
+                                    .collect()
+                                    .await
+                                    .map(|collected| collected.to_bytes())
+                                    .unwrap_or_default();
+
+                                Ok::<_, Infallible>(state.respond(Request::from_parts(parts, body)))
+                            }
+                        });
+
+                        let _ = http1::Builder::new()
+                            .serve_connection(TokioIo::new(stream), service)
+                            .await;
+                    });
+                }
+            }
+        });
+
+        Ok(Self {
+            addr,
+            state,
+            handle,
+        })
+    }
+
+    #[inline]
+    /// Returns the local address of the server.
+    pub const fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    #[inline]
+    /// Returns the URL of the path (and query) on the server, like
+    /// `http://127.0.0.1:12345/path`.
+    pub fn url(&self, path_and_query: &str) -> String {
+        format!("http://{}{path_and_query}", self.addr)
+    }
+
+    /// Register a mock, responding with `response` to the matched requests.
+    ///
+    /// Mocks registered later take precedence. Unmatched requests are
+    /// responded with `404 Not Found`, see [`unmatched`](Self::unmatched).
+    pub fn mock(&self, matcher: Matcher, response: ResponseExt) -> Mock {
+        let mock = Mock {
+            matcher: Arc::new(matcher),
+            calls: Arc::default(),
+        };
+
+        self.state
+            .routes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(Route {
+                mock: mock.clone(),
+                response,
+            });
+
+        mock
+    }
+
+    /// Returns the requests matching no mock.
+    pub fn unmatched(&self) -> Vec<Request<Bytes>> {
+        self.state
+            .unmatched
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|req| {
+                let mut cloned = Request::new(req.body().clone());
+                *cloned.method_mut() = req.method().clone();
+                *cloned.uri_mut() = req.uri().clone();
+                *cloned.headers_mut() = req.headers().clone();
+                cloned
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+
+    #[test]
+    fn test_match_path() {
+        assert!(match_path("/users/*", "/users/1"));
+        assert!(!match_path("/users/*", "/users/1/posts"));
+        assert!(match_path("/users/**", "/users/1/posts"));
+        assert!(match_path("/users/**", "/users"));
+        assert!(!match_path("/users", "/posts"));
+    }
+
+    #[test]
+    fn test_matcher() {
+        let matcher = Matcher::get("/search")
+            .with_query("q", "a b")
+            .with_query_rule(QueryRule::Absent(&["debug"]));
+
+        let req = Request::get("/search?q=a%20b").body(()).unwrap();
+        assert!(matcher.matches(&req));
+
+        let req = Request::get("/search?q=a%20b&debug=1").body(()).unwrap();
+        assert!(!matcher.matches(&req));
+
+        let req = Request::post("/search?q=a%20b").body(()).unwrap();
+        assert!(!matcher.matches(&req));
+
+        let req = Request::get("/search").body(()).unwrap();
+        assert!(!matcher.matches(&req));
+    }
+
+    /// Minimal HTTP/1.1 client, returning the raw response.
+    async fn send(addr: SocketAddr, path: &str) -> String {
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                format!("GET {path} HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")
+                    .as_bytes(),
+            )
+            .await
+            .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_mock_server() {
+        let server = MockServer::start().await.unwrap();
+
+        let mock = server.mock(
+            Matcher::get("/hello"),
+            ResponseExt::builder().text("world").build().unwrap(),
+        );
+
+        let response = send(server.addr(), "/hello").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.ends_with("world"));
+
+        let response = send(server.addr(), "/missing").await;
+        assert!(response.starts_with("HTTP/1.1 404 Not Found"));
+
+        mock.assert_called_once();
+        assert_eq!(server.unmatched()[0].uri(), "/missing");
+    }
+}
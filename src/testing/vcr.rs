@@ -0,0 +1,489 @@
+//! Testing utilities: VCR-style record and replay.
+//!
+//! [`VcrLayer`] wraps a client [`Service`] returning [`ResponseExt`]. In
+//! record mode, requests are forwarded to the inner service and the
+//! interactions are kept (with responses in the
+//! [`ResponseSnapshot`](crate::response::ResponseSnapshot) format), which can
+//! be saved as a cassette file by [`VcrLayer::save`]. In replay mode, the
+//! responses are served from the cassette deterministically, according to the
+//! [`MatchRules`].
+//!
+//! ```rust,no_run
+//! # use miku_http_util::testing::vcr::{MatchRules, VcrLayer, VcrMode};
+//! let vcr = VcrLayer::new("tests/cassettes/login.json", VcrMode::Auto)
+//!     .unwrap()
+//!     .with_rules(MatchRules::new().with_headers(&["x-tenant"]).with_body(true));
+//!
+//! // let client = tower::ServiceBuilder::new().layer(vcr.clone()).service(client);
+//! // ... run the test ...
+//!
+//! vcr.save().unwrap();
+//! ```
+
+use std::{
+    fmt,
+    future::Future,
+    io,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{Arc, Mutex, PoisonError},
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use http::Request;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::response::{
+    snapshot::{DEFAULT_REDACTED_HEADERS, REDACTED},
+    ResponseExt, ResponseSnapshot, SnapshotError, SnapshotOptions,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Mode of [`VcrLayer`].
+pub enum VcrMode {
+    /// Forward requests to the inner service and record the interactions.
+    Record,
+
+    /// Serve responses from the cassette, never touching the inner service.
+    Replay,
+
+    /// [`Replay`](Self::Replay) if the cassette file exists, otherwise
+    /// [`Record`](Self::Record).
+    Auto,
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Rules to match a request against the recorded ones.
+pub struct MatchRules {
+    method: bool,
+    url: bool,
+    headers: &'static [&'static str],
+    body: bool,
+}
+
+impl Default for MatchRules {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MatchRules {
+    #[inline]
+    /// Create a new [`MatchRules`], matching the method and URL only.
+    pub const fn new() -> Self {
+        Self {
+            method: true,
+            url: true,
+            headers: &[],
+            body: false,
+        }
+    }
+
+    #[inline]
+    /// Whether to match the method.
+    pub const fn with_method(self, method: bool) -> Self {
+        Self { method, ..self }
+    }
+
+    #[inline]
+    /// Whether to match the URL.
+    pub const fn with_url(self, url: bool) -> Self {
+        Self { url, ..self }
+    }
+
+    #[inline]
+    /// Set the headers (lowercase) to match.
+    pub const fn with_headers(self, headers: &'static [&'static str]) -> Self {
+        Self { headers, ..self }
+    }
+
+    #[inline]
+    /// Whether to match the body (by its SHA-256 hash).
+    pub const fn with_body(self, body: bool) -> Self {
+        Self { body, ..self }
+    }
+
+    fn matches(&self, recorded: &RecordedRequest, request: &RecordedRequest) -> bool {
+        (!self.method || recorded.method == request.method)
+            && (!self.url || recorded.url == request.url)
+            && (!self.body || recorded.body_hash == request.body_hash)
+            && self.headers.iter().all(|name| {
+                let values = |request: &RecordedRequest| {
+                    request
+                        .headers
+                        .iter()
+                        .filter(|(k, _)| k.eq_ignore_ascii_case(name))
+                        .map(|(_, v)| v.clone())
+                        .collect::<Vec<_>>()
+                };
+
+                values(recorded) == values(request)
+            })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+/// The cassette, recorded interactions.
+pub struct Cassette {
+    /// The interactions, in order of recording.
+    pub interactions: Vec<Interaction>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+/// A recorded request / response pair.
+pub struct Interaction {
+    /// The request.
+    pub request: RecordedRequest,
+
+    /// The response.
+    pub response: ResponseSnapshot,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+/// A recorded request.
+pub struct RecordedRequest {
+    /// Method.
+    pub method: String,
+
+    /// URL.
+    pub url: String,
+
+    /// Headers in order, with sensitive values redacted.
+    pub headers: Vec<(String, String)>,
+
+    /// Hex-encoded SHA-256 hash of the body.
+    pub body_hash: String,
+}
+
+impl RecordedRequest {
+    fn new(request: &Request<Bytes>) -> Self {
+        let headers = request
+            .headers()
+            .iter()
+            .map(|(k, v)| {
+                let value = if DEFAULT_REDACTED_HEADERS.contains(&k.as_str()) {
+                    REDACTED.to_owned()
+                } else {
+                    String::from_utf8_lossy(v.as_bytes()).into_owned()
+                };
+
+                (k.as_str().to_owned(), value)
+            })
+            .collect();
+
+        let body_hash = Sha256::digest(request.body()).iter().fold(
+            String::with_capacity(64),
+            |mut hash, byte| {
+                use fmt::Write;
+
+                let _ = write!(hash, "{byte:02x}");
+                hash
+            },
+        );
+
+        Self {
+            method: request.method().to_string(),
+            url: request.uri().to_string(),
+            headers,
+            body_hash,
+        }
+    }
+}
+
+#[derive(Debug)]
+#[derive(thiserror::Error)]
+/// Errors of [`VcrService`].
+pub enum VcrError<E> {
+    #[error("inner service error: {0}")]
+    /// Error from the inner service.
+    Inner(E),
+
+    #[error("no recorded interaction matches {method} {url}")]
+    /// No recorded interaction matches the request in replay mode.
+    NoMatch {
+        /// The method
+        method: String,
+
+        /// The URL
+        url: String,
+    },
+
+    #[error(transparent)]
+    /// The recorded response is invalid.
+    Snapshot(#[from] SnapshotError),
+}
+
+#[derive(Debug)]
+struct State {
+    path: PathBuf,
+    replay: bool,
+    cassette: Cassette,
+    used: Vec<bool>,
+}
+
+#[derive(Debug, Clone)]
+/// [`Layer`] for recording and replaying interactions, see the module
+/// documentation.
+pub struct VcrLayer {
+    state: Arc<Mutex<State>>,
+    rules: MatchRules,
+}
+
+impl VcrLayer {
+    /// Create a new [`VcrLayer`] with given cassette file.
+    ///
+    /// # Errors
+    ///
+    /// IO or JSON errors when loading the cassette in replay mode.
+    pub fn new(path: impl AsRef<Path>, mode: VcrMode) -> io::Result<Self> {
+        let path = path.as_ref().to_owned();
+
+        let replay = match mode {
+            VcrMode::Record => false,
+            VcrMode::Replay => true,
+            VcrMode::Auto => path.exists(),
+        };
+
+        let cassette: Cassette = if replay {
+            serde_json::from_slice(&std::fs::read(&path)?)?
+        } else {
+            Cassette::default()
+        };
+
+        Ok(Self::with_cassette(path, replay, cassette))
+    }
+
+    /// Create a new [`VcrLayer`] replaying the given cassette, mainly for
+    /// fixtures built in code.
+    pub fn replay(cassette: Cassette) -> Self {
+        Self::with_cassette(PathBuf::new(), true, cassette)
+    }
+
+    fn with_cassette(path: PathBuf, replay: bool, cassette: Cassette) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State {
+                path,
+                replay,
+                used: vec![false; cassette.interactions.len()],
+                cassette,
+            })),
+            rules: MatchRules::new(),
+        }
+    }
+
+    #[inline]
+    /// Set the [`MatchRules`].
+    pub fn with_rules(self, rules: MatchRules) -> Self {
+        Self { rules, ..self }
+    }
+
+    /// Returns if it's replaying.
+    pub fn is_replay(&self) -> bool {
+        self.state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .replay
+    }
+
+    /// Returns a copy of the current cassette.
+    pub fn cassette(&self) -> Cassette {
+        self.state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .cassette
+            .clone()
+    }
+
+    /// Save the recorded interactions to the cassette file, creating the
+    /// parent directories if needed. No-op in replay mode.
+    ///
+    /// # Errors
+    ///
+    /// IO or JSON errors.
+    pub fn save(&self) -> io::Result<()> {
+        let state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if state.replay {
+            return Ok(());
+        }
+
+        if let Some(parent) = state.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        std::fs::write(&state.path, serde_json::to_vec_pretty(&state.cassette)?)
+    }
+}
+
+impl<S> Layer<S> for VcrLayer {
+    type Service = VcrService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        VcrService {
+            inner,
+            state: self.state.clone(),
+            rules: self.rules,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// [`Service`] for recording and replaying interactions, see [`VcrLayer`].
+pub struct VcrService<S> {
+    inner: S,
+    state: Arc<Mutex<State>>,
+    rules: MatchRules,
+}
+
+impl<S> Service<Request<Bytes>> for VcrService<S>
+where
+    S: Service<Request<Bytes>, Response = ResponseExt>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Error = VcrError<S::Error>;
+    type Future = Pin<Box<dyn Future<Output = Result<ResponseExt, Self::Error>> + Send>>;
+    type Response = ResponseExt;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self
+            .state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .replay
+        {
+            Poll::Ready(Ok(()))
+        } else {
+            self.inner.poll_ready(cx).map_err(VcrError::Inner)
+        }
+    }
+
+    fn call(&mut self, req: Request<Bytes>) -> Self::Future {
+        let recorded = RecordedRequest::new(&req);
+
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if state.replay {
+            let State { cassette, used, .. } = &mut *state;
+
+            let result = cassette
+                .interactions
+                .iter()
+                .zip(used.iter_mut())
+                .find(|(interaction, used)| {
+                    !**used && self.rules.matches(&interaction.request, &recorded)
+                })
+                .map(|(interaction, used)| {
+                    *used = true;
+                    ResponseExt::from_snapshot(interaction.response.clone())
+                });
+
+            let result = match result {
+                Some(response) => response.map_err(VcrError::Snapshot),
+                None => {
+                    #[cfg(feature = "feat-tracing")]
+                    tracing::error!(
+                        "No recorded interaction matches {} {}",
+                        recorded.method,
+                        recorded.url
+                    );
+
+                    Err(VcrError::NoMatch {
+                        method: recorded.method,
+                        url: recorded.url,
+                    })
+                }
+            };
+
+            return Box::pin(std::future::ready(result));
+        }
+
+        drop(state);
+
+        let state = self.state.clone();
+        let future = self.inner.call(req);
+
+        Box::pin(async move {
+            let response = future.await.map_err(VcrError::Inner)?;
+
+            let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+            state.cassette.interactions.push(Interaction {
+                request: recorded,
+                response: response.snapshot(SnapshotOptions::new()),
+            });
+            state.used.push(false);
+
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use super::*;
+
+    fn echo(req: Request<Bytes>) -> std::future::Ready<Result<ResponseExt, Infallible>> {
+        let (response_parts, ()) = http::Response::new(()).into_parts();
+
+        std::future::ready(Ok(ResponseExt {
+            response_parts,
+            body: req.into_body(),
+        }))
+    }
+
+    fn request(url: &str, body: &'static str) -> Request<Bytes> {
+        Request::post(url)
+            .header("authorization", "Bearer secret")
+            .body(Bytes::from_static(body.as_bytes()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let recorder = VcrLayer::new("/nonexistent/cassette.json", VcrMode::Record).unwrap();
+        let mut service = recorder.layer(tower::service_fn(echo));
+
+        let response = service.call(request("/a", "first")).await.unwrap();
+        assert_eq!(response.body, "first");
+        let _ = service.call(request("/a", "second")).await.unwrap();
+
+        let cassette = recorder.cassette();
+        assert_eq!(cassette.interactions.len(), 2);
+        assert_eq!(cassette.interactions[0].request.headers[0].1, REDACTED);
+
+        // Round trip through JSON, as saved to disk.
+        let cassette: Cassette =
+            serde_json::from_slice(&serde_json::to_vec(&cassette).unwrap()).unwrap();
+
+        // Matching method and URL only, replayed in order.
+        let mut service = VcrLayer::replay(cassette.clone()).layer(tower::service_fn(echo));
+        let response = service.call(request("/a", "other")).await.unwrap();
+        assert_eq!(response.body, "first");
+        let response = service.call(request("/a", "other")).await.unwrap();
+        assert_eq!(response.body, "second");
+        assert!(matches!(
+            service.call(request("/a", "other")).await,
+            Err(VcrError::NoMatch { .. })
+        ));
+
+        // Matching body too.
+        let mut service = VcrLayer::replay(cassette)
+            .with_rules(MatchRules::new().with_body(true))
+            .layer(tower::service_fn(echo));
+        let response = service.call(request("/a", "second")).await.unwrap();
+        assert_eq!(response.body, "second");
+        assert!(matches!(
+            service.call(request("/b", "first")).await,
+            Err(VcrError::NoMatch { .. })
+        ));
+    }
+}
@@ -0,0 +1,20 @@
+//! Time helpers, see [`now`].
+
+use std::time::SystemTime;
+
+#[inline]
+/// Returns the current system time.
+///
+/// [`SystemTime::now`] panics on `wasm32-unknown-unknown`, where the time is
+/// read from `Date.now()` instead with feature `js`.
+pub(crate) fn now() -> SystemTime {
+    #[cfg(all(feature = "js", target_arch = "wasm32", target_os = "unknown"))]
+    {
+        SystemTime::UNIX_EPOCH + std::time::Duration::from_secs_f64(js_sys::Date::now() / 1000.0)
+    }
+
+    #[cfg(not(all(feature = "js", target_arch = "wasm32", target_os = "unknown")))]
+    {
+        SystemTime::now()
+    }
+}
@@ -0,0 +1,493 @@
+//! HTTP version negotiation hints: `Upgrade` (RFC 9110), `HTTP2-Settings`
+//! (RFC 7540, for `h2c`) and ALPN protocol preference lists, plus
+//! [`VersionInfo`] recording the version a response arrived on.
+
+use std::fmt;
+
+use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine};
+use http::{
+    header::{InvalidHeaderValue, UPGRADE},
+    response::Parts,
+    HeaderMap, HeaderName, HeaderValue, Version,
+};
+
+/// `HTTP2-Settings`
+pub const HTTP2_SETTINGS: HeaderName = HeaderName::from_static("http2-settings");
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Protocol listed in `Upgrade`, like `h2c`, `websocket` or `HTTP/2.0`.
+pub struct UpgradeProtocol {
+    /// The protocol name, e.g. `h2c`.
+    pub name: String,
+
+    /// The protocol version, if any, e.g. `2.0` of `HTTP/2.0`.
+    pub version: Option<String>,
+}
+
+impl UpgradeProtocol {
+    #[inline]
+    /// Create a new [`UpgradeProtocol`].
+    pub fn new(name: impl Into<String>, version: Option<String>) -> Self {
+        Self {
+            name: name.into(),
+            version,
+        }
+    }
+
+    /// Parse the `Upgrade` headers, in order of preference. Invalid entries
+    /// are skipped.
+    pub fn from_headers(headers: &HeaderMap) -> Vec<Self> {
+        headers
+            .get_all(UPGRADE)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .filter_map(Self::parse)
+            .collect()
+    }
+
+    /// Parse a single entry, like `HTTP/2.0`.
+    fn parse(entry: &str) -> Option<Self> {
+        let entry = entry.trim();
+
+        let (name, version) = match entry.split_once('/') {
+            Some((name, version)) => (name, Some(version)),
+            None => (entry, None),
+        };
+
+        if name.is_empty() || version.is_some_and(str::is_empty) {
+            return None;
+        }
+
+        Some(Self::new(name, version.map(str::to_owned)))
+    }
+
+    /// Returns the `Upgrade` header value of the protocols, in order of
+    /// preference.
+    ///
+    /// # Errors
+    ///
+    /// [`InvalidHeaderValue`] if any of the protocols contains invalid
+    /// characters.
+    pub fn to_header_value(protocols: &[Self]) -> Result<HeaderValue, InvalidHeaderValue> {
+        let value = protocols
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        HeaderValue::try_from(value)
+    }
+
+    /// Whether it's the given protocol, case-insensitive, regardless of the
+    /// version.
+    pub fn is(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+    }
+}
+
+impl fmt::Display for UpgradeProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.version {
+            Some(version) => write!(f, "{}/{version}", self.name),
+            None => f.write_str(&self.name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// HTTP/2 SETTINGS parameters carried by `HTTP2-Settings` of the `h2c`
+/// upgrade request, i.e. the SETTINGS frame payload in base64url.
+///
+/// ```rust
+/// # use miku_http_util::version::Http2Settings;
+/// let settings = Http2Settings::new()
+///     .with(Http2Settings::ENABLE_PUSH, 0)
+///     .with(Http2Settings::INITIAL_WINDOW_SIZE, 1 << 20);
+///
+/// let value = settings.to_header_value();
+/// assert_eq!(value, "AAIAAAAAAAQAEAAA");
+/// assert_eq!(Http2Settings::from_header_value(&value), Some(settings));
+/// ```
+pub struct Http2Settings {
+    settings: Vec<(u16, u32)>,
+}
+
+impl Http2Settings {
+    /// `SETTINGS_HEADER_TABLE_SIZE`
+    pub const HEADER_TABLE_SIZE: u16 = 0x1;
+
+    /// `SETTINGS_ENABLE_PUSH`
+    pub const ENABLE_PUSH: u16 = 0x2;
+
+    /// `SETTINGS_MAX_CONCURRENT_STREAMS`
+    pub const MAX_CONCURRENT_STREAMS: u16 = 0x3;
+
+    /// `SETTINGS_INITIAL_WINDOW_SIZE`
+    pub const INITIAL_WINDOW_SIZE: u16 = 0x4;
+
+    /// `SETTINGS_MAX_FRAME_SIZE`
+    pub const MAX_FRAME_SIZE: u16 = 0x5;
+
+    /// `SETTINGS_MAX_HEADER_LIST_SIZE`
+    pub const MAX_HEADER_LIST_SIZE: u16 = 0x6;
+
+    #[inline]
+    /// Create an empty [`Http2Settings`].
+    pub const fn new() -> Self {
+        Self {
+            settings: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    /// Set the parameter, replacing the previous one of the same identifier.
+    pub fn with(mut self, id: u16, value: u32) -> Self {
+        match self.settings.iter_mut().find(|(i, _)| *i == id) {
+            Some((_, v)) => *v = value,
+            None => self.settings.push((id, value)),
+        }
+        self
+    }
+
+    #[inline]
+    /// Returns the value of the parameter, if any.
+    pub fn get(&self, id: u16) -> Option<u32> {
+        self.settings
+            .iter()
+            .find(|(i, _)| *i == id)
+            .map(|&(_, value)| value)
+    }
+
+    #[inline]
+    /// Returns the parameters, in order.
+    pub fn settings(&self) -> &[(u16, u32)] {
+        &self.settings
+    }
+
+    /// Parse the `HTTP2-Settings` header value, `None` if invalid.
+    ///
+    /// For repeated identifiers, the last one wins as in the SETTINGS frame.
+    pub fn from_header_value(value: &HeaderValue) -> Option<Self> {
+        let payload = BASE64_URL_SAFE_NO_PAD
+            .decode(value.as_bytes().trim_ascii())
+            .ok()?;
+
+        if payload.len() % 6 != 0 {
+            return None;
+        }
+
+        Some(
+            payload
+                .chunks_exact(6)
+                .fold(Self::new(), |settings, entry| {
+                    settings.with(
+                        u16::from_be_bytes([entry[0], entry[1]]),
+                        u32::from_be_bytes([entry[2], entry[3], entry[4], entry[5]]),
+                    )
+                }),
+        )
+    }
+
+    #[inline]
+    /// Parse `HTTP2-Settings` of the request headers, `None` if missing or
+    /// invalid.
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        Self::from_header_value(headers.get(HTTP2_SETTINGS)?)
+    }
+
+    #[allow(clippy::missing_panics_doc, reason = "base64url is always valid")]
+    /// Returns the `HTTP2-Settings` header value.
+    pub fn to_header_value(&self) -> HeaderValue {
+        let mut payload = Vec::with_capacity(self.settings.len() * 6);
+
+        for &(id, value) in &self.settings {
+            payload.extend_from_slice(&id.to_be_bytes());
+            payload.extend_from_slice(&value.to_be_bytes());
+        }
+
+        HeaderValue::try_from(BASE64_URL_SAFE_NO_PAD.encode(payload))
+            .expect("must be valid header value")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// ALPN protocol preference list, most preferred first.
+///
+/// ```rust
+/// # use miku_http_util::version::AlpnPreference;
+/// let preference = AlpnPreference::default();
+///
+/// assert_eq!(preference.to_wire(), b"\x02h2\x08http/1.1");
+/// assert_eq!(preference.select([&b"http/1.1"[..]]), Some(&b"http/1.1"[..]));
+/// ```
+pub struct AlpnPreference {
+    protocols: Vec<Vec<u8>>,
+}
+
+impl Default for AlpnPreference {
+    /// `h2`, then `http/1.1`.
+    fn default() -> Self {
+        Self::new()
+            .with_protocol(AlpnPreference::H2)
+            .with_protocol(AlpnPreference::HTTP_1_1)
+    }
+}
+
+impl AlpnPreference {
+    /// ALPN protocol ID of HTTP/1.1.
+    pub const HTTP_1_1: &'static [u8] = b"http/1.1";
+
+    /// ALPN protocol ID of HTTP/2 over TLS.
+    pub const H2: &'static [u8] = b"h2";
+
+    /// ALPN protocol ID of HTTP/3.
+    pub const H3: &'static [u8] = b"h3";
+
+    #[inline]
+    /// Create an empty [`AlpnPreference`].
+    pub const fn new() -> Self {
+        Self {
+            protocols: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    /// Append the protocol as the least preferred one. Duplicated or invalid
+    /// (empty, or longer than 255 bytes) ones are ignored.
+    pub fn with_protocol(mut self, protocol: impl Into<Vec<u8>>) -> Self {
+        let protocol = protocol.into();
+
+        if (1..=255).contains(&protocol.len()) && !self.protocols.contains(&protocol) {
+            self.protocols.push(protocol);
+        }
+
+        self
+    }
+
+    #[inline]
+    /// Returns the protocol IDs, most preferred first, e.g. for
+    /// `rustls::ClientConfig::alpn_protocols`.
+    pub fn protocols(&self) -> &[Vec<u8>] {
+        &self.protocols
+    }
+
+    /// Parse the wire format, i.e. the length-prefixed protocol IDs of the
+    /// TLS extension, `None` if malformed.
+    pub fn from_wire(mut wire: &[u8]) -> Option<Self> {
+        let mut preference = Self::new();
+
+        while let Some((&len, rest)) = wire.split_first() {
+            let len = usize::from(len);
+
+            if len == 0 || rest.len() < len {
+                return None;
+            }
+
+            preference.protocols.push(rest[..len].to_vec());
+            wire = &rest[len..];
+        }
+
+        Some(preference)
+    }
+
+    /// Returns the wire format, i.e. the length-prefixed protocol IDs.
+    pub fn to_wire(&self) -> Vec<u8> {
+        let mut wire = Vec::with_capacity(self.protocols.iter().map(|p| p.len() + 1).sum());
+
+        for protocol in &self.protocols {
+            #[allow(clippy::cast_possible_truncation, reason = "checked when added")]
+            wire.push(protocol.len() as u8);
+            wire.extend_from_slice(protocol);
+        }
+
+        wire
+    }
+
+    /// Select the most preferred protocol offered by the peer.
+    pub fn select<'a, I>(&self, offered: I) -> Option<&'a [u8]>
+    where
+        I: IntoIterator<Item = &'a [u8]>,
+    {
+        let offered: Vec<_> = offered.into_iter().collect();
+
+        self.protocols.iter().find_map(|protocol| {
+            offered
+                .iter()
+                .find(|offered| **offered == protocol.as_slice())
+                .copied()
+        })
+    }
+
+    /// Returns the HTTP version of the well-known protocol ID.
+    pub fn version_of(protocol: &[u8]) -> Option<Version> {
+        match protocol {
+            Self::HTTP_1_1 => Some(Version::HTTP_11),
+            Self::H2 => Some(Version::HTTP_2),
+            Self::H3 => Some(Version::HTTP_3),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The HTTP version a response arrived on, for logging and deciding on the
+/// protocol fallback.
+///
+/// ```rust
+/// # use http::Version;
+/// # use miku_http_util::version::VersionInfo;
+/// let (parts, _) = http::Response::builder()
+///     .version(Version::HTTP_11)
+///     .body(())
+///     .unwrap()
+///     .into_parts();
+///
+/// let info = VersionInfo::from_parts(&parts).with_preferred(Version::HTTP_2);
+/// assert!(info.is_fallback());
+/// assert_eq!(info.to_string(), "HTTP/1.1 (preferred HTTP/2.0)");
+/// ```
+pub struct VersionInfo {
+    /// The version the response arrived on.
+    pub version: Version,
+
+    /// The version preferred by the client, if any.
+    pub preferred: Option<Version>,
+}
+
+impl VersionInfo {
+    #[inline]
+    /// Record the version of the response parts.
+    pub const fn from_parts(parts: &Parts) -> Self {
+        Self {
+            version: parts.version,
+            preferred: None,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Set the version preferred by the client, e.g. the one of the most
+    /// preferred ALPN protocol.
+    pub const fn with_preferred(self, preferred: Version) -> Self {
+        Self {
+            preferred: Some(preferred),
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Whether the response arrived on an older version than the preferred
+    /// one.
+    pub fn is_fallback(&self) -> bool {
+        self.preferred
+            .is_some_and(|preferred| self.version < preferred)
+    }
+
+    #[inline]
+    /// Whether the version multiplexes requests over a connection, i.e.
+    /// HTTP/2 or later.
+    pub fn is_multiplexed(&self) -> bool {
+        self.version >= Version::HTTP_2
+    }
+}
+
+impl fmt::Display for VersionInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.version)?;
+
+        match self.preferred {
+            Some(preferred) if preferred != self.version => {
+                write!(f, " (preferred {preferred:?})")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upgrade() {
+        let mut headers = HeaderMap::new();
+        headers.insert(UPGRADE, HeaderValue::from_static("h2c, HTTP/2.0 ,, a/"));
+
+        let protocols = UpgradeProtocol::from_headers(&headers);
+        assert_eq!(
+            protocols,
+            [
+                UpgradeProtocol::new("h2c", None),
+                UpgradeProtocol::new("HTTP", Some("2.0".to_owned())),
+            ]
+        );
+        assert!(protocols[0].is("H2C"));
+        assert_eq!(
+            UpgradeProtocol::to_header_value(&protocols).unwrap(),
+            "h2c, HTTP/2.0"
+        );
+    }
+
+    #[test]
+    fn test_http2_settings() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            HTTP2_SETTINGS,
+            Http2Settings::new()
+                .with(Http2Settings::MAX_CONCURRENT_STREAMS, 100)
+                .with(Http2Settings::MAX_CONCURRENT_STREAMS, 200)
+                .to_header_value(),
+        );
+
+        let settings = Http2Settings::from_headers(&headers).unwrap();
+        assert_eq!(settings.settings(), &[(3, 200)]);
+        assert_eq!(settings.get(Http2Settings::ENABLE_PUSH), None);
+
+        assert_eq!(
+            Http2Settings::from_header_value(&HeaderValue::from_static("AAIAAA")),
+            None
+        );
+        assert_eq!(
+            Http2Settings::from_header_value(&HeaderValue::from_static("")),
+            Some(Http2Settings::new())
+        );
+    }
+
+    #[test]
+    fn test_alpn() {
+        let preference = AlpnPreference::new()
+            .with_protocol(AlpnPreference::H3)
+            .with_protocol(AlpnPreference::H2)
+            .with_protocol(AlpnPreference::H2)
+            .with_protocol(Vec::new());
+
+        let wire = preference.to_wire();
+        assert_eq!(wire, b"\x02h3\x02h2");
+        assert_eq!(AlpnPreference::from_wire(&wire), Some(preference.clone()));
+        assert_eq!(AlpnPreference::from_wire(b"\x03h2"), None);
+
+        let selected = preference.select([&b"http/1.1"[..], b"h2"]).unwrap();
+        assert_eq!(selected, b"h2");
+        assert_eq!(AlpnPreference::version_of(selected), Some(Version::HTTP_2));
+        assert_eq!(preference.select([&b"http/1.1"[..]]), None);
+    }
+
+    #[test]
+    fn test_version_info() {
+        let (parts, ()) = http::Response::builder()
+            .version(Version::HTTP_2)
+            .body(())
+            .unwrap()
+            .into_parts();
+
+        let info = VersionInfo::from_parts(&parts);
+        assert!(info.is_multiplexed());
+        assert!(!info.is_fallback());
+        assert_eq!(info.to_string(), "HTTP/2.0");
+
+        let info = info.with_preferred(Version::HTTP_3);
+        assert!(info.is_fallback());
+        assert_eq!(info.to_string(), "HTTP/2.0 (preferred HTTP/3.0)");
+    }
+}
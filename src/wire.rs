@@ -0,0 +1,259 @@
+//! Sans-IO HTTP/1.1 message heads: serialize request / response heads to
+//! bytes, and parse response heads from a buffer.
+//!
+//! Only the heads are covered, bodies (and their framing) are left to the
+//! transport.
+
+use http::{
+    header::HOST, HeaderMap, HeaderName, HeaderValue, Method, Request, Response, StatusCode,
+    Version,
+};
+
+/// The max size of the response head accepted by [`parse_response_head`],
+/// 64 KiB.
+pub const MAX_HEAD_SIZE: usize = 64 * 1024;
+
+#[derive(Debug)]
+#[derive(thiserror::Error)]
+/// Error parsing the response head.
+pub enum WireError {
+    #[error("response head exceeds {MAX_HEAD_SIZE} bytes")]
+    /// The head exceeds [`MAX_HEAD_SIZE`].
+    TooLarge,
+
+    #[error("invalid status line")]
+    /// Invalid status line, or unsupported HTTP version.
+    InvalidStatusLine,
+
+    #[error("invalid header line")]
+    /// Invalid header name or value, or obsolete line folding.
+    InvalidHeader,
+}
+
+/// Serialize the request head (request line and headers, with the terminating
+/// empty line) to the buffer.
+///
+/// The request target is in authority-form for `CONNECT`, and origin-form
+/// otherwise. `Host` is added from the URI if missing.
+pub fn encode_request_head<B>(request: &Request<B>, buf: &mut Vec<u8>) {
+    let uri = request.uri();
+
+    buf.extend_from_slice(request.method().as_str().as_bytes());
+    buf.push(b' ');
+
+    match uri.authority() {
+        Some(authority) if request.method() == Method::CONNECT => {
+            buf.extend_from_slice(authority.as_str().as_bytes());
+        }
+        _ => {
+            buf.extend_from_slice(
+                uri.path_and_query()
+                    .map_or("/", |pq| pq.as_str())
+                    .as_bytes(),
+            );
+        }
+    }
+
+    buf.push(b' ');
+    buf.extend_from_slice(version(request.version()).as_bytes());
+    buf.extend_from_slice(b"\r\n");
+
+    if !request.headers().contains_key(HOST) {
+        if let Some(authority) = uri.authority() {
+            encode_header(&HOST, authority.as_str().as_bytes(), buf);
+        }
+    }
+
+    encode_headers(request.headers(), buf);
+}
+
+/// Serialize the response head (status line and headers, with the
+/// terminating empty line) to the buffer.
+pub fn encode_response_head<B>(response: &Response<B>, buf: &mut Vec<u8>) {
+    let status = response.status();
+
+    buf.extend_from_slice(version(response.version()).as_bytes());
+    buf.push(b' ');
+    buf.extend_from_slice(status.as_str().as_bytes());
+    buf.push(b' ');
+    buf.extend_from_slice(status.canonical_reason().unwrap_or_default().as_bytes());
+    buf.extend_from_slice(b"\r\n");
+
+    encode_headers(response.headers(), buf);
+}
+
+fn version(version: Version) -> &'static str {
+    match version {
+        Version::HTTP_10 => "HTTP/1.0",
+        _ => "HTTP/1.1",
+    }
+}
+
+fn encode_header(name: &HeaderName, value: &[u8], buf: &mut Vec<u8>) {
+    buf.extend_from_slice(name.as_str().as_bytes());
+    buf.extend_from_slice(b": ");
+    buf.extend_from_slice(value);
+    buf.extend_from_slice(b"\r\n");
+}
+
+fn encode_headers(headers: &HeaderMap, buf: &mut Vec<u8>) {
+    for (name, value) in headers {
+        encode_header(name, value.as_bytes(), buf);
+    }
+
+    buf.extend_from_slice(b"\r\n");
+}
+
+/// Parse the response head (status line and headers) from the start of the
+/// buffer.
+///
+/// Returns the response (with an empty body) and the length of the head, i.e.
+/// where the body starts, or `None` if the head is incomplete yet.
+///
+/// # Errors
+///
+/// See [`WireError`].
+pub fn parse_response_head(buf: &[u8]) -> Result<Option<(Response<()>, usize)>, WireError> {
+    let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") else {
+        return if buf.len() > MAX_HEAD_SIZE {
+            Err(WireError::TooLarge)
+        } else {
+            Ok(None)
+        };
+    };
+
+    if end + 4 > MAX_HEAD_SIZE {
+        return Err(WireError::TooLarge);
+    }
+
+    let mut lines = buf[..end]
+        .split(|&b| b == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line));
+
+    let (version, status) = lines
+        .next()
+        .and_then(parse_status_line)
+        .ok_or(WireError::InvalidStatusLine)?;
+
+    let mut response = Response::new(());
+    *response.version_mut() = version;
+    *response.status_mut() = status;
+
+    for line in lines {
+        if line.first().is_some_and(|b| matches!(b, b' ' | b'\t')) {
+            return Err(WireError::InvalidHeader);
+        }
+
+        let colon = line
+            .iter()
+            .position(|&b| b == b':')
+            .ok_or(WireError::InvalidHeader)?;
+
+        let name = HeaderName::from_bytes(&line[..colon]).map_err(|_| WireError::InvalidHeader)?;
+        let value = HeaderValue::from_bytes(line[colon + 1..].trim_ascii())
+            .map_err(|_| WireError::InvalidHeader)?;
+
+        response.headers_mut().append(name, value);
+    }
+
+    Ok(Some((response, end + 4)))
+}
+
+fn parse_status_line(line: &[u8]) -> Option<(Version, StatusCode)> {
+    let line = core::str::from_utf8(line).ok()?;
+
+    let (version, rest) = line.split_once(' ')?;
+    let version = match version {
+        "HTTP/1.0" => Version::HTTP_10,
+        "HTTP/1.1" => Version::HTTP_11,
+        _ => return None,
+    };
+
+    let code = rest.split_once(' ').map_or(rest, |(code, _reason)| code);
+    if code.len() != 3 {
+        return None;
+    }
+
+    StatusCode::from_bytes(code.as_bytes())
+        .ok()
+        .map(|status| (version, status))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_request_head() {
+        let request = Request::post("http://example.com:8080/a?b=1")
+            .header("content-length", "2")
+            .body(())
+            .unwrap();
+
+        let mut buf = Vec::new();
+        encode_request_head(&request, &mut buf);
+        assert_eq!(
+            buf,
+            b"POST /a?b=1 HTTP/1.1\r\nhost: example.com:8080\r\ncontent-length: 2\r\n\r\n"
+        );
+
+        let request = Request::connect("example.com:443")
+            .header("host", "example.com:443")
+            .body(())
+            .unwrap();
+
+        buf.clear();
+        encode_request_head(&request, &mut buf);
+        assert_eq!(
+            buf,
+            b"CONNECT example.com:443 HTTP/1.1\r\nhost: example.com:443\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn test_response_head() {
+        let response = Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .header("content-type", "text/plain")
+            .header("set-cookie", "a=1")
+            .header("set-cookie", "b=2")
+            .body(())
+            .unwrap();
+
+        let mut buf = Vec::new();
+        encode_response_head(&response, &mut buf);
+        assert_eq!(
+            buf,
+            b"HTTP/1.1 404 Not Found\r\ncontent-type: text/plain\r\nset-cookie: a=1\r\nset-cookie: b=2\r\n\r\n"
+        );
+
+        buf.extend_from_slice(b"body");
+
+        let (parsed, len) = parse_response_head(&buf).unwrap().unwrap();
+        assert_eq!(&buf[len..], b"body");
+        assert_eq!(parsed.status(), StatusCode::NOT_FOUND);
+        assert_eq!(parsed.headers(), response.headers());
+
+        let (parsed, _) = parse_response_head(b"HTTP/1.0 200\r\nA:  b \r\n\r\n")
+            .unwrap()
+            .unwrap();
+        assert_eq!(parsed.version(), Version::HTTP_10);
+        assert_eq!(parsed.headers()["a"], "b");
+
+        assert!(parse_response_head(b"HTTP/1.1 200 OK\r\n")
+            .unwrap()
+            .is_none());
+        assert!(matches!(
+            parse_response_head(b"HTTP/2 200 OK\r\n\r\n"),
+            Err(WireError::InvalidStatusLine)
+        ));
+        assert!(matches!(
+            parse_response_head(b"HTTP/1.1 200 OK\r\na: b\r\n c\r\n\r\n"),
+            Err(WireError::InvalidHeader)
+        ));
+        assert!(matches!(
+            parse_response_head(&vec![b'a'; MAX_HEAD_SIZE + 1]),
+            Err(WireError::TooLarge)
+        ));
+    }
+}
@@ -0,0 +1,311 @@
+//! WebSocket (RFC 6455) opening handshake utilities, see [`Handshake`].
+//!
+//! Only the handshake is covered, framing is left to the transport.
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use http::{
+    header::{CONNECTION, HOST, UPGRADE},
+    HeaderMap, HeaderName, HeaderValue, Request, StatusCode, Uri,
+};
+
+/// The GUID appended to the key when computing `Sec-WebSocket-Accept`.
+pub const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// The only WebSocket version supported, `13`.
+pub const WEBSOCKET_VERSION: &str = "13";
+
+/// `Sec-WebSocket-Key`
+pub const SEC_WEBSOCKET_KEY: HeaderName = HeaderName::from_static("sec-websocket-key");
+
+/// `Sec-WebSocket-Accept`
+pub const SEC_WEBSOCKET_ACCEPT: HeaderName = HeaderName::from_static("sec-websocket-accept");
+
+/// `Sec-WebSocket-Version`
+pub const SEC_WEBSOCKET_VERSION: HeaderName = HeaderName::from_static("sec-websocket-version");
+
+/// `Sec-WebSocket-Protocol`
+pub const SEC_WEBSOCKET_PROTOCOL: HeaderName = HeaderName::from_static("sec-websocket-protocol");
+
+#[derive(Debug)]
+#[derive(thiserror::Error)]
+/// Error returned by [`Handshake`].
+pub enum HandshakeError {
+    #[error(transparent)]
+    /// Invalid URI or header value.
+    Http(#[from] http::Error),
+
+    #[error("unsupported scheme `{0}`, expect `ws` or `wss`")]
+    /// The URI scheme is not `ws`, `wss`, `http` or `https`.
+    UnsupportedScheme(String),
+
+    #[error("missing host in URI")]
+    /// The URI has no host.
+    MissingHost,
+
+    #[error("unexpected status `{0}`, expect `101 Switching Protocols`")]
+    /// The server did not switch protocols.
+    UnexpectedStatus(StatusCode),
+
+    #[error("missing or invalid header `{0}`")]
+    /// Required response header is missing or has unexpected value.
+    InvalidHeader(HeaderName),
+
+    #[error("`Sec-WebSocket-Accept` mismatch")]
+    /// `Sec-WebSocket-Accept` does not match the key sent.
+    AcceptMismatch,
+
+    #[error("unexpected subprotocol `{0}`")]
+    /// The server selected a subprotocol not requested.
+    UnexpectedProtocol(String),
+}
+
+/// Generate a random `Sec-WebSocket-Key`, i.e. 16 random bytes base64
+/// encoded.
+///
+/// # Panics
+///
+/// Panics if the OS random number generator fails, see [`getrandom::fill`].
+pub fn generate_key() -> String {
+    let mut nonce = [0; 16];
+    getrandom::fill(&mut nonce).expect("failed to generate random bytes");
+
+    BASE64_STANDARD.encode(nonce)
+}
+
+/// Compute the `Sec-WebSocket-Accept` value of given `Sec-WebSocket-Key`.
+pub fn accept_key(key: &str) -> String {
+    use sha1::{Digest, Sha1};
+
+    let mut hasher = Sha1::new();
+    hasher.update(key.as_bytes());
+    hasher.update(WEBSOCKET_GUID.as_bytes());
+
+    BASE64_STANDARD.encode(hasher.finalize())
+}
+
+#[derive(Debug, Clone)]
+/// Client side WebSocket opening handshake.
+///
+/// Build the upgrade request with [`Handshake::request`], send it with any
+/// HTTP/1.1 transport, then validate the response with
+/// [`Handshake::verify`].
+pub struct Handshake {
+    key: String,
+    protocols: Vec<String>,
+}
+
+impl Default for Handshake {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Handshake {
+    #[inline]
+    /// Create a new [`Handshake`] with a random key, see [`generate_key`].
+    pub fn new() -> Self {
+        Self {
+            key: generate_key(),
+            protocols: Vec::new(),
+        }
+    }
+
+    #[inline]
+    /// Set the `Sec-WebSocket-Key`, mostly for testing.
+    pub fn with_key(self, key: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Add a subprotocol to request, in order of preference.
+    pub fn with_protocol(mut self, protocol: impl Into<String>) -> Self {
+        self.protocols.push(protocol.into());
+        self
+    }
+
+    #[inline]
+    /// Returns the `Sec-WebSocket-Key`.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    #[inline]
+    /// Returns the subprotocols requested.
+    pub fn protocols(&self) -> &[String] {
+        &self.protocols
+    }
+
+    /// Build the upgrade request.
+    ///
+    /// The URI scheme should be `ws` or `wss` (`http` or `https` are also
+    /// accepted), and is kept as is in the request URI.
+    ///
+    /// # Errors
+    ///
+    /// - Invalid URI or subprotocol.
+    /// - Unsupported scheme or missing host.
+    pub fn request<U>(&self, uri: U) -> Result<Request<()>, HandshakeError>
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<http::Error>,
+    {
+        let uri = Uri::try_from(uri).map_err(Into::into)?;
+
+        match uri.scheme_str() {
+            Some("ws" | "wss" | "http" | "https") => {}
+            scheme => {
+                return Err(HandshakeError::UnsupportedScheme(
+                    scheme.unwrap_or_default().to_owned(),
+                ))
+            }
+        }
+
+        let host = match (uri.host(), uri.port()) {
+            (Some(host), Some(port)) => format!("{host}:{port}"),
+            (Some(host), None) => host.to_owned(),
+            (None, _) => return Err(HandshakeError::MissingHost),
+        };
+
+        let mut builder = Request::get(uri)
+            .header(HOST, host)
+            .header(CONNECTION, "Upgrade")
+            .header(UPGRADE, "websocket")
+            .header(SEC_WEBSOCKET_VERSION, WEBSOCKET_VERSION)
+            .header(SEC_WEBSOCKET_KEY, &self.key);
+
+        if !self.protocols.is_empty() {
+            builder = builder.header(SEC_WEBSOCKET_PROTOCOL, self.protocols.join(", "));
+        }
+
+        Ok(builder.body(())?)
+    }
+
+    /// Validate the response of the upgrade request, returning the
+    /// negotiated subprotocol if any.
+    ///
+    /// # Errors
+    ///
+    /// - Status is not `101 Switching Protocols`.
+    /// - `Upgrade`, `Connection` or `Sec-WebSocket-Accept` is missing or
+    ///   invalid.
+    /// - The negotiated subprotocol was not requested.
+    pub fn verify(
+        &self,
+        status: StatusCode,
+        headers: &HeaderMap,
+    ) -> Result<Option<String>, HandshakeError> {
+        if status != StatusCode::SWITCHING_PROTOCOLS {
+            return Err(HandshakeError::UnexpectedStatus(status));
+        }
+
+        let header = |name: &HeaderName| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .ok_or_else(|| HandshakeError::InvalidHeader(name.clone()))
+        };
+
+        if !header(&UPGRADE)?.eq_ignore_ascii_case("websocket") {
+            return Err(HandshakeError::InvalidHeader(UPGRADE));
+        }
+
+        if !header(&CONNECTION)?
+            .split(',')
+            .any(|token| token.trim().eq_ignore_ascii_case("upgrade"))
+        {
+            return Err(HandshakeError::InvalidHeader(CONNECTION));
+        }
+
+        if header(&SEC_WEBSOCKET_ACCEPT)? != accept_key(&self.key) {
+            return Err(HandshakeError::AcceptMismatch);
+        }
+
+        match headers.get(SEC_WEBSOCKET_PROTOCOL).map(HeaderValue::to_str) {
+            None => Ok(None),
+            Some(Ok(protocol)) if self.protocols.iter().any(|p| p == protocol) => {
+                Ok(Some(protocol.to_owned()))
+            }
+            Some(Ok(protocol)) => Err(HandshakeError::UnexpectedProtocol(protocol.to_owned())),
+            Some(Err(_)) => Err(HandshakeError::InvalidHeader(SEC_WEBSOCKET_PROTOCOL)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accept_key() {
+        // RFC 6455, section 1.3
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+        assert_eq!(BASE64_STANDARD.decode(generate_key()).unwrap().len(), 16);
+    }
+
+    #[test]
+    fn test_handshake() {
+        let handshake = Handshake::new()
+            .with_key("dGhlIHNhbXBsZSBub25jZQ==")
+            .with_protocol("chat")
+            .with_protocol("superchat");
+
+        let request = handshake
+            .request("wss://example.com:8443/chat?a=1")
+            .unwrap();
+        assert_eq!(request.uri(), "wss://example.com:8443/chat?a=1");
+        assert_eq!(request.headers()[HOST], "example.com:8443");
+        assert_eq!(request.headers()[UPGRADE], "websocket");
+        assert_eq!(request.headers()[SEC_WEBSOCKET_VERSION], "13");
+        assert_eq!(request.headers()[SEC_WEBSOCKET_PROTOCOL], "chat, superchat");
+
+        assert!(matches!(
+            handshake.request("ftp://example.com"),
+            Err(HandshakeError::UnsupportedScheme(_))
+        ));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(UPGRADE, HeaderValue::from_static("websocket"));
+        headers.insert(CONNECTION, HeaderValue::from_static("keep-alive, Upgrade"));
+        headers.insert(
+            SEC_WEBSOCKET_ACCEPT,
+            HeaderValue::from_static("s3pPLMBiTxaQ9kYGzzhZRbK+xOo="),
+        );
+        assert_eq!(
+            handshake
+                .verify(StatusCode::SWITCHING_PROTOCOLS, &headers)
+                .unwrap(),
+            None
+        );
+        assert!(matches!(
+            handshake.verify(StatusCode::OK, &headers),
+            Err(HandshakeError::UnexpectedStatus(StatusCode::OK))
+        ));
+
+        headers.insert(SEC_WEBSOCKET_PROTOCOL, HeaderValue::from_static("chat"));
+        assert_eq!(
+            handshake
+                .verify(StatusCode::SWITCHING_PROTOCOLS, &headers)
+                .unwrap()
+                .as_deref(),
+            Some("chat")
+        );
+
+        headers.insert(SEC_WEBSOCKET_PROTOCOL, HeaderValue::from_static("mqtt"));
+        assert!(matches!(
+            handshake.verify(StatusCode::SWITCHING_PROTOCOLS, &headers),
+            Err(HandshakeError::UnexpectedProtocol(_))
+        ));
+
+        headers.insert(SEC_WEBSOCKET_ACCEPT, HeaderValue::from_static("bogus"));
+        assert!(matches!(
+            handshake.verify(StatusCode::SWITCHING_PROTOCOLS, &headers),
+            Err(HandshakeError::AcceptMismatch)
+        ));
+    }
+}